@@ -0,0 +1,100 @@
+use super::{parse_duration_secs, scalar};
+use crate::output::Output;
+use anyhow::Result;
+use clap::{Args, Subcommand};
+use rusqlite::Connection;
+use serde_json::Value;
+
+#[derive(Debug, Subcommand)]
+pub enum CleanupCommand {
+    /// Delete completed, failed and cancelled tasks older than the TTL.
+    Tasks(CleanupArgs),
+    /// Delete events older than the TTL.
+    Events(CleanupArgs),
+    /// Apply every queue's retention settings until nothing is left to do.
+    Maintenance {
+        /// Rows deleted per queue per batch.
+        #[arg(long, default_value_t = 1000)]
+        batch_size: i64,
+    },
+}
+
+#[derive(Debug, Args)]
+pub struct CleanupArgs {
+    queue: String,
+    /// Age after which rows are deleted, e.g. 7d, 12h, 30m or seconds.
+    #[arg(long, value_parser = parse_duration_secs)]
+    ttl: i64,
+    /// Rows deleted per batch. Batches repeat until the backlog is cleared.
+    #[arg(long, default_value_t = 1000)]
+    batch_size: i64,
+}
+
+pub fn run(conn: &Connection, command: CleanupCommand) -> Result<Output> {
+    let (queue, deleted) = match command {
+        CleanupCommand::Tasks(args) => {
+            let deleted = drain(args.batch_size, |limit| {
+                scalar(
+                    conn,
+                    "select absurd_cleanup_tasks(?1, ?2, ?3)",
+                    rusqlite::params![args.queue, args.ttl, limit],
+                )
+            })?;
+            (Some(args.queue), deleted)
+        }
+        CleanupCommand::Events(args) => {
+            let deleted = drain(args.batch_size, |limit| {
+                scalar(
+                    conn,
+                    "select absurd_cleanup_events(?1, ?2, ?3)",
+                    rusqlite::params![args.queue, args.ttl, limit],
+                )
+            })?;
+            (Some(args.queue), deleted)
+        }
+        CleanupCommand::Maintenance { batch_size } => {
+            // Maintenance cleans every queue per call, so a call can delete
+            // more than one batch; stop once a call finds nothing.
+            let mut deleted = 0;
+            loop {
+                let batch = as_count(scalar(
+                    conn,
+                    "select absurd_run_maintenance(?1)",
+                    [batch_size],
+                )?);
+                if batch == 0 {
+                    break;
+                }
+                deleted += batch;
+            }
+            (None, deleted)
+        }
+    };
+
+    let mut fields = Vec::new();
+    if let Some(queue) = queue {
+        fields.push(("queue_name".to_string(), Value::String(queue)));
+    }
+    fields.push(("deleted".to_string(), Value::from(deleted)));
+    Ok(Output::Record(fields))
+}
+
+/// Call `batch` until it deletes fewer rows than the limit, so each call
+/// holds the write lock for one bounded batch only.
+fn drain(limit: i64, mut batch: impl FnMut(i64) -> Result<Value>) -> Result<i64> {
+    if limit <= 0 {
+        anyhow::bail!("--batch-size must be positive");
+    }
+    let mut deleted = 0;
+    loop {
+        let count = as_count(batch(limit)?);
+        deleted += count;
+        if count < limit {
+            return Ok(deleted);
+        }
+    }
+}
+
+fn as_count(value: Value) -> i64 {
+    value.as_i64().unwrap_or(0)
+}
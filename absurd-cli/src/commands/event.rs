@@ -0,0 +1,51 @@
+use super::{json_arg, scalar};
+use crate::output::Output;
+use anyhow::Result;
+use clap::{Args, Subcommand};
+use rusqlite::Connection;
+use serde_json::Value;
+
+#[derive(Debug, Subcommand)]
+pub enum EventCommand {
+    /// Emit an event and wake any tasks waiting for it.
+    Emit(EmitArgs),
+}
+
+#[derive(Debug, Args)]
+pub struct EmitArgs {
+    queue: String,
+    event_name: String,
+    /// Event payload as JSON.
+    #[arg(long)]
+    payload: Option<String>,
+    /// Also deliver the event to this queue. May be repeated.
+    #[arg(long = "target-queue")]
+    target_queues: Vec<String>,
+}
+
+pub fn run(conn: &Connection, command: EventCommand) -> Result<Output> {
+    match command {
+        EventCommand::Emit(args) => {
+            let payload = args
+                .payload
+                .as_deref()
+                .map(|payload| json_arg("--payload", payload))
+                .transpose()?;
+            let target_queues = if args.target_queues.is_empty() {
+                None
+            } else {
+                Some(serde_json::to_string(&args.target_queues)?)
+            };
+            scalar(
+                conn,
+                "select absurd_emit_event(?1, ?2, ?3, ?4)",
+                rusqlite::params![args.queue, args.event_name, payload, target_queues],
+            )?;
+            Ok(Output::Record(vec![
+                ("queue_name".to_string(), Value::String(args.queue)),
+                ("event_name".to_string(), Value::String(args.event_name)),
+                ("status".to_string(), Value::from("emitted")),
+            ]))
+        }
+    }
+}
@@ -0,0 +1,45 @@
+use super::{rows, scalar};
+use crate::output::Output;
+use anyhow::Result;
+use clap::Subcommand;
+use rusqlite::Connection;
+
+#[derive(Debug, Subcommand)]
+pub enum MigrationsCommand {
+    /// List bundled migrations and whether each one is applied.
+    Status,
+    /// Apply pending migrations.
+    Apply {
+        /// Stop at this migration id instead of the latest.
+        #[arg(long)]
+        target: Option<i64>,
+    },
+}
+
+pub fn run(conn: &Connection, command: MigrationsCommand) -> Result<Output> {
+    match command {
+        MigrationsCommand::Status => rows(
+            conn,
+            "select a.id,
+                    a.introduced_version,
+                    r.applied_time is not null as applied,
+                    r.applied_time
+               from absurd_available_migrations() a
+               left join absurd_migration_records() r on r.id = a.id
+              order by a.id",
+            [],
+        ),
+        MigrationsCommand::Apply { target } => {
+            let applied = scalar(conn, "select absurd_apply_migrations(?1)", [target])?;
+            let current = scalar(conn, "select max(id) from absurd_migration_records()", [])?;
+            Ok(Output::Record(vec![
+                ("applied".to_string(), applied),
+                ("current_version".to_string(), current),
+                (
+                    "extension_version".to_string(),
+                    scalar(conn, "select absurd_version()", [])?,
+                ),
+            ]))
+        }
+    }
+}
@@ -0,0 +1,66 @@
+pub mod cleanup;
+pub mod event;
+pub mod migrations;
+pub mod queue;
+pub mod stats;
+pub mod task;
+pub mod transfer;
+
+use crate::output::{self, Output, Rows};
+use anyhow::{Context, Result};
+use rusqlite::types::Value as SqlValue;
+use rusqlite::{Connection, Params};
+use serde_json::Value;
+
+/// Evaluate a single-value query, such as a scalar `absurd_*` function call.
+pub fn scalar<P: Params>(conn: &Connection, sql: &str, params: P) -> Result<Value> {
+    let value = conn.query_row(sql, params, |row| row.get::<_, SqlValue>(0))?;
+    Ok(output::sql_to_json(value))
+}
+
+/// Run a query and return all rows.
+pub fn rows<P: Params>(conn: &Connection, sql: &str, params: P) -> Result<Output> {
+    let mut stmt = conn.prepare(sql)?;
+    Ok(Output::Rows(Rows::query(&mut stmt, params)?))
+}
+
+/// Make sure a `--params`/`--payload` style argument is valid JSON before it
+/// reaches SQLite, so the error names the flag rather than a SQL function.
+pub fn json_arg(flag: &str, text: &str) -> Result<String> {
+    serde_json::from_str::<Value>(text).with_context(|| format!("{} must be valid JSON", flag))?;
+    Ok(text.to_string())
+}
+
+/// Parse a duration like `7d`, `12h`, `30m`, `45s` or plain seconds, matching
+/// the format accepted by the queue retention settings.
+pub fn parse_duration_secs(text: &str) -> Result<i64, String> {
+    let text = text.trim();
+    let (number, unit) = match text.char_indices().last() {
+        Some((idx, unit)) if unit.is_ascii_alphabetic() => (&text[..idx], Some(unit)),
+        _ => (text, None),
+    };
+    let multiplier = match unit.map(|unit| unit.to_ascii_lowercase()) {
+        None | Some('s') => 1,
+        Some('m') => 60,
+        Some('h') => 60 * 60,
+        Some('d') => 24 * 60 * 60,
+        Some('w') => 7 * 24 * 60 * 60,
+        Some(_) => return Err(duration_error(text)),
+    };
+    let number = number
+        .trim()
+        .parse::<i64>()
+        .ok()
+        .filter(|number| *number >= 0)
+        .ok_or_else(|| duration_error(text))?;
+    number
+        .checked_mul(multiplier)
+        .ok_or_else(|| duration_error(text))
+}
+
+fn duration_error(text: &str) -> String {
+    format!(
+        "invalid duration {:?}; use a value like 7d, 12h, 30m, 45s or seconds",
+        text
+    )
+}
@@ -0,0 +1,70 @@
+use super::{rows, scalar};
+use crate::output::Output;
+use anyhow::Result;
+use clap::Subcommand;
+use rusqlite::Connection;
+use serde_json::Value;
+
+#[derive(Debug, Subcommand)]
+pub enum QueueCommand {
+    /// Create a queue.
+    Create { queue: String },
+    /// Drop a queue and everything in it.
+    Drop { queue: String },
+    /// List queues.
+    List,
+    /// Stop handing out new claims for a queue.
+    Pause { queue: String },
+    /// Resume claiming on a paused queue.
+    Resume { queue: String },
+}
+
+pub fn run(conn: &Connection, command: QueueCommand) -> Result<Output> {
+    match command {
+        QueueCommand::Create { queue } => {
+            scalar(conn, "select absurd_create_queue(?1)", [&queue])?;
+            Ok(queue_record(queue, "created"))
+        }
+        QueueCommand::Drop { queue } => {
+            let dropped = scalar(conn, "select absurd_drop_queue(?1)", [&queue])?;
+            if dropped == 0 {
+                anyhow::bail!("queue not found: {}", queue);
+            }
+            Ok(queue_record(queue, "dropped"))
+        }
+        QueueCommand::List => rows(
+            conn,
+            "select q.queue_name,
+                    q.created_at,
+                    coalesce(s.value = '1', 0) as paused
+               from absurd_list_queues() q
+               left join absurd_queue_settings s
+                 on s.queue_name = q.queue_name and s.key = 'paused'
+              order by q.queue_name",
+            [],
+        ),
+        QueueCommand::Pause { queue } => {
+            scalar(
+                conn,
+                "select absurd_set_queue_setting(?1, 'paused', 'true')",
+                [&queue],
+            )?;
+            Ok(queue_record(queue, "paused"))
+        }
+        QueueCommand::Resume { queue } => {
+            scalar(
+                conn,
+                "select absurd_set_queue_setting(?1, 'paused', null)",
+                [&queue],
+            )?;
+            Ok(queue_record(queue, "resumed"))
+        }
+    }
+}
+
+fn queue_record(queue: String, status: &str) -> Output {
+    Output::Record(vec![
+        ("queue_name".to_string(), Value::String(queue)),
+        ("status".to_string(), Value::from(status)),
+    ])
+}
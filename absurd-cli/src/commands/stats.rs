@@ -0,0 +1,31 @@
+use super::rows;
+use crate::output::Output;
+use anyhow::Result;
+use clap::Args;
+use rusqlite::Connection;
+
+#[derive(Debug, Args)]
+pub struct StatsArgs {
+    /// Only this queue.
+    queue: Option<String>,
+}
+
+pub fn run(conn: &Connection, args: StatsArgs) -> Result<Output> {
+    rows(
+        conn,
+        "select q.queue_name,
+                count(t.task_id) as total,
+                count(*) filter (where t.state = 'pending') as pending,
+                count(*) filter (where t.state = 'running') as running,
+                count(*) filter (where t.state = 'sleeping') as sleeping,
+                count(*) filter (where t.state = 'completed') as completed,
+                count(*) filter (where t.state = 'failed') as failed,
+                count(*) filter (where t.state = 'cancelled') as cancelled
+           from absurd_queues q
+           left join absurd_tasks t on t.queue_name = q.queue_name
+          where ?1 is null or q.queue_name = ?1
+          group by q.queue_name
+          order by q.queue_name",
+        [args.queue],
+    )
+}
@@ -0,0 +1,181 @@
+use super::{json_arg, rows, scalar};
+use crate::output::{Output, Rows};
+use anyhow::Result;
+use clap::{Args, Subcommand};
+use rusqlite::types::Value as SqlValue;
+use rusqlite::{params_from_iter, Connection};
+use serde_json::Value;
+
+/// Columns holding JSON text in `task show`; they are parsed so that JSON
+/// output nests them instead of quoting them.
+const JSON_COLUMNS: &[&str] = &[
+    "params",
+    "headers",
+    "retry_strategy",
+    "cancellation",
+    "completed_payload",
+    "result",
+    "failure_reason",
+];
+
+#[derive(Debug, Subcommand)]
+pub enum TaskCommand {
+    /// Spawn a task.
+    Spawn(SpawnArgs),
+    /// List tasks in a queue, newest first.
+    List(ListArgs),
+    /// Show a task and its runs.
+    Show { queue: String, task_id: String },
+    /// Cancel a task and any active runs.
+    Cancel { queue: String, task_id: String },
+    /// Start a new attempt for a failed or cancelled task, even past its
+    /// max_attempts.
+    Retry { queue: String, task_id: String },
+}
+
+#[derive(Debug, Args)]
+pub struct SpawnArgs {
+    queue: String,
+    task_name: String,
+    /// Task params as JSON.
+    #[arg(long, default_value = "{}")]
+    params: String,
+    /// Spawn options as JSON, e.g. '{"max_attempts": 3}'.
+    #[arg(long, default_value = "{}")]
+    options: String,
+}
+
+#[derive(Debug, Args)]
+pub struct ListArgs {
+    queue: String,
+    /// Only tasks in this state.
+    #[arg(long)]
+    state: Option<String>,
+    /// Only tasks with this task name.
+    #[arg(long)]
+    name: Option<String>,
+    /// Maximum number of tasks to list.
+    #[arg(long, default_value_t = 50)]
+    limit: i64,
+}
+
+pub fn run(conn: &Connection, command: TaskCommand) -> Result<Output> {
+    match command {
+        TaskCommand::Spawn(args) => {
+            let params = json_arg("--params", &args.params)?;
+            let options = json_arg("--options", &args.options)?;
+            let mut stmt = conn.prepare(
+                "select task_id, run_id, attempt, created
+                   from absurd_spawn_task(?1, ?2, ?3, ?4)",
+            )?;
+            let spawned =
+                Rows::query(&mut stmt, [&args.queue, &args.task_name, &params, &options])?;
+            Output::record_from(spawned)
+                .ok_or_else(|| anyhow::anyhow!("absurd_spawn_task returned no row"))
+        }
+        TaskCommand::List(args) => list(conn, args),
+        TaskCommand::Show { queue, task_id } => show(conn, &queue, &task_id),
+        TaskCommand::Cancel { queue, task_id } => {
+            scalar(
+                conn,
+                "select absurd_cancel_task(?1, ?2)",
+                [&queue, &task_id],
+            )?;
+            Ok(task_record(queue, task_id, vec![]))
+        }
+        TaskCommand::Retry { queue, task_id } => {
+            let run_id = scalar(conn, "select absurd_retry_task(?1, ?2)", [&queue, &task_id])?;
+            Ok(task_record(
+                queue,
+                task_id,
+                vec![("run_id".to_string(), run_id)],
+            ))
+        }
+    }
+}
+
+fn list(conn: &Connection, args: ListArgs) -> Result<Output> {
+    let mut sql = String::from(
+        "select task_id, task_name, state, attempts, enqueue_at, first_started_at
+           from absurd_tasks
+          where queue_name = ?",
+    );
+    let mut params = vec![SqlValue::Text(args.queue)];
+    if let Some(state) = args.state {
+        sql.push_str(" and state = ?");
+        params.push(SqlValue::Text(state));
+    }
+    if let Some(name) = args.name {
+        sql.push_str(" and task_name = ?");
+        params.push(SqlValue::Text(name));
+    }
+    sql.push_str(" order by enqueue_at desc, task_id desc limit ?");
+    params.push(SqlValue::Integer(args.limit));
+    rows(conn, &sql, params_from_iter(params))
+}
+
+fn show(conn: &Connection, queue: &str, task_id: &str) -> Result<Output> {
+    let mut stmt = conn.prepare(
+        "select task_id, task_name, state, attempts, max_attempts,
+                enqueue_at, first_started_at, cancelled_at, last_attempt_run,
+                absurd_decode_payload(json(params)) as params,
+                absurd_decode_payload(json(headers)) as headers,
+                json(retry_strategy) as retry_strategy,
+                json(cancellation) as cancellation,
+                absurd_decode_payload(json(completed_payload)) as completed_payload
+           from absurd_tasks
+          where queue_name = ?1 and task_id = ?2",
+    )?;
+    let task = Rows::query(&mut stmt, [queue, task_id])?;
+    let Some(Output::Record(record)) = Output::record_from(task) else {
+        anyhow::bail!("task not found: {}", task_id);
+    };
+
+    let mut stmt = conn.prepare(
+        "select run_id, attempt, state, claimed_by, available_at, started_at,
+                completed_at, failed_at,
+                absurd_decode_payload(json(result)) as result,
+                json(failure_reason) as failure_reason
+           from absurd_runs
+          where queue_name = ?1 and task_id = ?2
+          order by attempt",
+    )?;
+    let mut runs = Rows::query(&mut stmt, [queue, task_id])?;
+    for row in &mut runs.rows {
+        for (column, value) in runs.columns.iter().zip(row.iter_mut()) {
+            parse_json_column(column, value);
+        }
+    }
+
+    Ok(Output::Detail {
+        record: record
+            .into_iter()
+            .map(|(column, mut value)| {
+                parse_json_column(&column, &mut value);
+                (column, value)
+            })
+            .collect(),
+        name: "runs".to_string(),
+        rows: runs,
+    })
+}
+
+fn parse_json_column(column: &str, value: &mut Value) {
+    if !JSON_COLUMNS.contains(&column) {
+        return;
+    }
+    if let Value::String(text) = value {
+        if let Ok(parsed) = serde_json::from_str(text) {
+            *value = parsed;
+        }
+    }
+}
+
+fn task_record(queue: String, task_id: String, extra: Vec<(String, Value)>) -> Output {
+    let mut fields = vec![
+        ("queue_name".to_string(), Value::String(queue)),
+        ("task_id".to_string(), Value::String(task_id)),
+    ];
+    fields.extend(extra);
+    Output::Record(fields)
+}
@@ -0,0 +1,81 @@
+use super::scalar;
+use crate::output::Output;
+use anyhow::{Context, Result};
+use clap::Args;
+use rusqlite::Connection;
+use serde_json::Value;
+use std::io::Read;
+use std::path::PathBuf;
+
+#[derive(Debug, Args)]
+pub struct ExportArgs {
+    queue: String,
+    /// Write the export to this file instead of stdout.
+    #[arg(long)]
+    file: Option<PathBuf>,
+}
+
+#[derive(Debug, Args)]
+pub struct ImportArgs {
+    queue: String,
+    /// Read the export from this file instead of stdin.
+    #[arg(long)]
+    file: Option<PathBuf>,
+    /// Give imported tasks and runs new ids, e.g. to copy a queue within one
+    /// database.
+    #[arg(long)]
+    remap_ids: bool,
+}
+
+pub fn export(conn: &Connection, args: ExportArgs) -> Result<Output> {
+    let mut stmt = conn.prepare("select line from absurd_export_queue(?1) order by line_no")?;
+    let lines = stmt
+        .query_map([&args.queue], |row| row.get::<_, String>(0))?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    let mut jsonl = lines.join("\n");
+    jsonl.push('\n');
+
+    match args.file {
+        None => Ok(Output::Raw(jsonl)),
+        Some(path) => {
+            std::fs::write(&path, jsonl)
+                .with_context(|| format!("write export to {}", path.display()))?;
+            Ok(Output::Record(vec![
+                ("queue_name".to_string(), Value::String(args.queue)),
+                // The header line is not a record.
+                (
+                    "record_count".to_string(),
+                    Value::from(lines.len().saturating_sub(1)),
+                ),
+                (
+                    "file".to_string(),
+                    Value::String(path.display().to_string()),
+                ),
+            ]))
+        }
+    }
+}
+
+pub fn import(conn: &Connection, args: ImportArgs) -> Result<Output> {
+    let jsonl = match &args.file {
+        Some(path) => std::fs::read_to_string(path)
+            .with_context(|| format!("read export from {}", path.display()))?,
+        None => {
+            let mut jsonl = String::new();
+            std::io::stdin()
+                .read_to_string(&mut jsonl)
+                .context("read export from stdin")?;
+            jsonl
+        }
+    };
+    let id_mode = if args.remap_ids { "remap" } else { "keep" };
+    let imported = scalar(
+        conn,
+        "select absurd_import_queue(?1, ?2, ?3)",
+        [&args.queue, &jsonl, id_mode],
+    )?;
+    Ok(Output::Record(vec![
+        ("queue_name".to_string(), Value::String(args.queue)),
+        ("imported_count".to_string(), imported),
+    ]))
+}
@@ -0,0 +1,38 @@
+use anyhow::{Context, Result};
+use rusqlite::Connection;
+use std::path::Path;
+
+/// Open the database and load the Absurd extension into the connection.
+///
+/// An explicit extension path wins; otherwise it is looked up the way
+/// `absurd-server` does it: next to the running binary, then (in debug
+/// builds) in the workspace `target` directory.
+pub fn open(db_path: &Path, extension: Option<&Path>) -> Result<Connection> {
+    let conn = Connection::open(db_path)
+        .with_context(|| format!("open database {}", db_path.display()))?;
+
+    let extension_path = match extension {
+        Some(path) => path.to_path_buf(),
+        None => absurd_server::db::resolve_extension_path().ok_or_else(|| {
+            anyhow::anyhow!("SQLite extension not found; pass --extension or set ABSURD_EXTENSION")
+        })?,
+    };
+
+    // Safety: the extension is our own build, and loading is disabled again
+    // right after it is registered.
+    unsafe {
+        conn.load_extension_enable()
+            .context("enable extension loading")?;
+        // rusqlite expects the path without the platform suffix.
+        let extension_path_no_ext = extension_path.with_extension("");
+        conn.load_extension(
+            extension_path_no_ext.to_string_lossy().as_ref(),
+            Some("sqlite3_absurd_init"),
+        )
+        .with_context(|| format!("load SQLite extension {}", extension_path.display()))?;
+        conn.load_extension_disable()
+            .context("disable extension loading")?;
+    }
+
+    Ok(conn)
+}
@@ -0,0 +1,83 @@
+mod commands;
+mod db;
+mod output;
+
+use anyhow::Result;
+use clap::{Parser, Subcommand};
+use output::OutputFormat;
+use std::path::PathBuf;
+
+/// Operate Absurd queues in a SQLite database without the desktop app.
+#[derive(Debug, Parser)]
+#[command(name = "absurd", version, about)]
+struct Cli {
+    /// Path to the SQLite database.
+    #[arg(long, env = "ABSURD_DB", global = true)]
+    db: Option<PathBuf>,
+
+    /// Path to the Absurd SQLite extension. Defaults to the library next to
+    /// this binary.
+    #[arg(long, env = "ABSURD_EXTENSION", global = true)]
+    extension: Option<PathBuf>,
+
+    /// Output format.
+    #[arg(long, value_enum, default_value_t = OutputFormat::Table, global = true)]
+    output: OutputFormat,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Create, drop, list, pause and resume queues.
+    #[command(subcommand)]
+    Queue(commands::queue::QueueCommand),
+    /// Spawn, inspect, cancel and retry tasks.
+    #[command(subcommand)]
+    Task(commands::task::TaskCommand),
+    /// Emit events.
+    #[command(subcommand)]
+    Event(commands::event::EventCommand),
+    /// Delete old terminal tasks and events.
+    #[command(subcommand)]
+    Cleanup(commands::cleanup::CleanupCommand),
+    /// Inspect and apply schema migrations.
+    #[command(subcommand)]
+    Migrations(commands::migrations::MigrationsCommand),
+    /// Show task counts per queue and state.
+    Stats(commands::stats::StatsArgs),
+    /// Export a queue as JSON Lines.
+    Export(commands::transfer::ExportArgs),
+    /// Import a JSON Lines export into a queue.
+    Import(commands::transfer::ImportArgs),
+}
+
+fn main() {
+    let cli = Cli::parse();
+    if let Err(err) = run(cli) {
+        eprintln!("error: {:#}", err);
+        std::process::exit(1);
+    }
+}
+
+fn run(cli: Cli) -> Result<()> {
+    let db_path = cli
+        .db
+        .ok_or_else(|| anyhow::anyhow!("no database given; pass --db or set ABSURD_DB"))?;
+    let conn = db::open(&db_path, cli.extension.as_deref())?;
+
+    let output = match cli.command {
+        Command::Queue(command) => commands::queue::run(&conn, command)?,
+        Command::Task(command) => commands::task::run(&conn, command)?,
+        Command::Event(command) => commands::event::run(&conn, command)?,
+        Command::Cleanup(command) => commands::cleanup::run(&conn, command)?,
+        Command::Migrations(command) => commands::migrations::run(&conn, command)?,
+        Command::Stats(args) => commands::stats::run(&conn, args)?,
+        Command::Export(args) => commands::transfer::export(&conn, args)?,
+        Command::Import(args) => commands::transfer::import(&conn, args)?,
+    };
+
+    let stdout = std::io::stdout();
+    output.write(cli.output, &mut stdout.lock())
+}
@@ -0,0 +1,185 @@
+use anyhow::Result;
+use clap::ValueEnum;
+use rusqlite::types::Value as SqlValue;
+use rusqlite::{Params, Statement};
+use serde_json::{Map, Value};
+use std::io::Write;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    /// Aligned columns for humans.
+    #[default]
+    Table,
+    /// One JSON document, for scripts.
+    Json,
+}
+
+/// What a command produced, rendered according to `--output`.
+pub enum Output {
+    /// Rows with a fixed set of columns. JSON renders an array of objects.
+    Rows(Rows),
+    /// A single record. Table renders one `key  value` line per field.
+    Record(Vec<(String, Value)>),
+    /// A record followed by related rows, e.g. a task and its runs. JSON
+    /// nests the rows under `name`.
+    Detail {
+        record: Vec<(String, Value)>,
+        name: String,
+        rows: Rows,
+    },
+    /// Text written verbatim regardless of the output format, e.g. JSONL.
+    Raw(String),
+}
+
+pub struct Rows {
+    pub columns: Vec<String>,
+    pub rows: Vec<Vec<Value>>,
+}
+
+impl Rows {
+    /// Run `stmt` and collect every row, converting SQLite values to JSON.
+    pub fn query<P: Params>(stmt: &mut Statement<'_>, params: P) -> Result<Self> {
+        let columns = stmt
+            .column_names()
+            .into_iter()
+            .map(str::to_string)
+            .collect::<Vec<_>>();
+        let width = columns.len();
+        let rows = stmt
+            .query_map(params, |row| {
+                (0..width)
+                    .map(|idx| row.get::<_, SqlValue>(idx).map(sql_to_json))
+                    .collect::<rusqlite::Result<Vec<_>>>()
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(Self { columns, rows })
+    }
+
+    fn into_objects(self) -> Value {
+        Value::Array(
+            self.rows
+                .into_iter()
+                .map(|row| {
+                    Value::Object(
+                        self.columns
+                            .iter()
+                            .cloned()
+                            .zip(row)
+                            .collect::<Map<String, Value>>(),
+                    )
+                })
+                .collect(),
+        )
+    }
+}
+
+impl Output {
+    /// Build a record from a single-row result.
+    pub fn record_from(rows: Rows) -> Option<Self> {
+        let Rows { columns, rows } = rows;
+        let row = rows.into_iter().next()?;
+        Some(Output::Record(columns.into_iter().zip(row).collect()))
+    }
+
+    pub fn write(self, format: OutputFormat, out: &mut impl Write) -> Result<()> {
+        match (self, format) {
+            (Output::Raw(text), _) => {
+                out.write_all(text.as_bytes())?;
+            }
+            (Output::Rows(rows), OutputFormat::Json) => {
+                writeln!(
+                    out,
+                    "{}",
+                    serde_json::to_string_pretty(&rows.into_objects())?
+                )?;
+            }
+            (Output::Record(fields), OutputFormat::Json) => {
+                let object = fields.into_iter().collect::<Map<String, Value>>();
+                writeln!(out, "{}", serde_json::to_string_pretty(&object)?)?;
+            }
+            (Output::Detail { record, name, rows }, OutputFormat::Json) => {
+                let mut object = record.into_iter().collect::<Map<String, Value>>();
+                object.insert(name, rows.into_objects());
+                writeln!(out, "{}", serde_json::to_string_pretty(&object)?)?;
+            }
+            (Output::Rows(rows), OutputFormat::Table) => write_table(&rows, out)?,
+            (Output::Record(fields), OutputFormat::Table) => write_record(&fields, out)?,
+            (Output::Detail { record, name, rows }, OutputFormat::Table) => {
+                write_record(&record, out)?;
+                writeln!(out)?;
+                writeln!(out, "{}:", name)?;
+                write_table(&rows, out)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn write_record(fields: &[(String, Value)], out: &mut impl Write) -> Result<()> {
+    let width = fields.iter().map(|(key, _)| key.len()).max().unwrap_or(0);
+    for (key, value) in fields {
+        writeln!(out, "{:width$}  {}", key, cell(value), width = width)?;
+    }
+    Ok(())
+}
+
+fn write_table(rows: &Rows, out: &mut impl Write) -> Result<()> {
+    let cells = rows
+        .rows
+        .iter()
+        .map(|row| row.iter().map(cell).collect::<Vec<_>>())
+        .collect::<Vec<_>>();
+    let widths = rows
+        .columns
+        .iter()
+        .enumerate()
+        .map(|(idx, column)| {
+            cells
+                .iter()
+                .map(|row| row[idx].chars().count())
+                .chain(std::iter::once(column.len()))
+                .max()
+                .unwrap_or(0)
+        })
+        .collect::<Vec<_>>();
+
+    let header = rows
+        .columns
+        .iter()
+        .zip(&widths)
+        .map(|(column, width)| format!("{:width$}", column.to_uppercase(), width = width))
+        .collect::<Vec<_>>();
+    writeln!(out, "{}", header.join("  ").trim_end())?;
+    for row in &cells {
+        let line = row
+            .iter()
+            .zip(&widths)
+            .map(|(value, width)| format!("{:width$}", value, width = width))
+            .collect::<Vec<_>>();
+        writeln!(out, "{}", line.join("  ").trim_end())?;
+    }
+    Ok(())
+}
+
+fn cell(value: &Value) -> String {
+    match value {
+        Value::Null => "-".to_string(),
+        Value::String(text) => text.clone(),
+        other => other.to_string(),
+    }
+}
+
+/// Convert an SQLite value to JSON. Blobs are rendered as their UTF-8 text
+/// when possible, since payload columns are stored as JSONB or text.
+pub fn sql_to_json(value: SqlValue) -> Value {
+    match value {
+        SqlValue::Null => Value::Null,
+        SqlValue::Integer(number) => Value::from(number),
+        SqlValue::Real(number) => Value::from(number),
+        SqlValue::Text(text) => Value::String(text),
+        SqlValue::Blob(bytes) => match String::from_utf8(bytes) {
+            Ok(text) => Value::String(text),
+            Err(err) => Value::String(format!("<{} bytes>", err.into_bytes().len())),
+        },
+    }
+}
@@ -0,0 +1,231 @@
+use serde_json::Value;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+use std::sync::Once;
+use uuid::Uuid;
+
+static BUILD_EXTENSION: Once = Once::new();
+
+fn extension_path() -> PathBuf {
+    let manifest_dir = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let workspace_root = manifest_dir.parent().expect("workspace root not found");
+    let lib_name = format!("libabsurd{}", std::env::consts::DLL_SUFFIX);
+    workspace_root.join("target").join("debug").join(lib_name)
+}
+
+fn build_extension() {
+    BUILD_EXTENSION.call_once(|| {
+        let manifest_dir = Path::new(env!("CARGO_MANIFEST_DIR"));
+        let cargo = std::env::var("CARGO").unwrap_or_else(|_| "cargo".to_string());
+        let status = Command::new(cargo)
+            .args([
+                "build",
+                "--manifest-path",
+                manifest_dir
+                    .join("..")
+                    .join("absurd-sqlite-extension")
+                    .join("Cargo.toml")
+                    .to_str()
+                    .expect("manifest path"),
+            ])
+            .status()
+            .expect("run cargo build");
+        if !status.success() {
+            panic!("cargo build failed");
+        }
+    });
+}
+
+struct Cli {
+    db_path: PathBuf,
+}
+
+impl Cli {
+    fn new() -> Self {
+        build_extension();
+        let cli = Self {
+            db_path: std::env::temp_dir().join(format!("absurd-cli-{}.db", Uuid::new_v4())),
+        };
+        cli.json(&["migrations", "apply"]);
+        cli
+    }
+
+    fn run(&self, args: &[&str], stdin: Option<&str>) -> Output {
+        use std::io::Write;
+
+        let mut command = Command::new(env!("CARGO_BIN_EXE_absurd"));
+        command
+            .arg("--db")
+            .arg(&self.db_path)
+            .arg("--extension")
+            .arg(extension_path())
+            .args(args)
+            .stdin(std::process::Stdio::piped())
+            .stdout(std::process::Stdio::piped())
+            .stderr(std::process::Stdio::piped());
+        let mut child = command.spawn().expect("spawn absurd");
+        child
+            .stdin
+            .take()
+            .expect("stdin")
+            .write_all(stdin.unwrap_or_default().as_bytes())
+            .expect("write stdin");
+        child.wait_with_output().expect("wait for absurd")
+    }
+
+    fn json(&self, args: &[&str]) -> Value {
+        let mut full_args = vec!["--output", "json"];
+        full_args.extend_from_slice(args);
+        let output = self.run(&full_args, None);
+        assert!(
+            output.status.success(),
+            "absurd {:?} failed: {}",
+            args,
+            String::from_utf8_lossy(&output.stderr)
+        );
+        serde_json::from_slice(&output.stdout).expect("json output")
+    }
+}
+
+impl Drop for Cli {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.db_path);
+    }
+}
+
+#[test]
+fn test_queue_and_task_lifecycle() {
+    let cli = Cli::new();
+
+    cli.json(&["queue", "create", "alpha"]);
+    cli.json(&["queue", "pause", "alpha"]);
+    let queues = cli.json(&["queue", "list"]);
+    assert_eq!(queues[0]["queue_name"], "alpha");
+    assert_eq!(queues[0]["paused"], 1);
+    cli.json(&["queue", "resume", "alpha"]);
+    assert_eq!(cli.json(&["queue", "list"])[0]["paused"], 0);
+
+    let spawned = cli.json(&["task", "spawn", "alpha", "demo", "--params", r#"{"n":1}"#]);
+    let task_id = spawned["task_id"].as_str().expect("task_id").to_string();
+    assert_eq!(spawned["attempt"], 1);
+
+    let listed = cli.json(&["task", "list", "alpha", "--state", "pending"]);
+    assert_eq!(listed.as_array().map(Vec::len), Some(1));
+
+    cli.json(&["task", "cancel", "alpha", &task_id]);
+    let retried = cli.json(&["task", "retry", "alpha", &task_id]);
+    assert!(retried["run_id"].is_string());
+
+    let shown = cli.json(&["task", "show", "alpha", &task_id]);
+    assert_eq!(shown["state"], "pending");
+    assert_eq!(shown["params"]["n"], 1);
+    let runs = shown["runs"].as_array().expect("runs");
+    assert_eq!(runs.len(), 2);
+    assert_eq!(runs[0]["state"], "cancelled");
+    assert_eq!(runs[1]["run_id"], retried["run_id"]);
+
+    let stats = cli.json(&["stats", "alpha"]);
+    assert_eq!(stats[0]["total"], 1);
+    assert_eq!(stats[0]["pending"], 1);
+
+    cli.json(&[
+        "event",
+        "emit",
+        "alpha",
+        "ready",
+        "--payload",
+        r#"{"ok":true}"#,
+    ]);
+    let cleaned = cli.json(&["cleanup", "tasks", "alpha", "--ttl", "7d"]);
+    assert_eq!(cleaned["deleted"], 0);
+
+    let status = cli.json(&["migrations", "status"]);
+    let migrations = status.as_array().expect("migrations");
+    assert!(!migrations.is_empty());
+    assert!(migrations.iter().all(|migration| migration["applied"] == 1));
+
+    cli.json(&["queue", "drop", "alpha"]);
+    assert_eq!(cli.json(&["queue", "list"]), Value::Array(vec![]));
+}
+
+#[test]
+fn test_task_show_decodes_stored_payloads() {
+    let cli = Cli::new();
+    cli.json(&["queue", "create", "alpha"]);
+    // The CLI has no settings command; write the setting the extension reads.
+    rusqlite::Connection::open(&cli.db_path)
+        .expect("open db")
+        .execute(
+            "insert into absurd_queue_settings (queue_name, key, value)
+             values ('alpha', 'compression', 'zstd')",
+            [],
+        )
+        .expect("enable compression");
+
+    let note = "x".repeat(256);
+    let params = serde_json::json!({ "note": note }).to_string();
+    let spawned = cli.json(&["task", "spawn", "alpha", "demo", "--params", &params]);
+    let shown = cli.json(&[
+        "task",
+        "show",
+        "alpha",
+        spawned["task_id"].as_str().expect("task_id"),
+    ]);
+    assert_eq!(shown["params"]["note"], note.as_str());
+
+    let spawned = cli.json(&[
+        "task",
+        "spawn",
+        "alpha",
+        "demo",
+        "--params",
+        r#"{"$absurd_tag":1}"#,
+    ]);
+    let shown = cli.json(&[
+        "task",
+        "show",
+        "alpha",
+        spawned["task_id"].as_str().expect("task_id"),
+    ]);
+    assert_eq!(shown["params"], serde_json::json!({ "$absurd_tag": 1 }));
+}
+
+#[test]
+fn test_export_import_round_trip() {
+    let cli = Cli::new();
+    cli.json(&["queue", "create", "alpha"]);
+    cli.json(&["task", "spawn", "alpha", "demo"]);
+
+    let exported = cli.run(&["export", "alpha"], None);
+    assert!(exported.status.success());
+    let jsonl = String::from_utf8(exported.stdout).expect("utf-8 export");
+    assert!(jsonl.starts_with(r#"{"exported_at":"#));
+
+    cli.json(&["queue", "create", "beta"]);
+    let output = cli.run(
+        &["--output", "json", "import", "beta", "--remap-ids"],
+        Some(&jsonl),
+    );
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let imported: Value = serde_json::from_slice(&output.stdout).expect("json output");
+    assert!(imported["imported_count"].as_i64().unwrap_or(0) > 0);
+
+    let stats = cli.json(&["stats", "beta"]);
+    assert_eq!(stats[0]["total"], 1);
+}
+
+#[test]
+fn test_errors_exit_non_zero() {
+    let cli = Cli::new();
+    let output = cli.run(&["task", "retry", "missing", "nope"], None);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).starts_with("error: "));
+
+    let output = cli.run(&["cleanup", "events", "alpha", "--ttl", "soon"], None);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("invalid duration"));
+}
@@ -0,0 +1,288 @@
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fmt;
+use std::net::IpAddr;
+use std::str::FromStr;
+use subtle::ConstantTimeEq;
+
+/// Procedures that only look at the database or the desktop app's worker,
+/// and so are open to the read scope once authentication is configured.
+/// Anything not listed here, including procedures added later, needs admin.
+/// `exportQueue` is deliberately absent: it returns every payload verbatim.
+pub const READ_PROCEDURES: &[&str] = &[
+    "getOverviewMetrics",
+    "getQueueMetrics",
+    "getTaskRuns",
+    "getTaskRunsForQueue",
+    "getTaskRunsPage",
+    "getTaskHistory",
+    "getTaskInfo",
+    "getTaskCheckpointStatuses",
+    "getTaskCheckpoints",
+    "getTaskCheckpointHistory",
+    "getQueueNames",
+    "getQueueSummaries",
+    "getTaskNameOptions",
+    "getEventFilterDefaults",
+    "getEvents",
+    "getFilteredEvents",
+    "getSettingsInfo",
+    "getWorkers",
+    "getTaskTypes",
+    "getMigrations",
+    "getWorkerStatus",
+    "getWorkerLogs",
+];
+
+/// Procedures answered without credentials, so clients can find the API.
+const PUBLIC_PROCEDURES: &[&str] = &["health"];
+
+/// What a caller may do. `Admin` implies `Read`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Scope {
+    Read,
+    Admin,
+}
+
+impl Scope {
+    /// The scope needed to call `procedure`.
+    pub fn required_for(procedure: &str) -> Scope {
+        if READ_PROCEDURES.contains(&procedure) {
+            Scope::Read
+        } else {
+            Scope::Admin
+        }
+    }
+}
+
+impl fmt::Display for Scope {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Scope::Read => write!(f, "read"),
+            Scope::Admin => write!(f, "admin"),
+        }
+    }
+}
+
+impl FromStr for Scope {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "read" => Ok(Scope::Read),
+            "admin" => Ok(Scope::Admin),
+            other => Err(format!("unknown scope {:?}; use read or admin", other)),
+        }
+    }
+}
+
+/// A bearer token. Only the SHA-256 of the secret is kept, so the secret is
+/// shown once when the token is created and cannot be read back.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ApiToken {
+    pub name: String,
+    pub scope: Scope,
+    pub token_hash: String,
+    #[serde(default)]
+    pub created_at: Option<i64>,
+}
+
+impl ApiToken {
+    pub fn from_secret(name: &str, scope: Scope, secret: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            scope,
+            token_hash: hash_secret(secret),
+            created_at: None,
+        }
+    }
+
+    fn matches(&self, secret: &str) -> bool {
+        self.token_hash
+            .as_bytes()
+            .ct_eq(hash_secret(secret).as_bytes())
+            .into()
+    }
+}
+
+/// Generate a new random token secret.
+pub fn generate_secret() -> anyhow::Result<String> {
+    let mut bytes = [0u8; 32];
+    getrandom::fill(&mut bytes).map_err(|err| anyhow::anyhow!("generate token: {}", err))?;
+    Ok(format!("absurd_{}", hex::encode(bytes)))
+}
+
+fn hash_secret(secret: &str) -> String {
+    hex::encode(Sha256::digest(secret.as_bytes()))
+}
+
+/// Who may call the API. With no tokens and no client certificates every
+/// caller is an admin, which is only allowed on a loopback address.
+#[derive(Clone, Debug, Default)]
+pub struct AuthConfig {
+    pub tokens: Vec<ApiToken>,
+    /// Scope granted to connections that presented a client certificate
+    /// signed by the configured CA. `None` when mTLS is not in use.
+    pub client_cert_scope: Option<Scope>,
+}
+
+/// Why a request was turned away.
+#[derive(Debug, PartialEq, Eq)]
+pub enum AuthError {
+    /// No usable credentials.
+    Unauthorized,
+    /// Valid credentials without the scope the procedure needs.
+    Forbidden { required: Scope },
+}
+
+impl AuthConfig {
+    pub fn is_enabled(&self) -> bool {
+        !self.tokens.is_empty() || self.client_cert_scope.is_some()
+    }
+
+    /// Refuse to listen beyond loopback without any credentials configured.
+    pub fn check_bind(&self, ip: IpAddr) -> Result<(), String> {
+        if ip.is_loopback() || self.is_enabled() {
+            return Ok(());
+        }
+        Err(format!(
+            "refusing to listen on {} without authentication; add a token or a client CA, or bind to 127.0.0.1",
+            ip
+        ))
+    }
+
+    /// Check a call to `procedure` given the request's bearer token, if any,
+    /// and whether the connection presented a verified client certificate.
+    /// The caller gets the broadest scope among its credentials.
+    pub fn authorize(
+        &self,
+        procedure: &str,
+        bearer: Option<&str>,
+        client_cert: bool,
+    ) -> Result<(), AuthError> {
+        if !self.is_enabled() || PUBLIC_PROCEDURES.contains(&procedure) {
+            return Ok(());
+        }
+
+        let token_scope = bearer.and_then(|secret| {
+            self.tokens
+                .iter()
+                .filter(|token| token.matches(secret))
+                .map(|token| token.scope)
+                .max()
+        });
+        let cert_scope = self.client_cert_scope.filter(|_| client_cert);
+        let granted = token_scope.max(cert_scope).ok_or(AuthError::Unauthorized)?;
+
+        let required = Scope::required_for(procedure);
+        if granted < required {
+            return Err(AuthError::Forbidden { required });
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> AuthConfig {
+        AuthConfig {
+            tokens: vec![
+                ApiToken::from_secret("dashboard", Scope::Read, "read-secret"),
+                ApiToken::from_secret("ops", Scope::Admin, "admin-secret"),
+            ],
+            client_cert_scope: None,
+        }
+    }
+
+    #[test]
+    fn open_config_allows_everything() {
+        let config = AuthConfig::default();
+        assert_eq!(config.authorize("createQueue", None, false), Ok(()));
+    }
+
+    #[test]
+    fn tokens_grant_their_scope() {
+        let config = config();
+        assert_eq!(config.authorize("health", None, false), Ok(()));
+        assert_eq!(
+            config.authorize("getQueueNames", None, false),
+            Err(AuthError::Unauthorized)
+        );
+        assert_eq!(
+            config.authorize("getQueueNames", Some("wrong"), false),
+            Err(AuthError::Unauthorized)
+        );
+        assert_eq!(
+            config.authorize("getQueueNames", Some("read-secret"), false),
+            Ok(())
+        );
+        assert_eq!(
+            config.authorize("createQueue", Some("read-secret"), false),
+            Err(AuthError::Forbidden {
+                required: Scope::Admin
+            })
+        );
+        assert_eq!(
+            config.authorize("startWorker", Some("admin-secret"), false),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn unlisted_procedures_need_admin() {
+        let config = config();
+        for procedure in ["exportQueue", "someFutureProcedure"] {
+            assert_eq!(
+                config.authorize(procedure, Some("read-secret"), false),
+                Err(AuthError::Forbidden {
+                    required: Scope::Admin
+                })
+            );
+            assert_eq!(
+                config.authorize(procedure, Some("admin-secret"), false),
+                Ok(())
+            );
+        }
+    }
+
+    #[test]
+    fn client_certificates_grant_the_configured_scope() {
+        let config = AuthConfig {
+            tokens: config().tokens,
+            client_cert_scope: Some(Scope::Read),
+        };
+        assert_eq!(config.authorize("getQueueNames", None, true), Ok(()));
+        assert_eq!(
+            config.authorize("cleanupQueue", None, true),
+            Err(AuthError::Forbidden {
+                required: Scope::Admin
+            })
+        );
+        assert_eq!(
+            config.authorize("cleanupQueue", Some("admin-secret"), true),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn non_loopback_binds_need_credentials() {
+        let open = AuthConfig::default();
+        assert!(open.check_bind("127.0.0.1".parse().unwrap()).is_ok());
+        assert!(open.check_bind("::1".parse().unwrap()).is_ok());
+        assert!(open.check_bind("0.0.0.0".parse().unwrap()).is_err());
+        assert!(config().check_bind("0.0.0.0".parse().unwrap()).is_ok());
+    }
+
+    #[test]
+    fn generated_secrets_are_distinct_and_hash_stably() {
+        let first = generate_secret().expect("secret");
+        let second = generate_secret().expect("secret");
+        assert_ne!(first, second);
+        assert!(ApiToken::from_secret("a", Scope::Read, &first).matches(&first));
+        assert!(!ApiToken::from_secret("a", Scope::Read, &first).matches(&second));
+    }
+}
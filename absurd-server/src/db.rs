@@ -0,0 +1,94 @@
+use anyhow::{Context, Result};
+use rusqlite::Connection;
+use std::path::{Path, PathBuf};
+
+/// Open `db_path` and load the Absurd extension from `extension_path`.
+pub fn open(db_path: &str, extension_path: &Path) -> Result<Connection> {
+    let conn = Connection::open(db_path)?;
+
+    log::info!("using SQLite version: {}", rusqlite::version());
+    log::debug!("Loading SQLite extension from {:?}", extension_path);
+    // Safety: extension from own build
+    unsafe {
+        if let Err(err) = conn
+            .load_extension_enable()
+            .context("enable extension loading")
+        {
+            log::error!("Failed to enable SQLite extension loading: {:#}", err);
+            return Err(err);
+        }
+        // remove the extension part from the path
+        let extension_path_no_ext = extension_path.with_extension("");
+        if let Err(err) = conn
+            .load_extension(
+                extension_path_no_ext.to_string_lossy().as_ref(),
+                Some("sqlite3_absurd_init"),
+            )
+            .context("load SQLite extension")
+        {
+            log::error!("Failed to load SQLite extension: {:#}", err);
+            return Err(err);
+        }
+        if let Err(err) = conn
+            .load_extension_disable()
+            .context("disable extension loading")
+        {
+            log::error!("Failed to disable SQLite extension loading: {:#}", err);
+            return Err(err);
+        }
+    }
+    log::debug!("SQLite extension loaded successfully");
+
+    Ok(conn)
+}
+
+/// Find the extension for a standalone binary: next to the executable, in a
+/// sibling `lib` directory, or (in debug builds) in the workspace `target`.
+pub fn resolve_extension_path() -> Option<PathBuf> {
+    let lib_name = extension_lib_name();
+
+    if let Some(exe_dir) = std::env::current_exe()
+        .ok()
+        .and_then(|exe| exe.parent().map(Path::to_path_buf))
+    {
+        let candidates = [
+            exe_dir.join(&lib_name),
+            exe_dir.join("..").join("lib").join(&lib_name),
+        ];
+        for path in candidates {
+            log::debug!("Checking SQLite extension at {}", path.display());
+            if path.exists() {
+                return Some(path);
+            }
+        }
+    }
+
+    #[cfg(debug_assertions)]
+    {
+        let manifest_dir = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        let workspace_root = manifest_dir.parent().unwrap_or(&manifest_dir);
+        let target_dir = workspace_root.join("target");
+        let candidates = [
+            target_dir.join("debug").join(&lib_name),
+            target_dir.join("release").join(&lib_name),
+        ];
+        for path in candidates {
+            log::debug!("Checking SQLite extension at {}", path.display());
+            if path.exists() {
+                return Some(path);
+            }
+        }
+    }
+
+    None
+}
+
+pub fn extension_lib_name() -> String {
+    if cfg!(target_os = "windows") {
+        "absurd.dll".to_string()
+    } else if cfg!(target_os = "macos") {
+        "libabsurd.dylib".to_string()
+    } else {
+        "libabsurd.so".to_string()
+    }
+}
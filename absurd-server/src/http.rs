@@ -0,0 +1,426 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::{header::AUTHORIZATION, HeaderMap, StatusCode},
+    routing::post,
+    Extension, Json, Router,
+};
+use hyper_util::rt::{TokioExecutor, TokioIo};
+use hyper_util::service::TowerToHyperService;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::future::Future;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::net::TcpListener;
+use tower_http::cors::CorsLayer;
+
+use crate::auth::{AuthConfig, AuthError};
+use crate::procedures::{handle_procedure, ProcedureHost};
+
+type SharedHost = Arc<dyn ProcedureHost>;
+
+#[derive(Clone)]
+struct ApiState {
+    host: SharedHost,
+    auth: Arc<AuthConfig>,
+}
+
+/// Marks requests on a TLS connection whose client certificate was verified
+/// against the configured CA.
+#[derive(Clone, Copy, Debug)]
+pub struct ClientCertificate;
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TrpcRequest {
+    id: Option<Value>,
+    json: Option<Value>,
+    input: Option<Value>,
+}
+
+#[derive(Deserialize)]
+struct TrpcQuery {
+    input: Option<String>,
+    json: Option<String>,
+}
+
+/// Serve the data procedures tRPC-style under `/absurd-data/:procedure`, the
+/// shape the web UI's `trpc` provider expects.
+///
+/// CORS stays permissive unless client certificates are in use: bearer
+/// tokens must be sent explicitly, but a browser may attach a client
+/// certificate to cross-site requests on its own.
+pub fn router(host: SharedHost, auth: AuthConfig) -> Router {
+    let client_certs = auth.client_cert_scope.is_some();
+    let router = Router::new()
+        .route(
+            "/absurd-data/:procedure",
+            post(trpc_post_handler).get(trpc_get_handler),
+        )
+        .with_state(ApiState {
+            host,
+            auth: Arc::new(auth),
+        });
+    if client_certs {
+        router
+    } else {
+        router.layer(CorsLayer::permissive())
+    }
+}
+
+/// Serve `router` on `listener` until `shutdown` resolves, over TLS when a
+/// config is given.
+pub async fn serve(
+    listener: TcpListener,
+    router: Router,
+    tls: Option<Arc<rustls::ServerConfig>>,
+    shutdown: impl Future<Output = ()> + Send + 'static,
+) -> std::io::Result<()> {
+    let Some(tls) = tls else {
+        return axum::serve(listener, router)
+            .with_graceful_shutdown(shutdown)
+            .await;
+    };
+
+    let acceptor = tokio_rustls::TlsAcceptor::from(tls);
+    tokio::pin!(shutdown);
+    loop {
+        let (stream, peer) = tokio::select! {
+            accepted = listener.accept() => match accepted {
+                Ok(accepted) => accepted,
+                Err(err) => {
+                    log::warn!("accept failed: {}", err);
+                    tokio::time::sleep(Duration::from_secs(1)).await;
+                    continue;
+                }
+            },
+            _ = &mut shutdown => return Ok(()),
+        };
+
+        let acceptor = acceptor.clone();
+        let router = router.clone();
+        tokio::spawn(async move {
+            let stream = match acceptor.accept(stream).await {
+                Ok(stream) => stream,
+                Err(err) => {
+                    log::debug!("TLS handshake with {} failed: {}", peer, err);
+                    return;
+                }
+            };
+            let client_cert = stream
+                .get_ref()
+                .1
+                .peer_certificates()
+                .is_some_and(|certs| !certs.is_empty());
+            let app = if client_cert {
+                router.layer(Extension(ClientCertificate))
+            } else {
+                router
+            };
+            let result = hyper_util::server::conn::auto::Builder::new(TokioExecutor::new())
+                .serve_connection_with_upgrades(TokioIo::new(stream), TowerToHyperService::new(app))
+                .await;
+            if let Err(err) = result {
+                log::debug!("connection from {} failed: {}", peer, err);
+            }
+        });
+    }
+}
+
+async fn trpc_post_handler(
+    Path(procedure): Path<String>,
+    State(state): State<ApiState>,
+    headers: HeaderMap,
+    client_cert: Option<Extension<ClientCertificate>>,
+    Json(payload): Json<TrpcRequest>,
+) -> (StatusCode, Json<Value>) {
+    let id = payload.id.unwrap_or(Value::Null);
+    let input = payload.json.or(payload.input).unwrap_or(Value::Null);
+    execute_trpc(state, &headers, client_cert.is_some(), procedure, id, input).await
+}
+
+async fn trpc_get_handler(
+    Path(procedure): Path<String>,
+    State(state): State<ApiState>,
+    headers: HeaderMap,
+    client_cert: Option<Extension<ClientCertificate>>,
+    Query(query): Query<TrpcQuery>,
+) -> (StatusCode, Json<Value>) {
+    let input = query
+        .input
+        .or(query.json)
+        .and_then(|value| serde_json::from_str::<Value>(&value).ok())
+        .unwrap_or(Value::Null);
+    execute_trpc(
+        state,
+        &headers,
+        client_cert.is_some(),
+        procedure,
+        Value::Null,
+        input,
+    )
+    .await
+}
+
+async fn execute_trpc(
+    state: ApiState,
+    headers: &HeaderMap,
+    client_cert: bool,
+    procedure: String,
+    id: Value,
+    input: Value,
+) -> (StatusCode, Json<Value>) {
+    let bearer = headers
+        .get(AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .map(str::trim);
+    if let Err(err) = state.auth.authorize(&procedure, bearer, client_cert) {
+        let (status, code, message) = match err {
+            AuthError::Unauthorized => (
+                StatusCode::UNAUTHORIZED,
+                "UNAUTHORIZED",
+                "Missing or invalid credentials".to_string(),
+            ),
+            AuthError::Forbidden { required } => (
+                StatusCode::FORBIDDEN,
+                "FORBIDDEN",
+                format!("{} requires the {} scope", procedure, required),
+            ),
+        };
+        return (status, error_body(id, &message, code, status));
+    }
+
+    let host = state.host;
+    let result =
+        tokio::task::spawn_blocking(move || handle_procedure(host.as_ref(), &procedure, input))
+            .await
+            .map_err(|err| err.to_string())
+            .and_then(|result| result);
+
+    let body = match result {
+        Ok(data) => Json(json!({ "id": id, "result": { "data": data } })),
+        Err(message) => error_body(
+            id,
+            &message,
+            "INTERNAL_SERVER_ERROR",
+            StatusCode::INTERNAL_SERVER_ERROR,
+        ),
+    };
+
+    (StatusCode::OK, body)
+}
+
+fn error_body(id: Value, message: &str, code: &str, status: StatusCode) -> Json<Value> {
+    let rpc_code = match status {
+        StatusCode::UNAUTHORIZED => -32001,
+        StatusCode::FORBIDDEN => -32003,
+        _ => -32603,
+    };
+    Json(json!({
+        "id": id,
+        "error": {
+            "message": message,
+            "code": rpc_code,
+            "data": { "code": code, "httpStatus": status.as_u16() }
+        }
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auth::{ApiToken, Scope};
+    use axum::body::Body;
+    use axum::http::Request;
+    use rusqlite::Connection;
+    use tower::ServiceExt;
+
+    /// Serves from a schema-only database; the extension is not loaded, so
+    /// only procedures that query tables directly work.
+    struct SchemaHost {
+        db_path: String,
+    }
+
+    impl ProcedureHost for SchemaHost {
+        fn connect(&self) -> anyhow::Result<Connection> {
+            Ok(Connection::open(&self.db_path)?)
+        }
+
+        fn db_path(&self) -> anyhow::Result<String> {
+            Ok(self.db_path.clone())
+        }
+
+        fn handle_host_procedure(
+            &self,
+            procedure: &str,
+            _input: Value,
+        ) -> Option<Result<Value, String>> {
+            (procedure == "hostOnly").then(|| Ok(json!("from host")))
+        }
+    }
+
+    impl Drop for SchemaHost {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_file(&self.db_path);
+        }
+    }
+
+    fn schema_host(name: &str) -> SharedHost {
+        let db_path =
+            std::env::temp_dir().join(format!("absurd-server-{}-{}.db", name, std::process::id()));
+        let _ = std::fs::remove_file(&db_path);
+        let conn = Connection::open(&db_path).expect("open db");
+        conn.execute_batch(include_str!(
+            "../../absurd-sqlite-extension/migrations/1.sql"
+        ))
+        .expect("apply schema");
+        conn.execute(
+            "insert into absurd_queues (queue_name, created_at) values ('alpha', 1)",
+            [],
+        )
+        .expect("seed queue");
+        Arc::new(SchemaHost {
+            db_path: db_path.to_string_lossy().to_string(),
+        })
+    }
+
+    async fn call_with(
+        host: SharedHost,
+        auth: AuthConfig,
+        request: Request<Body>,
+    ) -> (StatusCode, Value) {
+        let response = router(host, auth).oneshot(request).await.expect("response");
+        let status = response.status();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .expect("body");
+        (status, serde_json::from_slice(&body).expect("json body"))
+    }
+
+    async fn call(host: SharedHost, request: Request<Body>) -> Value {
+        call_with(host, AuthConfig::default(), request).await.1
+    }
+
+    #[tokio::test]
+    async fn serves_procedures_over_get_and_post() {
+        let host = schema_host("procedures");
+
+        let body = call(
+            host.clone(),
+            Request::get("/absurd-data/getQueueNames")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await;
+        assert_eq!(body["result"]["data"], json!(["alpha"]));
+
+        let body = call(
+            host.clone(),
+            Request::post("/absurd-data/health")
+                .header("content-type", "application/json")
+                .body(Body::from(r#"{"id":7}"#))
+                .unwrap(),
+        )
+        .await;
+        assert_eq!(body["id"], 7);
+        assert_eq!(body["result"]["data"]["ok"], true);
+
+        let body = call(
+            host,
+            Request::get("/absurd-data/hostOnly")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await;
+        assert_eq!(body["result"]["data"], "from host");
+    }
+
+    #[tokio::test]
+    async fn reports_unknown_procedures_as_errors() {
+        let body = call(
+            schema_host("unknown"),
+            Request::get("/absurd-data/nope")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await;
+        assert_eq!(body["error"]["message"], "Unknown procedure: nope");
+        assert_eq!(body["error"]["data"]["code"], "INTERNAL_SERVER_ERROR");
+    }
+
+    #[tokio::test]
+    async fn enforces_token_scopes() {
+        let host = schema_host("auth");
+        let auth = AuthConfig {
+            tokens: vec![
+                ApiToken::from_secret("viewer", Scope::Read, "read-secret"),
+                ApiToken::from_secret("ops", Scope::Admin, "admin-secret"),
+            ],
+            client_cert_scope: None,
+        };
+        let request = |procedure: &str, token: Option<&str>| {
+            let mut builder = Request::post(format!("/absurd-data/{}", procedure))
+                .header("content-type", "application/json");
+            if let Some(token) = token {
+                builder = builder.header("authorization", format!("Bearer {}", token));
+            }
+            builder
+                .body(Body::from(r#"{"json":{"queueName":"beta"}}"#))
+                .unwrap()
+        };
+
+        let (status, _) = call_with(host.clone(), auth.clone(), request("health", None)).await;
+        assert_eq!(status, StatusCode::OK);
+
+        let (status, body) =
+            call_with(host.clone(), auth.clone(), request("getQueueNames", None)).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        assert_eq!(body["error"]["data"]["code"], "UNAUTHORIZED");
+
+        let (status, body) = call_with(
+            host.clone(),
+            auth.clone(),
+            request("getQueueNames", Some("read-secret")),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["result"]["data"], json!(["alpha"]));
+
+        let (status, body) = call_with(
+            host.clone(),
+            auth.clone(),
+            request("createQueue", Some("read-secret")),
+        )
+        .await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        assert_eq!(
+            body["error"]["message"],
+            "createQueue requires the admin scope"
+        );
+
+        let (status, body) = call_with(
+            host.clone(),
+            auth.clone(),
+            request("exportQueue", Some("read-secret")),
+        )
+        .await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        assert_eq!(
+            body["error"]["message"],
+            "exportQueue requires the admin scope"
+        );
+
+        let (status, body) = call_with(
+            host.clone(),
+            auth.clone(),
+            request("createQueue", Some("admin-secret")),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(body.get("error").is_none());
+
+        let (_, body) = call_with(host, auth, request("getQueueNames", Some("admin-secret"))).await;
+        assert_eq!(body["result"]["data"], json!(["alpha", "beta"]));
+    }
+}
@@ -0,0 +1,12 @@
+//! Data procedures behind the Absurd web UI, usable without Tauri.
+//!
+//! The desktop app serves these through its commands and dev API; the
+//! `absurd-server` binary serves them over HTTP for a database on a headless
+//! machine.
+
+pub mod auth;
+pub mod db;
+pub mod http;
+pub mod procedures;
+pub mod provider;
+pub mod tls;
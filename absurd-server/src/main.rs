@@ -0,0 +1,138 @@
+use absurd_server::auth::{ApiToken, AuthConfig, Scope};
+use absurd_server::procedures::ProcedureHost;
+use absurd_server::tls::{self, TlsOptions};
+use absurd_server::{db, http};
+use anyhow::Result;
+use clap::Parser;
+use rusqlite::Connection;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+/// Serve the Absurd data procedures over HTTP for one SQLite database.
+#[derive(Debug, Parser)]
+#[command(name = "absurd-server", version, about)]
+struct Args {
+    /// Path to the SQLite database.
+    #[arg(long, env = "ABSURD_DB")]
+    db: PathBuf,
+
+    /// Path to the Absurd SQLite extension. Defaults to the library next to
+    /// this binary.
+    #[arg(long, env = "ABSURD_EXTENSION")]
+    extension: Option<PathBuf>,
+
+    /// Address to listen on. Addresses other than loopback require a token
+    /// or a client CA.
+    #[arg(long, env = "ABSURD_BIND", default_value = "127.0.0.1:11223")]
+    bind: SocketAddr,
+
+    /// Accept a bearer token, as `read:<secret>` or `admin:<secret>`. May be
+    /// repeated; the environment variable takes a comma-separated list.
+    #[arg(long = "token", env = "ABSURD_API_TOKENS", value_delimiter = ',', value_parser = parse_token, hide_env_values = true)]
+    tokens: Vec<(Scope, String)>,
+
+    /// PEM certificate chain to serve HTTPS with.
+    #[arg(long, env = "ABSURD_TLS_CERT", requires = "tls_key")]
+    tls_cert: Option<PathBuf>,
+
+    /// PEM private key for --tls-cert.
+    #[arg(long, env = "ABSURD_TLS_KEY", requires = "tls_cert")]
+    tls_key: Option<PathBuf>,
+
+    /// Require client certificates signed by this PEM CA (mTLS).
+    #[arg(long, env = "ABSURD_CLIENT_CA", requires = "tls_cert")]
+    client_ca: Option<PathBuf>,
+
+    /// Scope granted to clients with a verified certificate.
+    #[arg(long, env = "ABSURD_CLIENT_CERT_SCOPE", default_value = "read")]
+    client_cert_scope: Scope,
+}
+
+fn parse_token(value: &str) -> Result<(Scope, String), String> {
+    let (scope, secret) = value
+        .split_once(':')
+        .ok_or_else(|| "expected <scope>:<secret>".to_string())?;
+    if secret.is_empty() {
+        return Err("token secret is empty".to_string());
+    }
+    Ok((scope.parse()?, secret.to_string()))
+}
+
+struct ServerHost {
+    db_path: String,
+    extension_path: PathBuf,
+}
+
+impl ProcedureHost for ServerHost {
+    fn connect(&self) -> Result<Connection> {
+        db::open(&self.db_path, &self.extension_path)
+    }
+
+    fn db_path(&self) -> Result<String> {
+        Ok(self.db_path.clone())
+    }
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info")).init();
+    let args = Args::parse();
+
+    let extension_path = match args.extension {
+        Some(path) => path,
+        None => db::resolve_extension_path().ok_or_else(|| {
+            anyhow::anyhow!("SQLite extension not found; pass --extension or set ABSURD_EXTENSION")
+        })?,
+    };
+    let host = ServerHost {
+        db_path: args.db.to_string_lossy().to_string(),
+        extension_path,
+    };
+    // Fail at startup rather than on the first request if the database or
+    // extension cannot be opened.
+    host.connect()?;
+
+    let auth = AuthConfig {
+        tokens: args
+            .tokens
+            .iter()
+            .enumerate()
+            .map(|(idx, (scope, secret))| {
+                ApiToken::from_secret(&format!("token-{}", idx + 1), *scope, secret)
+            })
+            .collect(),
+        client_cert_scope: args.client_ca.as_ref().map(|_| args.client_cert_scope),
+    };
+    auth.check_bind(args.bind.ip())
+        .map_err(anyhow::Error::msg)?;
+
+    let tls = match (args.tls_cert, args.tls_key) {
+        (Some(cert_path), Some(key_path)) => Some(tls::server_config(&TlsOptions {
+            cert_path,
+            key_path,
+            client_ca_path: args.client_ca,
+        })?),
+        _ => None,
+    };
+
+    let listener = tokio::net::TcpListener::bind(args.bind).await?;
+    log::info!(
+        "serving {} on {}://{}/absurd-data ({})",
+        host.db_path,
+        if tls.is_some() { "https" } else { "http" },
+        listener.local_addr()?,
+        if auth.is_enabled() {
+            "authentication required"
+        } else {
+            "no authentication"
+        }
+    );
+
+    let router = http::router(Arc::new(host), auth);
+    http::serve(listener, router, tls, async {
+        let _ = tokio::signal::ctrl_c().await;
+    })
+    .await?;
+    Ok(())
+}
@@ -0,0 +1,276 @@
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use crate::provider::{
+    CleanupQueueOptions, CleanupTarget, DataProvider, EventFilters, ImportQueueOptions,
+    TaskRunFilters,
+};
+
+/// Where the data procedures get their database from, plus any procedures
+/// only one embedding serves, such as the desktop app's worker controls.
+pub trait ProcedureHost: Send + Sync {
+    /// Open a connection with the Absurd extension loaded.
+    fn connect(&self) -> anyhow::Result<rusqlite::Connection>;
+
+    /// Path of the database file, reported by `getSettingsInfo`.
+    fn db_path(&self) -> anyhow::Result<String>;
+
+    /// Handle a procedure outside the shared set. `None` means unknown.
+    fn handle_host_procedure(
+        &self,
+        _procedure: &str,
+        _input: Value,
+    ) -> Option<Result<Value, String>> {
+        None
+    }
+}
+
+/// Run a data procedure by name. Procedures outside the shared set are
+/// offered to the host before being rejected.
+pub fn handle_procedure(
+    host: &dyn ProcedureHost,
+    procedure: &str,
+    input: Value,
+) -> Result<Value, String> {
+    match procedure {
+        "health" => Ok(json!({ "ok": true })),
+        "getOverviewMetrics" => with_provider(host, |provider| {
+            let metrics = provider.get_overview_metrics()?;
+            Ok(serde_json::to_value(metrics)?)
+        }),
+        "getQueueMetrics" => with_provider(host, |provider| {
+            let metrics = provider.get_queue_metrics()?;
+            Ok(serde_json::to_value(metrics)?)
+        }),
+        "getTaskRuns" => with_provider(host, |provider| {
+            let runs = provider.get_task_runs()?;
+            Ok(serde_json::to_value(runs)?)
+        }),
+        "getTaskRunsForQueue" => {
+            let payload: QueueNameInput = parse_input(input)?;
+            with_provider(host, |provider| {
+                let runs = provider.get_task_runs_for_queue(&payload.queue_name)?;
+                Ok(serde_json::to_value(runs)?)
+            })
+        }
+        "getTaskRunsPage" => {
+            let payload: TaskRunFilters = parse_input(input)?;
+            with_provider(host, |provider| {
+                let page = provider.get_task_runs_page(payload)?;
+                Ok(serde_json::to_value(page)?)
+            })
+        }
+        "getTaskHistory" => {
+            let payload: TaskIdInput = parse_input(input)?;
+            with_provider(host, |provider| {
+                let runs = provider.get_task_history(&payload.task_id)?;
+                Ok(serde_json::to_value(runs)?)
+            })
+        }
+        "getTaskInfo" => {
+            let payload: TaskIdInput = parse_input(input)?;
+            with_provider(host, |provider| {
+                let info = provider.get_task_info(&payload.task_id)?;
+                Ok(serde_json::to_value(info)?)
+            })
+        }
+        "getTaskCheckpointStatuses" => {
+            let payload: TaskIdInput = parse_input(input)?;
+            with_provider(host, |provider| {
+                let statuses = provider.get_task_checkpoint_statuses(&payload.task_id)?;
+                Ok(serde_json::to_value(statuses)?)
+            })
+        }
+        "getTaskCheckpoints" => {
+            let payload: TaskIdInput = parse_input(input)?;
+            with_provider(host, |provider| {
+                let checkpoints = provider.get_task_checkpoints(&payload.task_id)?;
+                Ok(serde_json::to_value(checkpoints)?)
+            })
+        }
+        "getTaskCheckpointHistory" => {
+            let payload: CheckpointHistoryInput = parse_input(input)?;
+            with_provider(host, |provider| {
+                let versions = provider
+                    .get_task_checkpoint_history(&payload.task_id, &payload.checkpoint_name)?;
+                Ok(serde_json::to_value(versions)?)
+            })
+        }
+        "getQueueNames" => with_provider(host, |provider| {
+            let names = provider.get_queue_names()?;
+            Ok(serde_json::to_value(names)?)
+        }),
+        "getQueueSummaries" => with_provider(host, |provider| {
+            let summaries = provider.get_queue_summaries()?;
+            Ok(serde_json::to_value(summaries)?)
+        }),
+        "getTaskNameOptions" => {
+            let payload: OptionalQueueNameInput = parse_optional_input(input)?;
+            with_provider(host, |provider| {
+                let names = provider.get_task_name_options(payload.queue_name.as_deref())?;
+                Ok(serde_json::to_value(names)?)
+            })
+        }
+        "createQueue" => {
+            let payload: QueueNameInput = parse_input(input)?;
+            with_provider(host, |provider| {
+                provider.create_queue(&payload.queue_name)?;
+                Ok(Value::Null)
+            })
+        }
+        "cleanupQueue" => {
+            let payload: CleanupQueueInput = parse_input(input)?;
+            with_provider(host, |provider| {
+                let result = provider.cleanup_queue(payload.into())?;
+                Ok(serde_json::to_value(result)?)
+            })
+        }
+        "exportQueue" => {
+            let payload: QueueNameInput = parse_input(input)?;
+            with_provider(host, |provider| {
+                let export = provider.export_queue(&payload.queue_name)?;
+                Ok(serde_json::to_value(export)?)
+            })
+        }
+        "importQueue" => {
+            let payload: ImportQueueOptions = parse_input(input)?;
+            with_provider(host, |provider| {
+                let result = provider.import_queue(payload)?;
+                Ok(serde_json::to_value(result)?)
+            })
+        }
+        "getEventFilterDefaults" => {
+            let payload: OptionalQueueNameInput = parse_optional_input(input)?;
+            with_provider(host, |provider| {
+                let defaults = provider.get_event_filter_defaults(payload.queue_name.as_deref())?;
+                Ok(serde_json::to_value(defaults)?)
+            })
+        }
+        "getEvents" => with_provider(host, |provider| {
+            let events = provider.get_events()?;
+            Ok(serde_json::to_value(events)?)
+        }),
+        "getFilteredEvents" => {
+            let payload: EventFilters = parse_input(input)?;
+            with_provider(host, |provider| {
+                let events = provider.get_filtered_events(Some(payload))?;
+                Ok(serde_json::to_value(events)?)
+            })
+        }
+        "getSettingsInfo" => {
+            let db_path = host.db_path().map_err(|err| err.to_string())?;
+            with_provider(host, |provider| {
+                let info = provider.get_settings_info(db_path)?;
+                Ok(serde_json::to_value(info)?)
+            })
+        }
+        "getWorkers" => with_provider(host, |provider| {
+            let workers = provider.get_workers()?;
+            Ok(serde_json::to_value(workers)?)
+        }),
+        "getTaskTypes" => with_provider(host, |provider| {
+            let task_types = provider.get_task_types()?;
+            Ok(serde_json::to_value(task_types)?)
+        }),
+        "releaseWorkerClaims" => {
+            let payload: WorkerIdInput = parse_input(input)?;
+            with_provider(host, |provider| {
+                let result = provider.release_worker_claims(&payload.worker_id)?;
+                Ok(serde_json::to_value(result)?)
+            })
+        }
+        "getMigrations" => with_provider(host, |provider| {
+            let migrations = provider.get_migrations()?;
+            Ok(serde_json::to_value(migrations)?)
+        }),
+        "applyMigrationsAll" => with_provider(host, |provider| {
+            let applied = provider.apply_migrations_all()?;
+            Ok(serde_json::to_value(applied)?)
+        }),
+        "applyMigration" => {
+            let payload: MigrationInput = parse_input(input)?;
+            with_provider(host, |provider| {
+                let applied = provider.apply_migration(payload.migration_id)?;
+                Ok(serde_json::to_value(applied)?)
+            })
+        }
+        _ => host
+            .handle_host_procedure(procedure, input)
+            .unwrap_or_else(|| Err(format!("Unknown procedure: {}", procedure))),
+    }
+}
+
+fn with_provider<T>(
+    host: &dyn ProcedureHost,
+    f: impl FnOnce(&DataProvider) -> anyhow::Result<T>,
+) -> Result<T, String> {
+    let conn = host.connect().map_err(|err| err.to_string())?;
+    let provider = DataProvider::new(&conn);
+    f(&provider).map_err(|err| err.to_string())
+}
+
+fn parse_input<T: for<'de> Deserialize<'de>>(value: Value) -> Result<T, String> {
+    serde_json::from_value(value).map_err(|err| err.to_string())
+}
+
+fn parse_optional_input<T: for<'de> Deserialize<'de> + Default>(value: Value) -> Result<T, String> {
+    if value.is_null() {
+        return Ok(T::default());
+    }
+    serde_json::from_value(value).map_err(|err| err.to_string())
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct QueueNameInput {
+    queue_name: String,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct OptionalQueueNameInput {
+    queue_name: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TaskIdInput {
+    task_id: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CheckpointHistoryInput {
+    task_id: String,
+    checkpoint_name: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct WorkerIdInput {
+    worker_id: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct MigrationInput {
+    migration_id: i64,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CleanupQueueInput {
+    queue_name: String,
+    target: CleanupTarget,
+    ttl_seconds: i64,
+}
+
+impl From<CleanupQueueInput> for CleanupQueueOptions {
+    fn from(value: CleanupQueueInput) -> Self {
+        Self {
+            queue_name: value.queue_name,
+            target: value.target,
+            ttl_seconds: value.ttl_seconds,
+        }
+    }
+}
@@ -0,0 +1,1934 @@
+use anyhow::Result;
+use chrono::{TimeZone, Utc};
+use rusqlite::{params_from_iter, types::Value as SqlValue, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OverviewMetrics {
+    pub active_queues: i64,
+    pub messages_processed: i64,
+    pub messages_in_queue: i64,
+    pub visible_now: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QueueMetric {
+    pub name: String,
+    pub in_queue: i64,
+    pub visible: i64,
+    pub newest_age: String,
+    pub oldest_age: String,
+    pub total_seen: i64,
+    pub scraped_at: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum TaskStatus {
+    Running,
+    Failed,
+    Completed,
+    Sleeping,
+    Pending,
+    Cancelled,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TaskRun {
+    pub id: String,
+    pub name: String,
+    pub queue: String,
+    pub status: TaskStatus,
+    pub attempt: String,
+    pub attempt_number: i64,
+    pub run_id: String,
+    pub age: String,
+    pub started_at: String,
+    pub updated_at: String,
+    pub created_at_ms: i64,
+    pub updated_at_ms: i64,
+    pub created_ago: String,
+    pub updated_ago: String,
+    pub params_summary: String,
+    pub params_json: String,
+    pub final_state_json: Option<String>,
+    pub worker: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TaskInfo {
+    pub id: String,
+    pub name: String,
+    pub queue: String,
+    pub checkpoint_count: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CheckpointStatusCount {
+    pub status: String,
+    pub count: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TaskCheckpoint {
+    pub name: String,
+    pub status: String,
+    pub owner_run_id: Option<String>,
+    pub updated_at: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CheckpointVersion {
+    pub attempt: i64,
+    pub run_id: Option<String>,
+    pub status: String,
+    pub state_json: Option<String>,
+    pub updated_at: String,
+    pub changes: Vec<CheckpointChange>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CheckpointChange {
+    pub path: String,
+    pub kind: String,
+    pub before: Option<String>,
+    pub after: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TaskRunFilters {
+    pub queue_name: Option<String>,
+    pub status: Option<String>,
+    pub task_name: Option<String>,
+    pub search: Option<String>,
+    pub limit: Option<i64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TaskRunPage {
+    pub runs: Vec<TaskRun>,
+    pub total_count: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QueueSummaryStat {
+    pub label: String,
+    pub value: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QueueSummary {
+    pub name: String,
+    pub created_at: String,
+    pub age: String,
+    pub stats: Vec<QueueSummaryStat>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EventFilterDefaults {
+    pub event_name_placeholder: String,
+    pub queue_label: String,
+    pub queue_options: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EventEntry {
+    pub id: String,
+    pub name: String,
+    pub queue: String,
+    pub created_at: String,
+    pub payload_preview: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MigrationStatus {
+    pub status: String,
+    pub applied_count: i64,
+    pub latest_version: Option<String>,
+    pub latest_applied_at: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SettingsInfo {
+    pub absurd_version: String,
+    pub sqlite_version: String,
+    pub db_path: String,
+    pub db_size_bytes: Option<u64>,
+    pub migration: MigrationStatus,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MigrationEntry {
+    pub id: i64,
+    pub introduced_version: String,
+    pub applied_at: Option<String>,
+    pub status: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EventFilters {
+    pub queue_name: Option<String>,
+    pub event_name: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CleanupTarget {
+    Tasks,
+    Events,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CleanupQueueOptions {
+    pub queue_name: String,
+    pub target: CleanupTarget,
+    pub ttl_seconds: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CleanupResult {
+    pub deleted_count: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QueueExport {
+    pub queue_name: String,
+    pub record_count: i64,
+    pub jsonl: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportQueueOptions {
+    pub queue_name: String,
+    pub jsonl: String,
+    #[serde(default)]
+    pub remap_ids: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportResult {
+    pub imported_count: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WorkerSummary {
+    pub worker_id: String,
+    pub hostname: Option<String>,
+    pub pid: Option<i64>,
+    pub queues: Vec<String>,
+    pub first_seen: String,
+    pub last_seen: String,
+    pub last_seen_ms: i64,
+    pub last_seen_ago: String,
+    pub held_runs: Vec<TaskRun>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TaskTypeSummary {
+    pub queue_name: String,
+    pub task_name: String,
+    pub description: Option<String>,
+    pub owner_team: Option<String>,
+    pub claim_timeout: Option<i64>,
+    pub concurrency_limit: Option<i64>,
+    pub schema_json: Option<String>,
+    pub default_options_json: Option<String>,
+    pub pending: i64,
+    pub running: i64,
+    pub sleeping: i64,
+    pub completed: i64,
+    pub failed: i64,
+    pub cancelled: i64,
+    pub updated_at: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReleaseClaimsResult {
+    pub released_count: i64,
+}
+
+/// Queries behind the Absurd data procedures, shared by the desktop app's
+/// commands and the HTTP API.
+pub struct DataProvider<'a> {
+    conn: &'a Connection,
+    now_ms: i64,
+}
+
+impl<'a> DataProvider<'a> {
+    pub fn new(conn: &'a Connection) -> Self {
+        Self {
+            conn,
+            now_ms: current_time_ms(),
+        }
+    }
+
+    #[allow(unused)]
+    pub fn with_now(conn: &'a Connection, now_ms: i64) -> Self {
+        Self { conn, now_ms }
+    }
+
+    pub fn get_overview_metrics(&self) -> Result<OverviewMetrics> {
+        let active_queues: i64 = self
+            .conn
+            .query_row(
+                "select count(distinct queue_name) from absurd_tasks where state in ('pending', 'running', 'sleeping')",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap_or(0);
+
+        let messages_processed: i64 = self
+            .conn
+            .query_row(
+                "select count(*) from absurd_tasks where state in ('completed', 'failed', 'cancelled')",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap_or(0);
+
+        let messages_in_queue: i64 = self
+            .conn
+            .query_row(
+                "select count(*) from absurd_tasks where state = 'pending'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap_or(0);
+
+        let visible_now: i64 = self
+            .conn
+            .query_row(
+                "select count(*) from absurd_runs where state = 'pending' and available_at <= ?",
+                [self.now_ms],
+                |row| row.get(0),
+            )
+            .unwrap_or(0);
+
+        Ok(OverviewMetrics {
+            active_queues,
+            messages_processed,
+            messages_in_queue,
+            visible_now,
+        })
+    }
+
+    pub fn get_queue_names(&self) -> Result<Vec<String>> {
+        let mut stmt = self
+            .conn
+            .prepare("select queue_name from absurd_queues order by queue_name")?;
+        let names = stmt
+            .query_map([], |row| row.get(0))?
+            .collect::<std::result::Result<Vec<String>, _>>()?;
+        Ok(names)
+    }
+
+    pub fn create_queue(&self, queue_name: &str) -> Result<()> {
+        let trimmed = queue_name.trim();
+        if trimmed.is_empty() {
+            return Err(anyhow::anyhow!("Queue name is required"));
+        }
+
+        self.conn.execute(
+            "insert or ignore into absurd_queues (queue_name, created_at) values (?1, ?2)",
+            rusqlite::params![trimmed, current_time_ms()],
+        )?;
+        Ok(())
+    }
+
+    pub fn cleanup_queue(&self, options: CleanupQueueOptions) -> Result<CleanupResult> {
+        let queue_name = options.queue_name.trim();
+        if queue_name.is_empty() {
+            return Err(anyhow::anyhow!("Queue name is required"));
+        }
+
+        let ttl_seconds = options.ttl_seconds.max(0);
+        let limit = 5000_i64;
+        let mut deleted_total = 0;
+
+        loop {
+            let deleted = match options.target {
+                CleanupTarget::Events => self.cleanup_events(queue_name, ttl_seconds, limit)?,
+                CleanupTarget::Tasks => self.cleanup_tasks(queue_name, ttl_seconds, limit)?,
+            };
+
+            deleted_total += deleted;
+            if ttl_seconds != 0 || deleted < limit {
+                break;
+            }
+        }
+
+        Ok(CleanupResult {
+            deleted_count: deleted_total,
+        })
+    }
+
+    pub fn export_queue(&self, queue_name: &str) -> Result<QueueExport> {
+        let queue_name = queue_name.trim();
+        if queue_name.is_empty() {
+            return Err(anyhow::anyhow!("Queue name is required"));
+        }
+
+        let mut stmt = self
+            .conn
+            .prepare("select line from absurd_export_queue(?1) order by line_no")?;
+        let lines = stmt
+            .query_map([queue_name], |row| row.get::<_, String>(0))?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+
+        Ok(QueueExport {
+            queue_name: queue_name.to_string(),
+            // The first line is the export header.
+            record_count: lines.len().saturating_sub(1) as i64,
+            jsonl: lines.join("\n") + "\n",
+        })
+    }
+
+    pub fn import_queue(&self, options: ImportQueueOptions) -> Result<ImportResult> {
+        let queue_name = options.queue_name.trim();
+        if queue_name.is_empty() {
+            return Err(anyhow::anyhow!("Queue name is required"));
+        }
+
+        let id_mode = if options.remap_ids { "remap" } else { "keep" };
+        let imported: i64 = self.conn.query_row(
+            "select absurd_import_queue(?1, ?2, ?3)",
+            rusqlite::params![queue_name, options.jsonl, id_mode],
+            |row| row.get(0),
+        )?;
+
+        Ok(ImportResult {
+            imported_count: imported,
+        })
+    }
+
+    fn cleanup_tasks(&self, queue_name: &str, ttl_seconds: i64, limit: i64) -> Result<i64> {
+        let deleted: i64 = self.conn.query_row(
+            "select absurd_cleanup_tasks(?1, ?2, ?3)",
+            rusqlite::params![queue_name, ttl_seconds, limit],
+            |row| row.get(0),
+        )?;
+        Ok(deleted)
+    }
+
+    fn cleanup_events(&self, queue_name: &str, ttl_seconds: i64, limit: i64) -> Result<i64> {
+        let deleted: i64 = self.conn.query_row(
+            "select absurd_cleanup_events(?1, ?2, ?3)",
+            rusqlite::params![queue_name, ttl_seconds, limit],
+            |row| row.get(0),
+        )?;
+        Ok(deleted)
+    }
+
+    pub fn get_queue_metrics(&self) -> Result<Vec<QueueMetric>> {
+        let queue_names = self.get_queue_names()?;
+        let mut metrics = Vec::with_capacity(queue_names.len());
+
+        for queue in queue_names {
+            let in_queue: i64 = self
+                .conn
+                .query_row(
+                    "select count(*) from absurd_tasks where queue_name = ? and state = 'pending'",
+                    [&queue],
+                    |row| row.get(0),
+                )
+                .unwrap_or(0);
+
+            let visible: i64 = self
+                .conn
+                .query_row(
+                    "select count(*) from absurd_runs where queue_name = ? and state = 'pending' and available_at <= ?",
+                    rusqlite::params![queue, self.now_ms],
+                    |row| row.get(0),
+                )
+                .unwrap_or(0);
+
+            let total_seen: i64 = self
+                .conn
+                .query_row(
+                    "select count(*) from absurd_runs where queue_name = ?",
+                    [&queue],
+                    |row| row.get(0),
+                )
+                .unwrap_or(0);
+
+            let mut stmt = self.conn.prepare(
+                "select min(enqueue_at), max(enqueue_at) from absurd_tasks where queue_name = ?",
+            )?;
+            let (oldest, newest): (Option<i64>, Option<i64>) =
+                stmt.query_row([&queue], |row| Ok((row.get(0)?, row.get(1)?)))?;
+
+            let (oldest_age, newest_age) = match (oldest, newest) {
+                (Some(oldest), Some(newest)) => (
+                    format_age_short(self.now_ms - oldest),
+                    format_age_short(self.now_ms - newest),
+                ),
+                _ => ("--".to_string(), "--".to_string()),
+            };
+
+            metrics.push(QueueMetric {
+                name: queue,
+                in_queue,
+                visible,
+                newest_age,
+                oldest_age,
+                total_seen,
+                scraped_at: format_datetime_with_seconds(self.now_ms),
+            });
+        }
+
+        Ok(metrics)
+    }
+
+    pub fn get_task_runs(&self) -> Result<Vec<TaskRun>> {
+        self.fetch_task_runs(None)
+    }
+
+    pub fn get_task_runs_for_queue(&self, queue_name: &str) -> Result<Vec<TaskRun>> {
+        self.fetch_task_runs(Some(queue_name))
+    }
+
+    pub fn get_task_runs_page(&self, filters: TaskRunFilters) -> Result<TaskRunPage> {
+        let (filter_sql, mut params) = build_task_run_filters(&filters);
+        let count_sql = format!(
+            "select count(*) from absurd_runs r
+             join absurd_tasks t
+               on t.queue_name = r.queue_name and t.task_id = r.task_id{}",
+            filter_sql
+        );
+
+        let total_count: i64 = self
+            .conn
+            .query_row(&count_sql, params_from_iter(params.iter()), |row| {
+                row.get(0)
+            })
+            .unwrap_or(0);
+
+        let limit = filters.limit.unwrap_or(500);
+        let mut sql = format!(
+            "select
+                r.queue_name,
+                t.task_id,
+                t.task_name,
+                r.state,
+                r.attempt,
+                r.run_id,
+                r.started_at,
+                r.created_at,
+                r.completed_at,
+                r.failed_at,
+                absurd_decode_payload(json(t.params)),
+                t.max_attempts,
+                r.claimed_by,
+                absurd_decode_payload(json(r.result)),
+                json(r.failure_reason)
+             from absurd_runs r
+             join absurd_tasks t
+               on t.queue_name = r.queue_name and t.task_id = r.task_id{}
+             order by r.created_at desc",
+            filter_sql
+        );
+
+        if limit > 0 {
+            sql.push_str(" limit ?");
+            params.push(SqlValue::Integer(limit));
+        }
+
+        let mut stmt = self.conn.prepare(&sql)?;
+        let rows = stmt.query_map(params_from_iter(params.iter()), |row| {
+            map_task_run_row(row, self.now_ms)
+        })?;
+
+        Ok(TaskRunPage {
+            runs: rows.collect::<std::result::Result<Vec<_>, _>>()?,
+            total_count,
+        })
+    }
+
+    pub fn get_task_name_options(&self, queue_name: Option<&str>) -> Result<Vec<String>> {
+        let queue_filter =
+            queue_name.filter(|value| !value.is_empty() && value.to_lowercase() != "all queues");
+        let (sql, params) = if let Some(queue) = queue_filter {
+            (
+                "select distinct task_name from absurd_tasks where queue_name = ? order by task_name",
+                vec![SqlValue::Text(queue.to_string())],
+            )
+        } else {
+            (
+                "select distinct task_name from absurd_tasks order by task_name",
+                Vec::new(),
+            )
+        };
+
+        let mut stmt = self.conn.prepare(sql)?;
+        let names = stmt
+            .query_map(params_from_iter(params.iter()), |row| row.get(0))?
+            .collect::<std::result::Result<Vec<String>, _>>()?;
+        Ok(names)
+    }
+
+    pub fn get_task_history(&self, task_id: &str) -> Result<Vec<TaskRun>> {
+        let mut stmt = self.conn.prepare(
+            "select
+                r.queue_name,
+                t.task_id,
+                t.task_name,
+                r.state,
+                r.attempt,
+                r.run_id,
+                r.started_at,
+                r.created_at,
+                r.completed_at,
+                r.failed_at,
+                absurd_decode_payload(json(t.params)),
+                t.max_attempts,
+                r.claimed_by,
+                absurd_decode_payload(json(r.result)),
+                json(r.failure_reason)
+             from absurd_runs r
+             join absurd_tasks t
+               on t.queue_name = r.queue_name and t.task_id = r.task_id
+             where t.task_id = ?
+             order by r.created_at desc",
+        )?;
+
+        let rows = stmt.query_map([task_id], |row| map_task_run_row(row, self.now_ms))?;
+        Ok(rows.collect::<std::result::Result<Vec<_>, _>>()?)
+    }
+
+    pub fn get_task_info(&self, task_id: &str) -> Result<Option<TaskInfo>> {
+        self.conn
+            .query_row(
+                "select
+                    t.task_id,
+                    t.task_name,
+                    t.queue_name,
+                    (select count(*) from absurd_checkpoints c where c.task_id = t.task_id and c.queue_name = t.queue_name)
+                 from absurd_tasks t
+                 where t.task_id = ?",
+                [task_id],
+                |row| {
+                    Ok(TaskInfo {
+                        id: row.get(0)?,
+                        name: row.get(1)?,
+                        queue: row.get(2)?,
+                        checkpoint_count: row.get(3)?,
+                    })
+                },
+            )
+            .optional()
+            .map_err(Into::into)
+    }
+
+    pub fn get_task_checkpoint_statuses(
+        &self,
+        task_id: &str,
+    ) -> Result<Vec<CheckpointStatusCount>> {
+        let mut stmt = self.conn.prepare(
+            "select coalesce(status, 'unknown') as status, count(*)
+             from absurd_checkpoints
+             where task_id = ?
+             group by status
+             order by status",
+        )?;
+
+        let rows = stmt.query_map([task_id], |row| {
+            Ok(CheckpointStatusCount {
+                status: row.get(0)?,
+                count: row.get(1)?,
+            })
+        })?;
+
+        Ok(rows.collect::<std::result::Result<Vec<_>, _>>()?)
+    }
+
+    pub fn get_task_checkpoints(&self, task_id: &str) -> Result<Vec<TaskCheckpoint>> {
+        let mut stmt = self.conn.prepare(
+            "select checkpoint_name, coalesce(status, 'unknown'), owner_run_id, updated_at
+             from absurd_checkpoints
+             where task_id = ?
+             order by updated_at desc",
+        )?;
+
+        let rows = stmt.query_map([task_id], |row| {
+            let updated_at: i64 = row.get(3)?;
+            Ok(TaskCheckpoint {
+                name: row.get(0)?,
+                status: row.get(1)?,
+                owner_run_id: row.get(2)?,
+                updated_at: format_datetime(updated_at),
+            })
+        })?;
+
+        Ok(rows.collect::<std::result::Result<Vec<_>, _>>()?)
+    }
+
+    pub fn get_task_checkpoint_history(
+        &self,
+        task_id: &str,
+        checkpoint_name: &str,
+    ) -> Result<Vec<CheckpointVersion>> {
+        let mut stmt = self.conn.prepare(
+            "select attempt, run_id, status, absurd_decode_payload(json(state)), updated_at
+             from absurd_checkpoint_history
+             where task_id = ? and checkpoint_name = ?
+             order by history_id asc",
+        )?;
+
+        let rows = stmt.query_map([task_id, checkpoint_name], |row| {
+            Ok((
+                row.get::<_, i64>(0)?,
+                row.get::<_, Option<String>>(1)?,
+                row.get::<_, String>(2)?,
+                row.get::<_, Option<String>>(3)?,
+                row.get::<_, i64>(4)?,
+            ))
+        })?;
+
+        let mut versions = Vec::new();
+        let mut previous = Value::Null;
+        for row in rows {
+            let (attempt, run_id, status, state_raw, updated_at) = row?;
+            let state = state_raw
+                .as_deref()
+                .and_then(|raw| serde_json::from_str::<Value>(raw).ok())
+                .unwrap_or(Value::Null);
+            let changes = if versions.is_empty() {
+                Vec::new()
+            } else {
+                diff_json(&previous, &state)
+            };
+            versions.push(CheckpointVersion {
+                attempt,
+                run_id,
+                status,
+                state_json: state_raw.map(|_| format_json_pretty(&state)),
+                updated_at: format_datetime_with_seconds(updated_at),
+                changes,
+            });
+            previous = state;
+        }
+
+        Ok(versions)
+    }
+
+    pub fn get_queue_summaries(&self) -> Result<Vec<QueueSummary>> {
+        let mut stmt = self
+            .conn
+            .prepare("select queue_name, created_at from absurd_queues order by queue_name")?;
+        let queue_rows = stmt.query_map([], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)?))
+        })?;
+
+        let mut summaries = Vec::new();
+
+        for row in queue_rows {
+            let (queue_name, created_at) = row?;
+            let stats = self.get_queue_stats(&queue_name)?;
+
+            summaries.push(QueueSummary {
+                name: queue_name,
+                created_at: format!("Created {}", format_datetime(created_at)),
+                age: format_age_ago(self.now_ms - created_at),
+                stats,
+            });
+        }
+
+        Ok(summaries)
+    }
+
+    pub fn get_event_filter_defaults(
+        &self,
+        queue_name: Option<&str>,
+    ) -> Result<EventFilterDefaults> {
+        let placeholder: Option<String> = self
+            .conn
+            .query_row(
+                "select event_name from absurd_events order by emitted_at desc limit 1",
+                [],
+                |row| row.get(0),
+            )
+            .optional()?;
+
+        let queue_options = {
+            let mut options = Vec::new();
+            options.push("All queues".to_string());
+            options.extend(self.get_queue_names()?);
+            options
+        };
+
+        Ok(EventFilterDefaults {
+            event_name_placeholder: placeholder.unwrap_or_else(|| "payment.completed".to_string()),
+            queue_label: queue_name.unwrap_or("All queues").to_string(),
+            queue_options,
+        })
+    }
+
+    pub fn get_events(&self) -> Result<Vec<EventEntry>> {
+        self.get_filtered_events(None)
+    }
+
+    pub fn get_filtered_events(&self, filters: Option<EventFilters>) -> Result<Vec<EventEntry>> {
+        let queue_filter = filters
+            .as_ref()
+            .and_then(|filters| filters.queue_name.clone())
+            .filter(|value| !value.is_empty() && value.to_lowercase() != "all queues");
+        let event_filter = filters
+            .as_ref()
+            .and_then(|filters| filters.event_name.clone())
+            .map(|value| value.trim().to_lowercase())
+            .filter(|value| !value.is_empty());
+
+        let mut stmt = self.conn.prepare(
+            "select queue_name, event_name, emitted_at, json(payload)
+             from absurd_events
+             order by emitted_at desc",
+        )?;
+
+        let rows = stmt.query_map([], |row| {
+            let queue: String = row.get(0)?;
+            let name: String = row.get(1)?;
+            let emitted_at: i64 = row.get(2)?;
+            let payload: SqlValue = row.get(3)?;
+
+            Ok((queue, name, emitted_at, payload))
+        })?;
+
+        let mut entries = Vec::new();
+
+        for row in rows {
+            let (queue, name, emitted_at, payload) = row?;
+
+            if let Some(filter_queue) = &queue_filter {
+                if queue.to_lowercase() != filter_queue.to_lowercase() {
+                    continue;
+                }
+            }
+
+            if let Some(filter_event) = &event_filter {
+                if !name.to_lowercase().contains(filter_event) {
+                    continue;
+                }
+            }
+
+            let payload_preview = payload_preview_from_value(json_from_sql_value(payload));
+
+            entries.push(EventEntry {
+                id: format!("{}_{}", queue, name),
+                name,
+                queue,
+                created_at: format_datetime(emitted_at),
+                payload_preview,
+            });
+        }
+
+        Ok(entries)
+    }
+
+    pub fn get_settings_info(&self, db_path: String) -> Result<SettingsInfo> {
+        let absurd_version: String = self
+            .conn
+            .query_row("select absurd_version()", [], |row| row.get(0))?;
+        let sqlite_version: String = self
+            .conn
+            .query_row("select sqlite_version()", [], |row| row.get(0))?;
+        let db_size_bytes = std::fs::metadata(&db_path).map(|meta| meta.len()).ok();
+
+        let applied_count: i64 = self
+            .conn
+            .query_row(
+                "select count(*) from absurd_migration_records()",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap_or(0);
+        let latest: Option<(String, i64)> = self
+            .conn
+            .query_row(
+                "select introduced_version, applied_time from absurd_migration_records() order by id desc limit 1",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .optional()?;
+
+        let (latest_version, latest_applied_at) = match latest {
+            Some((version, applied_time)) => (Some(version), Some(format_datetime(applied_time))),
+            None => (None, None),
+        };
+
+        let status = if applied_count > 0 {
+            "applied".to_string()
+        } else {
+            "missing".to_string()
+        };
+
+        Ok(SettingsInfo {
+            absurd_version,
+            sqlite_version,
+            db_path,
+            db_size_bytes,
+            migration: MigrationStatus {
+                status,
+                applied_count,
+                latest_version,
+                latest_applied_at,
+            },
+        })
+    }
+
+    pub fn get_migrations(&self) -> Result<Vec<MigrationEntry>> {
+        let mut stmt = self.conn.prepare(
+            "select id, introduced_version, applied_time from absurd_migration_records() order by id",
+        )?;
+        let applied_rows = stmt
+            .query_map([], |row| {
+                Ok((row.get::<_, i64>(0)?, row.get(1)?, row.get(2)?))
+            })?
+            .collect::<std::result::Result<Vec<(i64, String, i64)>, _>>()?;
+
+        let mut applied_map: HashMap<i64, (String, i64)> = HashMap::new();
+        for (id, introduced_version, applied_time) in applied_rows {
+            applied_map.insert(id, (introduced_version, applied_time));
+        }
+
+        let mut entries = Vec::new();
+        let mut known_ids = HashMap::new();
+        for migration in read_known_migrations(self.conn)? {
+            let applied = applied_map.get(&migration.id);
+            entries.push(MigrationEntry {
+                id: migration.id,
+                introduced_version: applied
+                    .map(|(version, _)| version.clone())
+                    .unwrap_or(migration.introduced_version),
+                applied_at: applied.map(|(_, time)| format_datetime(*time)),
+                status: if applied.is_some() {
+                    "applied".to_string()
+                } else {
+                    "pending".to_string()
+                },
+            });
+            known_ids.insert(migration.id, ());
+        }
+
+        for (id, (introduced_version, applied_time)) in applied_map {
+            if known_ids.contains_key(&id) {
+                continue;
+            }
+            entries.push(MigrationEntry {
+                id,
+                introduced_version,
+                applied_at: Some(format_datetime(applied_time)),
+                status: "applied".to_string(),
+            });
+        }
+
+        entries.sort_by_key(|entry| entry.id);
+        Ok(entries)
+    }
+
+    pub fn apply_migrations_all(&self) -> Result<i64> {
+        let applied: i64 = self
+            .conn
+            .query_row("select absurd_apply_migrations()", [], |row| row.get(0))?;
+        Ok(applied)
+    }
+
+    pub fn apply_migration(&self, migration_id: i64) -> Result<i64> {
+        let applied: i64 = self.conn.query_row(
+            "select absurd_apply_migrations(?1)",
+            [migration_id],
+            |row| row.get(0),
+        )?;
+        Ok(applied)
+    }
+
+    pub fn get_workers(&self) -> Result<Vec<WorkerSummary>> {
+        let mut stmt = self.conn.prepare(
+            "select worker_id, hostname, pid, queues, first_seen, last_seen
+             from absurd_workers
+             order by last_seen desc, worker_id",
+        )?;
+        let rows = stmt.query_map([], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, Option<String>>(1)?,
+                row.get::<_, Option<i64>>(2)?,
+                row.get::<_, String>(3)?,
+                row.get::<_, i64>(4)?,
+                row.get::<_, i64>(5)?,
+            ))
+        })?;
+
+        let mut workers = Vec::new();
+        for row in rows {
+            let (worker_id, hostname, pid, queues_raw, first_seen, last_seen) = row?;
+            let queues = serde_json::from_str::<Vec<String>>(&queues_raw).unwrap_or_default();
+            let held_runs = self.get_worker_runs(&worker_id)?;
+            workers.push(WorkerSummary {
+                worker_id,
+                hostname,
+                pid,
+                queues,
+                first_seen: format_datetime(first_seen),
+                last_seen: format_datetime_with_seconds(last_seen),
+                last_seen_ms: last_seen,
+                last_seen_ago: format_age_ago(self.now_ms - last_seen),
+                held_runs,
+            });
+        }
+        Ok(workers)
+    }
+
+    pub fn get_task_types(&self) -> Result<Vec<TaskTypeSummary>> {
+        let mut stmt = self.conn.prepare(
+            "select tt.queue_name,
+                    tt.task_name,
+                    tt.description,
+                    tt.owner_team,
+                    tt.claim_timeout,
+                    tt.concurrency_limit,
+                    json(tt.json_schema),
+                    json(tt.default_options),
+                    count(t.task_id) filter (where t.state = 'pending'),
+                    count(t.task_id) filter (where t.state = 'running'),
+                    count(t.task_id) filter (where t.state = 'sleeping'),
+                    count(t.task_id) filter (where t.state = 'completed'),
+                    count(t.task_id) filter (where t.state = 'failed'),
+                    count(t.task_id) filter (where t.state = 'cancelled'),
+                    tt.updated_at
+             from absurd_task_types tt
+             left join absurd_tasks t
+               on t.queue_name = tt.queue_name
+              and t.task_name = tt.task_name
+             group by tt.queue_name, tt.task_name
+             order by tt.queue_name, tt.task_name",
+        )?;
+        let pretty = |raw: Option<String>| {
+            raw.map(|raw| match serde_json::from_str::<Value>(&raw) {
+                Ok(value) => format_json_pretty(&value),
+                Err(_) => raw,
+            })
+        };
+        let rows = stmt.query_map([], |row| {
+            let updated_at: i64 = row.get(14)?;
+            Ok(TaskTypeSummary {
+                queue_name: row.get(0)?,
+                task_name: row.get(1)?,
+                description: row.get(2)?,
+                owner_team: row.get(3)?,
+                claim_timeout: row.get(4)?,
+                concurrency_limit: row.get(5)?,
+                schema_json: pretty(row.get(6)?),
+                default_options_json: pretty(row.get(7)?),
+                pending: row.get(8)?,
+                running: row.get(9)?,
+                sleeping: row.get(10)?,
+                completed: row.get(11)?,
+                failed: row.get(12)?,
+                cancelled: row.get(13)?,
+                updated_at: format_datetime(updated_at),
+            })
+        })?;
+
+        Ok(rows.collect::<std::result::Result<Vec<_>, _>>()?)
+    }
+
+    pub fn get_worker_runs(&self, worker_id: &str) -> Result<Vec<TaskRun>> {
+        let mut stmt = self.conn.prepare(
+            "select
+                r.queue_name,
+                t.task_id,
+                t.task_name,
+                r.state,
+                r.attempt,
+                r.run_id,
+                r.started_at,
+                r.created_at,
+                r.completed_at,
+                r.failed_at,
+                absurd_decode_payload(json(t.params)),
+                t.max_attempts,
+                r.claimed_by,
+                absurd_decode_payload(json(r.result)),
+                json(r.failure_reason)
+             from absurd_runs r
+             join absurd_tasks t
+               on t.queue_name = r.queue_name and t.task_id = r.task_id
+             where r.claimed_by = ?
+               and r.state = 'running'
+             order by r.started_at desc",
+        )?;
+
+        let rows = stmt.query_map([worker_id], |row| map_task_run_row(row, self.now_ms))?;
+        Ok(rows.collect::<std::result::Result<Vec<_>, _>>()?)
+    }
+
+    /// Return every run held by `worker_id` to pending without counting an
+    /// attempt, so other workers pick it up right away instead of waiting for
+    /// the claim timeout (e.g. after a host crash).
+    pub fn release_worker_claims(&self, worker_id: &str) -> Result<ReleaseClaimsResult> {
+        let worker_id = worker_id.trim();
+        if worker_id.is_empty() {
+            return Err(anyhow::anyhow!("Worker id is required"));
+        }
+
+        let queues: Vec<String> = {
+            let mut stmt = self.conn.prepare(
+                "select distinct queue_name
+                 from absurd_runs
+                 where claimed_by = ? and state = 'running'",
+            )?;
+            let rows = stmt.query_map([worker_id], |row| row.get(0))?;
+            rows.collect::<std::result::Result<Vec<_>, _>>()?
+        };
+
+        let mut released_count = 0;
+        for queue_name in &queues {
+            let released: i64 = self.conn.query_row(
+                "select absurd_release_worker_claims(?1, ?2)",
+                rusqlite::params![queue_name, worker_id],
+                |row| row.get(0),
+            )?;
+            released_count += released;
+        }
+
+        Ok(ReleaseClaimsResult { released_count })
+    }
+
+    /// Return runs held by the worker process `pid` to pending without
+    /// counting an attempt. Workers are matched through the registry's pid.
+    pub fn release_process_claims(&self, pid: u32) -> Result<ReleaseClaimsResult> {
+        let held: Vec<(String, String)> = {
+            let mut stmt = self.conn.prepare(
+                "select distinct r.queue_name, r.claimed_by
+                 from absurd_runs r
+                 join absurd_workers w on w.worker_id = r.claimed_by
+                 where w.pid = ? and r.state = 'running'",
+            )?;
+            let rows = stmt.query_map([pid], |row| Ok((row.get(0)?, row.get(1)?)))?;
+            rows.collect::<std::result::Result<Vec<_>, _>>()?
+        };
+
+        let mut released_count = 0;
+        for (queue_name, worker_id) in &held {
+            let released: i64 = self.conn.query_row(
+                "select absurd_release_worker_claims(?1, ?2)",
+                rusqlite::params![queue_name, worker_id],
+                |row| row.get(0),
+            )?;
+            released_count += released;
+        }
+
+        Ok(ReleaseClaimsResult { released_count })
+    }
+
+    fn get_queue_stats(&self, queue_name: &str) -> Result<Vec<QueueSummaryStat>> {
+        let mut stmt = self.conn.prepare(
+            "select state, count(*) from absurd_tasks where queue_name = ? group by state",
+        )?;
+        let rows = stmt.query_map([queue_name], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)?))
+        })?;
+
+        let mut counts = std::collections::HashMap::new();
+        for row in rows {
+            let (state, count) = row?;
+            counts.insert(state, count);
+        }
+
+        let labels = [
+            ("pending", "Pending"),
+            ("running", "Running"),
+            ("sleeping", "Sleeping"),
+            ("completed", "Completed"),
+            ("failed", "Failed"),
+            ("cancelled", "Cancelled"),
+        ];
+
+        Ok(labels
+            .iter()
+            .map(|(state, label)| QueueSummaryStat {
+                label: (*label).to_string(),
+                value: *counts.get(*state).unwrap_or(&0),
+            })
+            .collect())
+    }
+
+    fn fetch_task_runs(&self, queue_name: Option<&str>) -> Result<Vec<TaskRun>> {
+        if let Some(queue) = queue_name {
+            let mut stmt = self.conn.prepare(
+                r#"select
+                    r.queue_name,
+                    t.task_id,
+                    t.task_name,
+                    r.state,
+                    r.attempt,
+                    r.run_id,
+                    r.started_at,
+                    r.created_at,
+                    r.completed_at,
+                    r.failed_at,
+                    absurd_decode_payload(json(t.params)),
+                    t.max_attempts,
+                    r.claimed_by,
+                    absurd_decode_payload(json(r.result)),
+                    json(r.failure_reason)
+                 from absurd_runs r
+                 join absurd_tasks t
+                   on t.queue_name = r.queue_name and t.task_id = r.task_id
+                 where r.queue_name = ?
+                 order by r.created_at desc"#,
+            )?;
+            let rows = stmt.query_map(rusqlite::params![queue], |row| {
+                map_task_run_row(row, self.now_ms)
+            })?;
+            return Ok(rows.collect::<std::result::Result<Vec<_>, _>>()?);
+        }
+
+        let sql = r#"select
+                r.queue_name,
+                t.task_id,
+                t.task_name,
+                r.state,
+                r.attempt,
+                r.run_id,
+                r.started_at,
+                r.created_at,
+                r.completed_at,
+                r.failed_at,
+                absurd_decode_payload(json(t.params)),
+                t.max_attempts,
+                r.claimed_by,
+                absurd_decode_payload(json(r.result)),
+                json(r.failure_reason)
+             from absurd_runs r
+             join absurd_tasks t
+               on t.queue_name = r.queue_name and t.task_id = r.task_id
+             order by r.created_at desc"#;
+
+        let mut stmt = self.conn.prepare(sql)?;
+        let rows = stmt.query_map([], |row| map_task_run_row(row, self.now_ms))?;
+        Ok(rows.collect::<std::result::Result<Vec<_>, _>>()?)
+    }
+}
+
+fn build_task_run_filters(filters: &TaskRunFilters) -> (String, Vec<SqlValue>) {
+    let mut clauses: Vec<String> = Vec::new();
+    let mut params: Vec<SqlValue> = Vec::new();
+
+    if let Some(queue) = filters
+        .queue_name
+        .as_ref()
+        .map(|value| value.trim().to_string())
+        .filter(|value| !value.is_empty() && value.to_lowercase() != "all queues")
+    {
+        clauses.push("r.queue_name = ?".to_string());
+        params.push(SqlValue::Text(queue));
+    }
+
+    if let Some(status) = filters
+        .status
+        .as_ref()
+        .map(|value| value.trim().to_lowercase())
+        .filter(|value| !value.is_empty())
+    {
+        clauses.push("lower(r.state) = ?".to_string());
+        params.push(SqlValue::Text(status));
+    }
+
+    if let Some(task_name) = filters
+        .task_name
+        .as_ref()
+        .map(|value| value.trim().to_string())
+        .filter(|value| !value.is_empty())
+    {
+        clauses.push("t.task_name = ?".to_string());
+        params.push(SqlValue::Text(task_name));
+    }
+
+    if let Some(search) = filters
+        .search
+        .as_ref()
+        .map(|value| value.trim().to_lowercase())
+        .filter(|value| !value.is_empty())
+    {
+        let like = format!("%{}%", search);
+        clauses.push(
+            "(lower(t.task_id) like ?
+              or lower(r.run_id) like ?
+              or lower(t.task_name) like ?
+              or lower(r.queue_name) like ?
+              or lower(r.state) like ?
+              or lower(coalesce(r.claimed_by, '')) like ?
+              or lower(coalesce(absurd_decode_payload(json(t.params)), '')) like ?
+              or lower(coalesce(absurd_decode_payload(json(r.result)), '')) like ?
+              or lower(coalesce(json(r.failure_reason), '')) like ?)"
+                .to_string(),
+        );
+        for _ in 0..9 {
+            params.push(SqlValue::Text(like.clone()));
+        }
+    }
+
+    let filter_sql = if clauses.is_empty() {
+        "".to_string()
+    } else {
+        format!(" where {}", clauses.join(" and "))
+    };
+
+    (filter_sql, params)
+}
+
+fn map_task_run_row(row: &rusqlite::Row<'_>, now_ms: i64) -> rusqlite::Result<TaskRun> {
+    let queue: String = row.get(0)?;
+    let task_id: String = row.get(1)?;
+    let task_name: String = row.get(2)?;
+    let state: String = row.get(3)?;
+    let attempt_number: i64 = row.get(4)?;
+    let run_id: String = row.get(5)?;
+    let started_at: Option<i64> = row.get(6)?;
+    let created_at: i64 = row.get(7)?;
+    let completed_at: Option<i64> = row.get(8)?;
+    let failed_at: Option<i64> = row.get(9)?;
+    let params_value = json_from_sql_value(row.get::<_, SqlValue>(10)?).unwrap_or(Value::Null);
+    let max_attempts: Option<i64> = row.get(11)?;
+    let claimed_by: Option<String> = row.get(12)?;
+    let result_value = json_from_sql_value(row.get::<_, SqlValue>(13)?);
+    let failure_value = json_from_sql_value(row.get::<_, SqlValue>(14)?);
+    let params_summary = build_params_summary(&params_value);
+    let params_json = format_json_pretty(&params_value);
+
+    let final_state_json = match state.as_str() {
+        "failed" => failure_value.as_ref().map(format_json_pretty),
+        "completed" => result_value.as_ref().map(format_json_pretty),
+        _ => None,
+    };
+
+    let updated_at = completed_at
+        .or(failed_at)
+        .or(started_at)
+        .unwrap_or(created_at);
+    let started_at = started_at.unwrap_or(created_at);
+
+    Ok(TaskRun {
+        id: task_id,
+        name: task_name,
+        queue,
+        status: map_task_status(&state),
+        attempt: format_attempt(attempt_number, max_attempts),
+        attempt_number,
+        run_id,
+        age: format_age_short(now_ms - created_at),
+        started_at: format_datetime(started_at),
+        updated_at: format_datetime(updated_at),
+        created_at_ms: created_at,
+        updated_at_ms: updated_at,
+        created_ago: format_age_ago(now_ms - created_at),
+        updated_ago: format_age_ago(now_ms - updated_at),
+        params_summary,
+        params_json,
+        final_state_json,
+        worker: claimed_by.unwrap_or_else(|| "--".to_string()),
+    })
+}
+
+fn map_task_status(state: &str) -> TaskStatus {
+    match state {
+        "running" => TaskStatus::Running,
+        "failed" => TaskStatus::Failed,
+        "completed" => TaskStatus::Completed,
+        "sleeping" => TaskStatus::Sleeping,
+        "pending" => TaskStatus::Pending,
+        "cancelled" => TaskStatus::Cancelled,
+        _ => TaskStatus::Pending,
+    }
+}
+
+fn format_attempt(attempt: i64, max_attempts: Option<i64>) -> String {
+    match max_attempts {
+        Some(max) => format!("{} / {}", attempt, max),
+        None => format!("{} / inf", attempt),
+    }
+}
+
+fn format_json_pretty(value: &Value) -> String {
+    serde_json::to_string_pretty(value).unwrap_or_else(|_| value.to_string())
+}
+
+fn build_params_summary(value: &Value) -> String {
+    if let Some(obj) = value.as_object() {
+        let mut parts = Vec::new();
+        for (key, val) in obj.iter().take(2) {
+            let formatted = serde_json::to_string(val).unwrap_or_else(|_| "null".to_string());
+            parts.push(format!("\"{}\": {}", key, formatted));
+        }
+        return format!("{{ {} }}", parts.join(", "));
+    }
+
+    value.to_string()
+}
+
+fn payload_preview_from_value(value: Option<Value>) -> String {
+    let Some(value) = value else {
+        return String::new();
+    };
+    let compact = serde_json::to_string(&value).unwrap_or_else(|_| value.to_string());
+    truncate_string(&compact, 120)
+}
+
+fn json_from_sql_value(value: SqlValue) -> Option<Value> {
+    let text = match value {
+        SqlValue::Null => return None,
+        SqlValue::Text(text) => text,
+        SqlValue::Blob(blob) => String::from_utf8(blob).ok()?,
+        SqlValue::Integer(value) => value.to_string(),
+        SqlValue::Real(value) => value.to_string(),
+    };
+
+    serde_json::from_str(&text).ok()
+}
+
+fn truncate_string(value: &str, max_len: usize) -> String {
+    if value.len() <= max_len {
+        return value.to_string();
+    }
+
+    let truncated = &value[..max_len.saturating_sub(3)];
+    format!("{}...", truncated)
+}
+
+struct KnownMigration {
+    id: i64,
+    introduced_version: String,
+}
+
+/// Migrations bundled with the loaded extension. Asking the extension rather
+/// than reading the migration files keeps this working outside a source tree.
+fn read_known_migrations(conn: &Connection) -> Result<Vec<KnownMigration>> {
+    let mut stmt = conn
+        .prepare("select id, introduced_version from absurd_available_migrations() order by id")?;
+    let migrations = stmt
+        .query_map([], |row| {
+            Ok(KnownMigration {
+                id: row.get(0)?,
+                introduced_version: row.get(1)?,
+            })
+        })?
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+    Ok(migrations)
+}
+
+/// List the JSON paths that differ between two checkpoint values. Objects are
+/// compared key by key and arrays index by index; anything else is a change.
+fn diff_json(before: &Value, after: &Value) -> Vec<CheckpointChange> {
+    let mut changes = Vec::new();
+    diff_json_at("$", before, after, &mut changes);
+    changes
+}
+
+fn diff_json_at(path: &str, before: &Value, after: &Value, changes: &mut Vec<CheckpointChange>) {
+    match (before, after) {
+        (Value::Object(left), Value::Object(right)) => {
+            for (key, left_value) in left {
+                let child = format!("{}.{}", path, key);
+                match right.get(key) {
+                    Some(right_value) => diff_json_at(&child, left_value, right_value, changes),
+                    None => changes.push(CheckpointChange {
+                        path: child,
+                        kind: "removed".to_string(),
+                        before: Some(left_value.to_string()),
+                        after: None,
+                    }),
+                }
+            }
+            for (key, right_value) in right {
+                if !left.contains_key(key) {
+                    changes.push(CheckpointChange {
+                        path: format!("{}.{}", path, key),
+                        kind: "added".to_string(),
+                        before: None,
+                        after: Some(right_value.to_string()),
+                    });
+                }
+            }
+        }
+        (Value::Array(left), Value::Array(right)) => {
+            for index in 0..left.len().max(right.len()) {
+                let child = format!("{}[{}]", path, index);
+                match (left.get(index), right.get(index)) {
+                    (Some(l), Some(r)) => diff_json_at(&child, l, r, changes),
+                    (Some(l), None) => changes.push(CheckpointChange {
+                        path: child,
+                        kind: "removed".to_string(),
+                        before: Some(l.to_string()),
+                        after: None,
+                    }),
+                    (None, Some(r)) => changes.push(CheckpointChange {
+                        path: child,
+                        kind: "added".to_string(),
+                        before: None,
+                        after: Some(r.to_string()),
+                    }),
+                    (None, None) => {}
+                }
+            }
+        }
+        _ if before != after => changes.push(CheckpointChange {
+            path: path.to_string(),
+            kind: "changed".to_string(),
+            before: Some(before.to_string()),
+            after: Some(after.to_string()),
+        }),
+        _ => {}
+    }
+}
+
+fn format_datetime(ms: i64) -> String {
+    let fallback = Utc.timestamp_millis_opt(0).single().unwrap();
+    let dt = Utc.timestamp_millis_opt(ms).single().unwrap_or(fallback);
+    dt.format("%b %-d, %Y, %-I:%M %p").to_string()
+}
+
+fn format_datetime_with_seconds(ms: i64) -> String {
+    let fallback = Utc.timestamp_millis_opt(0).single().unwrap();
+    let dt = Utc.timestamp_millis_opt(ms).single().unwrap_or(fallback);
+    dt.format("%b %-d, %Y, %-I:%M:%S %p").to_string()
+}
+
+fn format_age_short(delta_ms: i64) -> String {
+    let mut delta = delta_ms;
+    if delta < 0 {
+        delta = 0;
+    }
+
+    let seconds = delta / 1000;
+    let days = seconds / 86_400;
+    if days > 0 {
+        return format!("{}d", days);
+    }
+
+    let hours = seconds / 3_600;
+    if hours > 0 {
+        return format!("{}h", hours);
+    }
+
+    let minutes = seconds / 60;
+    if minutes > 0 {
+        return format!("{}m", minutes);
+    }
+
+    format!("{}s", seconds)
+}
+
+fn format_age_ago(delta_ms: i64) -> String {
+    format!("{} ago", format_age_short(delta_ms))
+}
+
+fn current_time_ms() -> i64 {
+    use std::time::{SystemTime, UNIX_EPOCH};
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+    now.as_millis() as i64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn setup_connection() -> Connection {
+        let conn = Connection::open_in_memory().expect("open in-memory");
+        // The queries decode payloads through the extension; these fixtures
+        // store plain JSON, so a pass-through stands in for it.
+        conn.create_scalar_function(
+            "absurd_decode_payload",
+            1,
+            rusqlite::functions::FunctionFlags::SQLITE_UTF8,
+            |ctx| ctx.get::<Option<String>>(0),
+        )
+        .expect("register absurd_decode_payload");
+        conn.execute_batch(include_str!(
+            "../../absurd-sqlite-extension/migrations/1.sql"
+        ))
+        .expect("apply schema");
+        conn.execute_batch(include_str!(
+            "../../absurd-sqlite-extension/migrations/3.sql"
+        ))
+        .expect("apply worker schema");
+        conn
+    }
+
+    fn seed_data(conn: &Connection, now_ms: i64) {
+        conn.execute(
+            "insert into absurd_queues (queue_name, created_at) values (?, ?)",
+            rusqlite::params!["default", now_ms - 1000],
+        )
+        .expect("insert queue");
+
+        let params = serde_json::json!({"tenant": "absurd", "retries": 2}).to_string();
+
+        conn.execute(
+            "insert into absurd_tasks (queue_name, task_id, task_name, params, enqueue_at, state, attempts)
+             values (?, ?, ?, ?, ?, ?, ?)",
+            rusqlite::params![
+                "default",
+                "task_pending",
+                "pending",
+                params,
+                now_ms - 10_000,
+                "pending",
+                0
+            ],
+        )
+        .expect("insert task pending");
+
+        conn.execute(
+            "insert into absurd_tasks (queue_name, task_id, task_name, params, enqueue_at, state, attempts)
+             values (?, ?, ?, ?, ?, ?, ?)",
+            rusqlite::params![
+                "default",
+                "task_running",
+                "running",
+                params,
+                now_ms - 20_000,
+                "running",
+                1
+            ],
+        )
+        .expect("insert task running");
+
+        conn.execute(
+            "insert into absurd_tasks (queue_name, task_id, task_name, params, enqueue_at, state, attempts)
+             values (?, ?, ?, ?, ?, ?, ?)",
+            rusqlite::params![
+                "default",
+                "task_completed",
+                "completed",
+                params,
+                now_ms - 30_000,
+                "completed",
+                1
+            ],
+        )
+        .expect("insert task completed");
+
+        conn.execute(
+            "insert into absurd_tasks (queue_name, task_id, task_name, params, enqueue_at, state, attempts)
+             values (?, ?, ?, ?, ?, ?, ?)",
+            rusqlite::params![
+                "default",
+                "task_cancelled",
+                "cancelled",
+                params,
+                now_ms - 15_000,
+                "cancelled",
+                1
+            ],
+        )
+        .expect("insert task cancelled");
+
+        conn.execute(
+            "insert into absurd_runs (queue_name, run_id, task_id, attempt, state, available_at, created_at, started_at, claimed_by)
+             values (?, ?, ?, ?, ?, ?, ?, ?, ?)",
+            rusqlite::params![
+                "default",
+                "run_running",
+                "task_running",
+                1,
+                "running",
+                now_ms - 5_000,
+                now_ms - 20_000,
+                now_ms - 15_000,
+                "worker-1"
+            ],
+        )
+        .expect("insert run running");
+
+        conn.execute(
+            "insert into absurd_runs (queue_name, run_id, task_id, attempt, state, available_at, created_at, completed_at, result)
+             values (?, ?, ?, ?, ?, ?, ?, ?, ?)",
+            rusqlite::params![
+                "default",
+                "run_completed",
+                "task_completed",
+                1,
+                "completed",
+                now_ms - 25_000,
+                now_ms - 25_000,
+                now_ms - 5_000,
+                serde_json::json!({"ok": true}).to_string()
+            ],
+        )
+        .expect("insert run completed");
+
+        conn.execute(
+            "insert into absurd_runs (queue_name, run_id, task_id, attempt, state, available_at, created_at)
+             values (?, ?, ?, ?, ?, ?, ?)",
+            rusqlite::params![
+                "default",
+                "run_pending",
+                "task_pending",
+                1,
+                "pending",
+                now_ms - 1_000,
+                now_ms - 9_000
+            ],
+        )
+        .expect("insert run pending");
+
+        conn.execute(
+            "insert into absurd_runs (queue_name, run_id, task_id, attempt, state, available_at, created_at)
+             values (?, ?, ?, ?, ?, ?, ?)",
+            rusqlite::params![
+                "default",
+                "run_cancelled",
+                "task_cancelled",
+                1,
+                "cancelled",
+                now_ms - 1_000,
+                now_ms - 8_000
+            ],
+        )
+        .expect("insert run cancelled");
+
+        conn.execute(
+            "insert into absurd_events (queue_name, event_name, payload, emitted_at)
+             values (?, ?, ?, ?)",
+            rusqlite::params![
+                "default",
+                "task.completed",
+                serde_json::json!({"taskId": "task_completed"}).to_string(),
+                now_ms - 2_000
+            ],
+        )
+        .expect("insert event");
+    }
+
+    #[test]
+    fn overview_metrics_counts() {
+        let now_ms = 1_700_000_000_000;
+        let conn = setup_connection();
+        seed_data(&conn, now_ms);
+
+        let provider = DataProvider::with_now(&conn, now_ms);
+        let metrics = provider.get_overview_metrics().expect("metrics");
+
+        assert_eq!(metrics.active_queues, 1);
+        assert_eq!(metrics.messages_processed, 2);
+        assert_eq!(metrics.messages_in_queue, 1);
+        assert_eq!(metrics.visible_now, 1);
+    }
+
+    #[test]
+    fn queue_metrics_show_ages_and_counts() {
+        let now_ms = 1_700_000_000_000;
+        let conn = setup_connection();
+        seed_data(&conn, now_ms);
+
+        let provider = DataProvider::with_now(&conn, now_ms);
+        let metrics = provider.get_queue_metrics().expect("metrics");
+
+        assert_eq!(metrics.len(), 1);
+        let metric = &metrics[0];
+        assert_eq!(metric.name, "default");
+        assert_eq!(metric.in_queue, 1);
+        assert_eq!(metric.visible, 1);
+        assert_eq!(metric.total_seen, 4);
+        assert_eq!(metric.newest_age, format_age_short(10_000));
+        assert_eq!(metric.oldest_age, format_age_short(30_000));
+        assert_eq!(metric.scraped_at, format_datetime_with_seconds(now_ms));
+    }
+
+    #[test]
+    fn task_runs_include_status_and_attempts() {
+        let now_ms = 1_700_000_000_000;
+        let conn = setup_connection();
+        seed_data(&conn, now_ms);
+
+        let provider = DataProvider::with_now(&conn, now_ms);
+        let runs = provider.get_task_runs().expect("runs");
+
+        assert_eq!(runs.len(), 4);
+        let running = runs
+            .iter()
+            .find(|run| run.run_id == "run_running")
+            .expect("running run");
+
+        assert_eq!(running.status, TaskStatus::Running);
+        assert_eq!(running.attempt, "1 / inf");
+        assert_eq!(running.worker, "worker-1");
+
+        let cancelled = runs
+            .iter()
+            .find(|run| run.run_id == "run_cancelled")
+            .expect("cancelled run");
+        assert_eq!(cancelled.status, TaskStatus::Cancelled);
+    }
+
+    #[test]
+    fn queue_summaries_include_state_counts() {
+        let now_ms = 1_700_000_000_000;
+        let conn = setup_connection();
+        seed_data(&conn, now_ms);
+
+        let provider = DataProvider::with_now(&conn, now_ms);
+        let summaries = provider.get_queue_summaries().expect("summaries");
+
+        assert_eq!(summaries.len(), 1);
+        let summary = &summaries[0];
+        assert_eq!(summary.name, "default");
+        assert_eq!(summary.age, format_age_ago(1000));
+        assert_eq!(summary.stats.len(), 6);
+        assert_eq!(summary.stats[0].value, 1); // Pending
+        assert_eq!(summary.stats[5].value, 1); // Cancelled
+    }
+
+    #[test]
+    fn events_and_filters_work() {
+        let now_ms = 1_700_000_000_000;
+        let conn = setup_connection();
+        seed_data(&conn, now_ms);
+
+        let provider = DataProvider::with_now(&conn, now_ms);
+        let defaults = provider.get_event_filter_defaults(None).expect("defaults");
+        assert_eq!(defaults.event_name_placeholder, "task.completed");
+        assert_eq!(defaults.queue_label, "All queues");
+        assert_eq!(defaults.queue_options, vec!["All queues", "default"]);
+
+        let events = provider.get_events().expect("events");
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].queue, "default");
+
+        let filtered = provider
+            .get_filtered_events(Some(EventFilters {
+                queue_name: Some("default".to_string()),
+                event_name: Some("completed".to_string()),
+            }))
+            .expect("filtered");
+        assert_eq!(filtered.len(), 1);
+    }
+
+    #[test]
+    fn workers_include_held_runs() {
+        let now_ms = 1_700_000_000_000;
+        let conn = setup_connection();
+        seed_data(&conn, now_ms);
+        conn.execute(
+            "insert into absurd_workers (worker_id, hostname, pid, queues, first_seen, last_seen)
+             values (?, ?, ?, ?, ?, ?)",
+            rusqlite::params![
+                "worker-1",
+                "host-a",
+                4242,
+                r#"["default"]"#,
+                now_ms - 60_000,
+                now_ms - 3_000
+            ],
+        )
+        .expect("insert worker");
+
+        let provider = DataProvider::with_now(&conn, now_ms);
+        let workers = provider.get_workers().expect("workers");
+
+        assert_eq!(workers.len(), 1);
+        let worker = &workers[0];
+        assert_eq!(worker.worker_id, "worker-1");
+        assert_eq!(worker.hostname.as_deref(), Some("host-a"));
+        assert_eq!(worker.queues, vec!["default"]);
+        assert_eq!(worker.last_seen_ago, format_age_ago(3_000));
+        assert_eq!(worker.held_runs.len(), 1);
+        assert_eq!(worker.held_runs[0].run_id, "run_running");
+    }
+
+    #[test]
+    fn task_types_include_live_counts() {
+        let now_ms = 1_700_000_000_000;
+        let conn = setup_connection();
+        for migration in [
+            include_str!("../../absurd-sqlite-extension/migrations/11.sql"),
+            include_str!("../../absurd-sqlite-extension/migrations/12.sql"),
+        ] {
+            conn.execute_batch(migration)
+                .expect("apply task type schema");
+        }
+        seed_data(&conn, now_ms);
+        conn.execute(
+            "insert into absurd_task_types (queue_name, task_name, default_options, owner_team, concurrency_limit, updated_at)
+             values ('default', 'pending', jsonb('{\"max_attempts\":3}'), 'billing', 2, ?)",
+            [now_ms],
+        )
+        .expect("insert task type");
+
+        let provider = DataProvider::with_now(&conn, now_ms);
+        let task_types = provider.get_task_types().expect("task types");
+
+        assert_eq!(task_types.len(), 1);
+        let task_type = &task_types[0];
+        assert_eq!(task_type.task_name, "pending");
+        assert_eq!(task_type.owner_team.as_deref(), Some("billing"));
+        assert_eq!(task_type.concurrency_limit, Some(2));
+        assert_eq!(task_type.pending, 1);
+        assert_eq!(task_type.running, 0);
+        assert!(task_type.schema_json.is_none());
+        assert!(task_type
+            .default_options_json
+            .as_deref()
+            .is_some_and(|json| json.contains("\"max_attempts\": 3")));
+    }
+
+    #[test]
+    fn checkpoint_diff_lists_changed_paths() {
+        let before = serde_json::json!({"step": 1, "items": ["a"], "gone": true});
+        let after = serde_json::json!({"step": 2, "items": ["a", "b"], "new": null});
+
+        let changes = diff_json(&before, &after);
+        let mut summary: Vec<(&str, &str)> = changes
+            .iter()
+            .map(|change| (change.path.as_str(), change.kind.as_str()))
+            .collect();
+        summary.sort();
+        assert_eq!(
+            summary,
+            vec![
+                ("$.gone", "removed"),
+                ("$.items[1]", "added"),
+                ("$.new", "added"),
+                ("$.step", "changed"),
+            ]
+        );
+        let step = changes
+            .iter()
+            .find(|change| change.path == "$.step")
+            .unwrap();
+        assert_eq!(step.before.as_deref(), Some("1"));
+        assert_eq!(step.after.as_deref(), Some("2"));
+        assert!(diff_json(&after, &after).is_empty());
+    }
+}
@@ -0,0 +1,205 @@
+use absurd_server::auth::{AuthConfig, Scope};
+use absurd_server::http;
+use absurd_server::procedures::ProcedureHost;
+use absurd_server::tls::{self, TlsOptions};
+use rcgen::{BasicConstraints, CertificateParams, ExtendedKeyUsagePurpose, IsCa, KeyPair};
+use rusqlite::Connection;
+use rustls::pki_types::{CertificateDer, PrivateKeyDer, ServerName};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::sync::oneshot;
+
+struct SchemaHost {
+    db_path: String,
+}
+
+impl ProcedureHost for SchemaHost {
+    fn connect(&self) -> anyhow::Result<Connection> {
+        Ok(Connection::open(&self.db_path)?)
+    }
+
+    fn db_path(&self) -> anyhow::Result<String> {
+        Ok(self.db_path.clone())
+    }
+}
+
+struct Pki {
+    dir: PathBuf,
+    ca_pem: String,
+    client_cert_pem: String,
+    client_key_pem: String,
+}
+
+impl Drop for Pki {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.dir);
+    }
+}
+
+fn generate_pki() -> Pki {
+    let dir = std::env::temp_dir().join(format!("absurd-server-tls-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).expect("create pki dir");
+
+    let ca_key = KeyPair::generate().expect("ca key");
+    let mut ca_params = CertificateParams::new(Vec::<String>::new()).expect("ca params");
+    ca_params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+    let ca_cert = ca_params.self_signed(&ca_key).expect("ca cert");
+
+    let server_key = KeyPair::generate().expect("server key");
+    let mut server_params =
+        CertificateParams::new(vec!["localhost".to_string()]).expect("server params");
+    server_params.extended_key_usages = vec![ExtendedKeyUsagePurpose::ServerAuth];
+    let server_cert = server_params
+        .signed_by(&server_key, &ca_cert, &ca_key)
+        .expect("server cert");
+
+    let client_key = KeyPair::generate().expect("client key");
+    let mut client_params =
+        CertificateParams::new(vec!["client".to_string()]).expect("client params");
+    client_params.extended_key_usages = vec![ExtendedKeyUsagePurpose::ClientAuth];
+    let client_cert = client_params
+        .signed_by(&client_key, &ca_cert, &ca_key)
+        .expect("client cert");
+
+    std::fs::write(dir.join("ca.pem"), ca_cert.pem()).expect("write ca");
+    std::fs::write(dir.join("server.pem"), server_cert.pem()).expect("write cert");
+    std::fs::write(dir.join("server.key"), server_key.serialize_pem()).expect("write key");
+
+    Pki {
+        dir,
+        ca_pem: ca_cert.pem(),
+        client_cert_pem: client_cert.pem(),
+        client_key_pem: client_key.serialize_pem(),
+    }
+}
+
+fn schema_db(dir: &Path) -> String {
+    let db_path = dir.join("absurd.db");
+    let conn = Connection::open(&db_path).expect("open db");
+    conn.execute_batch(include_str!(
+        "../../absurd-sqlite-extension/migrations/1.sql"
+    ))
+    .expect("apply schema");
+    conn.execute(
+        "insert into absurd_queues (queue_name, created_at) values ('alpha', 1)",
+        [],
+    )
+    .expect("seed queue");
+    db_path.to_string_lossy().to_string()
+}
+
+fn client_config(pki: &Pki, with_cert: bool) -> Arc<rustls::ClientConfig> {
+    let provider = Arc::new(rustls::crypto::ring::default_provider());
+    let mut roots = rustls::RootCertStore::empty();
+    for cert in rustls_pemfile::certs(&mut pki.ca_pem.as_bytes()) {
+        roots.add(cert.expect("ca cert")).expect("add root");
+    }
+    let builder = rustls::ClientConfig::builder_with_provider(provider)
+        .with_safe_default_protocol_versions()
+        .expect("protocol versions")
+        .with_root_certificates(roots);
+    let config = if with_cert {
+        let chain = rustls_pemfile::certs(&mut pki.client_cert_pem.as_bytes())
+            .collect::<Result<Vec<CertificateDer<'static>>, _>>()
+            .expect("client chain");
+        let key: PrivateKeyDer<'static> =
+            rustls_pemfile::private_key(&mut pki.client_key_pem.as_bytes())
+                .expect("client key")
+                .expect("client key present");
+        builder
+            .with_client_auth_cert(chain, key)
+            .expect("client auth")
+    } else {
+        builder.with_no_client_auth()
+    };
+    Arc::new(config)
+}
+
+/// Send one GET over TLS and return the raw response, or the I/O error that
+/// ended the exchange.
+async fn get(
+    addr: std::net::SocketAddr,
+    config: Arc<rustls::ClientConfig>,
+    path: &str,
+) -> std::io::Result<String> {
+    let tcp = tokio::net::TcpStream::connect(addr).await?;
+    let connector = tokio_rustls::TlsConnector::from(config);
+    let mut stream = connector
+        .connect(ServerName::try_from("localhost").unwrap(), tcp)
+        .await?;
+    stream
+        .write_all(
+            format!(
+                "GET {} HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n",
+                path
+            )
+            .as_bytes(),
+        )
+        .await?;
+    let mut response = String::new();
+    stream.read_to_string(&mut response).await?;
+    Ok(response)
+}
+
+#[tokio::test]
+async fn mtls_requires_a_client_certificate() {
+    let pki = generate_pki();
+    let host = SchemaHost {
+        db_path: schema_db(&pki.dir),
+    };
+    let tls_config = tls::server_config(&TlsOptions {
+        cert_path: pki.dir.join("server.pem"),
+        key_path: pki.dir.join("server.key"),
+        client_ca_path: Some(pki.dir.join("ca.pem")),
+    })
+    .expect("server tls config");
+    let auth = AuthConfig {
+        tokens: Vec::new(),
+        client_cert_scope: Some(Scope::Read),
+    };
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0")
+        .await
+        .expect("bind");
+    let addr = listener.local_addr().expect("local addr");
+    let (shutdown_tx, shutdown_rx) = oneshot::channel::<()>();
+    let server = tokio::spawn(http::serve(
+        listener,
+        http::router(Arc::new(host), auth),
+        Some(tls_config),
+        async move {
+            let _ = shutdown_rx.await;
+        },
+    ));
+
+    let response = get(
+        addr,
+        client_config(&pki, true),
+        "/absurd-data/getQueueNames",
+    )
+    .await
+    .expect("request with client certificate");
+    assert!(response.starts_with("HTTP/1.1 200"), "{}", response);
+    assert!(response.contains(r#""data":["alpha"]"#), "{}", response);
+
+    let response = get(addr, client_config(&pki, true), "/absurd-data/createQueue")
+        .await
+        .expect("admin request with read certificate");
+    assert!(response.starts_with("HTTP/1.1 403"), "{}", response);
+
+    let rejected = get(
+        addr,
+        client_config(&pki, false),
+        "/absurd-data/getQueueNames",
+    )
+    .await;
+    assert!(
+        rejected.is_err() || rejected.as_deref().is_ok_and(str::is_empty),
+        "{:?}",
+        rejected
+    );
+
+    let _ = shutdown_tx.send(());
+    server.await.expect("join server").expect("serve");
+}
@@ -0,0 +1,35 @@
+mod common;
+
+use common::{claim, reset_claims, seed_pending, setup_db};
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion, Throughput};
+
+const QUEUE_DEPTHS: [usize; 3] = [10_000, 100_000, 1_000_000];
+const CLAIM_SIZES: [i64; 3] = [1, 10, 100];
+
+/// Claim latency against a queue whose depth stays constant: claimed runs
+/// are put back to pending between iterations, outside the measurement.
+fn bench_claim_task(c: &mut Criterion) {
+    for depth in QUEUE_DEPTHS {
+        let conn = setup_db();
+        seed_pending(&conn, depth);
+
+        let mut group = c.benchmark_group(format!("claim_task/depth_{}", depth));
+        if depth >= 1_000_000 {
+            group.sample_size(10);
+        }
+        for qty in CLAIM_SIZES {
+            group.throughput(Throughput::Elements(qty as u64));
+            group.bench_with_input(format!("qty_{}", qty), &qty, |b, &qty| {
+                b.iter_batched(
+                    || reset_claims(&conn),
+                    |_| assert_eq!(claim(&conn, "bench", qty).len() as i64, qty),
+                    BatchSize::PerIteration,
+                );
+            });
+        }
+        group.finish();
+    }
+}
+
+criterion_group!(benches, bench_claim_task);
+criterion_main!(benches);
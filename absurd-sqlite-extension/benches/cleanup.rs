@@ -6,6 +6,7 @@ const QUEUE_NAME: &str = "alpha";
 const BATCH_LIMIT: i64 = 1_000;
 const BENCH_SIZES: [usize; 5] = [100, 500, 1_000, 5_000, 10_000];
 
+#[allow(clippy::missing_transmute_annotations)]
 fn setup_db(task_count: usize, event_count: usize) -> Connection {
     unsafe {
         sqlite3_auto_extension(Some(std::mem::transmute(sqlite3_absurd_init as *const ())));
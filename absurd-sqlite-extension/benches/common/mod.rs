@@ -0,0 +1,196 @@
+//! Shared setup for the criterion benches.
+//!
+//! To check a change to the claim or run SQL for regressions, record a
+//! baseline first and compare against it afterwards:
+//!
+//! ```text
+//! cargo bench -p absurd-sqlite-extension -- --save-baseline before
+//! # apply the change
+//! cargo bench -p absurd-sqlite-extension -- --baseline before
+//! ```
+#![allow(dead_code)]
+
+use absurd::sqlite3_absurd_init;
+use rusqlite::{ffi::sqlite3_auto_extension, params, Connection};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+pub const QUEUE_NAME: &str = "alpha";
+
+#[allow(clippy::missing_transmute_annotations)]
+pub fn register_extension() {
+    unsafe {
+        sqlite3_auto_extension(Some(std::mem::transmute(sqlite3_absurd_init as *const ())));
+    }
+}
+
+/// A migrated in-memory database with an empty `QUEUE_NAME` queue.
+pub fn setup_db() -> Connection {
+    register_extension();
+    let conn = Connection::open_in_memory().unwrap();
+    init_schema(&conn);
+    conn
+}
+
+fn init_schema(conn: &Connection) {
+    let _: i64 = conn
+        .query_row("select absurd_apply_migrations()", [], |row| row.get(0))
+        .unwrap();
+    let _: i64 = conn
+        .query_row("select absurd_create_queue(?1)", [QUEUE_NAME], |row| {
+            row.get(0)
+        })
+        .unwrap();
+}
+
+/// Insert `count` pending tasks, each with one pending run, directly in SQL.
+/// Much faster than spawning when a bench needs a deep queue.
+pub fn seed_pending(conn: &Connection, count: usize) {
+    let offset: i64 = conn
+        .query_row("select count(*) from absurd_tasks", [], |row| row.get(0))
+        .unwrap();
+    conn.execute_batch("begin").unwrap();
+    conn.execute(
+        "with recursive seq(i) as (
+           select ?3 + 1
+           union all
+           select i + 1 from seq where i < ?3 + ?2
+         )
+         insert into absurd_tasks (queue_name, task_id, task_name, params, state, enqueue_at)
+         select ?1, 'task_' || i, 'demo', jsonb('{}'), 'pending', i from seq",
+        params![QUEUE_NAME, count as i64, offset],
+    )
+    .unwrap();
+    conn.execute(
+        "with recursive seq(i) as (
+           select ?3 + 1
+           union all
+           select i + 1 from seq where i < ?3 + ?2
+         )
+         insert into absurd_runs (queue_name, run_id, task_id, attempt, state, available_at)
+         select ?1, 'run_' || i, 'task_' || i, 1, 'pending', i from seq",
+        params![QUEUE_NAME, count as i64, offset],
+    )
+    .unwrap();
+    conn.execute(
+        "update absurd_tasks
+            set last_attempt_run = 'run_' || substr(task_id, 6)
+          where queue_name = ?1
+            and last_attempt_run is null",
+        [QUEUE_NAME],
+    )
+    .unwrap();
+    conn.execute_batch("commit").unwrap();
+}
+
+/// Put every running run back to pending so the queue depth stays constant
+/// across iterations.
+pub fn reset_claims(conn: &Connection) {
+    conn.execute(
+        "update absurd_runs
+            set state = 'pending', claimed_by = null, claim_expires_at = null
+          where queue_name = ?1
+            and state = 'running'",
+        [QUEUE_NAME],
+    )
+    .unwrap();
+    conn.execute(
+        "update absurd_tasks
+            set state = 'pending'
+          where queue_name = ?1
+            and state = 'running'",
+        [QUEUE_NAME],
+    )
+    .unwrap();
+}
+
+/// Claim up to `qty` runs, returning their run ids.
+pub fn claim(conn: &Connection, worker_id: &str, qty: i64) -> Vec<String> {
+    try_claim(conn, worker_id, qty).unwrap()
+}
+
+pub fn try_claim(conn: &Connection, worker_id: &str, qty: i64) -> rusqlite::Result<Vec<String>> {
+    conn.prepare_cached("select run_id from absurd_claim_task(?1, ?2, 30, ?3)")?
+        .query_map(params![QUEUE_NAME, worker_id, qty], |row| row.get(0))?
+        .collect()
+}
+
+pub fn complete(conn: &Connection, run_id: &str) {
+    try_complete(conn, run_id).unwrap()
+}
+
+pub fn try_complete(conn: &Connection, run_id: &str) -> rusqlite::Result<()> {
+    conn.prepare_cached("select absurd_complete_run(?1, ?2, '{}')")?
+        .query_row(params![QUEUE_NAME, run_id], |row| {
+            row.get::<_, Option<i64>>(0)
+        })?;
+    Ok(())
+}
+
+/// Run `f` until it stops failing with a busy error. The extension's
+/// functions open their write transaction from inside the caller's
+/// statement, so SQLite reports busy without waiting on the busy timeout
+/// and workers sharing a file have to retry.
+pub fn retry_busy<T>(mut f: impl FnMut() -> rusqlite::Result<T>) -> T {
+    loop {
+        match f() {
+            Ok(value) => return value,
+            Err(err) if is_busy(&err) => std::thread::yield_now(),
+            Err(err) => panic!("{}", err),
+        }
+    }
+}
+
+/// Busy errors from inside the extension surface as a message carrying the
+/// SQLite result code, which may be an extended busy code such as
+/// SQLITE_BUSY_SNAPSHOT.
+fn is_busy(err: &rusqlite::Error) -> bool {
+    let message = err.to_string();
+    message
+        .split_once("(code ")
+        .and_then(|(_, rest)| rest.split_once(')'))
+        .and_then(|(code, _)| code.parse::<i32>().ok())
+        .is_some_and(|code| code & 0xff == rusqlite::ffi::SQLITE_BUSY)
+}
+
+/// A file-backed database (WAL, as the extension sets on load) that is
+/// deleted when dropped.
+pub struct TempDb {
+    path: PathBuf,
+}
+
+impl TempDb {
+    pub fn new() -> TempDb {
+        static COUNTER: AtomicUsize = AtomicUsize::new(0);
+        register_extension();
+        let path = std::env::temp_dir().join(format!(
+            "absurd-bench-{}-{}.db",
+            std::process::id(),
+            COUNTER.fetch_add(1, Ordering::Relaxed)
+        ));
+        let db = TempDb { path };
+        init_schema(&db.connect());
+        db
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn connect(&self) -> Connection {
+        let conn = Connection::open(&self.path).unwrap();
+        conn.busy_timeout(std::time::Duration::from_secs(30))
+            .unwrap();
+        conn
+    }
+}
+
+impl Drop for TempDb {
+    fn drop(&mut self) {
+        for suffix in ["", "-wal", "-shm"] {
+            let mut path = self.path.clone().into_os_string();
+            path.push(suffix);
+            let _ = std::fs::remove_file(path);
+        }
+    }
+}
@@ -0,0 +1,64 @@
+mod common;
+
+use common::{retry_busy, seed_pending, try_claim, try_complete, TempDb};
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion, Throughput};
+use std::thread;
+
+const TASKS: usize = 2_000;
+const WORKER_COUNTS: [usize; 4] = [1, 2, 4, 8];
+const CLAIM_BATCH: i64 = 10;
+
+/// Drain a file-backed WAL database with several workers, each on its own
+/// connection, claiming in batches and completing every run it gets. Busy
+/// calls are retried, as a real worker would.
+fn drain(db: &TempDb, workers: usize) {
+    let completed: usize = thread::scope(|scope| {
+        let handles: Vec<_> = (0..workers)
+            .map(|index| {
+                let conn = db.connect();
+                scope.spawn(move || {
+                    let worker_id = format!("bench-{}", index);
+                    let mut completed = 0;
+                    loop {
+                        let run_ids = retry_busy(|| try_claim(&conn, &worker_id, CLAIM_BATCH));
+                        if run_ids.is_empty() {
+                            return completed;
+                        }
+                        for run_id in &run_ids {
+                            retry_busy(|| try_complete(&conn, run_id));
+                        }
+                        completed += run_ids.len();
+                    }
+                })
+            })
+            .collect();
+        handles.into_iter().map(|h| h.join().unwrap()).sum()
+    });
+    assert_eq!(completed, TASKS);
+}
+
+fn bench_contention(c: &mut Criterion) {
+    let mut group = c.benchmark_group("wal_contention");
+    group.sample_size(10);
+    group.throughput(Throughput::Elements(TASKS as u64));
+    for workers in WORKER_COUNTS {
+        group.bench_with_input(format!("workers_{}", workers), &workers, |b, &workers| {
+            b.iter_batched(
+                || {
+                    let db = TempDb::new();
+                    seed_pending(&db.connect(), TASKS);
+                    db
+                },
+                |db| {
+                    drain(&db, workers);
+                    db
+                },
+                BatchSize::PerIteration,
+            );
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_contention);
+criterion_main!(benches);
@@ -0,0 +1,131 @@
+mod common;
+
+use common::{claim, complete, setup_db, QUEUE_NAME};
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion, Throughput};
+use rusqlite::{params, Connection};
+
+const ROUND_TRIP_TASKS: usize = 100;
+const FANOUT_SIZES: [usize; 4] = [10, 100, 1_000, 10_000];
+
+fn spawn_tasks(conn: &Connection, count: usize, options: &str) {
+    let items: Vec<String> = (0..count)
+        .map(|_| {
+            format!(
+                r#"{{"task_name":"demo","params":{{}},"options":{}}}"#,
+                options
+            )
+        })
+        .collect();
+    let created: i64 = conn
+        .query_row(
+            "select sum(created) from absurd_spawn_tasks(?1, ?2)",
+            params![QUEUE_NAME, format!("[{}]", items.join(","))],
+            |row| row.get(0),
+        )
+        .unwrap();
+    assert_eq!(created as usize, count);
+}
+
+/// Claim one run at a time and finish it, the way a single worker loops.
+fn bench_round_trips(c: &mut Criterion) {
+    let mut group = c.benchmark_group("round_trip");
+    group.throughput(Throughput::Elements(ROUND_TRIP_TASKS as u64));
+
+    group.bench_function("complete", |b| {
+        b.iter_batched(
+            || {
+                let conn = setup_db();
+                spawn_tasks(&conn, ROUND_TRIP_TASKS, "{}");
+                conn
+            },
+            |conn| {
+                for _ in 0..ROUND_TRIP_TASKS {
+                    let run_ids = claim(&conn, "bench", 1);
+                    complete(&conn, &run_ids[0]);
+                }
+                conn
+            },
+            BatchSize::LargeInput,
+        );
+    });
+
+    group.bench_function("fail", |b| {
+        b.iter_batched(
+            || {
+                let conn = setup_db();
+                spawn_tasks(&conn, ROUND_TRIP_TASKS, r#"{"max_attempts":1}"#);
+                conn
+            },
+            |conn| {
+                for _ in 0..ROUND_TRIP_TASKS {
+                    let run_ids = claim(&conn, "bench", 1);
+                    let _: Option<i64> = conn
+                        .prepare_cached(
+                            "select absurd_fail_run(?1, ?2, '{\"name\":\"boom\"}', null)",
+                        )
+                        .unwrap()
+                        .query_row(params![QUEUE_NAME, run_ids[0]], |row| row.get(0))
+                        .unwrap();
+                }
+                conn
+            },
+            BatchSize::LargeInput,
+        );
+    });
+
+    group.finish();
+}
+
+/// A database where `waiters` suspended runs all await the same event.
+fn setup_waiters(waiters: usize) -> Connection {
+    let conn = setup_db();
+    spawn_tasks(&conn, waiters, "{}");
+    let runs: Vec<(String, String)> = conn
+        .prepare("select task_id, run_id from absurd_claim_task(?1, 'bench', 300, ?2)")
+        .unwrap()
+        .query_map(params![QUEUE_NAME, waiters as i64], |row| {
+            Ok((row.get(0)?, row.get(1)?))
+        })
+        .unwrap()
+        .map(|row| row.unwrap())
+        .collect();
+    let mut await_event = conn
+        .prepare("select should_suspend from absurd_await_event(?1, ?2, ?3, 'wait', 'go', null)")
+        .unwrap();
+    for (task_id, run_id) in &runs {
+        let suspended: i64 = await_event
+            .query_row(params![QUEUE_NAME, task_id, run_id], |row| row.get(0))
+            .unwrap();
+        assert_eq!(suspended, 1);
+    }
+    drop(await_event);
+    conn
+}
+
+fn bench_emit_fanout(c: &mut Criterion) {
+    let mut group = c.benchmark_group("emit_event_fanout");
+    group.sample_size(20);
+    for waiters in FANOUT_SIZES {
+        group.throughput(Throughput::Elements(waiters as u64));
+        group.bench_with_input(waiters.to_string(), &waiters, |b, &waiters| {
+            b.iter_batched(
+                || setup_waiters(waiters),
+                |conn| {
+                    let _: Option<i64> = conn
+                        .query_row(
+                            "select absurd_emit_event(?1, 'go', '{}')",
+                            [QUEUE_NAME],
+                            |row| row.get(0),
+                        )
+                        .unwrap();
+                    conn
+                },
+                BatchSize::LargeInput,
+            );
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_round_trips, bench_emit_fanout);
+criterion_main!(benches);
@@ -0,0 +1,71 @@
+mod common;
+
+use common::{setup_db, QUEUE_NAME};
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion, Throughput};
+use rusqlite::Connection;
+
+const BENCH_SIZES: [usize; 3] = [100, 1_000, 10_000];
+
+fn batch_json(count: usize) -> String {
+    let items: Vec<String> = (0..count)
+        .map(|index| format!(r#"{{"task_name":"demo","params":{{"index":{}}}}}"#, index))
+        .collect();
+    format!("[{}]", items.join(","))
+}
+
+fn spawn_one_by_one(conn: &Connection, count: usize) {
+    let mut stmt = conn
+        .prepare("select task_id from absurd_spawn_task(?1, 'demo', ?2)")
+        .unwrap();
+    for index in 0..count {
+        let params = format!(r#"{{"index":{}}}"#, index);
+        let _: String = stmt
+            .query_row((QUEUE_NAME, params.as_str()), |row| row.get(0))
+            .unwrap();
+    }
+}
+
+fn spawn_batch(conn: &Connection, tasks: &str, expected: usize) {
+    let created: i64 = conn
+        .query_row(
+            "select sum(created) from absurd_spawn_tasks(?1, ?2)",
+            (QUEUE_NAME, tasks),
+            |row| row.get(0),
+        )
+        .unwrap();
+    assert_eq!(created as usize, expected);
+}
+
+fn bench_spawn_task(c: &mut Criterion) {
+    let mut group = c.benchmark_group("spawn_task");
+    for size in BENCH_SIZES {
+        group.throughput(Throughput::Elements(size as u64));
+        group.bench_with_input(size.to_string(), &size, |b, &count| {
+            b.iter_batched(
+                setup_db,
+                |conn| spawn_one_by_one(&conn, count),
+                BatchSize::LargeInput,
+            );
+        });
+    }
+    group.finish();
+}
+
+fn bench_spawn_tasks(c: &mut Criterion) {
+    let mut group = c.benchmark_group("spawn_tasks");
+    for size in BENCH_SIZES {
+        group.throughput(Throughput::Elements(size as u64));
+        let tasks = batch_json(size);
+        group.bench_with_input(size.to_string(), &size, |b, &count| {
+            b.iter_batched(
+                setup_db,
+                |conn| spawn_batch(&conn, &tasks, count),
+                BatchSize::LargeInput,
+            );
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_spawn_task, bench_spawn_tasks);
+criterion_main!(benches);
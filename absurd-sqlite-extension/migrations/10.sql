@@ -0,0 +1,11 @@
+-- introduced_version: 0.1.0
+-- Content-addressed storage for payloads above a queue's
+-- `offload_threshold_bytes`. Rows hold deflate-compressed JSON keyed by the
+-- SHA-256 of the uncompressed text and are shared across queues.
+
+create table if not exists absurd_blobs (
+  hash text primary key,
+  size integer not null,
+  data blob not null,
+  created_at datetime not null default (cast(strftime('%s','now') as integer) * 1000)
+);
@@ -0,0 +1,16 @@
+-- introduced_version: 0.1.0
+-- Registered task types: an optional JSON Schema that spawned params must
+-- satisfy and default spawn options applied when a spawn omits them.
+
+create table if not exists absurd_task_types (
+  queue_name text not null,
+  task_name text not null,
+  json_schema blob,
+  default_options blob,
+  created_at datetime not null default (cast(strftime('%s','now') as integer) * 1000),
+  updated_at datetime not null default (cast(strftime('%s','now') as integer) * 1000),
+  primary key (queue_name, task_name),
+  foreign key (queue_name) references absurd_queues(queue_name) on delete cascade,
+  check (json_schema is null or json_valid(json(json_schema))),
+  check (default_options is null or json_valid(json(default_options)))
+);
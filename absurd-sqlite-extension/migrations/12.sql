@@ -0,0 +1,8 @@
+-- introduced_version: 0.1.0
+-- Task type metadata: ownership details shown in tooling, a claim timeout
+-- that overrides the worker's, and a cap on concurrently running tasks.
+
+alter table absurd_task_types add column description text;
+alter table absurd_task_types add column owner_team text;
+alter table absurd_task_types add column claim_timeout integer check (claim_timeout is null or claim_timeout > 0);
+alter table absurd_task_types add column concurrency_limit integer check (concurrency_limit is null or concurrency_limit > 0);
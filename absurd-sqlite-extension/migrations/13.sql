@@ -0,0 +1,6 @@
+-- introduced_version: 0.1.0
+-- Lets claims restricted to a set of task names find their candidates
+-- without scanning every run on the queue.
+
+create index if not exists absurd_tasks_task_name_idx
+  on absurd_tasks (queue_name, task_name, state);
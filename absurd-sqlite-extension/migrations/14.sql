@@ -0,0 +1,10 @@
+-- introduced_version: 0.1.0
+-- Task priority for queues claimed in priority order, and a supersede key:
+-- claiming a task cancels older pending tasks that share its key.
+
+alter table absurd_tasks add column priority integer not null default 0;
+alter table absurd_tasks add column supersede_key text;
+
+create index if not exists absurd_tasks_supersede_key_idx
+  on absurd_tasks (queue_name, supersede_key, state)
+  where supersede_key is not null;
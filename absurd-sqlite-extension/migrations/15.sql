@@ -0,0 +1,20 @@
+-- introduced_version: 0.1.0
+-- Per-queue event sequence counter. Log cleanup deletes old rows, so the
+-- next sequence cannot be derived from the log without going backwards.
+
+create table if not exists absurd_event_sequences (
+  queue_name text primary key,
+  last_seq integer not null,
+  foreign key (queue_name) references absurd_queues(queue_name) on delete cascade
+);
+
+insert into absurd_event_sequences (queue_name, last_seq)
+select queue_name, max(seq)
+  from (
+    select queue_name, seq from absurd_event_log
+    union all
+    select queue_name, seq from absurd_events where seq is not null
+  )
+ where seq is not null
+ group by queue_name
+on conflict (queue_name) do nothing;
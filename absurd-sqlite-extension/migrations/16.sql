@@ -0,0 +1,161 @@
+-- introduced_version: 0.1.0
+-- Reference counts for offloaded blobs. Triggers on every column that can
+-- hold a blob reference keep `ref_count` current, so garbage collection only
+-- has to delete rows that dropped to zero instead of scanning the payloads.
+
+alter table absurd_blobs add column ref_count integer not null default 0;
+
+with refs as (
+  select ref, count(*) as n
+    from (
+      select params ->> '$."$absurd_blob"' as ref from absurd_tasks
+      union all
+      select completed_payload ->> '$."$absurd_blob"' as ref from absurd_tasks
+      union all
+      select result ->> '$."$absurd_blob"' as ref from absurd_runs
+      union all
+      select state ->> '$."$absurd_blob"' as ref from absurd_checkpoints
+      union all
+      select state ->> '$."$absurd_blob"' as ref from absurd_checkpoint_history
+    )
+   where ref is not null
+   group by ref
+)
+update absurd_blobs
+   set ref_count = refs.n
+  from refs
+ where refs.ref = absurd_blobs.hash;
+
+create index if not exists absurd_blobs_unreferenced_idx
+  on absurd_blobs (hash)
+  where ref_count <= 0;
+
+create trigger if not exists absurd_tasks_params_blob_insert
+after insert on absurd_tasks
+when new.params ->> '$."$absurd_blob"' is not null
+begin
+  update absurd_blobs set ref_count = ref_count + 1
+   where hash = new.params ->> '$."$absurd_blob"';
+end;
+
+create trigger if not exists absurd_tasks_params_blob_update
+after update of params on absurd_tasks
+when old.params ->> '$."$absurd_blob"' is not null or new.params ->> '$."$absurd_blob"' is not null
+begin
+  update absurd_blobs set ref_count = ref_count - 1
+   where hash = old.params ->> '$."$absurd_blob"';
+  update absurd_blobs set ref_count = ref_count + 1
+   where hash = new.params ->> '$."$absurd_blob"';
+end;
+
+create trigger if not exists absurd_tasks_params_blob_delete
+after delete on absurd_tasks
+when old.params ->> '$."$absurd_blob"' is not null
+begin
+  update absurd_blobs set ref_count = ref_count - 1
+   where hash = old.params ->> '$."$absurd_blob"';
+end;
+
+create trigger if not exists absurd_tasks_completed_payload_blob_insert
+after insert on absurd_tasks
+when new.completed_payload ->> '$."$absurd_blob"' is not null
+begin
+  update absurd_blobs set ref_count = ref_count + 1
+   where hash = new.completed_payload ->> '$."$absurd_blob"';
+end;
+
+create trigger if not exists absurd_tasks_completed_payload_blob_update
+after update of completed_payload on absurd_tasks
+when old.completed_payload ->> '$."$absurd_blob"' is not null or new.completed_payload ->> '$."$absurd_blob"' is not null
+begin
+  update absurd_blobs set ref_count = ref_count - 1
+   where hash = old.completed_payload ->> '$."$absurd_blob"';
+  update absurd_blobs set ref_count = ref_count + 1
+   where hash = new.completed_payload ->> '$."$absurd_blob"';
+end;
+
+create trigger if not exists absurd_tasks_completed_payload_blob_delete
+after delete on absurd_tasks
+when old.completed_payload ->> '$."$absurd_blob"' is not null
+begin
+  update absurd_blobs set ref_count = ref_count - 1
+   where hash = old.completed_payload ->> '$."$absurd_blob"';
+end;
+
+create trigger if not exists absurd_runs_result_blob_insert
+after insert on absurd_runs
+when new.result ->> '$."$absurd_blob"' is not null
+begin
+  update absurd_blobs set ref_count = ref_count + 1
+   where hash = new.result ->> '$."$absurd_blob"';
+end;
+
+create trigger if not exists absurd_runs_result_blob_update
+after update of result on absurd_runs
+when old.result ->> '$."$absurd_blob"' is not null or new.result ->> '$."$absurd_blob"' is not null
+begin
+  update absurd_blobs set ref_count = ref_count - 1
+   where hash = old.result ->> '$."$absurd_blob"';
+  update absurd_blobs set ref_count = ref_count + 1
+   where hash = new.result ->> '$."$absurd_blob"';
+end;
+
+create trigger if not exists absurd_runs_result_blob_delete
+after delete on absurd_runs
+when old.result ->> '$."$absurd_blob"' is not null
+begin
+  update absurd_blobs set ref_count = ref_count - 1
+   where hash = old.result ->> '$."$absurd_blob"';
+end;
+
+create trigger if not exists absurd_checkpoints_state_blob_insert
+after insert on absurd_checkpoints
+when new.state ->> '$."$absurd_blob"' is not null
+begin
+  update absurd_blobs set ref_count = ref_count + 1
+   where hash = new.state ->> '$."$absurd_blob"';
+end;
+
+create trigger if not exists absurd_checkpoints_state_blob_update
+after update of state on absurd_checkpoints
+when old.state ->> '$."$absurd_blob"' is not null or new.state ->> '$."$absurd_blob"' is not null
+begin
+  update absurd_blobs set ref_count = ref_count - 1
+   where hash = old.state ->> '$."$absurd_blob"';
+  update absurd_blobs set ref_count = ref_count + 1
+   where hash = new.state ->> '$."$absurd_blob"';
+end;
+
+create trigger if not exists absurd_checkpoints_state_blob_delete
+after delete on absurd_checkpoints
+when old.state ->> '$."$absurd_blob"' is not null
+begin
+  update absurd_blobs set ref_count = ref_count - 1
+   where hash = old.state ->> '$."$absurd_blob"';
+end;
+
+create trigger if not exists absurd_checkpoint_history_state_blob_insert
+after insert on absurd_checkpoint_history
+when new.state ->> '$."$absurd_blob"' is not null
+begin
+  update absurd_blobs set ref_count = ref_count + 1
+   where hash = new.state ->> '$."$absurd_blob"';
+end;
+
+create trigger if not exists absurd_checkpoint_history_state_blob_update
+after update of state on absurd_checkpoint_history
+when old.state ->> '$."$absurd_blob"' is not null or new.state ->> '$."$absurd_blob"' is not null
+begin
+  update absurd_blobs set ref_count = ref_count - 1
+   where hash = old.state ->> '$."$absurd_blob"';
+  update absurd_blobs set ref_count = ref_count + 1
+   where hash = new.state ->> '$."$absurd_blob"';
+end;
+
+create trigger if not exists absurd_checkpoint_history_state_blob_delete
+after delete on absurd_checkpoint_history
+when old.state ->> '$."$absurd_blob"' is not null
+begin
+  update absurd_blobs set ref_count = ref_count - 1
+   where hash = old.state ->> '$."$absurd_blob"';
+end;
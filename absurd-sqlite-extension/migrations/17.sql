@@ -0,0 +1,5 @@
+-- introduced_version: 0.1.0
+-- The step that parked a sleeping run. A payload-less wake only counts as a
+-- timeout for that step, not for a later await on the same event name.
+
+alter table absurd_runs add column wake_step text;
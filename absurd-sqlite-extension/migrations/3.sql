@@ -0,0 +1,21 @@
+-- introduced_version: 0.1.0
+-- Worker registry refreshed by claim and heartbeat calls.
+
+create table if not exists absurd_workers (
+  worker_id text primary key,
+  hostname text,
+  pid integer,
+  metadata blob,
+  queues text not null default '[]',
+  first_seen datetime not null default (cast(strftime('%s','now') as integer) * 1000),
+  last_seen datetime not null default (cast(strftime('%s','now') as integer) * 1000),
+  check (json_valid(queues)),
+  check (metadata is null or json_valid(json(metadata)))
+);
+
+create index if not exists absurd_workers_last_seen_idx
+  on absurd_workers (last_seen);
+
+create index if not exists absurd_runs_claimed_by_idx
+  on absurd_runs (claimed_by)
+  where claimed_by is not null;
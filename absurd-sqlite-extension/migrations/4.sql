@@ -0,0 +1,30 @@
+-- introduced_version: 0.1.0
+-- Per-queue settings and the opt-in append-only event log.
+
+create table if not exists absurd_queue_settings (
+  queue_name text not null,
+  key text not null,
+  value text not null,
+  primary key (queue_name, key),
+  foreign key (queue_name) references absurd_queues(queue_name) on delete cascade
+);
+
+create table if not exists absurd_event_log (
+  queue_name text not null,
+  seq integer not null,
+  event_name text not null,
+  payload blob,
+  emitted_at datetime not null default (cast(strftime('%s','now') as integer) * 1000),
+  primary key (queue_name, seq),
+  foreign key (queue_name) references absurd_queues(queue_name) on delete cascade,
+  check (payload is null or json_valid(json(payload)))
+);
+
+create index if not exists absurd_event_log_event_idx
+  on absurd_event_log (queue_name, event_name, seq);
+
+-- Latest logged sequence for an event, a wait's lower bound, and the
+-- emission a resolved await checkpoint was taken from.
+alter table absurd_events add column seq integer;
+alter table absurd_waits add column after_seq integer;
+alter table absurd_checkpoints add column event_seq integer;
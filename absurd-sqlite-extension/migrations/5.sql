@@ -0,0 +1,26 @@
+-- introduced_version: 0.1.0
+-- Glob-pattern waits, kept apart from absurd_waits so exact-name lookups
+-- stay on the (queue_name, event_name) index.
+
+create table if not exists absurd_pattern_waits (
+  queue_name text not null,
+  task_id text not null,
+  run_id text not null,
+  step_name text not null,
+  pattern text not null,
+  timeout_at datetime,
+  after_seq integer,
+  created_at datetime not null default (cast(strftime('%s','now') as integer) * 1000),
+  primary key (queue_name, run_id, step_name),
+  foreign key (queue_name, task_id) references absurd_tasks(queue_name, task_id) on delete cascade,
+  foreign key (queue_name, run_id) references absurd_runs(queue_name, run_id) on delete cascade
+);
+
+create index if not exists absurd_pattern_waits_queue_idx
+  on absurd_pattern_waits (queue_name, pattern);
+
+create index if not exists absurd_pattern_waits_task_idx
+  on absurd_pattern_waits (queue_name, task_id);
+
+-- Concrete event that resolved an await checkpoint.
+alter table absurd_checkpoints add column event_name text;
@@ -0,0 +1,35 @@
+-- introduced_version: 0.1.0
+-- Allow one step to wait on several events (any/all). The primary key now
+-- includes event_name, so absurd_waits is rebuilt.
+
+create table absurd_waits_new (
+  queue_name text not null,
+  task_id text not null,
+  run_id text not null,
+  step_name text not null,
+  event_name text not null,
+  timeout_at datetime,
+  created_at datetime not null default (cast(strftime('%s','now') as integer) * 1000),
+  after_seq integer,
+  wait_mode text check (wait_mode is null or wait_mode in ('any','all')),
+  fired_payload blob,
+  primary key (queue_name, run_id, step_name, event_name),
+  foreign key (queue_name, task_id) references absurd_tasks(queue_name, task_id) on delete cascade,
+  foreign key (queue_name, run_id) references absurd_runs(queue_name, run_id) on delete cascade,
+  foreign key (queue_name, event_name) references absurd_events(queue_name, event_name) on delete cascade,
+  check (fired_payload is null or json_valid(json(fired_payload)))
+);
+
+insert into absurd_waits_new (queue_name, task_id, run_id, step_name, event_name, timeout_at, created_at, after_seq)
+  select queue_name, task_id, run_id, step_name, event_name, timeout_at, created_at, after_seq
+    from absurd_waits;
+
+drop table absurd_waits;
+
+alter table absurd_waits_new rename to absurd_waits;
+
+create index if not exists absurd_waits_event_idx
+  on absurd_waits (queue_name, event_name);
+
+create index if not exists absurd_waits_task_idx
+  on absurd_waits (queue_name, task_id);
@@ -0,0 +1,33 @@
+-- introduced_version: 0.1.0
+-- Per-task signals. Unlike events they are addressed to a single task,
+-- buffered until awaited, and consumed by exactly one await.
+
+create table if not exists absurd_signals (
+  signal_id integer primary key autoincrement,
+  queue_name text not null,
+  task_id text not null,
+  signal_name text not null,
+  payload blob,
+  created_at datetime not null default (cast(strftime('%s','now') as integer) * 1000),
+  foreign key (queue_name, task_id) references absurd_tasks(queue_name, task_id) on delete cascade,
+  check (payload is null or json_valid(json(payload)))
+);
+
+create index if not exists absurd_signals_task_idx
+  on absurd_signals (queue_name, task_id, signal_name, signal_id);
+
+create table if not exists absurd_signal_waits (
+  queue_name text not null,
+  task_id text not null,
+  run_id text not null,
+  step_name text not null,
+  signal_name text not null,
+  timeout_at datetime,
+  created_at datetime not null default (cast(strftime('%s','now') as integer) * 1000),
+  primary key (queue_name, run_id, step_name),
+  foreign key (queue_name, task_id) references absurd_tasks(queue_name, task_id) on delete cascade,
+  foreign key (queue_name, run_id) references absurd_runs(queue_name, run_id) on delete cascade
+);
+
+create index if not exists absurd_signal_waits_task_idx
+  on absurd_signal_waits (queue_name, task_id, signal_name);
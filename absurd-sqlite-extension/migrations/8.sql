@@ -0,0 +1,14 @@
+-- introduced_version: 0.1.0
+-- Forwarding rules that redeliver emits from one queue into another.
+-- Rules are dropped with either queue.
+
+create table if not exists absurd_event_forwards (
+  source_queue text not null,
+  target_queue text not null,
+  event_pattern text not null default '*',
+  created_at datetime not null default (cast(strftime('%s','now') as integer) * 1000),
+  primary key (source_queue, target_queue, event_pattern),
+  foreign key (source_queue) references absurd_queues(queue_name) on delete cascade,
+  foreign key (target_queue) references absurd_queues(queue_name) on delete cascade,
+  check (source_queue <> target_queue)
+);
@@ -0,0 +1,20 @@
+-- introduced_version: 0.1.0
+-- Append-only record of checkpoint writes, filled only for queues with the
+-- `checkpoint_history` setting enabled.
+
+create table if not exists absurd_checkpoint_history (
+  history_id integer primary key autoincrement,
+  queue_name text not null,
+  task_id text not null,
+  checkpoint_name text not null,
+  attempt integer not null,
+  run_id text,
+  state blob,
+  status text not null,
+  updated_at datetime not null,
+  foreign key (queue_name, task_id) references absurd_tasks(queue_name, task_id) on delete cascade,
+  check (state is null or json_valid(json(state)))
+);
+
+create index if not exists absurd_checkpoint_history_step_idx
+  on absurd_checkpoint_history (queue_name, task_id, checkpoint_name, history_id);
@@ -0,0 +1,192 @@
+use crate::blob;
+use crate::run::{self, TerminalCutoffs};
+use crate::sql;
+use crate::validate;
+use sqlite3ext_sys::sqlite3;
+use sqlite_loadable::prelude::*;
+use sqlite_loadable::{api, Error, Result};
+
+/// Tables copied into the archive, with the columns that identify a row.
+/// Archive tables carry no constraints besides a unique index on these keys,
+/// so re-archiving the same rows replaces them instead of failing.
+const ARCHIVE_TABLES: &[(&str, &str)] = &[
+    ("absurd_tasks", "queue_name, task_id"),
+    ("absurd_runs", "queue_name, run_id"),
+    ("absurd_checkpoints", "queue_name, task_id, checkpoint_name"),
+    ("absurd_checkpoint_history", "history_id"),
+    ("absurd_blobs", "hash"),
+];
+
+/// Ensure `schema` names a database attached to this connection.
+fn ensure_attached(db: *mut sqlite3, schema: &str) -> Result<()> {
+    if schema.eq_ignore_ascii_case("main") || schema.eq_ignore_ascii_case("temp") {
+        return Err(Error::new_message(
+            "archive_schema must name an attached database, not main or temp",
+        ));
+    }
+    let attached = sql::query_row_i64(
+        db,
+        "select count(*) from pragma_database_list where name = ?1",
+        &[schema],
+    )?;
+    if attached == 0 {
+        return Err(Error::new_message(format!(
+            "archive schema is not attached: {}",
+            schema
+        )));
+    }
+    Ok(())
+}
+
+/// Create the archive copy of `table` if needed and add any columns that the
+/// main table gained since the archive was first written.
+fn ensure_archive_table(
+    db: *mut sqlite3,
+    schema: &str,
+    table: &str,
+    key: &str,
+) -> Result<Vec<String>> {
+    let columns = sql::table_columns(db, "main", table)?;
+    let archived = sql::table_columns(db, schema, table)?;
+    let schema_ident = sql::quote_ident(schema);
+
+    if archived.is_empty() {
+        let definition = columns
+            .iter()
+            .map(|(name, decl_type)| format!("{} {}", sql::quote_ident(name), decl_type))
+            .collect::<Vec<_>>()
+            .join(", ");
+        sql::exec_batch(
+            db,
+            &format!("create table {}.{} ({})", schema_ident, table, definition),
+        )?;
+        sql::exec_batch(
+            db,
+            &format!(
+                "create unique index if not exists {}.{}_archive_key on {} ({})",
+                schema_ident, table, table, key
+            ),
+        )?;
+    } else {
+        for (name, decl_type) in &columns {
+            if archived.iter().any(|(existing, _)| existing == name) {
+                continue;
+            }
+            sql::exec_batch(
+                db,
+                &format!(
+                    "alter table {}.{} add column {} {}",
+                    schema_ident,
+                    table,
+                    sql::quote_ident(name),
+                    decl_type
+                ),
+            )?;
+        }
+    }
+
+    Ok(columns
+        .into_iter()
+        .map(|(name, _)| sql::quote_ident(&name))
+        .collect())
+}
+
+/// Copy the tasks listed in `temp_table`, with their runs, checkpoints,
+/// checkpoint history and any payload blobs they reference, into the archive
+/// schema.
+pub fn copy_selected_tasks(
+    db: *mut sqlite3,
+    schema: &str,
+    queue_name: &str,
+    temp_table: &str,
+) -> Result<()> {
+    let schema_ident = sql::quote_ident(schema);
+    for (table, key) in ARCHIVE_TABLES {
+        let columns = ensure_archive_table(db, schema, table, key)?.join(", ");
+        if *table == "absurd_blobs" {
+            sql::exec_with_bind_text(
+                db,
+                &format!(
+                    "insert or ignore into {schema}.absurd_blobs ({columns})
+                     select {columns}
+                       from main.absurd_blobs
+                      where hash in (
+                        select t.params ->> ?2 from main.absurd_tasks t
+                         where t.queue_name = ?1
+                           and t.task_id in (select task_id from {temp})
+                        union
+                        select t.completed_payload ->> ?2 from main.absurd_tasks t
+                         where t.queue_name = ?1
+                           and t.task_id in (select task_id from {temp})
+                        union
+                        select r.result ->> ?2 from main.absurd_runs r
+                         where r.queue_name = ?1
+                           and r.task_id in (select task_id from {temp})
+                        union
+                        select c.state ->> ?2 from main.absurd_checkpoints c
+                         where c.queue_name = ?1
+                           and c.task_id in (select task_id from {temp})
+                        union
+                        select h.state ->> ?2 from main.absurd_checkpoint_history h
+                         where h.queue_name = ?1
+                           and h.task_id in (select task_id from {temp})
+                      )",
+                    schema = schema_ident,
+                    columns = columns,
+                    temp = temp_table
+                ),
+                &[queue_name, blob::BLOB_REF_PATH],
+            )?;
+        } else {
+            sql::exec_with_bind_text(
+                db,
+                &format!(
+                    "insert or replace into {schema}.{table} ({columns})
+                     select {columns}
+                       from main.{table}
+                      where queue_name = ?1
+                        and task_id in (select task_id from {temp})",
+                    schema = schema_ident,
+                    table = table,
+                    columns = columns,
+                    temp = temp_table
+                ),
+                &[queue_name],
+            )?;
+        }
+    }
+    Ok(())
+}
+
+/// SQL: absurd_archive_tasks(queue_name, ttl_seconds, archive_schema[, limit])
+/// Usage: move terminal tasks older than TTL, with their runs, checkpoints, checkpoint history and payload blobs, into an attached database, then clean them up like absurd_cleanup_tasks.
+/// Section: Durable
+///
+/// The archive database must already be attached, e.g. with
+/// `attach 'archive.db' as archive`. Archive tables are created on first use
+/// and gain new columns as the main schema evolves.
+pub fn absurd_archive_tasks(
+    context: *mut sqlite3_context,
+    values: &[*mut sqlite3_value],
+) -> Result<()> {
+    let queue_name = api::value_text_notnull(values.first().expect("queue_name"))?;
+    let ttl_seconds =
+        run::parse_required_ttl_secs(values.get(1).expect("ttl_seconds"), "ttl_seconds")?;
+    let schema = api::value_text_notnull(values.get(2).expect("archive_schema"))?.trim();
+    let limit = run::parse_optional_limit(values.get(3).copied())?;
+
+    validate::queue_name(queue_name)?;
+    let db = api::context_db_handle(context);
+    ensure_attached(db, schema)?;
+    let now = sql::now_ms_from_db(db);
+    let cutoff = now - ttl_seconds.saturating_mul(1000);
+    let cutoffs = TerminalCutoffs {
+        completed: Some(cutoff),
+        failed: Some(cutoff),
+        cancelled: Some(cutoff),
+    };
+
+    let archived = run::purge_terminal_tasks(db, queue_name, &cutoffs, limit, Some(schema))?;
+    api::result_int64(context, archived);
+    Ok(())
+}
@@ -0,0 +1,103 @@
+use crate::queue;
+use crate::sql;
+use flate2::read::DeflateDecoder;
+use flate2::write::DeflateEncoder;
+use flate2::Compression;
+use sha2::{Digest, Sha256};
+use sqlite3ext_sys::sqlite3;
+use sqlite_loadable::{Error, Result};
+use std::borrow::Cow;
+use std::io::{Read, Write};
+
+/// Offloaded payloads are stored inline as `{"$absurd_blob":"<sha256>"}`.
+const BLOB_REF_PREFIX: &str = "{\"$absurd_blob\":\"";
+const BLOB_REF_SUFFIX: &str = "\"}";
+
+/// JSON path used in SQL to pull the hash out of a stored reference.
+pub const BLOB_REF_PATH: &str = "$.\"$absurd_blob\"";
+
+fn to_hex(bytes: &[u8]) -> String {
+    const DIGITS: &[u8; 16] = b"0123456789abcdef";
+    let mut out = String::with_capacity(bytes.len() * 2);
+    for byte in bytes {
+        out.push(DIGITS[(byte >> 4) as usize] as char);
+        out.push(DIGITS[(byte & 0x0f) as usize] as char);
+    }
+    out
+}
+
+/// Byte size above which payloads on `queue_name` are offloaded, if set.
+pub fn offload_threshold(db: *mut sqlite3, queue_name: &str) -> Result<Option<usize>> {
+    Ok(
+        queue::queue_setting(db, queue_name, "offload_threshold_bytes")?
+            .and_then(|value| value.parse::<usize>().ok()),
+    )
+}
+
+/// Store `json_text` in the blob table when it exceeds the queue threshold
+/// and return the reference to persist in its place; small payloads are
+/// returned unchanged.
+pub fn offload_json<'a>(
+    db: *mut sqlite3,
+    queue_name: &str,
+    json_text: &'a str,
+) -> Result<Cow<'a, str>> {
+    let exceeds =
+        offload_threshold(db, queue_name)?.is_some_and(|threshold| json_text.len() > threshold);
+    if !exceeds {
+        return Ok(Cow::Borrowed(json_text));
+    }
+
+    let hash = to_hex(&Sha256::digest(json_text.as_bytes()));
+    let mut encoder = DeflateEncoder::new(Vec::new(), Compression::default());
+    encoder
+        .write_all(json_text.as_bytes())
+        .map_err(|err| Error::new_message(format!("failed to compress payload: {:?}", err)))?;
+    let compressed = encoder
+        .finish()
+        .map_err(|err| Error::new_message(format!("failed to compress payload: {:?}", err)))?;
+
+    let size_value = json_text.len().to_string();
+    let now_value = sql::now_ms_from_db(db).to_string();
+    sql::exec_with_bind_blob(
+        db,
+        "insert or ignore into absurd_blobs (hash, size, created_at, data)
+         values (?1, cast(?2 as integer), cast(?3 as integer), ?4)",
+        &[&hash, &size_value, &now_value],
+        &compressed,
+    )?;
+
+    Ok(Cow::Owned(format!(
+        "{}{}{}",
+        BLOB_REF_PREFIX, hash, BLOB_REF_SUFFIX
+    )))
+}
+
+/// Replace a blob reference with the payload it points to. `raw` is the
+/// `json()` rendering of a stored value, so references have a fixed shape.
+pub fn inline_json<'a>(db: *mut sqlite3, raw: &'a str) -> Result<Cow<'a, str>> {
+    let hash = match raw
+        .strip_prefix(BLOB_REF_PREFIX)
+        .and_then(|rest| rest.strip_suffix(BLOB_REF_SUFFIX))
+    {
+        Some(hash) => hash,
+        None => return Ok(Cow::Borrowed(raw)),
+    };
+
+    let compressed =
+        sql::query_row_blob(db, "select data from absurd_blobs where hash = ?1", &[hash])?
+            .ok_or_else(|| Error::new_message(format!("blob not found: {}", hash)))?;
+
+    let mut decoded = String::new();
+    DeflateDecoder::new(compressed.as_slice())
+        .read_to_string(&mut decoded)
+        .map_err(|err| Error::new_message(format!("failed to decompress blob: {:?}", err)))?;
+    Ok(Cow::Owned(decoded))
+}
+
+/// Delete blobs that no stored payload references anymore. Reference counts
+/// are kept current by triggers on the payload columns.
+pub fn collect_garbage(db: *mut sqlite3) -> Result<i64> {
+    sql::exec_with_bind_text(db, "delete from absurd_blobs where ref_count <= 0", &[])?;
+    sql::query_row_i64(db, "select changes()", &[])
+}
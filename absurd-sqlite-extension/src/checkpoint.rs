@@ -1,3 +1,5 @@
+use crate::payload;
+use crate::queue;
 use crate::sql;
 use crate::validate;
 use serde_json::Value as JsonValue;
@@ -29,6 +31,32 @@ fn parse_optional_int(value: &*mut sqlite3_value) -> Option<i64> {
     }
 }
 
+/// Append the current value of a checkpoint to its history when the queue
+/// has `checkpoint_history` enabled.
+pub fn record_checkpoint_history(
+    db: *mut sqlite3,
+    queue_name: &str,
+    task_id: &str,
+    step_name: &str,
+) -> Result<()> {
+    if !queue::checkpoint_history_enabled(db, queue_name)? {
+        return Ok(());
+    }
+    sql::exec_with_bind_text(
+        db,
+        "insert into absurd_checkpoint_history (queue_name, task_id, checkpoint_name, attempt, run_id, state, status, updated_at)
+         select c.queue_name, c.task_id, c.checkpoint_name, coalesce(r.attempt, 0), c.owner_run_id, c.state, c.status, c.updated_at
+           from absurd_checkpoints c
+           left join absurd_runs r
+             on r.queue_name = c.queue_name
+            and r.run_id = c.owner_run_id
+          where c.queue_name = ?1
+            and c.task_id = ?2
+            and c.checkpoint_name = ?3",
+        &[queue_name, task_id, step_name],
+    )
+}
+
 /// SQL: absurd_set_task_checkpoint_state(queue_name, task_id, step_name, state_json, owner_run_id[, extend_claim_by_secs])
 /// Usage: store checkpoint state for a step; optionally extend the run claim.
 /// Section: Durable
@@ -153,6 +181,7 @@ pub fn absurd_set_task_checkpoint_state(
         }
 
         if allow_update {
+            let state_text = payload::encode_json(db, queue_name, &state_text)?;
             sql::exec_with_bind_text(
                 db,
                 "insert into absurd_checkpoints (queue_name, task_id, checkpoint_name, state, status, owner_run_id, updated_at)
@@ -164,6 +193,7 @@ pub fn absurd_set_task_checkpoint_state(
                                updated_at = excluded.updated_at",
                 &[queue_name, task_id, step_name, &state_text, owner_run, &now_value],
             )?;
+            record_checkpoint_history(db, queue_name, task_id, step_name)?;
         }
 
         Ok(())
@@ -522,3 +552,237 @@ impl VTabCursor for CheckpointCursor {
         Ok(self.rowid)
     }
 }
+
+static CHECKPOINT_HISTORY_SQL: &str = "CREATE TABLE x(checkpoint_name TEXT, attempt INTEGER, run_id TEXT, state BLOB, status TEXT, updated_at INTEGER, queue_name TEXT hidden, task_id TEXT hidden, step_name TEXT hidden)";
+
+struct CheckpointHistoryRow {
+    checkpoint_name: String,
+    attempt: i64,
+    run_id: Option<String>,
+    state: Option<String>,
+    status: String,
+    updated_at: i64,
+}
+
+/// SQL: absurd_get_task_checkpoint_history(queue_name, task_id[, step_name])
+/// Usage: list every recorded checkpoint write for a task, oldest first (requires the `checkpoint_history` queue setting).
+/// Section: Durable
+#[repr(C)]
+pub struct CheckpointHistoryTable {
+    base: sqlite3_vtab,
+    db: *mut sqlite3,
+}
+
+impl<'vtab> VTab<'vtab> for CheckpointHistoryTable {
+    type Aux = ();
+    type Cursor = CheckpointHistoryCursor;
+
+    fn connect(
+        db: *mut sqlite3,
+        _aux: Option<&Self::Aux>,
+        _args: VTabArguments,
+    ) -> Result<(String, CheckpointHistoryTable)> {
+        let base: sqlite3_vtab = unsafe { std::mem::zeroed() };
+        let vtab = CheckpointHistoryTable { base, db };
+        Ok((CHECKPOINT_HISTORY_SQL.to_owned(), vtab))
+    }
+
+    fn destroy(&self) -> Result<()> {
+        Ok(())
+    }
+
+    fn best_index(&self, mut info: IndexInfo) -> core::result::Result<(), BestIndexError> {
+        let mut has_queue = false;
+        let mut has_task = false;
+
+        for mut constraint in info.constraints() {
+            match constraint.column_idx() {
+                6 => {
+                    if constraint.usable() && constraint.op() == Some(ConstraintOperator::EQ) {
+                        constraint.set_omit(true);
+                        constraint.set_argv_index(1);
+                        has_queue = true;
+                    } else {
+                        return Err(BestIndexError::Constraint);
+                    }
+                }
+                7 => {
+                    if constraint.usable() && constraint.op() == Some(ConstraintOperator::EQ) {
+                        constraint.set_omit(true);
+                        constraint.set_argv_index(2);
+                        has_task = true;
+                    } else {
+                        return Err(BestIndexError::Constraint);
+                    }
+                }
+                8 => {
+                    if constraint.usable() && constraint.op() == Some(ConstraintOperator::EQ) {
+                        constraint.set_omit(true);
+                        constraint.set_argv_index(3);
+                    } else {
+                        return Err(BestIndexError::Constraint);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        if !has_queue || !has_task {
+            return Err(BestIndexError::Error);
+        }
+
+        info.set_estimated_cost(10.0);
+        info.set_estimated_rows(10);
+        info.set_idxnum(1);
+        Ok(())
+    }
+
+    fn open(&mut self) -> Result<CheckpointHistoryCursor> {
+        Ok(CheckpointHistoryCursor::new(self.db))
+    }
+}
+
+#[repr(C)]
+pub struct CheckpointHistoryCursor {
+    base: sqlite3_vtab_cursor,
+    db: *mut sqlite3,
+    rowid: i64,
+    results: Vec<CheckpointHistoryRow>,
+}
+
+impl CheckpointHistoryCursor {
+    fn new(db: *mut sqlite3) -> CheckpointHistoryCursor {
+        let base: sqlite3_vtab_cursor = unsafe { std::mem::zeroed() };
+        CheckpointHistoryCursor {
+            base,
+            db,
+            rowid: 0,
+            results: Vec::new(),
+        }
+    }
+}
+
+impl VTabCursor for CheckpointHistoryCursor {
+    fn filter(
+        &mut self,
+        _idx_num: c_int,
+        _idx_str: Option<&str>,
+        values: &[*mut sqlite3_value],
+    ) -> Result<()> {
+        let queue_name = api::value_text_notnull(values.first().expect("queue_name"))?;
+        let task_id = api::value_text_notnull(values.get(1).expect("task_id"))?;
+        let step_name = match values.get(2) {
+            Some(value) if !api::value_is_null(value) => api::value_text_notnull(value)?,
+            _ => "",
+        };
+        validate::queue_name(queue_name)?;
+
+        let mut stmt = sqlite_loadable::exec::Statement::prepare(
+            self.db,
+            "select checkpoint_name,
+                    attempt,
+                    coalesce(run_id, '') as run_id,
+                    coalesce(json(state), '') as state,
+                    status,
+                    updated_at
+               from absurd_checkpoint_history
+              where queue_name = ?1
+                and task_id = ?2
+                and (?3 = '' or checkpoint_name = ?3)
+              order by history_id asc",
+        )
+        .map_err(|err| {
+            Error::new_message(format!(
+                "failed to prepare checkpoint history query: {:?}",
+                err
+            ))
+        })?;
+        for (idx, value) in [queue_name, task_id, step_name].iter().enumerate() {
+            stmt.bind_text((idx + 1) as i32, value).map_err(|err| {
+                Error::new_message(format!("failed to bind parameter: {:?}", err))
+            })?;
+        }
+
+        let mut results = Vec::new();
+        for row in stmt.execute() {
+            let row =
+                row.map_err(|err| Error::new_message(format!("failed to read row: {:?}", err)))?;
+            let checkpoint_name = row.get::<String>(0).map_err(|err| {
+                Error::new_message(format!("failed to read checkpoint_name: {:?}", err))
+            })?;
+            let attempt = row
+                .get::<i64>(1)
+                .map_err(|err| Error::new_message(format!("failed to read attempt: {:?}", err)))?;
+            let run_raw = row
+                .get::<String>(2)
+                .map_err(|err| Error::new_message(format!("failed to read run_id: {:?}", err)))?;
+            let state_raw = row
+                .get::<String>(3)
+                .map_err(|err| Error::new_message(format!("failed to read state: {:?}", err)))?;
+            let status = row
+                .get::<String>(4)
+                .map_err(|err| Error::new_message(format!("failed to read status: {:?}", err)))?;
+            let updated_at = row.get::<i64>(5).map_err(|err| {
+                Error::new_message(format!("failed to read updated_at: {:?}", err))
+            })?;
+
+            results.push(CheckpointHistoryRow {
+                checkpoint_name,
+                attempt,
+                run_id: if run_raw.is_empty() {
+                    None
+                } else {
+                    Some(run_raw)
+                },
+                state: if state_raw.is_empty() {
+                    None
+                } else {
+                    Some(state_raw)
+                },
+                status,
+                updated_at,
+            });
+        }
+
+        self.results = results;
+        self.rowid = 0;
+        Ok(())
+    }
+
+    fn next(&mut self) -> Result<()> {
+        self.rowid += 1;
+        Ok(())
+    }
+
+    fn eof(&self) -> bool {
+        self.rowid as usize >= self.results.len()
+    }
+
+    fn column(&self, context: *mut sqlite3_context, i: c_int) -> Result<()> {
+        if self.rowid as usize >= self.results.len() {
+            api::result_null(context);
+            return Ok(());
+        }
+        let result = &self.results[self.rowid as usize];
+        match i {
+            0 => api::result_text(context, &result.checkpoint_name)?,
+            1 => api::result_int64(context, result.attempt),
+            2 => match &result.run_id {
+                Some(value) => api::result_text(context, value)?,
+                None => api::result_null(context),
+            },
+            3 => match &result.state {
+                Some(value) => sql::result_json_value(self.db, context, value)?,
+                None => api::result_null(context),
+            },
+            4 => api::result_text(context, &result.status)?,
+            5 => api::result_int64(context, result.updated_at),
+            _ => api::result_null(context),
+        }
+        Ok(())
+    }
+
+    fn rowid(&self) -> Result<i64> {
+        Ok(self.rowid)
+    }
+}
@@ -1,6 +1,9 @@
+use crate::event;
+use crate::queue;
 use crate::retry;
 use crate::sql;
 use crate::validate;
+use crate::worker;
 use serde_json::Value as JsonValue;
 use sqlite3ext_sys::sqlite3;
 use sqlite_loadable::prelude::*;
@@ -9,6 +12,7 @@ use sqlite_loadable::{
     table::{BestIndexError, ConstraintOperator, IndexInfo, VTab, VTabArguments, VTabCursor},
     Error, Result,
 };
+use std::collections::{HashMap, HashSet};
 use std::os::raw::c_int;
 use uuid::Uuid;
 
@@ -17,9 +21,66 @@ struct ClaimArgs {
     worker_id: String,
     claim_timeout_secs: i64,
     qty: i64,
+    filter: ClaimFilter,
+}
+
+/// Restricts a claim to some task names and/or required header values. Both
+/// are kept as JSON text for binding; empty means unrestricted.
+#[derive(Default)]
+pub(crate) struct ClaimFilter {
+    task_names: String,
+    headers: String,
+}
+
+pub(crate) fn parse_claim_filter(raw: &str) -> Result<ClaimFilter> {
+    let invalid = || {
+        Error::new_message(
+            "task_filter must be a JSON array of task names or an object with task_names and headers",
+        )
+    };
+    let parsed: JsonValue = serde_json::from_str(raw).map_err(|_| invalid())?;
+    let (task_names, headers) = match &parsed {
+        JsonValue::Array(_) => (Some(&parsed), None),
+        JsonValue::Object(obj) => {
+            if obj
+                .keys()
+                .any(|key| key != "task_names" && key != "headers")
+            {
+                return Err(invalid());
+            }
+            (
+                obj.get("task_names").filter(|v| !v.is_null()),
+                obj.get("headers").filter(|v| !v.is_null()),
+            )
+        }
+        _ => return Err(invalid()),
+    };
+
+    let mut filter = ClaimFilter::default();
+    if let Some(task_names) = task_names {
+        let names = task_names
+            .as_array()
+            .filter(|names| !names.is_empty() && names.iter().all(|v| v.is_string()))
+            .ok_or_else(|| Error::new_message("task_names must be a non-empty array of strings"))?;
+        filter.task_names = JsonValue::Array(names.clone()).to_string();
+    }
+    if let Some(headers) = headers {
+        let tags = headers
+            .as_object()
+            .filter(|tags| {
+                tags.values()
+                    .all(|v| v.is_string() || v.is_number() || v.is_boolean())
+            })
+            .ok_or_else(|| Error::new_message("headers must be an object of scalar values"))?;
+        if !tags.is_empty() {
+            filter.headers = headers.to_string();
+        }
+    }
+    Ok(filter)
 }
 
 struct ClaimResult {
+    queue_name: String,
     run_id: String,
     task_id: String,
     attempt: i64,
@@ -31,6 +92,31 @@ struct ClaimResult {
     headers: Option<String>,
     wake_event: Option<String>,
     event_payload: Option<String>,
+    /// Claim timeout from the task type, which overrides the worker's.
+    type_claim_timeout_secs: Option<i64>,
+    supersede_key: Option<String>,
+}
+
+fn optional_text(value: Option<&*mut sqlite3_value>) -> Option<String> {
+    let value = value?;
+    if api::value_is_null(value) {
+        return None;
+    }
+    let raw = api::value_text(value).ok()?.trim().to_string();
+    if raw.is_empty() {
+        None
+    } else {
+        Some(raw)
+    }
+}
+
+fn optional_i64(value: Option<&*mut sqlite3_value>) -> Option<i64> {
+    let value = value?;
+    if api::value_is_null(value) {
+        None
+    } else {
+        Some(api::value_int64(value))
+    }
 }
 
 fn parse_claim_args(values: &[*mut sqlite3_value]) -> Result<ClaimArgs> {
@@ -38,51 +124,21 @@ fn parse_claim_args(values: &[*mut sqlite3_value]) -> Result<ClaimArgs> {
         api::value_text_notnull(values.first().expect("queue_name is required"))?.to_string();
     validate::queue_name(&queue_name)?;
 
-    let worker_id = values
-        .get(1)
-        .and_then(|value| {
-            if api::value_is_null(value) {
-                None
-            } else {
-                let raw = api::value_text(value).ok()?.trim().to_string();
-                if raw.is_empty() {
-                    None
-                } else {
-                    Some(raw)
-                }
-            }
-        })
-        .unwrap_or_else(|| "worker".to_string());
-
-    let claim_timeout_secs = values
-        .get(2)
-        .and_then(|value| {
-            if api::value_is_null(value) {
-                None
-            } else {
-                Some(api::value_int64(value))
-            }
-        })
-        .unwrap_or(30)
-        .max(0);
+    let worker_id = optional_text(values.get(1)).unwrap_or_else(|| "worker".to_string());
+    let claim_timeout_secs = optional_i64(values.get(2)).unwrap_or(30).max(0);
+    let qty = optional_i64(values.get(3)).unwrap_or(1).max(1);
 
-    let qty = values
-        .get(3)
-        .and_then(|value| {
-            if api::value_is_null(value) {
-                None
-            } else {
-                Some(api::value_int64(value))
-            }
-        })
-        .unwrap_or(1)
-        .max(1);
+    let filter = match values.get(4) {
+        Some(value) if !api::value_is_null(value) => parse_claim_filter(api::value_text(value)?)?,
+        _ => ClaimFilter::default(),
+    };
 
     Ok(ClaimArgs {
         queue_name,
         worker_id,
         claim_timeout_secs,
         qty,
+        filter,
     })
 }
 
@@ -131,7 +187,8 @@ fn apply_cancellation_rules(db: *mut sqlite3, queue_name: &str, now: i64) -> Res
                 claimed_by = null,
                 claim_expires_at = null,
                 available_at = cast(?2 as integer),
-                wake_event = null
+                wake_event = null,
+                wake_step = null
           where queue_name = ?1
             and task_id in (select task_id from absurd_tasks where queue_name = ?1 and state = 'cancelled')
             and state <> 'cancelled'",
@@ -217,6 +274,7 @@ fn expire_claims(db: *mut sqlite3, queue_name: &str, now: i64) -> Result<()> {
             "update absurd_runs
                 set state = 'failed',
                     wake_event = null,
+                    wake_step = null,
                     failed_at = cast(?1 as integer),
                     failure_reason = jsonb(?2),
                     claimed_by = null,
@@ -226,13 +284,15 @@ fn expire_claims(db: *mut sqlite3, queue_name: &str, now: i64) -> Result<()> {
             &[&now_value, &failure_reason, queue_name, &run_id],
         )?;
 
-        sql::exec_with_bind_text(
-            db,
-            "delete from absurd_waits
-              where queue_name = ?1
-                and run_id = ?2",
-            &[queue_name, &run_id],
-        )?;
+        for table in event::WAIT_TABLES {
+            let delete_sql = format!(
+                "delete from {}
+                  where queue_name = ?1
+                    and run_id = ?2",
+                table
+            );
+            sql::exec_with_bind_text(db, &delete_sql, &[queue_name, &run_id])?;
+        }
 
         let next_attempt = attempt + 1;
         let max_attempts_opt = if max_attempts == 0 {
@@ -340,17 +400,7 @@ fn expire_claims(db: *mut sqlite3, queue_name: &str, now: i64) -> Result<()> {
     Ok(())
 }
 
-fn select_candidates(
-    db: *mut sqlite3,
-    queue_name: &str,
-    now: i64,
-    qty: i64,
-) -> Result<Vec<ClaimResult>> {
-    let now_value = now.to_string();
-    let qty_value = qty.to_string();
-    let mut stmt = sqlite_loadable::exec::Statement::prepare(
-        db,
-        "select r.run_id,
+const CANDIDATE_COLUMNS: &str = "r.run_id,
                 r.task_id,
                 r.attempt,
                 r.state,
@@ -360,19 +410,153 @@ fn select_candidates(
                 coalesce(t.max_attempts, 0) as max_attempts,
                 coalesce(json(t.headers), '') as headers,
                 coalesce(r.wake_event, '') as wake_event,
-                coalesce(json(r.event_payload), '') as event_payload
-           from absurd_runs r
-           join absurd_tasks t
-             on t.queue_name = r.queue_name
+                coalesce(json(r.event_payload), '') as event_payload,
+                coalesce(tt.claim_timeout, 0) as type_claim_timeout,
+                coalesce(t.supersede_key, '') as supersede_key";
+
+const CANDIDATE_JOIN: &str = "on t.queue_name = r.queue_name
             and t.task_id = r.task_id
+           left join absurd_task_types tt
+             on tt.queue_name = t.queue_name
+            and tt.task_name = t.task_name
           where r.queue_name = ?1
             and r.state in ('pending','sleeping')
             and t.state in ('pending','sleeping','running')
-            and r.available_at <= cast(?2 as integer)
-          order by r.available_at, r.run_id
-          limit cast(?3 as integer)",
-    )
-    .map_err(|err| Error::new_message(format!("failed to prepare claim query: {:?}", err)))?;
+            and r.available_at <= cast(?2 as integer)";
+
+/// `from ... where ...` for the claim query. Filters only add predicates
+/// when set. A task name filter drives the scan from tasks so
+/// `absurd_tasks_task_name_idx` serves it; `cross join` pins that order.
+fn candidate_source(filter: &ClaimFilter) -> String {
+    let mut source = if filter.task_names.is_empty() {
+        format!(
+            "from absurd_runs r
+           join absurd_tasks t
+             {}",
+            CANDIDATE_JOIN
+        )
+    } else {
+        format!(
+            "from absurd_tasks t
+           cross join absurd_runs r
+             {}
+            and t.queue_name = ?1
+            and t.task_name in (select value from json_each(?4))",
+            CANDIDATE_JOIN
+        )
+    };
+    if !filter.headers.is_empty() {
+        source.push_str(
+            "
+            and not exists (
+                  select 1 from json_each(?5) tag
+                   where (t.headers ->> tag.key) is not tag.value
+                )",
+        );
+    }
+    source
+}
+
+/// `order by` terms for a queue's ordering setting, as (terms over the
+/// candidate source, terms over the `eligible` CTE).
+fn candidate_order(ordering: &str) -> (&'static str, &'static str) {
+    match ordering {
+        "lifo" => (
+            "r.available_at desc, r.run_id desc",
+            "e.available_at desc, e.run_id desc",
+        ),
+        "priority" => (
+            "t.priority desc, r.available_at, r.run_id",
+            "e.priority desc, e.available_at, e.run_id",
+        ),
+        _ => ("r.available_at, r.run_id", "e.available_at, e.run_id"),
+    }
+}
+
+/// The claim query for a queue's ordering, ranking runs per task name when
+/// `limited` (some task type caps concurrency).
+pub(crate) fn candidate_query(filter: &ClaimFilter, ordering: &str, limited: bool) -> String {
+    let (source_order, eligible_order) = candidate_order(ordering);
+    let source = candidate_source(filter);
+    if limited {
+        format!(
+            "with eligible as (
+               select {columns},
+                      r.available_at as available_at,
+                      t.priority as priority,
+                      tt.concurrency_limit as concurrency_limit,
+                      row_number() over (
+                        partition by t.task_name order by {source_order}
+                      ) as type_rank
+               {source}
+             ),
+             running as (
+               select t.task_name, count(*) as running
+                 from absurd_runs r
+                 join absurd_tasks t
+                   on t.queue_name = r.queue_name
+                  and t.task_id = r.task_id
+                where r.queue_name = ?1
+                  and r.state = 'running'
+                group by t.task_name
+             )
+             select e.run_id, e.task_id, e.attempt, e.state, e.task_name, e.params,
+                    e.retry_strategy, e.max_attempts, e.headers, e.wake_event,
+                    e.event_payload, e.type_claim_timeout, e.supersede_key
+               from eligible e
+               left join running x
+                 on x.task_name = e.task_name
+              where e.concurrency_limit is null
+                 or e.type_rank + coalesce(x.running, 0) <= e.concurrency_limit
+              order by {eligible_order}
+              limit cast(?3 as integer)",
+            columns = CANDIDATE_COLUMNS,
+            source = source,
+        )
+    } else {
+        format!(
+            "select {columns}
+             {source}
+              order by {source_order}
+              limit cast(?3 as integer)",
+            columns = CANDIDATE_COLUMNS,
+            source = source,
+        )
+    }
+}
+
+fn select_candidates(
+    db: *mut sqlite3,
+    queue_name: &str,
+    now: i64,
+    qty: i64,
+    filter: &ClaimFilter,
+) -> Result<Vec<ClaimResult>> {
+    // Headers on encrypted queues are ciphertext, so SQL cannot compare them.
+    if !filter.headers.is_empty() && queue::encryption_enabled(db, queue_name)? {
+        return Err(Error::new_message(
+            "task_filter headers are not supported on encrypted queues",
+        ));
+    }
+    if queue::queue_paused(db, queue_name)? {
+        return Ok(Vec::new());
+    }
+    let now_value = now.to_string();
+    let qty_value = qty.to_string();
+    // Ranking runs per task name is only needed when some type on the queue
+    // caps concurrency; otherwise the plain ordered scan is used.
+    let limited = sql::query_row_i64(
+        db,
+        "select exists(
+           select 1 from absurd_task_types
+            where queue_name = ?1 and concurrency_limit is not null
+         )",
+        &[queue_name],
+    )? == 1;
+    let ordering = queue::claim_ordering(db, queue_name)?;
+    let query = candidate_query(filter, &ordering, limited);
+    let mut stmt = sqlite_loadable::exec::Statement::prepare(db, &query)
+        .map_err(|err| Error::new_message(format!("failed to prepare claim query: {:?}", err)))?;
 
     stmt.bind_text(1, queue_name)
         .map_err(|err| Error::new_message(format!("failed to bind queue_name: {:?}", err)))?;
@@ -380,6 +564,14 @@ fn select_candidates(
         .map_err(|err| Error::new_message(format!("failed to bind now: {:?}", err)))?;
     stmt.bind_text(3, &qty_value)
         .map_err(|err| Error::new_message(format!("failed to bind qty: {:?}", err)))?;
+    if !filter.task_names.is_empty() {
+        stmt.bind_text(4, &filter.task_names)
+            .map_err(|err| Error::new_message(format!("failed to bind task_names: {:?}", err)))?;
+    }
+    if !filter.headers.is_empty() {
+        stmt.bind_text(5, &filter.headers)
+            .map_err(|err| Error::new_message(format!("failed to bind headers: {:?}", err)))?;
+    }
 
     let mut results = Vec::new();
     for row in stmt.execute() {
@@ -418,8 +610,15 @@ fn select_candidates(
         let event_payload_raw = row.get::<String>(10).map_err(|err| {
             Error::new_message(format!("failed to read event_payload: {:?}", err))
         })?;
+        let type_claim_timeout = row.get::<i64>(11).map_err(|err| {
+            Error::new_message(format!("failed to read claim_timeout: {:?}", err))
+        })?;
+        let supersede_key = row.get::<String>(12).map_err(|err| {
+            Error::new_message(format!("failed to read supersede_key: {:?}", err))
+        })?;
 
         results.push(ClaimResult {
+            queue_name: queue_name.to_string(),
             run_id,
             task_id,
             attempt,
@@ -451,94 +650,283 @@ fn select_candidates(
             } else {
                 Some(event_payload_raw)
             },
+            type_claim_timeout_secs: if type_claim_timeout == 0 {
+                None
+            } else {
+                Some(type_claim_timeout)
+            },
+            supersede_key: if supersede_key.is_empty() {
+                None
+            } else {
+                Some(supersede_key)
+            },
         });
     }
 
     Ok(results)
 }
 
-fn claim_task_impl(db: *mut sqlite3, args: ClaimArgs) -> Result<Vec<ClaimResult>> {
-    let now = sql::now_ms_from_db(db);
-    let claim_expires_at = if args.claim_timeout_secs > 0 {
-        Some(now + args.claim_timeout_secs.saturating_mul(1000))
+/// Write a `{"timedOut": true}` checkpoint for every step of `run_id` whose
+/// wait has expired, so the resumed run can tell a timeout from a wake-up.
+/// Returns whether any timeout was recorded.
+fn record_wait_timeouts(
+    db: *mut sqlite3,
+    queue_name: &str,
+    run_id: &str,
+    now_value: &str,
+) -> Result<bool> {
+    sql::exec_with_bind_text(
+        db,
+        "insert into absurd_checkpoints (queue_name, task_id, checkpoint_name, state, status, owner_run_id, updated_at)
+         select distinct queue_name, task_id, step_name, jsonb('{\"timedOut\":true}'), 'timed_out', run_id, cast(?3 as integer)
+           from (
+             select queue_name, task_id, run_id, step_name, timeout_at from absurd_waits
+             union all
+             select queue_name, task_id, run_id, step_name, timeout_at from absurd_pattern_waits
+             union all
+             select queue_name, task_id, run_id, step_name, timeout_at from absurd_signal_waits
+           )
+          where queue_name = ?1
+            and run_id = ?2
+            and timeout_at is not null
+            and timeout_at <= cast(?3 as integer)
+         on conflict (queue_name, task_id, checkpoint_name)
+         do update set state = excluded.state,
+                       status = excluded.status,
+                       owner_run_id = excluded.owner_run_id,
+                       updated_at = excluded.updated_at",
+        &[queue_name, run_id, now_value],
+    )?;
+    let recorded = sql::query_row_i64(db, "select changes()", &[])? > 0;
+
+    if recorded && queue::checkpoint_history_enabled(db, queue_name)? {
+        sql::exec_with_bind_text(
+            db,
+            "insert into absurd_checkpoint_history (queue_name, task_id, checkpoint_name, attempt, run_id, state, status, updated_at)
+             select c.queue_name, c.task_id, c.checkpoint_name, r.attempt, c.owner_run_id, c.state, c.status, c.updated_at
+               from absurd_checkpoints c
+               join absurd_runs r
+                 on r.queue_name = c.queue_name
+                and r.run_id = c.owner_run_id
+              where c.queue_name = ?1
+                and c.owner_run_id = ?2
+                and c.status = 'timed_out'
+                and c.updated_at = cast(?3 as integer)",
+            &[queue_name, run_id, now_value],
+        )?;
+    }
+    Ok(recorded)
+}
+
+/// Housekeeping every claim runs on a queue before picking candidates.
+fn prepare_queue(db: *mut sqlite3, queue_name: &str, worker_id: &str, now: i64) -> Result<()> {
+    apply_cancellation_rules(db, queue_name, now)?;
+    expire_claims(db, queue_name, now)?;
+    worker::touch_worker(db, worker_id, queue_name, now)
+}
+
+fn mark_claimed(
+    db: *mut sqlite3,
+    worker_id: &str,
+    default_claim_timeout_secs: i64,
+    now: i64,
+    candidate: &ClaimResult,
+) -> Result<()> {
+    let now_value = now.to_string();
+    let queue_name = candidate.queue_name.as_str();
+    let claim_timeout_secs = candidate
+        .type_claim_timeout_secs
+        .unwrap_or(default_claim_timeout_secs);
+    let claim_expires_value = if claim_timeout_secs > 0 {
+        (now + claim_timeout_secs.saturating_mul(1000)).to_string()
     } else {
-        None
+        String::new()
     };
-    let claim_expires_value = claim_expires_at.map(|v| v.to_string()).unwrap_or_default();
-    let now_value = now.to_string();
+    sql::exec_with_bind_text(
+        db,
+        "update absurd_runs
+            set state = 'running',
+                claimed_by = ?1,
+                claim_expires_at = cast(nullif(?2, '') as integer),
+                started_at = case
+                  when ?7 = 'sleeping' then cast(?3 as integer)
+                  else coalesce(started_at, cast(?3 as integer))
+                end,
+                available_at = cast(?4 as integer)
+          where queue_name = ?5
+            and run_id = ?6",
+        &[
+            worker_id,
+            &claim_expires_value,
+            &now_value,
+            &now_value,
+            queue_name,
+            &candidate.run_id,
+            &candidate.run_state,
+        ],
+    )?;
 
-    sql::exec_with_bind_text(db, "begin immediate", &[])?;
+    let attempt_value = candidate.attempt.to_string();
+    sql::exec_with_bind_text(
+        db,
+        "update absurd_tasks
+            set state = 'running',
+                attempts = case
+                  when attempts < cast(?1 as integer) then cast(?1 as integer)
+                  else attempts
+                end,
+                first_started_at = coalesce(first_started_at, cast(?2 as integer)),
+                last_attempt_run = ?3
+          where queue_name = ?4
+            and task_id = ?5",
+        &[
+            &attempt_value,
+            &now_value,
+            &candidate.run_id,
+            queue_name,
+            &candidate.task_id,
+        ],
+    )?;
 
-    let result = (|| -> Result<Vec<ClaimResult>> {
-        apply_cancellation_rules(db, &args.queue_name, now)?;
-        expire_claims(db, &args.queue_name, now)?;
+    // The checkpoints now carry the timeout, so drop the wake: otherwise a
+    // later await on the same name would read it as another timeout.
+    if candidate.run_state == "sleeping"
+        && record_wait_timeouts(db, queue_name, &candidate.run_id, &now_value)?
+    {
+        sql::exec_with_bind_text(
+            db,
+            "update absurd_runs
+                set wake_event = null,
+                    wake_step = null,
+                    event_payload = null
+              where queue_name = ?1
+                and run_id = ?2",
+            &[queue_name, &candidate.run_id],
+        )?;
+    }
 
-        let candidates = select_candidates(db, &args.queue_name, now, args.qty)?;
-        for candidate in &candidates {
-            sql::exec_with_bind_text(
-                db,
-                "update absurd_runs
-                    set state = 'running',
-                        claimed_by = ?1,
-                        claim_expires_at = cast(nullif(?2, '') as integer),
-                        started_at = case
-                          when ?7 = 'sleeping' then cast(?3 as integer)
-                          else coalesce(started_at, cast(?3 as integer))
-                        end,
-                        available_at = cast(?4 as integer)
-                  where queue_name = ?5
-                    and run_id = ?6",
-                &[
-                    &args.worker_id,
-                    &claim_expires_value,
-                    &now_value,
-                    &now_value,
-                    &args.queue_name,
-                    &candidate.run_id,
-                    &candidate.run_state,
-                ],
-            )?;
+    for table in event::WAIT_TABLES {
+        let delete_sql = format!(
+            "delete from {}
+              where queue_name = ?1
+                and run_id = ?2
+                and timeout_at is not null
+                and timeout_at <= cast(?3 as integer)",
+            table
+        );
+        sql::exec_with_bind_text(
+            db,
+            &delete_sql,
+            &[queue_name, &candidate.run_id, &now_value],
+        )?;
+    }
+    Ok(())
+}
 
-            let attempt_value = candidate.attempt.to_string();
-            sql::exec_with_bind_text(
-                db,
-                "update absurd_tasks
-                    set state = 'running',
-                        attempts = case
-                          when attempts < cast(?1 as integer) then cast(?1 as integer)
-                          else attempts
-                        end,
-                        first_started_at = coalesce(first_started_at, cast(?2 as integer)),
-                        last_attempt_run = ?3
-                  where queue_name = ?4
-                    and task_id = ?5",
-                &[
-                    &attempt_value,
-                    &now_value,
-                    &candidate.run_id,
-                    &args.queue_name,
-                    &candidate.task_id,
-                ],
-            )?;
+/// Cancel pending or sleeping tasks enqueued before `candidate` that share its
+/// supersede key, dropping their waits, and return their task ids. Running
+/// siblings are left to finish.
+fn supersede_older(db: *mut sqlite3, candidate: &ClaimResult, now: i64) -> Result<Vec<String>> {
+    let Some(supersede_key) = candidate.supersede_key.as_deref() else {
+        return Ok(Vec::new());
+    };
+    let queue_name = candidate.queue_name.as_str();
+    let mut stmt = sqlite_loadable::exec::Statement::prepare(
+        db,
+        "select o.task_id
+           from absurd_tasks o
+           join absurd_tasks c
+             on c.queue_name = o.queue_name
+            and c.task_id = ?3
+          where o.queue_name = ?1
+            and o.supersede_key = ?2
+            and o.state in ('pending','sleeping')
+            and (o.enqueue_at, o.task_id) < (c.enqueue_at, c.task_id)",
+    )
+    .map_err(|err| Error::new_message(format!("failed to prepare supersede query: {:?}", err)))?;
+    for (idx, value) in [queue_name, supersede_key, candidate.task_id.as_str()]
+        .iter()
+        .enumerate()
+    {
+        stmt.bind_text((idx + 1) as i32, value)
+            .map_err(|err| Error::new_message(format!("failed to bind parameter: {:?}", err)))?;
+    }
+    let mut superseded = Vec::new();
+    for row in stmt.execute() {
+        let row =
+            row.map_err(|err| Error::new_message(format!("failed to read row: {:?}", err)))?;
+        superseded.push(
+            row.get::<String>(0)
+                .map_err(|err| Error::new_message(format!("failed to read task_id: {:?}", err)))?,
+        );
+    }
 
+    let now_value = now.to_string();
+    for task_id in &superseded {
+        sql::exec_with_bind_text(
+            db,
+            "update absurd_tasks
+                set state = 'cancelled',
+                    cancelled_at = coalesce(cancelled_at, cast(?1 as integer))
+              where queue_name = ?2
+                and task_id = ?3",
+            &[&now_value, queue_name, task_id],
+        )?;
+        sql::exec_with_bind_text(
+            db,
+            "update absurd_runs
+                set state = 'cancelled',
+                    claimed_by = null,
+                    claim_expires_at = null
+              where queue_name = ?1
+                and task_id = ?2
+                and state not in ('completed','failed','cancelled')",
+            &[queue_name, task_id],
+        )?;
+        for table in event::WAIT_TABLES {
             sql::exec_with_bind_text(
                 db,
-                "delete from absurd_waits
-                  where queue_name = ?1
-                    and run_id = ?2
-                    and timeout_at is not null
-                    and timeout_at <= cast(?3 as integer)",
-                &[&args.queue_name, &candidate.run_id, &now_value],
+                &format!(
+                    "delete from {}
+                      where queue_name = ?1
+                        and task_id = ?2",
+                    table
+                ),
+                &[queue_name, task_id],
             )?;
         }
+    }
+    Ok(superseded)
+}
 
-        Ok(candidates)
-    })();
+/// Claim `candidates` in order, skipping any that an earlier candidate in the
+/// same batch superseded.
+fn claim_candidates(
+    db: *mut sqlite3,
+    worker_id: &str,
+    default_claim_timeout_secs: i64,
+    now: i64,
+    candidates: Vec<ClaimResult>,
+) -> Result<Vec<ClaimResult>> {
+    let mut superseded: HashSet<String> = HashSet::new();
+    let mut claimed = Vec::with_capacity(candidates.len());
+    for candidate in candidates {
+        if superseded.contains(&candidate.task_id) {
+            continue;
+        }
+        mark_claimed(db, worker_id, default_claim_timeout_secs, now, &candidate)?;
+        superseded.extend(supersede_older(db, &candidate, now)?);
+        claimed.push(candidate);
+    }
+    Ok(claimed)
+}
 
-    match result {
-        Ok(results) => {
+fn in_transaction<T>(db: *mut sqlite3, f: impl FnOnce() -> Result<T>) -> Result<T> {
+    sql::exec_with_bind_text(db, "begin immediate", &[])?;
+    match f() {
+        Ok(value) => {
             sql::exec_with_bind_text(db, "commit", &[])?;
-            Ok(results)
+            Ok(value)
         }
         Err(err) => {
             let _ = sql::exec_with_bind_text(db, "rollback", &[]);
@@ -547,7 +935,241 @@ fn claim_task_impl(db: *mut sqlite3, args: ClaimArgs) -> Result<Vec<ClaimResult>
     }
 }
 
-static CLAIM_CREATE_SQL: &str = "CREATE TABLE x(run_id TEXT, task_id TEXT, attempt INTEGER, task_name TEXT, params BLOB, retry_strategy BLOB, max_attempts INTEGER, headers BLOB, wake_event TEXT, event_payload BLOB, queue_name TEXT hidden, worker_id TEXT hidden, claim_timeout INTEGER hidden, qty INTEGER hidden)";
+fn claim_task_impl(db: *mut sqlite3, args: ClaimArgs) -> Result<Vec<ClaimResult>> {
+    let now = sql::now_ms_from_db(db);
+    in_transaction(db, || {
+        prepare_queue(db, &args.queue_name, &args.worker_id, now)?;
+        let candidates = select_candidates(db, &args.queue_name, now, args.qty, &args.filter)?;
+        claim_candidates(
+            db,
+            &args.worker_id,
+            args.claim_timeout_secs,
+            now,
+            candidates,
+        )
+    })
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum MultiClaimStrategy {
+    /// Spread each poll across queues in proportion to their weights.
+    Weighted,
+    /// Drain queues in list order, only moving on when a queue is empty.
+    Priority,
+}
+
+struct QueueSpec {
+    queue_name: String,
+    weight: i64,
+    filter: ClaimFilter,
+}
+
+struct MultiClaimArgs {
+    strategy: MultiClaimStrategy,
+    queues: Vec<QueueSpec>,
+    worker_id: String,
+    claim_timeout_secs: i64,
+    qty: i64,
+}
+
+fn parse_queue_spec(entry: &JsonValue) -> Result<QueueSpec> {
+    let invalid = || {
+        Error::new_message(
+            "each queue must be a queue name or an object with queue_name, weight and task_filter",
+        )
+    };
+    let spec = match entry {
+        JsonValue::String(name) => QueueSpec {
+            queue_name: name.clone(),
+            weight: 1,
+            filter: ClaimFilter::default(),
+        },
+        JsonValue::Object(obj) => {
+            if obj
+                .keys()
+                .any(|key| !matches!(key.as_str(), "queue_name" | "weight" | "task_filter"))
+            {
+                return Err(invalid());
+            }
+            let queue_name = obj
+                .get("queue_name")
+                .and_then(|v| v.as_str())
+                .ok_or_else(invalid)?
+                .to_string();
+            let weight = match obj.get("weight") {
+                None | Some(JsonValue::Null) => 1,
+                Some(value) => value
+                    .as_i64()
+                    .filter(|weight| *weight > 0)
+                    .ok_or_else(|| Error::new_message("weight must be a positive integer"))?,
+            };
+            let filter = match obj.get("task_filter") {
+                None | Some(JsonValue::Null) => ClaimFilter::default(),
+                Some(value) => parse_claim_filter(&value.to_string())?,
+            };
+            QueueSpec {
+                queue_name,
+                weight,
+                filter,
+            }
+        }
+        _ => return Err(invalid()),
+    };
+    validate::queue_name(&spec.queue_name)?;
+    Ok(spec)
+}
+
+fn parse_multi_claim_args(values: &[*mut sqlite3_value]) -> Result<MultiClaimArgs> {
+    let raw = api::value_text_notnull(values.first().expect("queues is required"))?;
+    let invalid = || {
+        Error::new_message(
+            "queues must be a JSON array of queues or an object with strategy and queues",
+        )
+    };
+    let parsed: JsonValue = serde_json::from_str(raw).map_err(|_| invalid())?;
+    let (strategy, entries) = match &parsed {
+        JsonValue::Array(entries) => (MultiClaimStrategy::Weighted, entries),
+        JsonValue::Object(obj) => {
+            if obj.keys().any(|key| key != "strategy" && key != "queues") {
+                return Err(invalid());
+            }
+            let strategy = match obj.get("strategy").and_then(|v| v.as_str()) {
+                None | Some("weighted") => MultiClaimStrategy::Weighted,
+                Some("priority") => MultiClaimStrategy::Priority,
+                Some(_) => {
+                    return Err(Error::new_message(
+                        "strategy must be 'weighted' or 'priority'",
+                    ))
+                }
+            };
+            let entries = obj
+                .get("queues")
+                .and_then(|v| v.as_array())
+                .ok_or_else(invalid)?;
+            (strategy, entries)
+        }
+        _ => return Err(invalid()),
+    };
+    if entries.is_empty() {
+        return Err(Error::new_message("queues must not be empty"));
+    }
+
+    let mut queues: Vec<QueueSpec> = Vec::with_capacity(entries.len());
+    for entry in entries {
+        let spec = parse_queue_spec(entry)?;
+        if queues.iter().any(|q| q.queue_name == spec.queue_name) {
+            return Err(Error::new_message(format!(
+                "queue listed more than once: {}",
+                spec.queue_name
+            )));
+        }
+        queues.push(spec);
+    }
+
+    Ok(MultiClaimArgs {
+        strategy,
+        queues,
+        worker_id: optional_text(values.get(1)).unwrap_or_else(|| "worker".to_string()),
+        claim_timeout_secs: optional_i64(values.get(2)).unwrap_or(30).max(0),
+        qty: optional_i64(values.get(3)).unwrap_or(1).max(1),
+    })
+}
+
+/// Smooth weighted round-robin state per worker, kept on the connection so
+/// successive polls keep rotating between queues instead of always starting
+/// with the heaviest one, and dropped when the connection closes.
+type Rotations = HashMap<String, HashMap<String, i64>>;
+
+/// Decide which queue each of the (up to `qty`) claim slots goes to, given
+/// how many candidates each queue has. Returns queue indexes in claim order.
+fn weighted_slots(
+    db: *mut sqlite3,
+    worker_id: &str,
+    queues: &[QueueSpec],
+    available: &[usize],
+    qty: i64,
+) -> Result<Vec<usize>> {
+    sql::with_connection_state(db, c"absurd_rotations", |rotations: &mut Rotations| {
+        let current = rotations.entry(worker_id.to_string()).or_default();
+
+        let mut remaining = available.to_vec();
+        let mut slots = Vec::new();
+        while (slots.len() as i64) < qty {
+            let eligible: Vec<usize> = (0..queues.len()).filter(|&i| remaining[i] > 0).collect();
+            if eligible.is_empty() {
+                break;
+            }
+            let total: i64 = eligible.iter().map(|&i| queues[i].weight).sum();
+            let mut picked = eligible[0];
+            let mut best = i64::MIN;
+            for &i in &eligible {
+                let weight = current.entry(queues[i].queue_name.clone()).or_insert(0);
+                *weight += queues[i].weight;
+                if *weight > best {
+                    best = *weight;
+                    picked = i;
+                }
+            }
+            *current
+                .get_mut(&queues[picked].queue_name)
+                .expect("picked queue has state") -= total;
+            remaining[picked] -= 1;
+            slots.push(picked);
+        }
+        slots
+    })
+}
+
+fn claim_tasks_multi_impl(db: *mut sqlite3, args: MultiClaimArgs) -> Result<Vec<ClaimResult>> {
+    let now = sql::now_ms_from_db(db);
+    in_transaction(db, || {
+        for queue in &args.queues {
+            prepare_queue(db, &queue.queue_name, &args.worker_id, now)?;
+        }
+
+        let mut candidates = Vec::new();
+        match args.strategy {
+            MultiClaimStrategy::Priority => {
+                for queue in &args.queues {
+                    let remaining = args.qty - candidates.len() as i64;
+                    if remaining <= 0 {
+                        break;
+                    }
+                    candidates.extend(select_candidates(
+                        db,
+                        &queue.queue_name,
+                        now,
+                        remaining,
+                        &queue.filter,
+                    )?);
+                }
+            }
+            MultiClaimStrategy::Weighted => {
+                let mut per_queue = Vec::with_capacity(args.queues.len());
+                for queue in &args.queues {
+                    let queue_candidates =
+                        select_candidates(db, &queue.queue_name, now, args.qty, &queue.filter)?;
+                    per_queue.push(queue_candidates.into_iter());
+                }
+                let available: Vec<usize> = per_queue.iter().map(|c| c.len()).collect();
+                for slot in weighted_slots(db, &args.worker_id, &args.queues, &available, args.qty)?
+                {
+                    candidates.extend(per_queue[slot].next());
+                }
+            }
+        }
+
+        claim_candidates(
+            db,
+            &args.worker_id,
+            args.claim_timeout_secs,
+            now,
+            candidates,
+        )
+    })
+}
+
+static CLAIM_CREATE_SQL: &str = "CREATE TABLE x(run_id TEXT, task_id TEXT, attempt INTEGER, task_name TEXT, params BLOB, retry_strategy BLOB, max_attempts INTEGER, headers BLOB, wake_event TEXT, event_payload BLOB, queue_name TEXT hidden, worker_id TEXT hidden, claim_timeout INTEGER hidden, qty INTEGER hidden, task_filter TEXT hidden)";
 
 enum ClaimColumns {
     RunId,
@@ -564,6 +1186,8 @@ enum ClaimColumns {
     WorkerId,
     ClaimTimeout,
     Qty,
+    TaskFilter,
+    Queues,
 }
 
 fn claim_column(index: i32) -> Option<ClaimColumns> {
@@ -582,12 +1206,53 @@ fn claim_column(index: i32) -> Option<ClaimColumns> {
         11 => Some(ClaimColumns::WorkerId),
         12 => Some(ClaimColumns::ClaimTimeout),
         13 => Some(ClaimColumns::Qty),
+        14 => Some(ClaimColumns::TaskFilter),
         _ => None,
     }
 }
 
-/// SQL: absurd_claim_task(queue_name[, worker_id, claim_timeout_secs, qty])
-/// Usage: claim available runs for a worker (defaults: worker, 30s, qty=1).
+fn claim_result_column(
+    db: *mut sqlite3,
+    context: *mut sqlite3_context,
+    result: &ClaimResult,
+    column: Option<ClaimColumns>,
+) -> Result<()> {
+    match column {
+        Some(ClaimColumns::RunId) => api::result_text(context, &result.run_id)?,
+        Some(ClaimColumns::TaskId) => api::result_text(context, &result.task_id)?,
+        Some(ClaimColumns::Attempt) => api::result_int64(context, result.attempt),
+        Some(ClaimColumns::TaskName) => api::result_text(context, &result.task_name)?,
+        Some(ClaimColumns::Params) => sql::result_json_value(db, context, &result.params)?,
+        Some(ClaimColumns::RetryStrategy) => match &result.retry_strategy {
+            Some(value) => sql::result_json_text(context, value)?,
+            None => api::result_null(context),
+        },
+        Some(ClaimColumns::MaxAttempts) => match result.max_attempts {
+            Some(value) => api::result_int64(context, value),
+            None => api::result_null(context),
+        },
+        Some(ClaimColumns::Headers) => match &result.headers {
+            Some(value) => sql::result_json_value(db, context, value)?,
+            None => api::result_null(context),
+        },
+        Some(ClaimColumns::WakeEvent) => match &result.wake_event {
+            Some(value) => api::result_text(context, value)?,
+            None => api::result_null(context),
+        },
+        Some(ClaimColumns::EventPayload) => match &result.event_payload {
+            Some(value) => sql::result_json_text(context, value)?,
+            None => api::result_null(context),
+        },
+        Some(ClaimColumns::QueueName) => api::result_text(context, &result.queue_name)?,
+        _ => {
+            api::result_null(context);
+        }
+    }
+    Ok(())
+}
+
+/// SQL: absurd_claim_task(queue_name[, worker_id, claim_timeout_secs, qty, task_filter])
+/// Usage: claim available runs for a worker (defaults: worker, 30s, qty=1); task_filter is a JSON array of task names or `{"task_names": [...], "headers": {...}}` to only claim matching tasks; header filters are rejected on encrypted queues.
 /// Section: Durable
 #[repr(C)]
 pub struct ClaimTaskTable {
@@ -651,6 +1316,14 @@ impl<'vtab> VTab<'vtab> for ClaimTaskTable {
                         return Err(BestIndexError::Constraint);
                     }
                 }
+                Some(ClaimColumns::TaskFilter) => {
+                    if constraint.usable() && constraint.op() == Some(ConstraintOperator::EQ) {
+                        constraint.set_omit(true);
+                        constraint.set_argv_index(5);
+                    } else {
+                        return Err(BestIndexError::Constraint);
+                    }
+                }
                 _ => {}
             }
         }
@@ -718,40 +1391,146 @@ impl VTabCursor for ClaimTaskCursor {
             api::result_null(context);
             return Ok(());
         }
-        let result = &self.results[self.rowid as usize];
-        match claim_column(i) {
-            Some(ClaimColumns::RunId) => api::result_text(context, &result.run_id)?,
-            Some(ClaimColumns::TaskId) => api::result_text(context, &result.task_id)?,
-            Some(ClaimColumns::Attempt) => api::result_int64(context, result.attempt),
-            Some(ClaimColumns::TaskName) => api::result_text(context, &result.task_name)?,
-            Some(ClaimColumns::Params) => sql::result_json_value(self.db, context, &result.params)?,
-            Some(ClaimColumns::RetryStrategy) => match &result.retry_strategy {
-                Some(value) => sql::result_json_value(self.db, context, value)?,
-                None => api::result_null(context),
-            },
-            Some(ClaimColumns::MaxAttempts) => match result.max_attempts {
-                Some(value) => api::result_int64(context, value),
-                None => api::result_null(context),
-            },
-            Some(ClaimColumns::Headers) => match &result.headers {
-                Some(value) => sql::result_json_value(self.db, context, value)?,
-                None => api::result_null(context),
-            },
-            Some(ClaimColumns::WakeEvent) => match &result.wake_event {
-                Some(value) => api::result_text(context, value)?,
-                None => api::result_null(context),
-            },
-            Some(ClaimColumns::EventPayload) => match &result.event_payload {
-                Some(value) => sql::result_json_value(self.db, context, value)?,
-                None => api::result_null(context),
-            },
-            _ => {
-                api::result_null(context);
+        claim_result_column(
+            self.db,
+            context,
+            &self.results[self.rowid as usize],
+            claim_column(i),
+        )
+    }
+
+    fn rowid(&self) -> Result<i64> {
+        Ok(self.rowid)
+    }
+}
+
+static MULTI_CLAIM_CREATE_SQL: &str = "CREATE TABLE x(run_id TEXT, task_id TEXT, attempt INTEGER, task_name TEXT, params BLOB, retry_strategy BLOB, max_attempts INTEGER, headers BLOB, wake_event TEXT, event_payload BLOB, queue_name TEXT, queues TEXT hidden, worker_id TEXT hidden, claim_timeout INTEGER hidden, qty INTEGER hidden)";
+
+fn multi_claim_column(index: i32) -> Option<ClaimColumns> {
+    match index {
+        0..=10 => claim_column(index),
+        11 => Some(ClaimColumns::Queues),
+        12 => Some(ClaimColumns::WorkerId),
+        13 => Some(ClaimColumns::ClaimTimeout),
+        14 => Some(ClaimColumns::Qty),
+        _ => None,
+    }
+}
+
+/// SQL: absurd_claim_tasks_multi(queues_json[, worker_id, claim_timeout_secs, qty])
+/// Usage: claim up to qty runs across several queues in one transaction; queues_json is an array of queue names or `{"queue_name", "weight", "task_filter"}` objects (weighted round-robin), or `{"strategy": "priority", "queues": [...]}` to drain queues in order.
+/// Section: Durable
+#[repr(C)]
+pub struct ClaimTasksMultiTable {
+    base: sqlite3_vtab,
+    db: *mut sqlite3,
+}
+
+impl<'vtab> VTab<'vtab> for ClaimTasksMultiTable {
+    type Aux = ();
+    type Cursor = ClaimTasksMultiCursor;
+
+    fn connect(
+        db: *mut sqlite3,
+        _aux: Option<&Self::Aux>,
+        _args: VTabArguments,
+    ) -> Result<(String, ClaimTasksMultiTable)> {
+        let base: sqlite3_vtab = unsafe { std::mem::zeroed() };
+        let vtab = ClaimTasksMultiTable { base, db };
+        Ok((MULTI_CLAIM_CREATE_SQL.to_owned(), vtab))
+    }
+
+    fn destroy(&self) -> Result<()> {
+        Ok(())
+    }
+
+    fn best_index(&self, mut info: IndexInfo) -> core::result::Result<(), BestIndexError> {
+        let mut has_queues = false;
+
+        for mut constraint in info.constraints() {
+            let argv_index = match multi_claim_column(constraint.column_idx()) {
+                Some(ClaimColumns::Queues) => 1,
+                Some(ClaimColumns::WorkerId) => 2,
+                Some(ClaimColumns::ClaimTimeout) => 3,
+                Some(ClaimColumns::Qty) => 4,
+                _ => continue,
+            };
+            if constraint.usable() && constraint.op() == Some(ConstraintOperator::EQ) {
+                constraint.set_omit(true);
+                constraint.set_argv_index(argv_index);
+                has_queues |= argv_index == 1;
+            } else {
+                return Err(BestIndexError::Constraint);
             }
         }
+
+        if !has_queues {
+            return Err(BestIndexError::Error);
+        }
+
+        info.set_estimated_cost(10.0);
+        info.set_estimated_rows(10);
+        info.set_idxnum(1);
+        Ok(())
+    }
+
+    fn open(&mut self) -> Result<ClaimTasksMultiCursor> {
+        Ok(ClaimTasksMultiCursor::new(self.db))
+    }
+}
+
+#[repr(C)]
+pub struct ClaimTasksMultiCursor {
+    base: sqlite3_vtab_cursor,
+    db: *mut sqlite3,
+    rowid: i64,
+    results: Vec<ClaimResult>,
+}
+
+impl ClaimTasksMultiCursor {
+    fn new(db: *mut sqlite3) -> ClaimTasksMultiCursor {
+        let base: sqlite3_vtab_cursor = unsafe { std::mem::zeroed() };
+        ClaimTasksMultiCursor {
+            base,
+            db,
+            rowid: 0,
+            results: Vec::new(),
+        }
+    }
+}
+
+impl VTabCursor for ClaimTasksMultiCursor {
+    fn filter(
+        &mut self,
+        _idx_num: c_int,
+        _idx_str: Option<&str>,
+        values: &[*mut sqlite3_value],
+    ) -> Result<()> {
+        let args = parse_multi_claim_args(values)?;
+        self.results = claim_tasks_multi_impl(self.db, args)?;
+        self.rowid = 0;
+        Ok(())
+    }
+
+    fn next(&mut self) -> Result<()> {
+        self.rowid += 1;
         Ok(())
     }
 
+    fn eof(&self) -> bool {
+        self.rowid as usize >= self.results.len()
+    }
+
+    fn column(&self, context: *mut sqlite3_context, i: c_int) -> Result<()> {
+        match self.results.get(self.rowid as usize) {
+            Some(result) => claim_result_column(self.db, context, result, multi_claim_column(i)),
+            None => {
+                api::result_null(context);
+                Ok(())
+            }
+        }
+    }
+
     fn rowid(&self) -> Result<i64> {
         Ok(self.rowid)
     }
@@ -0,0 +1,319 @@
+use crate::blob;
+use crate::queue;
+use crate::sql;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng, Payload};
+use chacha20poly1305::{XChaCha20Poly1305, XNonce};
+use serde_json::Value as JsonValue;
+use sqlite3ext_sys::sqlite3;
+use sqlite_loadable::prelude::*;
+use sqlite_loadable::{api, Error, Result};
+use std::collections::HashMap;
+
+/// Encrypted payloads are stored as
+/// `{"$absurd_encrypted":"<key_id>","nonce":"<base64>","data":"<base64>"}`
+/// using XChaCha20-Poly1305 with the key id as associated data.
+pub const ENCRYPTED_PREFIX: &str = "{\"$absurd_encrypted\":\"";
+pub const ENCRYPTED_PATH: &str = "$.\"$absurd_encrypted\"";
+
+/// Keys registered on a connection. The most recently set key encrypts new
+/// writes; the others remain available to decrypt older rows.
+#[derive(Default)]
+struct KeyRing {
+    active: Option<String>,
+    keys: HashMap<String, [u8; 32]>,
+}
+
+/// Keys never touch the database, so they live on the connection handle and
+/// are dropped with it.
+fn with_keyring<T>(db: *mut sqlite3, f: impl FnOnce(&mut KeyRing) -> T) -> Result<T> {
+    sql::with_connection_state(db, c"absurd_keyring", f)
+}
+
+fn parse_key(raw: &str) -> Result<[u8; 32]> {
+    let raw = raw.trim();
+    let bytes = if raw.len() == 64 && raw.chars().all(|c| c.is_ascii_hexdigit()) {
+        (0..raw.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&raw[i..i + 2], 16).unwrap_or_default())
+            .collect()
+    } else {
+        BASE64
+            .decode(raw)
+            .map_err(|_| Error::new_message("key must be 32 bytes encoded as hex or base64"))?
+    };
+    bytes
+        .try_into()
+        .map_err(|_| Error::new_message("key must be 32 bytes encoded as hex or base64"))
+}
+
+fn validate_key_id(key_id: &str) -> Result<()> {
+    if key_id.is_empty() || key_id.len() > 64 {
+        return Err(Error::new_message("key_id must be 1-64 characters"));
+    }
+    if !key_id
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.'))
+    {
+        return Err(Error::new_message(
+            "key_id may only contain letters, digits, '_', '-' and '.'",
+        ));
+    }
+    Ok(())
+}
+
+fn active_key(db: *mut sqlite3) -> Result<(String, [u8; 32])> {
+    with_keyring(db, |ring| {
+        ring.active
+            .as_ref()
+            .and_then(|id| ring.keys.get(id).map(|key| (id.clone(), *key)))
+    })?
+    .ok_or_else(|| {
+        Error::new_message("no encryption key set; call absurd_set_encryption_key first")
+    })
+}
+
+fn encrypt_with(key_id: &str, key: &[u8; 32], json_text: &str) -> Result<String> {
+    let cipher = XChaCha20Poly1305::new(key.into());
+    let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+    let data = cipher
+        .encrypt(
+            &nonce,
+            Payload {
+                msg: json_text.as_bytes(),
+                aad: key_id.as_bytes(),
+            },
+        )
+        .map_err(|_| Error::new_message("failed to encrypt payload"))?;
+    Ok(format!(
+        "{}{}\",\"nonce\":\"{}\",\"data\":\"{}\"}}",
+        ENCRYPTED_PREFIX,
+        key_id,
+        BASE64.encode(nonce),
+        BASE64.encode(data)
+    ))
+}
+
+/// Encrypt a JSON payload with the connection's active key.
+pub fn encrypt_json(db: *mut sqlite3, json_text: &str) -> Result<String> {
+    let (key_id, key) = active_key(db)?;
+    encrypt_with(&key_id, &key, json_text)
+}
+
+/// The key id of an encryption envelope whose key this connection has not
+/// loaded, or `None` when the key is available.
+pub fn missing_key(db: *mut sqlite3, raw: &str) -> Result<Option<String>> {
+    let envelope: JsonValue =
+        serde_json::from_str(raw).map_err(|_| Error::new_message("invalid encrypted payload"))?;
+    let key_id = envelope
+        .get("$absurd_encrypted")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| Error::new_message("invalid encrypted payload"))?;
+    let loaded = with_keyring(db, |ring| ring.keys.contains_key(key_id))?;
+    Ok((!loaded).then(|| key_id.to_string()))
+}
+
+/// Decrypt one encryption envelope, returning the JSON it wraps.
+pub fn decrypt_json(db: *mut sqlite3, raw: &str) -> Result<String> {
+    let invalid = |_| Error::new_message("invalid encrypted payload");
+    let envelope: JsonValue = serde_json::from_str(raw).map_err(invalid)?;
+    let field = |name: &str| -> Result<&str> {
+        envelope
+            .get(name)
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| Error::new_message("invalid encrypted payload"))
+    };
+    let key_id = field("$absurd_encrypted")?;
+    let nonce = BASE64
+        .decode(field("nonce")?)
+        .map_err(|_| Error::new_message("invalid encrypted payload"))?;
+    let data = BASE64
+        .decode(field("data")?)
+        .map_err(|_| Error::new_message("invalid encrypted payload"))?;
+    if nonce.len() != 24 {
+        return Err(Error::new_message("invalid encrypted payload"));
+    }
+    let key = with_keyring(db, |ring| ring.keys.get(key_id).copied())?
+        .ok_or_else(|| Error::new_message(format!("encryption key not available: {}", key_id)))?;
+    let plain = XChaCha20Poly1305::new((&key).into())
+        .decrypt(
+            XNonce::from_slice(&nonce),
+            Payload {
+                msg: &data,
+                aad: key_id.as_bytes(),
+            },
+        )
+        .map_err(|_| {
+            Error::new_message(format!("failed to decrypt payload with key {}", key_id))
+        })?;
+    String::from_utf8(plain).map_err(|_| Error::new_message("invalid encrypted payload"))
+}
+
+/// SQL: absurd_set_encryption_key(key_id, key_or_null)
+/// Usage: register a 32-byte key (hex or base64) for this connection and make it the active key for encrypted queues; a NULL key forgets key_id.
+/// Section: Durable
+pub fn absurd_set_encryption_key(
+    context: *mut sqlite3_context,
+    values: &[*mut sqlite3_value],
+) -> Result<()> {
+    let key_id = api::value_text_notnull(values.first().expect("key_id"))?.trim();
+    validate_key_id(key_id)?;
+    let value = values.get(1).expect("key");
+    let key = if api::value_is_null(value) {
+        None
+    } else {
+        let raw = api::value_text(value)
+            .map_err(|err| Error::new_message(format!("invalid key: {:?}", err)))?;
+        Some(parse_key(raw)?)
+    };
+
+    let db = api::context_db_handle(context);
+    with_keyring(db, |ring| match key {
+        Some(key) => {
+            ring.keys.insert(key_id.to_string(), key);
+            ring.active = Some(key_id.to_string());
+        }
+        None => {
+            ring.keys.remove(key_id);
+            if ring.active.as_deref() == Some(key_id) {
+                ring.active = None;
+            }
+        }
+    })?;
+    api::result_int64(context, 1);
+    Ok(())
+}
+
+/// Stored payload columns rewritten by `absurd_rotate_encryption_key`, as
+/// (table, column).
+const ENCRYPTED_COLUMNS: &[(&str, &str)] = &[
+    ("absurd_tasks", "params"),
+    ("absurd_tasks", "headers"),
+    ("absurd_tasks", "completed_payload"),
+    ("absurd_runs", "result"),
+    ("absurd_checkpoints", "state"),
+    ("absurd_checkpoint_history", "state"),
+];
+
+/// SQL: absurd_rotate_encryption_key(queue_name[, batch_size])
+/// Usage: re-encrypt up to batch_size (default 500) payloads on an encrypted queue that are plaintext or use an older key; returns rows rewritten, call until 0.
+/// Section: Durable
+pub fn absurd_rotate_encryption_key(
+    context: *mut sqlite3_context,
+    values: &[*mut sqlite3_value],
+) -> Result<()> {
+    let queue_name = api::value_text_notnull(values.first().expect("queue_name"))?;
+    let batch_size = match values.get(1) {
+        Some(value) if !api::value_is_null(value) => api::value_int64(value),
+        _ => 500,
+    };
+    if batch_size <= 0 {
+        return Err(Error::new_message("batch_size must be positive"));
+    }
+
+    let db = api::context_db_handle(context);
+    if !queue::encryption_enabled(db, queue_name)? {
+        return Err(Error::new_message(
+            "encryption is not enabled for this queue",
+        ));
+    }
+    let (key_id, key) = active_key(db)?;
+
+    sql::exec_with_bind_text(db, "begin immediate", &[])?;
+
+    let result = (|| -> Result<i64> {
+        let mut remaining = batch_size;
+        for (table, column) in ENCRYPTED_COLUMNS {
+            if remaining == 0 {
+                break;
+            }
+            let rows = stale_rows(db, queue_name, table, column, &key_id, remaining)?;
+            for (rowid, stored) in rows {
+                let json_text = if stored.starts_with(ENCRYPTED_PREFIX) {
+                    decrypt_json(db, &stored)?
+                } else {
+                    stored
+                };
+                let encrypted = encrypt_with(&key_id, &key, &json_text)?;
+                sql::exec_with_bind_text(
+                    db,
+                    &format!(
+                        "update {} set {} = jsonb(?1) where rowid = cast(?2 as integer)",
+                        table, column
+                    ),
+                    &[&encrypted, &rowid.to_string()],
+                )?;
+                remaining -= 1;
+            }
+        }
+        Ok(batch_size - remaining)
+    })();
+
+    let rewritten = match result {
+        Ok(count) => {
+            sql::exec_with_bind_text(db, "commit", &[])?;
+            count
+        }
+        Err(err) => {
+            let _ = sql::exec_with_bind_text(db, "rollback", &[]);
+            return Err(err);
+        }
+    };
+    api::result_int64(context, rewritten);
+    Ok(())
+}
+
+/// Rows of `table.column` on the queue that are not encrypted with
+/// `key_id`. Blob references are skipped: their content lives in the shared
+/// blob table and is not rewritten here.
+fn stale_rows(
+    db: *mut sqlite3,
+    queue_name: &str,
+    table: &str,
+    column: &str,
+    key_id: &str,
+    limit: i64,
+) -> Result<Vec<(i64, String)>> {
+    let query = format!(
+        "select rowid, json({column})
+           from {table}
+          where queue_name = ?1
+            and {column} is not null
+            and coalesce({column} ->> ?2, '') <> ?3
+            and {column} ->> ?4 is null
+          order by rowid
+          limit cast(?5 as integer)",
+        column = column,
+        table = table,
+    );
+    let mut stmt = sqlite_loadable::exec::Statement::prepare(db, &query)
+        .map_err(|err| Error::new_message(format!("failed to prepare payload scan: {:?}", err)))?;
+    let limit_value = limit.to_string();
+    for (idx, value) in [
+        queue_name,
+        ENCRYPTED_PATH,
+        key_id,
+        blob::BLOB_REF_PATH,
+        &limit_value,
+    ]
+    .iter()
+    .enumerate()
+    {
+        stmt.bind_text((idx + 1) as i32, value)
+            .map_err(|err| Error::new_message(format!("failed to bind parameter: {:?}", err)))?;
+    }
+    let mut rows = Vec::new();
+    for row in stmt.execute() {
+        let row =
+            row.map_err(|err| Error::new_message(format!("failed to read row: {:?}", err)))?;
+        let rowid = row
+            .get::<i64>(0)
+            .map_err(|err| Error::new_message(format!("failed to read rowid: {:?}", err)))?;
+        let stored = row
+            .get::<String>(1)
+            .map_err(|err| Error::new_message(format!("failed to read payload: {:?}", err)))?;
+        rows.push((rowid, stored));
+    }
+    Ok(rows)
+}
@@ -1,3 +1,6 @@
+use crate::checkpoint;
+use crate::payload;
+use crate::queue;
 use crate::sql;
 use crate::validate;
 use serde_json::Value as JsonValue;
@@ -10,14 +13,38 @@ use sqlite_loadable::{
 };
 use std::os::raw::c_int;
 
-struct AwaitResult {
-    should_suspend: i64,
-    payload: Option<String>,
+pub struct AwaitResult {
+    pub should_suspend: i64,
+    pub payload: Option<String>,
+    pub seq: Option<i64>,
+    pub matched_event: Option<String>,
+    pub timed_out: bool,
 }
 
-const FAR_FUTURE_MS: i64 = 9_223_372_036_854_775_000;
+struct Emission {
+    payload: String,
+    seq: i64,
+    event_name: String,
+}
+
+struct WaitingRun {
+    run_id: String,
+    task_id: String,
+    step_name: String,
+    is_pattern: bool,
+    wait_mode: String,
+}
+
+/// Tables holding suspended waits, cleared together when a run or task ends.
+pub const WAIT_TABLES: [&str; 3] = [
+    "absurd_waits",
+    "absurd_pattern_waits",
+    "absurd_signal_waits",
+];
+
+pub const FAR_FUTURE_MS: i64 = 9_223_372_036_854_775_000;
 
-fn parse_optional_timeout_secs(value: Option<*mut sqlite3_value>) -> Result<Option<i64>> {
+pub fn parse_optional_timeout_secs(value: Option<*mut sqlite3_value>) -> Result<Option<i64>> {
     let value = match value {
         Some(value) => value,
         None => return Ok(None),
@@ -45,6 +72,414 @@ fn parse_optional_timeout_secs(value: Option<*mut sqlite3_value>) -> Result<Opti
     Ok(Some(timeout))
 }
 
+fn parse_optional_seq(value: Option<*mut sqlite3_value>) -> Result<Option<i64>> {
+    let value = match value {
+        Some(value) => value,
+        None => return Ok(None),
+    };
+    if api::value_is_null(&value) {
+        return Ok(None);
+    }
+    let seq = match api::value_type(&value) {
+        api::ValueType::Integer => api::value_int64(&value),
+        api::ValueType::Text => {
+            let raw = api::value_text(&value)
+                .map_err(|err| {
+                    Error::new_message(format!("after_seq must be non-negative integer: {:?}", err))
+                })?
+                .trim();
+            raw.parse::<i64>().map_err(|err| {
+                Error::new_message(format!("after_seq must be non-negative integer: {:?}", err))
+            })?
+        }
+        _ => return Err(Error::new_message("after_seq must be non-negative integer")),
+    };
+    if seq < 0 {
+        return Err(Error::new_message("after_seq must be non-negative"));
+    }
+    Ok(Some(seq))
+}
+
+/// Parse an await's `match_mode`: `exact` (the default) compares event names
+/// literally, `glob` treats the name as a pattern. Patterns are opt-in so
+/// exact names may contain `*`, `?` or `[`.
+fn parse_match_mode(value: Option<*mut sqlite3_value>) -> Result<bool> {
+    let value = match value {
+        Some(value) if !api::value_is_null(&value) => value,
+        _ => return Ok(false),
+    };
+    match api::value_text_notnull(&value)?
+        .trim()
+        .to_ascii_lowercase()
+        .as_str()
+    {
+        "" | "exact" => Ok(false),
+        "glob" => Ok(true),
+        _ => Err(Error::new_message("match_mode must be 'exact' or 'glob'")),
+    }
+}
+
+fn optional_text(value: String) -> Option<String> {
+    if value.is_empty() {
+        None
+    } else {
+        Some(value)
+    }
+}
+
+/// Find the emission an await should resolve against: the first logged
+/// emission past `after_seq` when given, otherwise the latest payload.
+fn lookup_emission(
+    db: *mut sqlite3,
+    queue_name: &str,
+    event_name: &str,
+    is_pattern: bool,
+    after_seq: Option<i64>,
+) -> Result<Option<Emission>> {
+    let name_filter = if is_pattern {
+        "event_name glob ?2"
+    } else {
+        "event_name = ?2"
+    };
+    let lookup_sql = match after_seq {
+        Some(_) => format!(
+            "select coalesce(json(payload), '') as payload, seq, event_name
+               from absurd_event_log
+              where queue_name = ?1
+                and {}
+                and seq > cast(?3 as integer)
+              order by seq
+              limit 1",
+            name_filter
+        ),
+        None => format!(
+            "select coalesce(json(payload), '') as payload, coalesce(seq, 0) as seq, event_name
+               from absurd_events
+              where queue_name = ?1
+                and {}
+                and payload is not null
+              order by emitted_at desc
+              limit 1",
+            name_filter
+        ),
+    };
+    let mut stmt = sqlite_loadable::exec::Statement::prepare(db, &lookup_sql)
+        .map_err(|err| Error::new_message(format!("failed to prepare event lookup: {:?}", err)))?;
+    stmt.bind_text(1, queue_name)
+        .map_err(|err| Error::new_message(format!("failed to bind queue_name: {:?}", err)))?;
+    stmt.bind_text(2, event_name)
+        .map_err(|err| Error::new_message(format!("failed to bind event_name: {:?}", err)))?;
+    if let Some(after_seq) = after_seq {
+        stmt.bind_text(3, &after_seq.to_string())
+            .map_err(|err| Error::new_message(format!("failed to bind after_seq: {:?}", err)))?;
+    }
+    let mut rows = stmt.execute();
+    let row = match rows.next() {
+        Some(Ok(row)) => row,
+        Some(Err(err)) => {
+            return Err(Error::new_message(format!(
+                "failed to read event row: {:?}",
+                err
+            )))
+        }
+        None => return Ok(None),
+    };
+    let payload = row
+        .get::<String>(0)
+        .map_err(|err| Error::new_message(format!("failed to read event payload: {:?}", err)))?;
+    let seq = row
+        .get::<i64>(1)
+        .map_err(|err| Error::new_message(format!("failed to read event seq: {:?}", err)))?;
+    let event_name = row
+        .get::<String>(2)
+        .map_err(|err| Error::new_message(format!("failed to read event_name: {:?}", err)))?;
+    Ok(Some(Emission {
+        payload,
+        seq,
+        event_name,
+    }))
+}
+
+fn optional_seq(seq: i64) -> Option<i64> {
+    if seq > 0 {
+        Some(seq)
+    } else {
+        None
+    }
+}
+
+pub struct AwaitRun {
+    pub state: String,
+    pub existing_payload: String,
+    pub wake_event: String,
+    pub wake_step: String,
+}
+
+/// Return the committed result of an await step that already resolved.
+pub fn load_await_checkpoint(
+    db: *mut sqlite3,
+    queue_name: &str,
+    task_id: &str,
+    step_name: &str,
+) -> Result<Option<AwaitResult>> {
+    let mut stmt = sqlite_loadable::exec::Statement::prepare(
+        db,
+        "select coalesce(json(state), '') as state,
+                coalesce(event_seq, 0) as event_seq,
+                coalesce(event_name, '') as event_name,
+                status
+           from absurd_checkpoints
+          where queue_name = ?1
+            and task_id = ?2
+            and checkpoint_name = ?3",
+    )
+    .map_err(|err| Error::new_message(format!("failed to prepare checkpoint lookup: {:?}", err)))?;
+    stmt.bind_text(1, queue_name)
+        .map_err(|err| Error::new_message(format!("failed to bind queue_name: {:?}", err)))?;
+    stmt.bind_text(2, task_id)
+        .map_err(|err| Error::new_message(format!("failed to bind task_id: {:?}", err)))?;
+    stmt.bind_text(3, step_name)
+        .map_err(|err| Error::new_message(format!("failed to bind step_name: {:?}", err)))?;
+    let mut rows = stmt.execute();
+    if let Some(Ok(row)) = rows.next() {
+        let checkpoint_payload = row.get::<String>(0).map_err(|err| {
+            Error::new_message(format!("failed to read checkpoint payload: {:?}", err))
+        })?;
+        let checkpoint_seq = row.get::<i64>(1).map_err(|err| {
+            Error::new_message(format!("failed to read checkpoint event_seq: {:?}", err))
+        })?;
+        let checkpoint_event = row.get::<String>(2).map_err(|err| {
+            Error::new_message(format!("failed to read checkpoint event_name: {:?}", err))
+        })?;
+        let status = row
+            .get::<String>(3)
+            .map_err(|err| Error::new_message(format!("failed to read status: {:?}", err)))?;
+        if !checkpoint_payload.is_empty() {
+            return Ok(Some(AwaitResult {
+                should_suspend: 0,
+                payload: Some(payload::decode_json(db, &checkpoint_payload)?.into_owned()),
+                seq: optional_seq(checkpoint_seq),
+                matched_event: optional_text(checkpoint_event),
+                timed_out: status == "timed_out",
+            }));
+        }
+    }
+    Ok(None)
+}
+
+/// Load the awaiting run, rejecting cancelled tasks. A payload left on the
+/// run by a wake-up is consumed here.
+pub fn load_await_run(db: *mut sqlite3, queue_name: &str, run_id: &str) -> Result<AwaitRun> {
+    let mut run_stmt = sqlite_loadable::exec::Statement::prepare(
+        db,
+        "select r.state,
+                coalesce(json(r.event_payload), '') as event_payload,
+                coalesce(r.wake_event, '') as wake_event,
+                coalesce(r.wake_step, '') as wake_step,
+                t.state as task_state
+           from absurd_runs r
+           join absurd_tasks t
+             on t.queue_name = r.queue_name
+            and t.task_id = r.task_id
+          where r.queue_name = ?1
+            and r.run_id = ?2",
+    )
+    .map_err(|err| Error::new_message(format!("failed to prepare run lookup: {:?}", err)))?;
+    run_stmt
+        .bind_text(1, queue_name)
+        .map_err(|err| Error::new_message(format!("failed to bind queue_name: {:?}", err)))?;
+    run_stmt
+        .bind_text(2, run_id)
+        .map_err(|err| Error::new_message(format!("failed to bind run_id: {:?}", err)))?;
+    let mut run_rows = run_stmt.execute();
+    let run_row = run_rows
+        .next()
+        .ok_or_else(|| Error::new_message("Run not found while awaiting event"))?
+        .map_err(|err| Error::new_message(format!("failed to read run row: {:?}", err)))?;
+    let state = run_row
+        .get::<String>(0)
+        .map_err(|err| Error::new_message(format!("failed to read run state: {:?}", err)))?;
+    let existing_payload = run_row
+        .get::<String>(1)
+        .map_err(|err| Error::new_message(format!("failed to read event payload: {:?}", err)))?;
+    let wake_event = run_row
+        .get::<String>(2)
+        .map_err(|err| Error::new_message(format!("failed to read wake_event: {:?}", err)))?;
+    let wake_step = run_row
+        .get::<String>(3)
+        .map_err(|err| Error::new_message(format!("failed to read wake_step: {:?}", err)))?;
+    let task_state = run_row
+        .get::<String>(4)
+        .map_err(|err| Error::new_message(format!("failed to read task state: {:?}", err)))?;
+
+    if task_state == "cancelled" {
+        return Err(Error::new_message("Task has been cancelled"));
+    }
+
+    if !existing_payload.is_empty() {
+        sql::exec_with_bind_text(
+            db,
+            "update absurd_runs
+                set event_payload = null
+              where queue_name = ?1
+                and run_id = ?2",
+            &[queue_name, run_id],
+        )?;
+    }
+
+    Ok(AwaitRun {
+        state,
+        existing_payload,
+        wake_event,
+        wake_step,
+    })
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn write_await_checkpoint(
+    db: *mut sqlite3,
+    queue_name: &str,
+    task_id: &str,
+    step_name: &str,
+    payload: &str,
+    run_id: &str,
+    now_value: &str,
+    seq_value: &str,
+    event_name: &str,
+) -> Result<()> {
+    let payload = payload::encode_json(db, queue_name, payload)?;
+    sql::exec_with_bind_text(
+        db,
+        "insert into absurd_checkpoints (queue_name, task_id, checkpoint_name, state, status, owner_run_id, updated_at, event_seq, event_name)
+         values (?1, ?2, ?3, jsonb(?4), 'committed', ?5, cast(?6 as integer), cast(nullif(?7, '') as integer), nullif(?8, ''))
+         on conflict (queue_name, task_id, checkpoint_name)
+         do update set state = excluded.state,
+                       status = excluded.status,
+                       owner_run_id = excluded.owner_run_id,
+                       updated_at = excluded.updated_at,
+                       event_seq = excluded.event_seq,
+                       event_name = excluded.event_name",
+        &[
+            queue_name, task_id, step_name, &payload, run_id, now_value, seq_value, event_name,
+        ],
+    )?;
+    checkpoint::record_checkpoint_history(db, queue_name, task_id, step_name)
+}
+
+/// Put the run and its task to sleep until an emit or `available_value`.
+/// `step_name` is the await that parked it.
+pub fn suspend_run(
+    db: *mut sqlite3,
+    queue_name: &str,
+    task_id: &str,
+    run_id: &str,
+    step_name: &str,
+    available_value: &str,
+    wake_event: &str,
+) -> Result<()> {
+    sql::exec_with_bind_text(
+        db,
+        "update absurd_runs
+            set state = 'sleeping',
+                claimed_by = null,
+                claim_expires_at = null,
+                available_at = cast(?1 as integer),
+                wake_event = ?2,
+                wake_step = ?3,
+                event_payload = null
+          where queue_name = ?4
+            and run_id = ?5",
+        &[available_value, wake_event, step_name, queue_name, run_id],
+    )?;
+
+    sql::exec_with_bind_text(
+        db,
+        "update absurd_tasks
+            set state = 'sleeping'
+          where queue_name = ?1
+            and task_id = ?2",
+        &[queue_name, task_id],
+    )
+}
+
+/// Checkpoint state recorded for an await that timed out.
+pub const TIMED_OUT_PAYLOAD: &str = r#"{"timedOut":true}"#;
+
+/// A wake for `wake_event` that carried no payload means the wait that
+/// `step_name` parked the run on timed out. The claim path normally records
+/// that as a checkpoint and clears the wake; when it did not (the wait row was
+/// already gone), record it here so the result, and every replay of it,
+/// reports `timed_out`.
+#[allow(clippy::too_many_arguments)]
+pub fn resume_timed_out_wake(
+    db: *mut sqlite3,
+    queue_name: &str,
+    task_id: &str,
+    step_name: &str,
+    run_id: &str,
+    run: &AwaitRun,
+    wake_event: &str,
+    now_value: &str,
+) -> Result<Option<AwaitResult>> {
+    if run.wake_event != wake_event
+        || run.wake_step != step_name
+        || !run.existing_payload.is_empty()
+    {
+        return Ok(None);
+    }
+    sql::exec_with_bind_text(
+        db,
+        "update absurd_runs
+            set wake_event = null,
+                wake_step = null
+          where queue_name = ?1
+            and run_id = ?2",
+        &[queue_name, run_id],
+    )?;
+    sql::exec_with_bind_text(
+        db,
+        "insert into absurd_checkpoints (queue_name, task_id, checkpoint_name, state, status, owner_run_id, updated_at)
+         values (?1, ?2, ?3, jsonb(?4), 'timed_out', ?5, cast(?6 as integer))
+         on conflict (queue_name, task_id, checkpoint_name)
+         do update set state = excluded.state,
+                       status = excluded.status,
+                       owner_run_id = excluded.owner_run_id,
+                       updated_at = excluded.updated_at,
+                       event_seq = null,
+                       event_name = null",
+        &[
+            queue_name,
+            task_id,
+            step_name,
+            TIMED_OUT_PAYLOAD,
+            run_id,
+            now_value,
+        ],
+    )?;
+    checkpoint::record_checkpoint_history(db, queue_name, task_id, step_name)?;
+    Ok(Some(AwaitResult {
+        should_suspend: 0,
+        payload: Some(TIMED_OUT_PAYLOAD.to_string()),
+        seq: None,
+        matched_event: None,
+        timed_out: true,
+    }))
+}
+
+pub fn finish_await<T>(db: *mut sqlite3, result: Result<T>) -> Result<T> {
+    match result {
+        Ok(result) => {
+            sql::exec_with_bind_text(db, "commit", &[])?;
+            Ok(result)
+        }
+        Err(err) => {
+            let _ = sql::exec_with_bind_text(db, "rollback", &[]);
+            Err(err)
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
 fn await_event_impl(
     db: *mut sqlite3,
     queue_name: &str,
@@ -52,11 +487,14 @@ fn await_event_impl(
     run_id: &str,
     step_name: &str,
     event_name: &str,
+    is_pattern: bool,
     timeout_secs: Option<i64>,
+    after_seq: Option<i64>,
 ) -> Result<AwaitResult> {
     validate::queue_name(queue_name)?;
     validate::step_name(step_name)?;
     validate::event_name(event_name)?;
+    let after_seq_value = after_seq.map(|v| v.to_string()).unwrap_or_default();
 
     let now = sql::now_ms_from_db(db);
     let now_value = now.to_string();
@@ -68,234 +506,680 @@ fn await_event_impl(
     sql::exec_with_bind_text(db, "begin immediate", &[])?;
 
     let result = (|| -> Result<AwaitResult> {
-        let mut checkpoint_stmt = sqlite_loadable::exec::Statement::prepare(
-            db,
-            "select coalesce(json(state), '') as state
-               from absurd_checkpoints
-              where queue_name = ?1
-                and task_id = ?2
-                and checkpoint_name = ?3",
-        )
-        .map_err(|err| {
-            Error::new_message(format!("failed to prepare checkpoint lookup: {:?}", err))
-        })?;
-        checkpoint_stmt
-            .bind_text(1, queue_name)
-            .map_err(|err| Error::new_message(format!("failed to bind queue_name: {:?}", err)))?;
-        checkpoint_stmt
-            .bind_text(2, task_id)
-            .map_err(|err| Error::new_message(format!("failed to bind task_id: {:?}", err)))?;
-        checkpoint_stmt
-            .bind_text(3, step_name)
-            .map_err(|err| Error::new_message(format!("failed to bind step_name: {:?}", err)))?;
-        let mut checkpoint_rows = checkpoint_stmt.execute();
-        if let Some(Ok(row)) = checkpoint_rows.next() {
-            let checkpoint_payload = row.get::<String>(0).map_err(|err| {
-                Error::new_message(format!("failed to read checkpoint payload: {:?}", err))
-            })?;
-            if !checkpoint_payload.is_empty() {
-                return Ok(AwaitResult {
-                    should_suspend: 0,
-                    payload: Some(checkpoint_payload),
-                });
-            }
+        if let Some(result) = load_await_checkpoint(db, queue_name, task_id, step_name)? {
+            return Ok(result);
         }
 
+        // Checked after the checkpoint so a wait resolved before the log was
+        // turned off still replays.
+        if after_seq.is_some() && !queue::event_log_enabled(db, queue_name)? {
+            return Err(Error::new_message(
+                "after_seq requires the event_log queue setting",
+            ));
+        }
+
+        if !is_pattern {
+            sql::exec_with_bind_text(
+                db,
+                "insert into absurd_events (queue_name, event_name, payload, emitted_at)
+                 values (?1, ?2, null, 0)
+                 on conflict (queue_name, event_name) do nothing",
+                &[queue_name, event_name],
+            )?;
+        }
+
+        let run = load_await_run(db, queue_name, run_id)?;
+        let emission = lookup_emission(db, queue_name, event_name, is_pattern, after_seq)?;
+
+        if run.state != "running" {
+            return Err(Error::new_message(
+                "Run must be running to await absurd_events",
+            ));
+        }
+
+        if let Some(emission) = emission.filter(|emission| !emission.payload.is_empty()) {
+            let seq = optional_seq(emission.seq);
+            let seq_value = seq.map(|v| v.to_string()).unwrap_or_default();
+            write_await_checkpoint(
+                db,
+                queue_name,
+                task_id,
+                step_name,
+                &emission.payload,
+                run_id,
+                &now_value,
+                &seq_value,
+                &emission.event_name,
+            )?;
+            return Ok(AwaitResult {
+                should_suspend: 0,
+                payload: Some(emission.payload),
+                seq,
+                matched_event: Some(emission.event_name),
+                timed_out: false,
+            });
+        }
+
+        if let Some(result) = resume_timed_out_wake(
+            db, queue_name, task_id, step_name, run_id, &run, event_name, &now_value,
+        )? {
+            return Ok(result);
+        }
+
+        let (wait_table, wait_column) = if is_pattern {
+            ("absurd_pattern_waits", "pattern")
+        } else {
+            ("absurd_waits", "event_name")
+        };
         sql::exec_with_bind_text(
             db,
-            "insert into absurd_events (queue_name, event_name, payload, emitted_at)
-             values (?1, ?2, null, 0)
-             on conflict (queue_name, event_name) do nothing",
-            &[queue_name, event_name],
+            &format!(
+                "delete from {} where queue_name = ?1 and run_id = ?2 and step_name = ?3",
+                wait_table
+            ),
+            &[queue_name, run_id, step_name],
+        )?;
+        sql::exec_with_bind_text(
+            db,
+            &format!(
+                "insert into {} (queue_name, task_id, run_id, step_name, {}, timeout_at, created_at, after_seq)
+                 values (?1, ?2, ?3, ?4, ?5, nullif(?6, ''), cast(?7 as integer), cast(nullif(?8, '') as integer))",
+                wait_table, wait_column
+            ),
+            &[
+                queue_name,
+                task_id,
+                run_id,
+                step_name,
+                event_name,
+                &timeout_at_value,
+                &now_value,
+                &after_seq_value,
+            ],
         )?;
 
-        let mut run_stmt = sqlite_loadable::exec::Statement::prepare(
+        suspend_run(
             db,
-            "select r.state,
-                    coalesce(json(r.event_payload), '') as event_payload,
-                    coalesce(r.wake_event, '') as wake_event,
-                    t.state as task_state
-               from absurd_runs r
-               join absurd_tasks t
-                 on t.queue_name = r.queue_name
-                and t.task_id = r.task_id
-              where r.queue_name = ?1
-                and r.run_id = ?2",
-        )
-        .map_err(|err| Error::new_message(format!("failed to prepare run lookup: {:?}", err)))?;
-        run_stmt
-            .bind_text(1, queue_name)
-            .map_err(|err| Error::new_message(format!("failed to bind queue_name: {:?}", err)))?;
-        run_stmt
-            .bind_text(2, run_id)
-            .map_err(|err| Error::new_message(format!("failed to bind run_id: {:?}", err)))?;
-        let mut run_rows = run_stmt.execute();
-        let run_row = run_rows
-            .next()
-            .ok_or_else(|| Error::new_message("Run not found while awaiting event"))?
-            .map_err(|err| Error::new_message(format!("failed to read run row: {:?}", err)))?;
-        let run_state = run_row
-            .get::<String>(0)
-            .map_err(|err| Error::new_message(format!("failed to read run state: {:?}", err)))?;
-        let existing_payload = run_row.get::<String>(1).map_err(|err| {
-            Error::new_message(format!("failed to read event payload: {:?}", err))
-        })?;
-        let wake_event = run_row
-            .get::<String>(2)
-            .map_err(|err| Error::new_message(format!("failed to read wake_event: {:?}", err)))?;
-        let task_state = run_row
-            .get::<String>(3)
-            .map_err(|err| Error::new_message(format!("failed to read task state: {:?}", err)))?;
+            queue_name,
+            task_id,
+            run_id,
+            step_name,
+            &available_value,
+            event_name,
+        )?;
+
+        Ok(AwaitResult {
+            should_suspend: 1,
+            payload: None,
+            seq: None,
+            matched_event: None,
+            timed_out: false,
+        })
+    })();
 
-        if task_state == "cancelled" {
-            return Err(Error::new_message("Task has been cancelled"));
+    finish_await(db, result)
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum WaitMode {
+    Any,
+    All,
+}
+
+impl WaitMode {
+    fn parse(raw: &str) -> Result<WaitMode> {
+        match raw.trim().to_ascii_lowercase().as_str() {
+            "any" => Ok(WaitMode::Any),
+            "all" => Ok(WaitMode::All),
+            _ => Err(Error::new_message("mode must be 'any' or 'all'")),
         }
+    }
 
-        let mut event_stmt = sqlite_loadable::exec::Statement::prepare(
-            db,
-            "select coalesce(json(payload), '') as payload
-               from absurd_events
-              where queue_name = ?1
-                and event_name = ?2",
-        )
-        .map_err(|err| Error::new_message(format!("failed to prepare event lookup: {:?}", err)))?;
-        event_stmt
-            .bind_text(1, queue_name)
-            .map_err(|err| Error::new_message(format!("failed to bind queue_name: {:?}", err)))?;
-        event_stmt
-            .bind_text(2, event_name)
-            .map_err(|err| Error::new_message(format!("failed to bind event_name: {:?}", err)))?;
-        let mut event_rows = event_stmt.execute();
-        let event_row = event_rows
-            .next()
-            .ok_or_else(|| Error::new_message("event not found"))?
-            .map_err(|err| Error::new_message(format!("failed to read event row: {:?}", err)))?;
-        let event_payload = event_row.get::<String>(0).map_err(|err| {
-            Error::new_message(format!("failed to read event payload: {:?}", err))
+    fn as_str(self) -> &'static str {
+        match self {
+            WaitMode::Any => "any",
+            WaitMode::All => "all",
+        }
+    }
+}
+
+fn parse_event_names(raw: &str) -> Result<Vec<String>> {
+    let value: JsonValue = serde_json::from_str(raw.trim()).map_err(|err| {
+        Error::new_message(format!("event_names must be a JSON array: {:?}", err))
+    })?;
+    let items = value
+        .as_array()
+        .ok_or_else(|| Error::new_message("event_names must be a JSON array"))?;
+    let mut names: Vec<String> = Vec::with_capacity(items.len());
+    for item in items {
+        let name = item
+            .as_str()
+            .ok_or_else(|| Error::new_message("event_names must contain strings"))?;
+        validate::event_name(name)?;
+        if !names.iter().any(|existing| existing == name) {
+            names.push(name.to_string());
+        }
+    }
+    if names.is_empty() {
+        return Err(Error::new_message("event_names must not be empty"));
+    }
+    Ok(names)
+}
+
+/// Build the `{"fired": [...], "payloads": {...}}` object checkpointed for
+/// multi-event awaits.
+fn fired_events_payload(fired: &[(String, String)]) -> Result<String> {
+    let mut names = Vec::with_capacity(fired.len());
+    let mut payloads = serde_json::Map::new();
+    for (name, payload) in fired {
+        let value: JsonValue = serde_json::from_str(payload).map_err(|err| {
+            Error::new_message(format!("failed to decode event payload: {:?}", err))
         })?;
+        names.push(JsonValue::String(name.clone()));
+        payloads.insert(name.clone(), value);
+    }
+    let mut object = serde_json::Map::new();
+    object.insert("fired".to_string(), JsonValue::Array(names));
+    object.insert("payloads".to_string(), JsonValue::Object(payloads));
+    serde_json::to_string(&JsonValue::Object(object))
+        .map_err(|err| Error::new_message(format!("failed to encode fired events: {:?}", err)))
+}
 
-        let mut resolved_payload: Option<String> = None;
+#[allow(clippy::too_many_arguments)]
+fn await_events_impl(
+    db: *mut sqlite3,
+    queue_name: &str,
+    task_id: &str,
+    run_id: &str,
+    step_name: &str,
+    event_names: &[String],
+    mode: WaitMode,
+    timeout_secs: Option<i64>,
+) -> Result<AwaitResult> {
+    validate::queue_name(queue_name)?;
+    validate::step_name(step_name)?;
+    let wake_key = serde_json::to_string(event_names)
+        .map_err(|err| Error::new_message(format!("failed to encode event_names: {:?}", err)))?;
+
+    let now = sql::now_ms_from_db(db);
+    let now_value = now.to_string();
+    let timeout_at = timeout_secs.map(|secs| now + secs.saturating_mul(1000));
+    let timeout_at_value = timeout_at.map(|v| v.to_string()).unwrap_or_default();
+    let available_at = timeout_at.unwrap_or(FAR_FUTURE_MS);
+    let available_value = available_at.to_string();
+
+    sql::exec_with_bind_text(db, "begin immediate", &[])?;
 
-        if !existing_payload.is_empty() {
+    let result = (|| -> Result<AwaitResult> {
+        if let Some(result) = load_await_checkpoint(db, queue_name, task_id, step_name)? {
+            return Ok(result);
+        }
+
+        for event_name in event_names {
             sql::exec_with_bind_text(
                 db,
-                "update absurd_runs
-                    set event_payload = null
-                  where queue_name = ?1
-                    and run_id = ?2",
-                &[queue_name, run_id],
+                "insert into absurd_events (queue_name, event_name, payload, emitted_at)
+                 values (?1, ?2, null, 0)
+                 on conflict (queue_name, event_name) do nothing",
+                &[queue_name, event_name],
             )?;
-
-            if !event_payload.is_empty() && event_payload == existing_payload {
-                resolved_payload = Some(existing_payload.clone());
-            }
         }
 
-        if run_state != "running" {
+        let run = load_await_run(db, queue_name, run_id)?;
+        if run.state != "running" {
             return Err(Error::new_message(
                 "Run must be running to await absurd_events",
             ));
         }
 
-        if resolved_payload.is_none() && !event_payload.is_empty() {
-            resolved_payload = Some(event_payload.clone());
+        let mut emitted: Vec<(String, Option<String>)> = Vec::with_capacity(event_names.len());
+        for event_name in event_names {
+            let payload = lookup_emission(db, queue_name, event_name, false, None)?
+                .map(|emission| emission.payload)
+                .filter(|payload| !payload.is_empty());
+            emitted.push((event_name.clone(), payload));
         }
 
-        if let Some(payload) = resolved_payload {
-            sql::exec_with_bind_text(
+        let fired: Vec<(String, String)> = match mode {
+            WaitMode::Any => emitted
+                .iter()
+                .find_map(|(name, payload)| payload.clone().map(|p| (name.clone(), p)))
+                .into_iter()
+                .collect(),
+            WaitMode::All if emitted.iter().all(|(_, payload)| payload.is_some()) => emitted
+                .iter()
+                .filter_map(|(name, payload)| payload.clone().map(|p| (name.clone(), p)))
+                .collect(),
+            WaitMode::All => Vec::new(),
+        };
+
+        if !fired.is_empty() {
+            let payload = fired_events_payload(&fired)?;
+            let matched_event = match mode {
+                WaitMode::Any => fired[0].0.clone(),
+                WaitMode::All => String::new(),
+            };
+            write_await_checkpoint(
                 db,
-                "insert into absurd_checkpoints (queue_name, task_id, checkpoint_name, state, status, owner_run_id, updated_at)
-                 values (?1, ?2, ?3, jsonb(?4), 'committed', ?5, cast(?6 as integer))
-                 on conflict (queue_name, task_id, checkpoint_name)
-                 do update set state = excluded.state,
-                               status = excluded.status,
-                               owner_run_id = excluded.owner_run_id,
-                               updated_at = excluded.updated_at",
-                &[queue_name, task_id, step_name, &payload, run_id, &now_value],
+                queue_name,
+                task_id,
+                step_name,
+                &payload,
+                run_id,
+                &now_value,
+                "",
+                &matched_event,
             )?;
             return Ok(AwaitResult {
                 should_suspend: 0,
                 payload: Some(payload),
+                seq: None,
+                matched_event: optional_text(matched_event),
+                timed_out: false,
             });
         }
 
-        if wake_event == event_name && existing_payload.is_empty() {
+        if let Some(result) = resume_timed_out_wake(
+            db, queue_name, task_id, step_name, run_id, &run, &wake_key, &now_value,
+        )? {
+            return Ok(result);
+        }
+
+        sql::exec_with_bind_text(
+            db,
+            "delete from absurd_waits where queue_name = ?1 and run_id = ?2 and step_name = ?3",
+            &[queue_name, run_id, step_name],
+        )?;
+        for (event_name, payload) in &emitted {
+            // For `all`, events already emitted count as fired up front.
+            let fired_payload = payload.as_deref().unwrap_or("");
             sql::exec_with_bind_text(
                 db,
-                "update absurd_runs
-                    set wake_event = null
-                  where queue_name = ?1
-                    and run_id = ?2",
-                &[queue_name, run_id],
+                "insert into absurd_waits (queue_name, task_id, run_id, step_name, event_name, timeout_at, created_at, wait_mode, fired_payload)
+                 values (?1, ?2, ?3, ?4, ?5, nullif(?6, ''), cast(?7 as integer), ?8,
+                         case when ?9 = '' then null else jsonb(?9) end)",
+                &[
+                    queue_name,
+                    task_id,
+                    run_id,
+                    step_name,
+                    event_name,
+                    &timeout_at_value,
+                    &now_value,
+                    mode.as_str(),
+                    fired_payload,
+                ],
             )?;
-            return Ok(AwaitResult {
-                should_suspend: 0,
-                payload: None,
-            });
         }
 
-        sql::exec_with_bind_text(
-            db,
-            "insert into absurd_waits (queue_name, task_id, run_id, step_name, event_name, timeout_at, created_at)
-             values (?1, ?2, ?3, ?4, ?5, nullif(?6, ''), cast(?7 as integer))
-             on conflict (queue_name, run_id, step_name)
-             do update set event_name = excluded.event_name,
-                           timeout_at = excluded.timeout_at,
-                           created_at = excluded.created_at",
-            &[
-                queue_name,
-                task_id,
-                run_id,
-                step_name,
+        suspend_run(
+            db,
+            queue_name,
+            task_id,
+            run_id,
+            step_name,
+            &available_value,
+            &wake_key,
+        )?;
+
+        Ok(AwaitResult {
+            should_suspend: 1,
+            payload: None,
+            seq: None,
+            matched_event: None,
+            timed_out: false,
+        })
+    })();
+
+    finish_await(db, result)
+}
+
+/// Collect runs waiting on `event_name`, either by exact name or by a
+/// matching glob pattern, that this emission (with log `seq`) satisfies.
+/// An emission without a sequence (the event log was turned off after the
+/// wait was created) satisfies any `after_seq`.
+fn matching_waits(
+    db: *mut sqlite3,
+    queue_name: &str,
+    event_name: &str,
+    now_value: &str,
+    seq_value: &str,
+) -> Result<Vec<WaitingRun>> {
+    let mut stmt = sqlite_loadable::exec::Statement::prepare(
+        db,
+        "select run_id, task_id, step_name, 0 as is_pattern, coalesce(wait_mode, '') as wait_mode
+           from absurd_waits
+          where queue_name = ?1
+            and event_name = ?2
+            and (timeout_at is null or timeout_at > cast(?3 as integer))
+            and (after_seq is null or ?4 = '' or after_seq < cast(?4 as integer))
+         union all
+         select run_id, task_id, step_name, 1 as is_pattern, '' as wait_mode
+           from absurd_pattern_waits
+          where queue_name = ?1
+            and ?2 glob pattern
+            and (timeout_at is null or timeout_at > cast(?3 as integer))
+            and (after_seq is null or ?4 = '' or after_seq < cast(?4 as integer))",
+    )
+    .map_err(|err| {
+        Error::new_message(format!("failed to prepare absurd_waits lookup: {:?}", err))
+    })?;
+    stmt.bind_text(1, queue_name)
+        .map_err(|err| Error::new_message(format!("failed to bind queue_name: {:?}", err)))?;
+    stmt.bind_text(2, event_name)
+        .map_err(|err| Error::new_message(format!("failed to bind event_name: {:?}", err)))?;
+    stmt.bind_text(3, now_value)
+        .map_err(|err| Error::new_message(format!("failed to bind now: {:?}", err)))?;
+    stmt.bind_text(4, seq_value)
+        .map_err(|err| Error::new_message(format!("failed to bind seq: {:?}", err)))?;
+
+    let mut waiting = Vec::new();
+    for row in stmt.execute() {
+        let row =
+            row.map_err(|err| Error::new_message(format!("failed to read wait row: {:?}", err)))?;
+        let run_id = row
+            .get::<String>(0)
+            .map_err(|err| Error::new_message(format!("failed to read run_id: {:?}", err)))?;
+        let task_id = row
+            .get::<String>(1)
+            .map_err(|err| Error::new_message(format!("failed to read task_id: {:?}", err)))?;
+        let step_name = row
+            .get::<String>(2)
+            .map_err(|err| Error::new_message(format!("failed to read step_name: {:?}", err)))?;
+        let is_pattern = row
+            .get::<i64>(3)
+            .map_err(|err| Error::new_message(format!("failed to read is_pattern: {:?}", err)))?;
+        let wait_mode = row
+            .get::<String>(4)
+            .map_err(|err| Error::new_message(format!("failed to read wait_mode: {:?}", err)))?;
+        waiting.push(WaitingRun {
+            run_id,
+            task_id,
+            step_name,
+            is_pattern: is_pattern != 0,
+            wait_mode,
+        });
+    }
+    Ok(waiting)
+}
+
+/// Record that `event_name` fired for an `all` wait and return the fired
+/// events once every event in the group has fired.
+fn fire_all_wait(
+    db: *mut sqlite3,
+    queue_name: &str,
+    wait: &WaitingRun,
+    event_name: &str,
+    payload_text: &str,
+) -> Result<Option<Vec<(String, String)>>> {
+    sql::exec_with_bind_text(
+        db,
+        "update absurd_waits
+            set fired_payload = jsonb(?1)
+          where queue_name = ?2
+            and run_id = ?3
+            and step_name = ?4
+            and event_name = ?5",
+        &[
+            payload_text,
+            queue_name,
+            &wait.run_id,
+            &wait.step_name,
+            event_name,
+        ],
+    )?;
+
+    let mut stmt = sqlite_loadable::exec::Statement::prepare(
+        db,
+        "select event_name, coalesce(json(fired_payload), '') as fired_payload
+           from absurd_waits
+          where queue_name = ?1
+            and run_id = ?2
+            and step_name = ?3
+          order by rowid",
+    )
+    .map_err(|err| Error::new_message(format!("failed to prepare fired lookup: {:?}", err)))?;
+    stmt.bind_text(1, queue_name)
+        .map_err(|err| Error::new_message(format!("failed to bind queue_name: {:?}", err)))?;
+    stmt.bind_text(2, &wait.run_id)
+        .map_err(|err| Error::new_message(format!("failed to bind run_id: {:?}", err)))?;
+    stmt.bind_text(3, &wait.step_name)
+        .map_err(|err| Error::new_message(format!("failed to bind step_name: {:?}", err)))?;
+    let mut fired = Vec::new();
+    for row in stmt.execute() {
+        let row =
+            row.map_err(|err| Error::new_message(format!("failed to read wait row: {:?}", err)))?;
+        let name = row
+            .get::<String>(0)
+            .map_err(|err| Error::new_message(format!("failed to read event_name: {:?}", err)))?;
+        let payload = row.get::<String>(1).map_err(|err| {
+            Error::new_message(format!("failed to read fired_payload: {:?}", err))
+        })?;
+        if payload.is_empty() {
+            return Ok(None);
+        }
+        fired.push((name, payload));
+    }
+    Ok(Some(fired))
+}
+
+/// Parse the optional JSON array of additional queues an emit targets.
+fn parse_target_queues(value: Option<&*mut sqlite3_value>) -> Result<Vec<String>> {
+    let value = match value {
+        Some(value) if !api::value_is_null(value) => value,
+        _ => return Ok(Vec::new()),
+    };
+    let raw = api::value_text(value).map_err(|err| {
+        Error::new_message(format!(
+            "target_queues must be a JSON array of queue names: {:?}",
+            err
+        ))
+    })?;
+    let targets: Vec<String> = serde_json::from_str(raw).map_err(|err| {
+        Error::new_message(format!(
+            "target_queues must be a JSON array of queue names: {:?}",
+            err
+        ))
+    })?;
+    for target in &targets {
+        validate::queue_name(target)?;
+    }
+    Ok(targets)
+}
+
+/// Resolve every queue an emit on `queue_name` is delivered to: the queue
+/// itself, explicit targets, then forwarding rules whose pattern matches.
+/// Forwarding is a single hop; a target's own forwards are not followed.
+fn emit_targets(
+    db: *mut sqlite3,
+    queue_name: &str,
+    event_name: &str,
+    extra_targets: &[String],
+) -> Result<Vec<String>> {
+    let mut targets = vec![queue_name.to_string()];
+    for target in extra_targets {
+        if targets.contains(target) {
+            continue;
+        }
+        let exists = sql::query_row_i64(
+            db,
+            "select count(*) from absurd_queues where queue_name = ?1",
+            &[target],
+        )?;
+        if exists == 0 {
+            return Err(Error::new_message(format!("Queue not found: {}", target)));
+        }
+        targets.push(target.clone());
+    }
+
+    let mut stmt = sqlite_loadable::exec::Statement::prepare(
+        db,
+        "select distinct target_queue
+           from absurd_event_forwards
+          where source_queue = ?1
+            and ?2 glob event_pattern
+          order by target_queue",
+    )
+    .map_err(|err| Error::new_message(format!("failed to prepare forward lookup: {:?}", err)))?;
+    stmt.bind_text(1, queue_name)
+        .map_err(|err| Error::new_message(format!("failed to bind queue_name: {:?}", err)))?;
+    stmt.bind_text(2, event_name)
+        .map_err(|err| Error::new_message(format!("failed to bind event_name: {:?}", err)))?;
+    for row in stmt.execute() {
+        let row = row
+            .map_err(|err| Error::new_message(format!("failed to read forward row: {:?}", err)))?;
+        let target = row
+            .get::<String>(0)
+            .map_err(|err| Error::new_message(format!("failed to read target_queue: {:?}", err)))?;
+        if !targets.contains(&target) {
+            targets.push(target);
+        }
+    }
+    Ok(targets)
+}
+
+/// Advance the queue's event sequence. The counter lives outside the log so
+/// it keeps increasing after cleanup deletes old log rows.
+fn next_event_seq(db: *mut sqlite3, queue_name: &str) -> Result<i64> {
+    sql::exec_with_bind_text(
+        db,
+        "insert into absurd_event_sequences (queue_name, last_seq)
+         values (?1, 1)
+         on conflict (queue_name) do update set last_seq = last_seq + 1",
+        &[queue_name],
+    )?;
+    sql::query_row_i64(
+        db,
+        "select last_seq from absurd_event_sequences where queue_name = ?1",
+        &[queue_name],
+    )
+}
+
+/// Store one emission in `queue_name` and wake the waiters it satisfies.
+fn emit_into_queue(
+    db: *mut sqlite3,
+    queue_name: &str,
+    event_name: &str,
+    payload_text: &str,
+    now_value: &str,
+) -> Result<()> {
+    let seq_value = if queue::event_log_enabled(db, queue_name)? {
+        let seq_value = next_event_seq(db, queue_name)?.to_string();
+        sql::exec_with_bind_text(
+            db,
+            "insert into absurd_event_log (queue_name, seq, event_name, payload, emitted_at)
+             values (?1, cast(?2 as integer), ?3, jsonb(?4), cast(?5 as integer))",
+            &[queue_name, &seq_value, event_name, payload_text, now_value],
+        )?;
+        seq_value
+    } else {
+        String::new()
+    };
+
+    sql::exec_with_bind_text(
+        db,
+        "insert into absurd_events (queue_name, event_name, payload, emitted_at, seq)
+         values (?1, ?2, jsonb(?3), cast(?4 as integer), cast(nullif(?5, '') as integer))
+         on conflict (queue_name, event_name)
+         do update set payload = excluded.payload,
+                       emitted_at = excluded.emitted_at,
+                       seq = excluded.seq",
+        &[queue_name, event_name, payload_text, now_value, &seq_value],
+    )?;
+
+    // Expired waits are left for the claim path, which records the timeout.
+    let waiting = matching_waits(db, queue_name, event_name, now_value, &seq_value)?;
+    for wait in waiting {
+        let (wake_payload, wake_seq, matched_event) = match wait.wait_mode.as_str() {
+            "any" => (
+                fired_events_payload(&[(event_name.to_string(), payload_text.to_string())])?,
+                "",
                 event_name,
-                &timeout_at_value,
-                &now_value,
-            ],
-        )?;
+            ),
+            "all" => match fire_all_wait(db, queue_name, &wait, event_name, payload_text)? {
+                Some(fired) => (fired_events_payload(&fired)?, "", ""),
+                None => continue,
+            },
+            _ => (payload_text.to_string(), seq_value.as_str(), event_name),
+        };
 
         sql::exec_with_bind_text(
             db,
             "update absurd_runs
-                set state = 'sleeping',
-                    claimed_by = null,
-                    claim_expires_at = null,
+                set state = 'pending',
                     available_at = cast(?1 as integer),
-                    wake_event = ?2,
-                    event_payload = null
+                    wake_event = null,
+                    wake_step = null,
+                    event_payload = jsonb(?2),
+                    claimed_by = null,
+                    claim_expires_at = null
               where queue_name = ?3
-                and run_id = ?4",
-            &[&available_value, event_name, queue_name, run_id],
+                and run_id = ?4
+                and state = 'sleeping'",
+            &[now_value, &wake_payload, queue_name, &wait.run_id],
+        )?;
+
+        let changes = sql::query_row_i64(db, "select changes()", &[])?;
+        if changes == 0 {
+            continue;
+        }
+
+        write_await_checkpoint(
+            db,
+            queue_name,
+            &wait.task_id,
+            &wait.step_name,
+            &wake_payload,
+            &wait.run_id,
+            now_value,
+            wake_seq,
+            matched_event,
         )?;
 
         sql::exec_with_bind_text(
             db,
             "update absurd_tasks
-                set state = 'sleeping'
+                set state = 'pending'
               where queue_name = ?1
                 and task_id = ?2",
-            &[queue_name, task_id],
+            &[queue_name, &wait.task_id],
         )?;
 
-        Ok(AwaitResult {
-            should_suspend: 1,
-            payload: None,
-        })
-    })();
-
-    match result {
-        Ok(result) => {
-            sql::exec_with_bind_text(db, "commit", &[])?;
-            Ok(result)
-        }
-        Err(err) => {
-            let _ = sql::exec_with_bind_text(db, "rollback", &[]);
-            Err(err)
+        if wait.is_pattern {
+            sql::exec_with_bind_text(
+                db,
+                "delete from absurd_pattern_waits
+                  where queue_name = ?1
+                    and run_id = ?2
+                    and step_name = ?3",
+                &[queue_name, &wait.run_id, &wait.step_name],
+            )?;
+        } else if !wait.wait_mode.is_empty() {
+            sql::exec_with_bind_text(
+                db,
+                "delete from absurd_waits
+                  where queue_name = ?1
+                    and run_id = ?2
+                    and step_name = ?3",
+                &[queue_name, &wait.run_id, &wait.step_name],
+            )?;
+        } else {
+            sql::exec_with_bind_text(
+                db,
+                "delete from absurd_waits
+                  where queue_name = ?1
+                    and run_id = ?2
+                    and event_name = ?3",
+                &[queue_name, &wait.run_id, event_name],
+            )?;
         }
     }
+
+    Ok(())
 }
 
-/// SQL: absurd_emit_event(queue_name, event_name[, payload_json])
-/// Usage: emit or update an event payload and wake waiting runs.
+/// SQL: absurd_emit_event(queue_name, event_name[, payload_json[, target_queues_json]])
+/// Usage: emit or update an event payload and wake exact and pattern waiters (appends to the event log when enabled); also delivers to the listed target queues and to queues forwarding from `queue_name`.
 /// Section: Durable
 pub fn absurd_emit_event(
     context: *mut sqlite3_context,
@@ -304,6 +1188,7 @@ pub fn absurd_emit_event(
     let queue_name = api::value_text_notnull(values.first().expect("queue_name"))?;
     let event_name = api::value_text_notnull(values.get(1).expect("event_name"))?;
     let payload_value = values.get(2);
+    let extra_targets = parse_target_queues(values.get(3))?;
 
     validate::queue_name(queue_name)?;
     validate::event_name(event_name)?;
@@ -336,111 +1221,9 @@ pub fn absurd_emit_event(
     sql::exec_with_bind_text(db, "begin immediate", &[])?;
 
     let result = (|| -> Result<()> {
-        sql::exec_with_bind_text(
-            db,
-            "insert into absurd_events (queue_name, event_name, payload, emitted_at)
-             values (?1, ?2, jsonb(?3), cast(?4 as integer))
-             on conflict (queue_name, event_name)
-             do update set payload = excluded.payload,
-                           emitted_at = excluded.emitted_at",
-            &[queue_name, event_name, &payload_text, &now_value],
-        )?;
-
-        sql::exec_with_bind_text(
-            db,
-            "delete from absurd_waits
-              where queue_name = ?1
-                and event_name = ?2
-                and timeout_at is not null
-                and timeout_at <= cast(?3 as integer)",
-            &[queue_name, event_name, &now_value],
-        )?;
-
-        let mut waits_stmt = sqlite_loadable::exec::Statement::prepare(
-            db,
-            "select run_id, task_id, step_name
-               from absurd_waits
-              where queue_name = ?1
-                and event_name = ?2
-                and (timeout_at is null or timeout_at > cast(?3 as integer))",
-        )
-        .map_err(|err| {
-            Error::new_message(format!("failed to prepare absurd_waits lookup: {:?}", err))
-        })?;
-        waits_stmt
-            .bind_text(1, queue_name)
-            .map_err(|err| Error::new_message(format!("failed to bind queue_name: {:?}", err)))?;
-        waits_stmt
-            .bind_text(2, event_name)
-            .map_err(|err| Error::new_message(format!("failed to bind event_name: {:?}", err)))?;
-        waits_stmt
-            .bind_text(3, &now_value)
-            .map_err(|err| Error::new_message(format!("failed to bind now: {:?}", err)))?;
-
-        for row in waits_stmt.execute() {
-            let row = row
-                .map_err(|err| Error::new_message(format!("failed to read wait row: {:?}", err)))?;
-            let run_id = row
-                .get::<String>(0)
-                .map_err(|err| Error::new_message(format!("failed to read run_id: {:?}", err)))?;
-            let task_id = row
-                .get::<String>(1)
-                .map_err(|err| Error::new_message(format!("failed to read task_id: {:?}", err)))?;
-            let step_name = row.get::<String>(2).map_err(|err| {
-                Error::new_message(format!("failed to read step_name: {:?}", err))
-            })?;
-
-            sql::exec_with_bind_text(
-                db,
-                "update absurd_runs
-                    set state = 'pending',
-                        available_at = cast(?1 as integer),
-                        wake_event = null,
-                        event_payload = jsonb(?2),
-                        claimed_by = null,
-                        claim_expires_at = null
-                  where queue_name = ?3
-                    and run_id = ?4
-                    and state = 'sleeping'",
-                &[&now_value, &payload_text, queue_name, &run_id],
-            )?;
-
-            let changes = sql::query_row_i64(db, "select changes()", &[])?;
-            if changes == 0 {
-                continue;
-            }
-
-            sql::exec_with_bind_text(
-                db,
-                "insert into absurd_checkpoints (queue_name, task_id, checkpoint_name, state, status, owner_run_id, updated_at)
-                 values (?1, ?2, ?3, jsonb(?4), 'committed', ?5, cast(?6 as integer))
-                 on conflict (queue_name, task_id, checkpoint_name)
-                 do update set state = excluded.state,
-                               status = excluded.status,
-                               owner_run_id = excluded.owner_run_id,
-                               updated_at = excluded.updated_at",
-                &[queue_name, &task_id, &step_name, &payload_text, &run_id, &now_value],
-            )?;
-
-            sql::exec_with_bind_text(
-                db,
-                "update absurd_tasks
-                    set state = 'pending'
-                  where queue_name = ?1
-                    and task_id = ?2",
-                &[queue_name, &task_id],
-            )?;
-
-            sql::exec_with_bind_text(
-                db,
-                "delete from absurd_waits
-                  where queue_name = ?1
-                    and run_id = ?2
-                    and event_name = ?3",
-                &[queue_name, &run_id, event_name],
-            )?;
+        for target in emit_targets(db, queue_name, event_name, &extra_targets)? {
+            emit_into_queue(db, &target, event_name, &payload_text, &now_value)?;
         }
-
         Ok(())
     })();
 
@@ -456,35 +1239,122 @@ pub fn absurd_emit_event(
     }
 }
 
-static AWAIT_CREATE_SQL: &str = "CREATE TABLE x(should_suspend INTEGER, payload BLOB, queue_name TEXT hidden, task_id TEXT hidden, run_id TEXT hidden, step_name TEXT hidden, event_name TEXT hidden, timeout INTEGER hidden)";
+fn forward_args(values: &[*mut sqlite3_value]) -> Result<(&str, &str, Option<&str>)> {
+    let source_queue = api::value_text_notnull(values.first().expect("source_queue"))?;
+    let target_queue = api::value_text_notnull(values.get(1).expect("target_queue"))?;
+    let event_pattern = match values.get(2) {
+        Some(value) if !api::value_is_null(value) => Some(api::value_text_notnull(value)?),
+        _ => None,
+    };
+    validate::queue_name(source_queue)?;
+    validate::queue_name(target_queue)?;
+    if let Some(pattern) = event_pattern {
+        validate::event_name(pattern)?;
+    }
+    Ok((source_queue, target_queue, event_pattern))
+}
+
+/// SQL: absurd_forward_events(source_queue, target_queue[, event_pattern])
+/// Usage: forward emits on `source_queue` whose name matches `event_pattern` (default `*`) to `target_queue`.
+/// Section: Durable
+pub fn absurd_forward_events(
+    context: *mut sqlite3_context,
+    values: &[*mut sqlite3_value],
+) -> Result<()> {
+    let (source_queue, target_queue, event_pattern) = forward_args(values)?;
+    if source_queue == target_queue {
+        return Err(Error::new_message(
+            "source_queue and target_queue must differ",
+        ));
+    }
+
+    let db = api::context_db_handle(context);
+    for queue_name in [source_queue, target_queue] {
+        let exists = sql::query_row_i64(
+            db,
+            "select count(*) from absurd_queues where queue_name = ?1",
+            &[queue_name],
+        )?;
+        if exists == 0 {
+            return Err(Error::new_message(format!(
+                "Queue not found: {}",
+                queue_name
+            )));
+        }
+    }
+
+    sql::exec_with_bind_text(
+        db,
+        "insert or ignore into absurd_event_forwards (source_queue, target_queue, event_pattern)
+         values (?1, ?2, ?3)",
+        &[source_queue, target_queue, event_pattern.unwrap_or("*")],
+    )?;
+    api::result_int64(context, 1);
+    Ok(())
+}
+
+/// SQL: absurd_unforward_events(source_queue, target_queue[, event_pattern])
+/// Usage: remove forwarding rules between two queues (all patterns when omitted); returns rows removed.
+/// Section: Durable
+pub fn absurd_unforward_events(
+    context: *mut sqlite3_context,
+    values: &[*mut sqlite3_value],
+) -> Result<()> {
+    let (source_queue, target_queue, event_pattern) = forward_args(values)?;
+
+    let db = api::context_db_handle(context);
+    sql::exec_with_bind_text(
+        db,
+        "delete from absurd_event_forwards
+          where source_queue = ?1
+            and target_queue = ?2
+            and (nullif(?3, '') is null or event_pattern = ?3)",
+        &[source_queue, target_queue, event_pattern.unwrap_or("")],
+    )?;
+    let changes = sql::query_row_i64(db, "select changes()", &[])?;
+    api::result_int64(context, changes);
+    Ok(())
+}
+
+static AWAIT_CREATE_SQL: &str = "CREATE TABLE x(should_suspend INTEGER, payload BLOB, seq INTEGER, matched_event TEXT, timed_out INTEGER, queue_name TEXT hidden, task_id TEXT hidden, run_id TEXT hidden, step_name TEXT hidden, event_name TEXT hidden, timeout INTEGER hidden, after_seq INTEGER hidden, match_mode TEXT hidden)";
 
 enum AwaitColumns {
     ShouldSuspend,
     Payload,
+    Seq,
+    MatchedEvent,
+    TimedOut,
     QueueName,
     TaskId,
     RunId,
     StepName,
     EventName,
     Timeout,
+    AfterSeq,
+    MatchMode,
 }
 
 fn await_column(index: i32) -> Option<AwaitColumns> {
     match index {
         0 => Some(AwaitColumns::ShouldSuspend),
         1 => Some(AwaitColumns::Payload),
-        2 => Some(AwaitColumns::QueueName),
-        3 => Some(AwaitColumns::TaskId),
-        4 => Some(AwaitColumns::RunId),
-        5 => Some(AwaitColumns::StepName),
-        6 => Some(AwaitColumns::EventName),
-        7 => Some(AwaitColumns::Timeout),
+        2 => Some(AwaitColumns::Seq),
+        3 => Some(AwaitColumns::MatchedEvent),
+        4 => Some(AwaitColumns::TimedOut),
+        5 => Some(AwaitColumns::QueueName),
+        6 => Some(AwaitColumns::TaskId),
+        7 => Some(AwaitColumns::RunId),
+        8 => Some(AwaitColumns::StepName),
+        9 => Some(AwaitColumns::EventName),
+        10 => Some(AwaitColumns::Timeout),
+        11 => Some(AwaitColumns::AfterSeq),
+        12 => Some(AwaitColumns::MatchMode),
         _ => None,
     }
 }
 
-/// SQL: absurd_await_event(queue_name, task_id, run_id, step_name, event_name[, timeout_secs])
-/// Usage: await an event or timeout; returns should_suspend and payload.
+/// SQL: absurd_await_event(queue_name, task_id, run_id, step_name, event_name[, timeout_secs[, after_seq[, match_mode]]])
+/// Usage: await an event or timeout; with match_mode `glob`, event_name is a pattern such as `order:*:paid`; returns should_suspend, payload, log seq, matched event name and timed_out.
 /// Section: Durable
 #[repr(C)]
 pub struct AwaitEventTable {
@@ -572,6 +1442,22 @@ impl<'vtab> VTab<'vtab> for AwaitEventTable {
                         return Err(BestIndexError::Constraint);
                     }
                 }
+                Some(AwaitColumns::AfterSeq) => {
+                    if constraint.usable() && constraint.op() == Some(ConstraintOperator::EQ) {
+                        constraint.set_omit(true);
+                        constraint.set_argv_index(7);
+                    } else {
+                        return Err(BestIndexError::Constraint);
+                    }
+                }
+                Some(AwaitColumns::MatchMode) => {
+                    if constraint.usable() && constraint.op() == Some(ConstraintOperator::EQ) {
+                        constraint.set_omit(true);
+                        constraint.set_argv_index(8);
+                    } else {
+                        return Err(BestIndexError::Constraint);
+                    }
+                }
                 _ => {}
             }
         }
@@ -624,9 +1510,12 @@ impl VTabCursor for AwaitCursor {
         let step_name = api::value_text_notnull(values.get(3).expect("step_name"))?;
         let event_name = api::value_text_notnull(values.get(4).expect("event_name"))?;
         let timeout = parse_optional_timeout_secs(values.get(5).copied())?;
+        let after_seq = parse_optional_seq(values.get(6).copied())?;
+        let is_pattern = parse_match_mode(values.get(7).copied())?;
 
         let result = await_event_impl(
-            self.db, queue_name, task_id, run_id, step_name, event_name, timeout,
+            self.db, queue_name, task_id, run_id, step_name, event_name, is_pattern, timeout,
+            after_seq,
         )?;
         self.result = Some(result);
         self.rowid = 0;
@@ -653,9 +1542,215 @@ impl VTabCursor for AwaitCursor {
         match await_column(i) {
             Some(AwaitColumns::ShouldSuspend) => api::result_int64(context, result.should_suspend),
             Some(AwaitColumns::Payload) => match &result.payload {
-                Some(value) => sql::result_json_value(self.db, context, value)?,
+                Some(value) => sql::result_json_text(context, value)?,
+                None => api::result_null(context),
+            },
+            Some(AwaitColumns::Seq) => match result.seq {
+                Some(seq) => api::result_int64(context, seq),
+                None => api::result_null(context),
+            },
+            Some(AwaitColumns::MatchedEvent) => match &result.matched_event {
+                Some(name) => api::result_text(context, name)?,
+                None => api::result_null(context),
+            },
+            Some(AwaitColumns::TimedOut) => api::result_int64(context, result.timed_out as i64),
+            _ => api::result_null(context),
+        }
+        Ok(())
+    }
+
+    fn rowid(&self) -> Result<i64> {
+        Ok(self.rowid)
+    }
+}
+
+static AWAIT_EVENTS_CREATE_SQL: &str = "CREATE TABLE x(should_suspend INTEGER, payload BLOB, matched_event TEXT, timed_out INTEGER, queue_name TEXT hidden, task_id TEXT hidden, run_id TEXT hidden, step_name TEXT hidden, event_names TEXT hidden, mode TEXT hidden, timeout INTEGER hidden)";
+
+enum AwaitEventsColumns {
+    ShouldSuspend,
+    Payload,
+    MatchedEvent,
+    TimedOut,
+    QueueName,
+    TaskId,
+    RunId,
+    StepName,
+    EventNames,
+    Mode,
+    Timeout,
+}
+
+fn await_events_column(index: i32) -> Option<AwaitEventsColumns> {
+    match index {
+        0 => Some(AwaitEventsColumns::ShouldSuspend),
+        1 => Some(AwaitEventsColumns::Payload),
+        2 => Some(AwaitEventsColumns::MatchedEvent),
+        3 => Some(AwaitEventsColumns::TimedOut),
+        4 => Some(AwaitEventsColumns::QueueName),
+        5 => Some(AwaitEventsColumns::TaskId),
+        6 => Some(AwaitEventsColumns::RunId),
+        7 => Some(AwaitEventsColumns::StepName),
+        8 => Some(AwaitEventsColumns::EventNames),
+        9 => Some(AwaitEventsColumns::Mode),
+        10 => Some(AwaitEventsColumns::Timeout),
+        _ => None,
+    }
+}
+
+/// SQL: absurd_await_events(queue_name, task_id, run_id, step_name, event_names_json[, mode[, timeout_secs]])
+/// Usage: await any (default) or all of a JSON array of events; the payload names the fired events.
+/// Section: Durable
+#[repr(C)]
+pub struct AwaitEventsTable {
+    base: sqlite3_vtab,
+    db: *mut sqlite3,
+}
+
+impl<'vtab> VTab<'vtab> for AwaitEventsTable {
+    type Aux = ();
+    type Cursor = AwaitEventsCursor;
+
+    fn connect(
+        db: *mut sqlite3,
+        _aux: Option<&Self::Aux>,
+        _args: VTabArguments,
+    ) -> Result<(String, AwaitEventsTable)> {
+        let base: sqlite3_vtab = unsafe { std::mem::zeroed() };
+        let vtab = AwaitEventsTable { base, db };
+        Ok((AWAIT_EVENTS_CREATE_SQL.to_owned(), vtab))
+    }
+
+    fn destroy(&self) -> Result<()> {
+        Ok(())
+    }
+
+    fn best_index(&self, mut info: IndexInfo) -> core::result::Result<(), BestIndexError> {
+        let mut required = 0;
+
+        for mut constraint in info.constraints() {
+            let argv_index = match await_events_column(constraint.column_idx()) {
+                Some(AwaitEventsColumns::QueueName) => 1,
+                Some(AwaitEventsColumns::TaskId) => 2,
+                Some(AwaitEventsColumns::RunId) => 3,
+                Some(AwaitEventsColumns::StepName) => 4,
+                Some(AwaitEventsColumns::EventNames) => 5,
+                Some(AwaitEventsColumns::Mode) => 6,
+                Some(AwaitEventsColumns::Timeout) => 7,
+                _ => continue,
+            };
+            if constraint.usable() && constraint.op() == Some(ConstraintOperator::EQ) {
+                constraint.set_omit(true);
+                constraint.set_argv_index(argv_index);
+                if argv_index <= 5 {
+                    required += 1;
+                }
+            } else {
+                return Err(BestIndexError::Constraint);
+            }
+        }
+
+        if required < 5 {
+            return Err(BestIndexError::Error);
+        }
+
+        info.set_estimated_cost(1.0);
+        info.set_estimated_rows(1);
+        info.set_idxnum(1);
+        Ok(())
+    }
+
+    fn open(&mut self) -> Result<AwaitEventsCursor> {
+        Ok(AwaitEventsCursor::new(self.db))
+    }
+}
+
+#[repr(C)]
+pub struct AwaitEventsCursor {
+    base: sqlite3_vtab_cursor,
+    db: *mut sqlite3,
+    rowid: i64,
+    result: Option<AwaitResult>,
+}
+
+impl AwaitEventsCursor {
+    fn new(db: *mut sqlite3) -> AwaitEventsCursor {
+        let base: sqlite3_vtab_cursor = unsafe { std::mem::zeroed() };
+        AwaitEventsCursor {
+            base,
+            db,
+            rowid: 0,
+            result: None,
+        }
+    }
+}
+
+impl VTabCursor for AwaitEventsCursor {
+    fn filter(
+        &mut self,
+        _idx_num: c_int,
+        _idx_str: Option<&str>,
+        values: &[*mut sqlite3_value],
+    ) -> Result<()> {
+        let queue_name = api::value_text_notnull(values.first().expect("queue_name"))?;
+        let task_id = api::value_text_notnull(values.get(1).expect("task_id"))?;
+        let run_id = api::value_text_notnull(values.get(2).expect("run_id"))?;
+        let step_name = api::value_text_notnull(values.get(3).expect("step_name"))?;
+        let event_names = parse_event_names(api::value_text_notnull(
+            values.get(4).expect("event_names"),
+        )?)?;
+        let mode = match values.get(5) {
+            Some(value) if !api::value_is_null(value) => WaitMode::parse(api::value_text(value)?)?,
+            _ => WaitMode::Any,
+        };
+        let timeout = parse_optional_timeout_secs(values.get(6).copied())?;
+
+        let result = await_events_impl(
+            self.db,
+            queue_name,
+            task_id,
+            run_id,
+            step_name,
+            &event_names,
+            mode,
+            timeout,
+        )?;
+        self.result = Some(result);
+        self.rowid = 0;
+        Ok(())
+    }
+
+    fn next(&mut self) -> Result<()> {
+        self.rowid += 1;
+        Ok(())
+    }
+
+    fn eof(&self) -> bool {
+        self.result.is_none() || self.rowid > 0
+    }
+
+    fn column(&self, context: *mut sqlite3_context, i: c_int) -> Result<()> {
+        let result = match &self.result {
+            Some(result) => result,
+            None => {
+                api::result_null(context);
+                return Ok(());
+            }
+        };
+        match await_events_column(i) {
+            Some(AwaitEventsColumns::ShouldSuspend) => {
+                api::result_int64(context, result.should_suspend)
+            }
+            Some(AwaitEventsColumns::Payload) => match &result.payload {
+                Some(value) => sql::result_json_text(context, value)?,
+                None => api::result_null(context),
+            },
+            Some(AwaitEventsColumns::MatchedEvent) => match &result.matched_event {
+                Some(name) => api::result_text(context, name)?,
                 None => api::result_null(context),
             },
+            Some(AwaitEventsColumns::TimedOut) => {
+                api::result_int64(context, result.timed_out as i64)
+            }
             _ => api::result_null(context),
         }
         Ok(())
@@ -0,0 +1,443 @@
+use crate::payload;
+use crate::sql;
+use crate::validate;
+use serde_json::{Map, Value as JsonValue};
+use sqlite3ext_sys::sqlite3;
+use sqlite_loadable::prelude::*;
+use sqlite_loadable::{
+    api,
+    table::{BestIndexError, ConstraintOperator, IndexInfo, VTab, VTabArguments, VTabCursor},
+    Error, Result,
+};
+use std::collections::HashMap;
+use std::os::raw::c_int;
+use uuid::Uuid;
+
+/// Version written in the header line; imports reject newer formats.
+const EXPORT_FORMAT: i64 = 1;
+
+/// Record types and the tables they are read from, in export order. Tasks
+/// come first so imports see a task before anything that refers to it.
+const RECORD_TABLES: &[(&str, &str)] = &[
+    ("task", "absurd_tasks"),
+    ("run", "absurd_runs"),
+    ("checkpoint", "absurd_checkpoints"),
+    ("event", "absurd_events"),
+    ("wait", "absurd_waits"),
+    ("pattern_wait", "absurd_pattern_waits"),
+    ("signal_wait", "absurd_signal_waits"),
+];
+
+/// Columns holding task or run IDs, rewritten when importing with `remap`.
+const ID_COLUMNS: &[&str] = &["task_id", "run_id", "last_attempt_run", "owner_run_id"];
+
+fn table_for(record_type: &str) -> Option<&'static str> {
+    RECORD_TABLES
+        .iter()
+        .find(|(kind, _)| *kind == record_type)
+        .map(|(_, table)| *table)
+}
+
+/// Read every record of `table` on the queue as a JSON object. Payloads are
+/// decoded so the output does not depend on the queue's compression, blob
+/// offloading or encryption settings.
+fn export_table(
+    db: *mut sqlite3,
+    queue_name: &str,
+    record_type: &str,
+    table: &str,
+) -> Result<Vec<String>> {
+    let columns = sql::table_columns(db, "main", table)?;
+    if columns.is_empty() {
+        // Databases that have not applied every migration lack some tables.
+        return Ok(Vec::new());
+    }
+    let fields = columns
+        .iter()
+        .filter(|(name, _)| name != "queue_name")
+        .map(|(name, decl_type)| {
+            let ident = sql::quote_ident(name);
+            if decl_type.eq_ignore_ascii_case("blob") {
+                format!("'{}', json({})", name, ident)
+            } else {
+                format!("'{}', {}", name, ident)
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(", ");
+    let query = format!(
+        "select json_object('type', '{}', {}) from main.{} where queue_name = ?1 order by rowid",
+        record_type, fields, table
+    );
+
+    let mut stmt = sqlite_loadable::exec::Statement::prepare(db, &query)
+        .map_err(|err| Error::new_message(format!("failed to prepare export: {:?}", err)))?;
+    stmt.bind_text(1, queue_name)
+        .map_err(|err| Error::new_message(format!("failed to bind queue_name: {:?}", err)))?;
+
+    let mut lines = Vec::new();
+    for row in stmt.execute() {
+        let row =
+            row.map_err(|err| Error::new_message(format!("failed to read row: {:?}", err)))?;
+        let raw = row
+            .get::<String>(0)
+            .map_err(|err| Error::new_message(format!("failed to read record: {:?}", err)))?;
+        let mut record: Map<String, JsonValue> = serde_json::from_str(&raw)
+            .map_err(|err| Error::new_message(format!("invalid record: {:?}", err)))?;
+        for (name, value) in record.iter_mut() {
+            if value.is_null() || !payload::is_payload_column(table, name) {
+                continue;
+            }
+            let raw = value.to_string();
+            let decoded = payload::decode_json(db, &raw)?;
+            *value = serde_json::from_str(&decoded)
+                .map_err(|err| Error::new_message(format!("invalid payload: {:?}", err)))?;
+        }
+        lines.push(JsonValue::Object(record).to_string());
+    }
+    Ok(lines)
+}
+
+/// Render a queue as JSON Lines: a header followed by one line per record.
+pub fn export_queue_impl(db: *mut sqlite3, queue_name: &str) -> Result<Vec<(String, String)>> {
+    validate::queue_name(queue_name)?;
+    let header = serde_json::json!({
+        "type": "header",
+        "format": EXPORT_FORMAT,
+        "queue_name": queue_name,
+        "exported_at": sql::now_ms_from_db(db),
+    });
+    let mut lines = vec![("header".to_string(), header.to_string())];
+    for (record_type, table) in RECORD_TABLES {
+        for line in export_table(db, queue_name, record_type, table)? {
+            lines.push((record_type.to_string(), line));
+        }
+    }
+    Ok(lines)
+}
+
+/// Insert one exported record into its table on `queue_name`. Fields the
+/// target schema does not know are ignored; missing ones take defaults.
+fn import_record(
+    db: *mut sqlite3,
+    queue_name: &str,
+    table: &str,
+    columns: &[(String, String)],
+    record: &Map<String, JsonValue>,
+) -> Result<()> {
+    let mut names = vec!["queue_name".to_string()];
+    let mut placeholders = vec!["?1".to_string()];
+    let mut params = vec![queue_name.to_string()];
+
+    for (name, decl_type) in columns {
+        if name == "queue_name" {
+            continue;
+        }
+        let Some(value) = record.get(name) else {
+            continue;
+        };
+        names.push(sql::quote_ident(name));
+        if value.is_null() {
+            placeholders.push("null".to_string());
+            continue;
+        }
+        let text = match value {
+            JsonValue::String(text) if !decl_type.eq_ignore_ascii_case("blob") => text.clone(),
+            JsonValue::Bool(flag) => if *flag { "1" } else { "0" }.to_string(),
+            other => other.to_string(),
+        };
+        params.push(if payload::is_payload_column(table, name) {
+            payload::encode_column(db, queue_name, table, name, &text)?.into_owned()
+        } else {
+            text
+        });
+        if decl_type.eq_ignore_ascii_case("blob") {
+            placeholders.push(format!("jsonb(?{})", params.len()));
+        } else {
+            placeholders.push(format!("?{}", params.len()));
+        }
+    }
+
+    let insert_sql = format!(
+        "insert into {} ({}) values ({})",
+        table,
+        names.join(", "),
+        placeholders.join(", ")
+    );
+    let param_refs: Vec<&str> = params.iter().map(String::as_str).collect();
+    sql::exec_with_bind_text(db, &insert_sql, &param_refs)
+}
+
+/// Recreate the records of an export on `queue_name`, creating the queue if
+/// needed. With `remap`, task and run IDs are replaced by fresh ones
+/// (consistently across records) and idempotency keys are dropped, so the
+/// same export can be loaded next to the original. Returns records imported.
+pub fn import_queue_impl(
+    db: *mut sqlite3,
+    queue_name: &str,
+    jsonl: &str,
+    remap: bool,
+) -> Result<i64> {
+    validate::queue_name(queue_name)?;
+
+    sql::exec_with_bind_text(db, "pragma foreign_keys = off", &[])?;
+    if let Err(err) = sql::exec_with_bind_text(db, "begin immediate", &[]) {
+        let _ = sql::exec_with_bind_text(db, "pragma foreign_keys = on", &[]);
+        return Err(err);
+    }
+
+    let imported = (|| -> Result<i64> {
+        sql::exec_with_bind_text(
+            db,
+            "insert or ignore into absurd_queues (queue_name) values (?1)",
+            &[queue_name],
+        )?;
+        let mut columns_by_table: HashMap<&str, Vec<(String, String)>> = HashMap::new();
+        let mut id_map: HashMap<String, String> = HashMap::new();
+        let mut imported = 0;
+
+        for (idx, line) in jsonl.lines().enumerate() {
+            let line_no = idx + 1;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let prefix = |err: Error| {
+                Error::new_message(format!("line {}: {}", line_no, err.result_error_message()))
+            };
+            let mut record = match serde_json::from_str::<JsonValue>(line) {
+                Ok(JsonValue::Object(record)) => record,
+                Ok(_) => return Err(prefix(Error::new_message("expected a JSON object"))),
+                Err(err) => {
+                    return Err(prefix(Error::new_message(format!("invalid JSON: {}", err))))
+                }
+            };
+            let record_type = record
+                .get("type")
+                .and_then(|v| v.as_str())
+                .unwrap_or("")
+                .to_string();
+            if record_type == "header" {
+                let format = record.get("format").and_then(|v| v.as_i64()).unwrap_or(0);
+                if format > EXPORT_FORMAT {
+                    return Err(prefix(Error::new_message(format!(
+                        "unsupported export format: {}",
+                        format
+                    ))));
+                }
+                continue;
+            }
+            let table = table_for(&record_type).ok_or_else(|| {
+                prefix(Error::new_message(format!(
+                    "unknown record type: {}",
+                    record_type
+                )))
+            })?;
+
+            if remap {
+                for column in ID_COLUMNS {
+                    if let Some(JsonValue::String(old)) = record.get(*column) {
+                        let new = id_map
+                            .entry(old.clone())
+                            .or_insert_with(|| Uuid::now_v7().to_string())
+                            .clone();
+                        record.insert(column.to_string(), JsonValue::String(new));
+                    }
+                }
+                if record.contains_key("idempotency_key") {
+                    record.insert("idempotency_key".to_string(), JsonValue::Null);
+                }
+            }
+
+            if !columns_by_table.contains_key(table) {
+                columns_by_table.insert(table, sql::table_columns(db, "main", table)?);
+            }
+            import_record(db, queue_name, table, &columns_by_table[table], &record)
+                .map_err(prefix)?;
+            imported += 1;
+        }
+
+        // Keep the queue's event sequence ahead of any imported emission.
+        sql::exec_with_bind_text(
+            db,
+            "insert into absurd_event_sequences (queue_name, last_seq)
+             select ?1, max(seq) from absurd_events where queue_name = ?1 and seq is not null
+             having max(seq) is not null
+             on conflict (queue_name) do update
+               set last_seq = max(last_seq, excluded.last_seq)",
+            &[queue_name],
+        )?;
+        Ok(imported)
+    })();
+
+    let result = match imported {
+        Ok(count) => match sql::exec_with_bind_text(db, "commit", &[]) {
+            Ok(()) => Ok(count),
+            Err(err) => {
+                let _ = sql::exec_with_bind_text(db, "rollback", &[]);
+                Err(err)
+            }
+        },
+        Err(err) => {
+            let _ = sql::exec_with_bind_text(db, "rollback", &[]);
+            Err(err)
+        }
+    };
+    // The pragma is a no-op inside a transaction, so restore it only once the
+    // transaction is over, whichever way it ended.
+    let restored = sql::exec_with_bind_text(db, "pragma foreign_keys = on", &[]);
+    let count = result?;
+    restored?;
+    Ok(count)
+}
+
+/// SQL: absurd_import_queue(queue_name, jsonl[, id_mode])
+/// Usage: recreate records from absurd_export_queue output on a queue; id_mode is `keep` (default) or `remap` to assign fresh task/run IDs; returns records imported.
+/// Section: Durable
+pub fn absurd_import_queue(
+    context: *mut sqlite3_context,
+    values: &[*mut sqlite3_value],
+) -> Result<()> {
+    let queue_name = api::value_text_notnull(values.first().expect("queue_name"))?;
+    let jsonl = api::value_text_notnull(values.get(1).expect("jsonl"))?;
+    let remap = match values.get(2) {
+        Some(value) if !api::value_is_null(value) => {
+            match api::value_text_notnull(value)?
+                .trim()
+                .to_ascii_lowercase()
+                .as_str()
+            {
+                "keep" => false,
+                "remap" => true,
+                _ => return Err(Error::new_message("id_mode must be 'keep' or 'remap'")),
+            }
+        }
+        _ => false,
+    };
+
+    let db = api::context_db_handle(context);
+    let imported = import_queue_impl(db, queue_name, jsonl, remap)?;
+    api::result_int64(context, imported);
+    Ok(())
+}
+
+static EXPORT_QUEUE_SQL: &str =
+    "CREATE TABLE x(line_no INTEGER, record_type TEXT, line TEXT, queue_name TEXT hidden)";
+
+/// SQL: absurd_export_queue(queue_name)
+/// Usage: export a queue's tasks, runs, checkpoints, events and waits as JSON Lines, one row per line after a header; payloads are written decoded.
+/// Section: Durable
+#[repr(C)]
+pub struct ExportQueueTable {
+    base: sqlite3_vtab,
+    db: *mut sqlite3,
+}
+
+impl<'vtab> VTab<'vtab> for ExportQueueTable {
+    type Aux = ();
+    type Cursor = ExportQueueCursor;
+
+    fn connect(
+        db: *mut sqlite3,
+        _aux: Option<&Self::Aux>,
+        _args: VTabArguments,
+    ) -> Result<(String, ExportQueueTable)> {
+        let base: sqlite3_vtab = unsafe { std::mem::zeroed() };
+        let vtab = ExportQueueTable { base, db };
+        Ok((EXPORT_QUEUE_SQL.to_owned(), vtab))
+    }
+
+    fn destroy(&self) -> Result<()> {
+        Ok(())
+    }
+
+    fn best_index(&self, mut info: IndexInfo) -> core::result::Result<(), BestIndexError> {
+        let mut has_queue = false;
+
+        for mut constraint in info.constraints() {
+            if constraint.column_idx() == 3 {
+                if constraint.usable() && constraint.op() == Some(ConstraintOperator::EQ) {
+                    constraint.set_omit(true);
+                    constraint.set_argv_index(1);
+                    has_queue = true;
+                } else {
+                    return Err(BestIndexError::Constraint);
+                }
+            }
+        }
+
+        if !has_queue {
+            return Err(BestIndexError::Error);
+        }
+
+        info.set_estimated_cost(1000.0);
+        info.set_estimated_rows(1000);
+        info.set_idxnum(1);
+        Ok(())
+    }
+
+    fn open(&mut self) -> Result<ExportQueueCursor> {
+        Ok(ExportQueueCursor::new(self.db))
+    }
+}
+
+#[repr(C)]
+pub struct ExportQueueCursor {
+    base: sqlite3_vtab_cursor,
+    db: *mut sqlite3,
+    rowid: i64,
+    results: Vec<(String, String)>,
+}
+
+impl ExportQueueCursor {
+    fn new(db: *mut sqlite3) -> ExportQueueCursor {
+        let base: sqlite3_vtab_cursor = unsafe { std::mem::zeroed() };
+        ExportQueueCursor {
+            base,
+            db,
+            rowid: 0,
+            results: Vec::new(),
+        }
+    }
+}
+
+impl VTabCursor for ExportQueueCursor {
+    fn filter(
+        &mut self,
+        _idx_num: c_int,
+        _idx_str: Option<&str>,
+        values: &[*mut sqlite3_value],
+    ) -> Result<()> {
+        let queue_name = api::value_text_notnull(values.first().expect("queue_name"))?;
+        self.results = export_queue_impl(self.db, queue_name)?;
+        self.rowid = 0;
+        Ok(())
+    }
+
+    fn next(&mut self) -> Result<()> {
+        self.rowid += 1;
+        Ok(())
+    }
+
+    fn eof(&self) -> bool {
+        self.rowid as usize >= self.results.len()
+    }
+
+    fn column(&self, context: *mut sqlite3_context, i: c_int) -> Result<()> {
+        if self.rowid as usize >= self.results.len() {
+            api::result_null(context);
+            return Ok(());
+        }
+        let (record_type, line) = &self.results[self.rowid as usize];
+        match i {
+            0 => api::result_int64(context, self.rowid + 1),
+            1 => api::result_text(context, record_type)?,
+            2 => api::result_text(context, line)?,
+            _ => api::result_null(context),
+        }
+        Ok(())
+    }
+
+    fn rowid(&self) -> Result<i64> {
+        Ok(self.rowid)
+    }
+}
@@ -4,18 +4,28 @@ use sqlite_loadable::{
     define_scalar_function, define_table_function, Error, FunctionFlags, Result,
 };
 
+mod archive;
+mod blob;
 mod checkpoint;
 mod claim;
+mod crypto;
 mod event;
+mod export;
+mod maintenance;
 mod migrate;
 mod migrations;
+mod payload;
 mod queue;
 mod retry;
 mod run;
+mod schema;
 mod settings;
+mod signal;
 mod spawn;
 mod sql;
+mod task_type;
 mod validate;
+mod worker;
 
 /// SQL: absurd_version()
 /// Usage: return extension version and git commit.
@@ -50,7 +60,7 @@ fn absurd_create_queue(context: *mut sqlite3_context, values: &[*mut sqlite3_val
 }
 
 /// SQL: absurd_drop_queue(queue_name)
-/// Usage: drop a queue and return the number of rows removed.
+/// Usage: drop a queue and return the number of rows removed; blobs only it referenced are deleted too.
 /// Section: Durable
 fn absurd_drop_queue(context: *mut sqlite3_context, values: &[*mut sqlite3_value]) -> Result<()> {
     let queue_name =
@@ -64,6 +74,7 @@ fn absurd_drop_queue(context: *mut sqlite3_context, values: &[*mut sqlite3_value
         &[queue_name],
     )?;
     let changes = sql::query_row_i64(db, "select changes()", &[])?;
+    blob::collect_garbage(db)?;
     sqlite_loadable::api::result_int64(context, changes);
     Ok(())
 }
@@ -111,6 +122,14 @@ fn absurd_init(db: *mut sqlite3) -> Result<()> {
         run::absurd_extend_claim,
         flags,
     )?;
+    define_scalar_function(db, "absurd_release_run", 2, run::absurd_release_run, flags)?;
+    define_scalar_function(
+        db,
+        "absurd_release_worker_claims",
+        2,
+        run::absurd_release_worker_claims,
+        flags,
+    )?;
     define_scalar_function(
         db,
         "absurd_cleanup_tasks",
@@ -139,7 +158,50 @@ fn absurd_init(db: *mut sqlite3) -> Result<()> {
         run::absurd_cleanup_events,
         flags,
     )?;
+    define_scalar_function(
+        db,
+        "absurd_import_queue",
+        2,
+        export::absurd_import_queue,
+        flags,
+    )?;
+    define_scalar_function(
+        db,
+        "absurd_import_queue",
+        3,
+        export::absurd_import_queue,
+        flags,
+    )?;
+    define_scalar_function(
+        db,
+        "absurd_archive_tasks",
+        3,
+        archive::absurd_archive_tasks,
+        flags,
+    )?;
+    define_scalar_function(
+        db,
+        "absurd_archive_tasks",
+        4,
+        archive::absurd_archive_tasks,
+        flags,
+    )?;
+    define_scalar_function(
+        db,
+        "absurd_run_maintenance",
+        0,
+        maintenance::absurd_run_maintenance,
+        flags,
+    )?;
+    define_scalar_function(
+        db,
+        "absurd_run_maintenance",
+        1,
+        maintenance::absurd_run_maintenance,
+        flags,
+    )?;
     define_scalar_function(db, "absurd_cancel_task", 2, run::absurd_cancel_task, flags)?;
+    define_scalar_function(db, "absurd_retry_task", 2, run::absurd_retry_task, flags)?;
     define_scalar_function(
         db,
         "absurd_set_task_checkpoint_state",
@@ -154,8 +216,142 @@ fn absurd_init(db: *mut sqlite3) -> Result<()> {
         checkpoint::absurd_set_task_checkpoint_state,
         flags,
     )?;
+    define_scalar_function(
+        db,
+        "absurd_set_queue_setting",
+        3,
+        queue::absurd_set_queue_setting,
+        flags,
+    )?;
+    define_scalar_function(
+        db,
+        "absurd_compress_payloads",
+        1,
+        payload::absurd_compress_payloads,
+        flags,
+    )?;
+    define_scalar_function(
+        db,
+        "absurd_compress_payloads",
+        2,
+        payload::absurd_compress_payloads,
+        flags,
+    )?;
+    define_scalar_function(
+        db,
+        "absurd_decode_payload",
+        1,
+        payload::absurd_decode_payload,
+        flags,
+    )?;
+    define_scalar_function(
+        db,
+        "absurd_set_encryption_key",
+        2,
+        crypto::absurd_set_encryption_key,
+        flags,
+    )?;
+    define_scalar_function(
+        db,
+        "absurd_rotate_encryption_key",
+        1,
+        crypto::absurd_rotate_encryption_key,
+        flags,
+    )?;
+    define_scalar_function(
+        db,
+        "absurd_rotate_encryption_key",
+        2,
+        crypto::absurd_rotate_encryption_key,
+        flags,
+    )?;
+    define_scalar_function(
+        db,
+        "absurd_register_task_type",
+        3,
+        task_type::absurd_register_task_type,
+        flags,
+    )?;
+    define_scalar_function(
+        db,
+        "absurd_register_task_type",
+        4,
+        task_type::absurd_register_task_type,
+        flags,
+    )?;
+    define_scalar_function(
+        db,
+        "absurd_register_task_type",
+        5,
+        task_type::absurd_register_task_type,
+        flags,
+    )?;
+    define_scalar_function(
+        db,
+        "absurd_unregister_task_type",
+        2,
+        task_type::absurd_unregister_task_type,
+        flags,
+    )?;
     define_scalar_function(db, "absurd_emit_event", 2, event::absurd_emit_event, flags)?;
     define_scalar_function(db, "absurd_emit_event", 3, event::absurd_emit_event, flags)?;
+    define_scalar_function(db, "absurd_emit_event", 4, event::absurd_emit_event, flags)?;
+    define_scalar_function(
+        db,
+        "absurd_forward_events",
+        2,
+        event::absurd_forward_events,
+        flags,
+    )?;
+    define_scalar_function(
+        db,
+        "absurd_forward_events",
+        3,
+        event::absurd_forward_events,
+        flags,
+    )?;
+    define_scalar_function(
+        db,
+        "absurd_unforward_events",
+        2,
+        event::absurd_unforward_events,
+        flags,
+    )?;
+    define_scalar_function(
+        db,
+        "absurd_unforward_events",
+        3,
+        event::absurd_unforward_events,
+        flags,
+    )?;
+    define_scalar_function(
+        db,
+        "absurd_signal_task",
+        3,
+        signal::absurd_signal_task,
+        flags,
+    )?;
+    define_scalar_function(
+        db,
+        "absurd_signal_task",
+        4,
+        signal::absurd_signal_task,
+        flags,
+    )?;
+    define_scalar_function(
+        db,
+        "absurd_register_worker",
+        1,
+        worker::absurd_register_worker,
+        flags,
+    )?;
+    define_scalar_function(
+        db,
+        "absurd_register_worker",
+        2,
+        worker::absurd_register_worker,
+        flags,
+    )?;
     define_scalar_function(
         db,
         "absurd_set_fake_now",
@@ -178,7 +374,9 @@ fn absurd_init(db: *mut sqlite3) -> Result<()> {
         flags,
     )?;
     define_table_function::<spawn::SpawnTaskTable>(db, "absurd_spawn_task", None)?;
+    define_table_function::<spawn::SpawnTasksTable>(db, "absurd_spawn_tasks", None)?;
     define_table_function::<claim::ClaimTaskTable>(db, "absurd_claim_task", None)?;
+    define_table_function::<claim::ClaimTasksMultiTable>(db, "absurd_claim_tasks_multi", None)?;
     define_table_function::<checkpoint::CheckpointStateTable>(
         db,
         "absurd_get_task_checkpoint_state",
@@ -189,9 +387,24 @@ fn absurd_init(db: *mut sqlite3) -> Result<()> {
         "absurd_get_task_checkpoint_states",
         None,
     )?;
+    define_table_function::<checkpoint::CheckpointHistoryTable>(
+        db,
+        "absurd_get_task_checkpoint_history",
+        None,
+    )?;
+    define_table_function::<export::ExportQueueTable>(db, "absurd_export_queue", None)?;
     define_table_function::<event::AwaitEventTable>(db, "absurd_await_event", None)?;
+    define_table_function::<event::AwaitEventsTable>(db, "absurd_await_events", None)?;
+    define_table_function::<signal::AwaitSignalTable>(db, "absurd_await_signal", None)?;
+    define_table_function::<task_type::ListTaskTypesTable>(db, "absurd_list_task_types", None)?;
     define_table_function::<queue::ListQueuesTable>(db, "absurd_list_queues", None)?;
+    define_table_function::<worker::ListWorkersTable>(db, "absurd_list_workers", None)?;
     define_table_function::<migrate::MigrationRecordsTable>(db, "absurd_migration_records", None)?;
+    define_table_function::<migrate::AvailableMigrationsTable>(
+        db,
+        "absurd_available_migrations",
+        None,
+    )?;
     Ok(())
 }
 
@@ -229,12 +442,22 @@ fn parse_sqlite_version(version: &str) -> Result<(i32, i32, i32)> {
     Ok((major, minor, patch))
 }
 
-#[sqlite_entrypoint]
-pub fn sqlite3_absurd_init(db: *mut sqlite3) -> Result<()> {
-    absurd_init(db)
+/// # Safety
+///
+/// Should only be called by SQLite when the extension is loaded, e.g. through
+/// `load_extension` or `sqlite3_auto_extension`.
+#[no_mangle]
+pub unsafe extern "C" fn sqlite3_absurd_init(
+    db: *mut sqlite3,
+    pz_err_msg: *mut *mut c_char,
+    p_api: *mut sqlite3_api_routines,
+) -> c_uint {
+    sql::set_api(p_api);
+    register_entrypoint(db, pz_err_msg, p_api, absurd_init)
 }
 
 #[cfg(test)]
+#[allow(clippy::missing_transmute_annotations)]
 mod tests {
     use super::*;
     use rusqlite::{ffi::sqlite3_auto_extension, params, Connection};
@@ -296,6 +519,15 @@ mod tests {
         assert!(!row.1.trim().is_empty());
         assert!(row.2 > 0);
 
+        let available: i64 = conn
+            .query_row(
+                "select count(*) from absurd_available_migrations()",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(available, count);
+
         let applied_again: i64 = conn
             .query_row("select absurd_apply_migrations()", [], |row| row.get(0))
             .unwrap();
@@ -589,6 +821,7 @@ mod tests {
         assert_eq!(claim.get("retry_strategy").unwrap(), "BLOB");
         assert_eq!(claim.get("max_attempts").unwrap(), "INTEGER");
         assert_eq!(claim.get("headers").unwrap(), "BLOB");
+        assert_eq!(claim.get("task_filter").unwrap(), "TEXT");
         assert_eq!(claim.get("wake_event").unwrap(), "TEXT");
         assert_eq!(claim.get("event_payload").unwrap(), "BLOB");
         assert_eq!(claim.get("queue_name").unwrap(), "TEXT");
@@ -596,6 +829,13 @@ mod tests {
         assert_eq!(claim.get("claim_timeout").unwrap(), "INTEGER");
         assert_eq!(claim.get("qty").unwrap(), "INTEGER");
 
+        let multi = table_column_types(&conn, "absurd_claim_tasks_multi");
+        assert_eq!(multi.get("run_id").unwrap(), "TEXT");
+        assert_eq!(multi.get("params").unwrap(), "BLOB");
+        assert_eq!(multi.get("queue_name").unwrap(), "TEXT");
+        assert_eq!(multi.get("queues").unwrap(), "TEXT");
+        assert_eq!(multi.get("qty").unwrap(), "INTEGER");
+
         let spawn = table_column_types(&conn, "absurd_spawn_task");
         assert_eq!(spawn.get("task_id").unwrap(), "TEXT");
         assert_eq!(spawn.get("run_id").unwrap(), "TEXT");
@@ -606,6 +846,22 @@ mod tests {
         assert_eq!(spawn.get("params").unwrap(), "TEXT");
         assert_eq!(spawn.get("options").unwrap(), "TEXT");
 
+        let spawn_batch = table_column_types(&conn, "absurd_spawn_tasks");
+        assert_eq!(spawn_batch.get("item_index").unwrap(), "INTEGER");
+        assert_eq!(spawn_batch.get("task_id").unwrap(), "TEXT");
+        assert_eq!(spawn_batch.get("created").unwrap(), "INTEGER");
+        assert_eq!(spawn_batch.get("tasks").unwrap(), "TEXT");
+
+        let available = table_column_types(&conn, "absurd_available_migrations");
+        assert_eq!(available.get("id").unwrap(), "INTEGER");
+        assert_eq!(available.get("introduced_version").unwrap(), "TEXT");
+
+        let export = table_column_types(&conn, "absurd_export_queue");
+        assert_eq!(export.get("line_no").unwrap(), "INTEGER");
+        assert_eq!(export.get("record_type").unwrap(), "TEXT");
+        assert_eq!(export.get("line").unwrap(), "TEXT");
+        assert_eq!(export.get("queue_name").unwrap(), "TEXT");
+
         let await_event = table_column_types(&conn, "absurd_await_event");
         assert_eq!(await_event.get("should_suspend").unwrap(), "INTEGER");
         assert_eq!(await_event.get("payload").unwrap(), "BLOB");
@@ -615,6 +871,31 @@ mod tests {
         assert_eq!(await_event.get("step_name").unwrap(), "TEXT");
         assert_eq!(await_event.get("event_name").unwrap(), "TEXT");
         assert_eq!(await_event.get("timeout").unwrap(), "INTEGER");
+        assert_eq!(await_event.get("seq").unwrap(), "INTEGER");
+        assert_eq!(await_event.get("matched_event").unwrap(), "TEXT");
+        assert_eq!(await_event.get("after_seq").unwrap(), "INTEGER");
+
+        let await_events = table_column_types(&conn, "absurd_await_events");
+        assert_eq!(await_events.get("payload").unwrap(), "BLOB");
+        assert_eq!(await_events.get("event_names").unwrap(), "TEXT");
+        assert_eq!(await_events.get("mode").unwrap(), "TEXT");
+
+        let await_signal = table_column_types(&conn, "absurd_await_signal");
+        assert_eq!(await_signal.get("payload").unwrap(), "BLOB");
+        assert_eq!(await_signal.get("timed_out").unwrap(), "INTEGER");
+        assert_eq!(await_signal.get("signal_name").unwrap(), "TEXT");
+
+        let history = table_column_types(&conn, "absurd_get_task_checkpoint_history");
+        assert_eq!(history.get("attempt").unwrap(), "INTEGER");
+        assert_eq!(history.get("run_id").unwrap(), "TEXT");
+        assert_eq!(history.get("state").unwrap(), "BLOB");
+        assert_eq!(history.get("step_name").unwrap(), "TEXT");
+
+        let task_types = table_column_types(&conn, "absurd_list_task_types");
+        assert_eq!(task_types.get("json_schema").unwrap(), "BLOB");
+        assert_eq!(task_types.get("concurrency_limit").unwrap(), "INTEGER");
+        assert_eq!(task_types.get("pending").unwrap(), "INTEGER");
+        assert_eq!(task_types.get("queue_name").unwrap(), "TEXT");
 
         let queues = table_column_types(&conn, "absurd_list_queues");
         assert_eq!(queues.get("queue_name").unwrap(), "TEXT");
@@ -883,7 +1164,7 @@ mod tests {
     }
 
     #[test]
-    fn test_checkpoint_roundtrip() {
+    fn test_worker_registry() {
         unsafe {
             sqlite3_auto_extension(Some(std::mem::transmute(sqlite3_absurd_init as *const ())));
         }
@@ -892,59 +1173,106 @@ mod tests {
         let _: i64 = conn
             .query_row("select absurd_apply_migrations()", [], |row| row.get(0))
             .unwrap();
-
         let _: i64 = conn
             .query_row("select absurd_create_queue('alpha')", [], |r| r.get(0))
             .unwrap();
+        let _: i64 = conn
+            .query_row("select absurd_create_queue('beta')", [], |r| r.get(0))
+            .unwrap();
+        let _: i64 = conn
+            .query_row("select absurd_set_fake_now(1000)", [], |r| r.get(0))
+            .unwrap();
 
-        let row: (String, String, i64, i64) = conn
+        let run_id: String = conn
             .query_row(
-                "select task_id, run_id, attempt, created from absurd_spawn_task('alpha','demo','{}','{}')",
+                "select run_id from absurd_spawn_task('alpha','demo','{}','{}')",
                 [],
-                |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?, r.get(3)?)),
+                |r| r.get(0),
             )
             .unwrap();
-
         let _: String = conn
             .query_row(
-                "select run_id from absurd_claim_task('alpha','worker',30,1)",
+                "select run_id from absurd_claim_task('alpha','host-a:4242',30,1)",
+                [],
+                |r| r.get(0),
+            )
+            .unwrap();
+        let claimed: i64 = conn
+            .query_row(
+                "select count(*) from absurd_claim_task('beta','host-a:4242',30,1)",
                 [],
                 |r| r.get(0),
             )
             .unwrap();
+        assert_eq!(claimed, 0);
 
-        let payload = r#"{"step":1}"#;
+        let row: (String, String, i64, String, i64, i64, i64) = conn
+            .query_row(
+                "select worker_id, hostname, pid, queues, first_seen, last_seen, running_count
+                   from absurd_list_workers()",
+                [],
+                |r| {
+                    Ok((
+                        r.get(0)?,
+                        r.get(1)?,
+                        r.get(2)?,
+                        r.get(3)?,
+                        r.get(4)?,
+                        r.get(5)?,
+                        r.get(6)?,
+                    ))
+                },
+            )
+            .unwrap();
+        assert_eq!(row.0, "host-a:4242");
+        assert_eq!(row.1, "host-a");
+        assert_eq!(row.2, 4242);
+        assert_eq!(row.3, r#"["alpha","beta"]"#);
+        assert_eq!(row.4, 1000);
+        assert_eq!(row.5, 1000);
+        assert_eq!(row.6, 1);
+
+        let _: i64 = conn
+            .query_row("select absurd_set_fake_now(5000)", [], |r| r.get(0))
+            .unwrap();
         let _: Option<i64> = conn
             .query_row(
-                "select absurd_set_task_checkpoint_state('alpha', ?1, 'stepA', ?2, ?3, null)",
-                (&row.0, payload, &row.1),
+                "select absurd_extend_claim('alpha', ?1, 60)",
+                [&run_id],
                 |r| r.get(0),
             )
             .unwrap();
-
-        let checkpoint: (String, String, String) = conn
+        let (first_seen, last_seen): (i64, i64) = conn
             .query_row(
-                "select checkpoint_name, json(state), status from absurd_get_task_checkpoint_state('alpha', ?1, 'stepA', 0)",
-                [&row.0],
-                |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?)),
+                "select first_seen, last_seen from absurd_list_workers() where worker_id = 'host-a:4242'",
+                [],
+                |r| Ok((r.get(0)?, r.get(1)?)),
             )
             .unwrap();
-        assert_eq!(checkpoint.0, "stepA");
-        assert_eq!(checkpoint.1, payload);
-        assert_eq!(checkpoint.2, "committed");
+        assert_eq!(first_seen, 1000);
+        assert_eq!(last_seen, 5000);
 
-        let count: i64 = conn
+        let _: i64 = conn
             .query_row(
-                "select count(*) from absurd_get_task_checkpoint_states('alpha', ?1, ?2)",
-                (&row.0, &row.1),
+                "select absurd_register_worker('custom', '{\"hostname\":\"box\",\"pid\":7,\"version\":\"1.2\"}')",
+                [],
                 |r| r.get(0),
             )
             .unwrap();
-        assert_eq!(count, 1);
+        let (hostname, pid, version): (String, i64, String) = conn
+            .query_row(
+                "select hostname, pid, metadata ->> '$.version' from absurd_list_workers() where worker_id = 'custom'",
+                [],
+                |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?)),
+            )
+            .unwrap();
+        assert_eq!(hostname, "box");
+        assert_eq!(pid, 7);
+        assert_eq!(version, "1.2");
     }
 
     #[test]
-    fn test_await_and_emit_event() {
+    fn test_release_run_and_worker_claims() {
         unsafe {
             sqlite3_auto_extension(Some(std::mem::transmute(sqlite3_absurd_init as *const ())));
         }
@@ -953,63 +1281,3505 @@ mod tests {
         let _: i64 = conn
             .query_row("select absurd_apply_migrations()", [], |row| row.get(0))
             .unwrap();
-
         let _: i64 = conn
             .query_row("select absurd_create_queue('alpha')", [], |r| r.get(0))
             .unwrap();
 
-        let row: (String, String, i64, i64) = conn
+        for _ in 0..3 {
+            let _: String = conn
+                .query_row(
+                    "select run_id from absurd_spawn_task('alpha','demo','{}','{}')",
+                    [],
+                    |r| r.get(0),
+                )
+                .unwrap();
+        }
+
+        let first: String = conn
             .query_row(
-                "select task_id, run_id, attempt, created from absurd_spawn_task('alpha','demo','{}','{}')",
+                "select run_id from absurd_claim_task('alpha','worker-a',30,1)",
                 [],
-                |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?, r.get(3)?)),
+                |r| r.get(0),
             )
             .unwrap();
-
-        let _: String = conn
+        let claimed_b: i64 = conn
             .query_row(
-                "select run_id from absurd_claim_task('alpha','worker',30,1)",
+                "select count(*) from absurd_claim_task('alpha','worker-b',30,2)",
                 [],
                 |r| r.get(0),
             )
             .unwrap();
+        assert_eq!(claimed_b, 2);
 
-        let await_row: (i64, Option<String>) = conn
+        let released: i64 = conn
+            .query_row("select absurd_release_run('alpha', ?1)", [&first], |r| {
+                r.get(0)
+            })
+            .unwrap();
+        assert_eq!(released, 1);
+
+        let (state, claimed_by, attempt): (String, Option<String>, i64) = conn
             .query_row(
-                "select should_suspend, json(payload) from absurd_await_event('alpha', ?1, ?2, 'stepA', 'eventA', null)",
-                (&row.0, &row.1),
-                |r| Ok((r.get(0)?, r.get(1)?)),
+                "select state, claimed_by, attempt from absurd_runs where queue_name = 'alpha' and run_id = ?1",
+                [&first],
+                |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?)),
             )
             .unwrap();
-        assert_eq!(await_row.0, 1);
-        assert!(await_row.1.is_none());
+        assert_eq!(state, "pending");
+        assert!(claimed_by.is_none());
+        assert_eq!(attempt, 1);
 
-        let payload = r#"{"ok":true}"#;
-        let _: Option<i64> = conn
+        let released_again: i64 = conn
+            .query_row("select absurd_release_run('alpha', ?1)", [&first], |r| {
+                r.get(0)
+            })
+            .unwrap();
+        assert_eq!(released_again, 0);
+
+        let released_worker: i64 = conn
             .query_row(
-                "select absurd_emit_event('alpha', 'eventA', ?1)",
-                [payload],
+                "select absurd_release_worker_claims('alpha', 'worker-b')",
+                [],
                 |r| r.get(0),
             )
             .unwrap();
+        assert_eq!(released_worker, 2);
 
-        let _: String = conn
+        let counts: (i64, i64, i64) = conn
             .query_row(
-                "select run_id from absurd_claim_task('alpha','worker',30,1)",
+                "select
+                    (select count(*) from absurd_runs where state = 'pending'),
+                    (select count(*) from absurd_runs),
+                    (select count(*) from absurd_tasks where state = 'pending' and attempts = 1)",
                 [],
-                |r| r.get(0),
+                |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?)),
             )
             .unwrap();
+        assert_eq!(counts, (3, 3, 3));
 
-        let await_row2: (i64, Option<String>) = conn
+        let (reclaimed, attempt): (String, i64) = conn
             .query_row(
-                "select should_suspend, json(payload) from absurd_await_event('alpha', ?1, ?2, 'stepA', 'eventA', null)",
-                (&row.0, &row.1),
+                "select run_id, attempt from absurd_claim_task('alpha','worker-c',30,1)",
+                [],
                 |r| Ok((r.get(0)?, r.get(1)?)),
             )
             .unwrap();
-        assert_eq!(await_row2.0, 0);
-        assert_eq!(await_row2.1.as_deref(), Some(payload));
+        assert_eq!(reclaimed, first);
+        assert_eq!(attempt, 1);
+    }
+
+    #[test]
+    fn test_checkpoint_roundtrip() {
+        unsafe {
+            sqlite3_auto_extension(Some(std::mem::transmute(sqlite3_absurd_init as *const ())));
+        }
+
+        let conn = Connection::open_in_memory().unwrap();
+        let _: i64 = conn
+            .query_row("select absurd_apply_migrations()", [], |row| row.get(0))
+            .unwrap();
+
+        let _: i64 = conn
+            .query_row("select absurd_create_queue('alpha')", [], |r| r.get(0))
+            .unwrap();
+
+        let row: (String, String, i64, i64) = conn
+            .query_row(
+                "select task_id, run_id, attempt, created from absurd_spawn_task('alpha','demo','{}','{}')",
+                [],
+                |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?, r.get(3)?)),
+            )
+            .unwrap();
+
+        let _: String = conn
+            .query_row(
+                "select run_id from absurd_claim_task('alpha','worker',30,1)",
+                [],
+                |r| r.get(0),
+            )
+            .unwrap();
+
+        let payload = r#"{"step":1}"#;
+        let _: Option<i64> = conn
+            .query_row(
+                "select absurd_set_task_checkpoint_state('alpha', ?1, 'stepA', ?2, ?3, null)",
+                (&row.0, payload, &row.1),
+                |r| r.get(0),
+            )
+            .unwrap();
+
+        let checkpoint: (String, String, String) = conn
+            .query_row(
+                "select checkpoint_name, json(state), status from absurd_get_task_checkpoint_state('alpha', ?1, 'stepA', 0)",
+                [&row.0],
+                |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?)),
+            )
+            .unwrap();
+        assert_eq!(checkpoint.0, "stepA");
+        assert_eq!(checkpoint.1, payload);
+        assert_eq!(checkpoint.2, "committed");
+
+        let count: i64 = conn
+            .query_row(
+                "select count(*) from absurd_get_task_checkpoint_states('alpha', ?1, ?2)",
+                (&row.0, &row.1),
+                |r| r.get(0),
+            )
+            .unwrap();
+        assert_eq!(count, 1);
+    }
+
+    #[test]
+    fn test_checkpoint_history() {
+        unsafe {
+            sqlite3_auto_extension(Some(std::mem::transmute(sqlite3_absurd_init as *const ())));
+        }
+
+        let conn = Connection::open_in_memory().unwrap();
+        let _: i64 = conn
+            .query_row("select absurd_apply_migrations()", [], |row| row.get(0))
+            .unwrap();
+        let _: i64 = conn
+            .query_row("select absurd_create_queue('alpha')", [], |r| r.get(0))
+            .unwrap();
+
+        let err = conn
+            .query_row(
+                "select absurd_set_queue_setting('alpha', 'checkpoint_history', 'maybe')",
+                [],
+                |r| r.get::<_, i64>(0),
+            )
+            .unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("checkpoint_history must be a boolean"));
+
+        let row: (String, String) = conn
+            .query_row(
+                "select task_id, run_id from absurd_spawn_task('alpha','demo','{}','{\"max_attempts\":3}')",
+                [],
+                |r| Ok((r.get(0)?, r.get(1)?)),
+            )
+            .unwrap();
+        let set_sql = "select absurd_set_task_checkpoint_state('alpha', ?1, 'step', ?2, ?3)";
+
+        // Writes before the setting is enabled are not recorded.
+        let _: String = conn
+            .query_row(
+                "select run_id from absurd_claim_task('alpha','worker',30,1)",
+                [],
+                |r| r.get(0),
+            )
+            .unwrap();
+        let _: Option<i64> = conn
+            .query_row(set_sql, (&row.0, "{\"v\":0}", &row.1), |r| r.get(0))
+            .unwrap();
+        let _: i64 = conn
+            .query_row(
+                "select absurd_set_queue_setting('alpha', 'checkpoint_history', 'on')",
+                [],
+                |r| r.get(0),
+            )
+            .unwrap();
+        let _: Option<i64> = conn
+            .query_row(set_sql, (&row.0, "{\"v\":1}", &row.1), |r| r.get(0))
+            .unwrap();
+
+        let _: Option<i64> = conn
+            .query_row(
+                "select absurd_fail_run('alpha', ?1, '{\"name\":\"boom\"}', null)",
+                [&row.1],
+                |r| r.get(0),
+            )
+            .unwrap();
+        let retry_run: String = conn
+            .query_row(
+                "select run_id from absurd_claim_task('alpha','worker',30,1)",
+                [],
+                |r| r.get(0),
+            )
+            .unwrap();
+        let _: Option<i64> = conn
+            .query_row(set_sql, (&row.0, "{\"v\":2}", &retry_run), |r| r.get(0))
+            .unwrap();
+
+        let mut stmt = conn
+            .prepare(
+                "select checkpoint_name, attempt, run_id, json(state), status
+                   from absurd_get_task_checkpoint_history('alpha', ?1, 'step')",
+            )
+            .unwrap();
+        let history: Vec<(String, i64, String, String, String)> = stmt
+            .query_map([&row.0], |r| {
+                Ok((r.get(0)?, r.get(1)?, r.get(2)?, r.get(3)?, r.get(4)?))
+            })
+            .unwrap()
+            .collect::<std::result::Result<_, _>>()
+            .unwrap();
+        assert_eq!(
+            history,
+            vec![
+                (
+                    "step".to_string(),
+                    1,
+                    row.1.clone(),
+                    r#"{"v":1}"#.to_string(),
+                    "committed".to_string()
+                ),
+                (
+                    "step".to_string(),
+                    2,
+                    retry_run.clone(),
+                    r#"{"v":2}"#.to_string(),
+                    "committed".to_string()
+                ),
+            ]
+        );
+
+        let current: String = conn
+            .query_row(
+                "select json(state) from absurd_get_task_checkpoint_state('alpha', ?1, 'step')",
+                [&row.0],
+                |r| r.get(0),
+            )
+            .unwrap();
+        assert_eq!(current, r#"{"v":2}"#);
+    }
+
+    #[test]
+    fn test_await_and_emit_event() {
+        unsafe {
+            sqlite3_auto_extension(Some(std::mem::transmute(sqlite3_absurd_init as *const ())));
+        }
+
+        let conn = Connection::open_in_memory().unwrap();
+        let _: i64 = conn
+            .query_row("select absurd_apply_migrations()", [], |row| row.get(0))
+            .unwrap();
+
+        let _: i64 = conn
+            .query_row("select absurd_create_queue('alpha')", [], |r| r.get(0))
+            .unwrap();
+
+        let row: (String, String, i64, i64) = conn
+            .query_row(
+                "select task_id, run_id, attempt, created from absurd_spawn_task('alpha','demo','{}','{}')",
+                [],
+                |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?, r.get(3)?)),
+            )
+            .unwrap();
+
+        let _: String = conn
+            .query_row(
+                "select run_id from absurd_claim_task('alpha','worker',30,1)",
+                [],
+                |r| r.get(0),
+            )
+            .unwrap();
+
+        let await_row: (i64, Option<String>) = conn
+            .query_row(
+                "select should_suspend, json(payload) from absurd_await_event('alpha', ?1, ?2, 'stepA', 'eventA', null)",
+                (&row.0, &row.1),
+                |r| Ok((r.get(0)?, r.get(1)?)),
+            )
+            .unwrap();
+        assert_eq!(await_row.0, 1);
+        assert!(await_row.1.is_none());
+
+        let payload = r#"{"ok":true}"#;
+        let _: Option<i64> = conn
+            .query_row(
+                "select absurd_emit_event('alpha', 'eventA', ?1)",
+                [payload],
+                |r| r.get(0),
+            )
+            .unwrap();
+
+        let _: String = conn
+            .query_row(
+                "select run_id from absurd_claim_task('alpha','worker',30,1)",
+                [],
+                |r| r.get(0),
+            )
+            .unwrap();
+
+        let await_row2: (i64, Option<String>) = conn
+            .query_row(
+                "select should_suspend, json(payload) from absurd_await_event('alpha', ?1, ?2, 'stepA', 'eventA', null)",
+                (&row.0, &row.1),
+                |r| Ok((r.get(0)?, r.get(1)?)),
+            )
+            .unwrap();
+        assert_eq!(await_row2.0, 0);
+        assert_eq!(await_row2.1.as_deref(), Some(payload));
+    }
+
+    #[test]
+    fn test_event_log_after_seq() {
+        unsafe {
+            sqlite3_auto_extension(Some(std::mem::transmute(sqlite3_absurd_init as *const ())));
+        }
+
+        let conn = Connection::open_in_memory().unwrap();
+        let _: i64 = conn
+            .query_row("select absurd_apply_migrations()", [], |row| row.get(0))
+            .unwrap();
+
+        let _: i64 = conn
+            .query_row("select absurd_create_queue('alpha')", [], |r| r.get(0))
+            .unwrap();
+        let _: i64 = conn
+            .query_row(
+                "select absurd_set_queue_setting('alpha', 'event_log', 'true')",
+                [],
+                |r| r.get(0),
+            )
+            .unwrap();
+
+        for payload in [r#"{"n":1}"#, r#"{"n":2}"#] {
+            let _: Option<i64> = conn
+                .query_row(
+                    "select absurd_emit_event('alpha', 'eventA', ?1)",
+                    [payload],
+                    |r| r.get(0),
+                )
+                .unwrap();
+        }
+        let logged: i64 = conn
+            .query_row(
+                "select count(*) from absurd_event_log where queue_name = 'alpha'",
+                [],
+                |r| r.get(0),
+            )
+            .unwrap();
+        assert_eq!(logged, 2);
+
+        let row: (String, String) = conn
+            .query_row(
+                "select task_id, run_id from absurd_spawn_task('alpha','demo','{}','{}')",
+                [],
+                |r| Ok((r.get(0)?, r.get(1)?)),
+            )
+            .unwrap();
+        let _: String = conn
+            .query_row(
+                "select run_id from absurd_claim_task('alpha','worker',30,1)",
+                [],
+                |r| r.get(0),
+            )
+            .unwrap();
+
+        let await_sql = "select should_suspend, json(payload), seq
+                           from absurd_await_event('alpha', ?1, ?2, ?3, 'eventA', null, ?4)";
+        let first: (i64, Option<String>, Option<i64>) = conn
+            .query_row(await_sql, (&row.0, &row.1, "stepA", 0), |r| {
+                Ok((r.get(0)?, r.get(1)?, r.get(2)?))
+            })
+            .unwrap();
+        assert_eq!(first, (0, Some(r#"{"n":1}"#.to_string()), Some(1)));
+
+        let second: (i64, Option<String>, Option<i64>) = conn
+            .query_row(await_sql, (&row.0, &row.1, "stepB", 1), |r| {
+                Ok((r.get(0)?, r.get(1)?, r.get(2)?))
+            })
+            .unwrap();
+        assert_eq!(second, (0, Some(r#"{"n":2}"#.to_string()), Some(2)));
+
+        let third: (i64, Option<String>, Option<i64>) = conn
+            .query_row(await_sql, (&row.0, &row.1, "stepC", 2), |r| {
+                Ok((r.get(0)?, r.get(1)?, r.get(2)?))
+            })
+            .unwrap();
+        assert_eq!(third, (1, None, None));
+
+        let _: Option<i64> = conn
+            .query_row(
+                "select absurd_emit_event('alpha', 'eventA', ?1)",
+                [r#"{"n":3}"#],
+                |r| r.get(0),
+            )
+            .unwrap();
+        let _: String = conn
+            .query_row(
+                "select run_id from absurd_claim_task('alpha','worker',30,1)",
+                [],
+                |r| r.get(0),
+            )
+            .unwrap();
+
+        let resumed: (i64, Option<String>, Option<i64>) = conn
+            .query_row(await_sql, (&row.0, &row.1, "stepC", 2), |r| {
+                Ok((r.get(0)?, r.get(1)?, r.get(2)?))
+            })
+            .unwrap();
+        assert_eq!(resumed, (0, Some(r#"{"n":3}"#.to_string()), Some(3)));
+
+        let _: i64 = conn
+            .query_row("select absurd_create_queue('beta')", [], |r| r.get(0))
+            .unwrap();
+        let beta: (String, String) = conn
+            .query_row(
+                "select task_id, run_id from absurd_spawn_task('beta','demo','{}','{}')",
+                [],
+                |r| Ok((r.get(0)?, r.get(1)?)),
+            )
+            .unwrap();
+        let _: String = conn
+            .query_row(
+                "select run_id from absurd_claim_task('beta','worker',30,1)",
+                [],
+                |r| r.get(0),
+            )
+            .unwrap();
+        let err = conn
+            .query_row(
+                "select should_suspend from absurd_await_event('beta', ?1, ?2, 'stepA', 'eventA', null, 0)",
+                (&beta.0, &beta.1),
+                |r| r.get::<_, i64>(0),
+            )
+            .unwrap_err();
+        assert!(err.to_string().contains("event_log"));
+    }
+
+    #[test]
+    fn test_event_seq_survives_cleanup_and_log_disable() {
+        unsafe {
+            sqlite3_auto_extension(Some(std::mem::transmute(sqlite3_absurd_init as *const ())));
+        }
+
+        let conn = Connection::open_in_memory().unwrap();
+        let _: i64 = conn
+            .query_row("select absurd_apply_migrations()", [], |row| row.get(0))
+            .unwrap();
+        let _: i64 = conn
+            .query_row("select absurd_create_queue('alpha')", [], |r| r.get(0))
+            .unwrap();
+        let _: i64 = conn
+            .query_row(
+                "select absurd_set_queue_setting('alpha', 'event_log', 'true')",
+                [],
+                |r| r.get(0),
+            )
+            .unwrap();
+        let base = 1_700_000_000_000i64;
+        let _: i64 = conn
+            .query_row("select absurd_set_fake_now(?1)", [base], |r| r.get(0))
+            .unwrap();
+        let emit = |payload: &str| {
+            let _: Option<i64> = conn
+                .query_row(
+                    "select absurd_emit_event('alpha', 'eventA', ?1)",
+                    [payload],
+                    |r| r.get(0),
+                )
+                .unwrap();
+        };
+        emit(r#"{"n":1}"#);
+        emit(r#"{"n":2}"#);
+
+        let _: i64 = conn
+            .query_row("select absurd_set_fake_now(?1)", [base + 86_400_000], |r| {
+                r.get(0)
+            })
+            .unwrap();
+        let _: i64 = conn
+            .query_row("select absurd_cleanup_events('alpha', 60)", [], |r| {
+                r.get(0)
+            })
+            .unwrap();
+        let logged: i64 = conn
+            .query_row("select count(*) from absurd_event_log", [], |r| r.get(0))
+            .unwrap();
+        assert_eq!(logged, 0);
+
+        let await_sql = "select should_suspend, json(payload), seq
+                           from absurd_await_event('alpha', ?1, ?2, ?3, 'eventA', null, ?4)";
+        let spawn_and_claim = || -> (String, String) {
+            let row: (String, String) = conn
+                .query_row(
+                    "select task_id, run_id from absurd_spawn_task('alpha','demo','{}','{}')",
+                    [],
+                    |r| Ok((r.get(0)?, r.get(1)?)),
+                )
+                .unwrap();
+            let _: String = conn
+                .query_row(
+                    "select run_id from absurd_claim_task('alpha','worker',30,1)",
+                    [],
+                    |r| r.get(0),
+                )
+                .unwrap();
+            row
+        };
+        let reclaim = || {
+            let _: String = conn
+                .query_row(
+                    "select run_id from absurd_claim_task('alpha','worker',30,1)",
+                    [],
+                    |r| r.get(0),
+                )
+                .unwrap();
+        };
+
+        // The sequence continues after cleanup emptied the log.
+        let first = spawn_and_claim();
+        let waiting: (i64, Option<String>, Option<i64>) = conn
+            .query_row(await_sql, (&first.0, &first.1, "stepA", 2), |r| {
+                Ok((r.get(0)?, r.get(1)?, r.get(2)?))
+            })
+            .unwrap();
+        assert_eq!(waiting, (1, None, None));
+        emit(r#"{"n":3}"#);
+        reclaim();
+        let resumed: (i64, Option<String>, Option<i64>) = conn
+            .query_row(await_sql, (&first.0, &first.1, "stepA", 2), |r| {
+                Ok((r.get(0)?, r.get(1)?, r.get(2)?))
+            })
+            .unwrap();
+        assert_eq!(resumed, (0, Some(r#"{"n":3}"#.to_string()), Some(3)));
+
+        // A wait holding after_seq still wakes once the log is turned off.
+        let second = spawn_and_claim();
+        let waiting: (i64, Option<String>, Option<i64>) = conn
+            .query_row(await_sql, (&second.0, &second.1, "stepA", 3), |r| {
+                Ok((r.get(0)?, r.get(1)?, r.get(2)?))
+            })
+            .unwrap();
+        assert_eq!(waiting.0, 1);
+        let _: i64 = conn
+            .query_row(
+                "select absurd_set_queue_setting('alpha', 'event_log', null)",
+                [],
+                |r| r.get(0),
+            )
+            .unwrap();
+        emit(r#"{"n":4}"#);
+        reclaim();
+        let resumed: (i64, Option<String>, Option<i64>) = conn
+            .query_row(await_sql, (&second.0, &second.1, "stepA", 3), |r| {
+                Ok((r.get(0)?, r.get(1)?, r.get(2)?))
+            })
+            .unwrap();
+        assert_eq!(resumed, (0, Some(r#"{"n":4}"#.to_string()), None));
+    }
+
+    #[test]
+    fn test_await_event_pattern() {
+        unsafe {
+            sqlite3_auto_extension(Some(std::mem::transmute(sqlite3_absurd_init as *const ())));
+        }
+
+        let conn = Connection::open_in_memory().unwrap();
+        let _: i64 = conn
+            .query_row("select absurd_apply_migrations()", [], |row| row.get(0))
+            .unwrap();
+
+        let _: i64 = conn
+            .query_row("select absurd_create_queue('alpha')", [], |r| r.get(0))
+            .unwrap();
+
+        let await_sql = "select should_suspend, json(payload), matched_event
+                           from absurd_await_event('alpha', ?1, ?2, 'wait', ?3, null, null, 'glob')";
+        let mut tasks = Vec::new();
+        for pattern in ["order:*:paid", "payment.*"] {
+            let row: (String, String) = conn
+                .query_row(
+                    "select task_id, run_id from absurd_spawn_task('alpha','demo','{}','{}')",
+                    [],
+                    |r| Ok((r.get(0)?, r.get(1)?)),
+                )
+                .unwrap();
+            let _: String = conn
+                .query_row(
+                    "select run_id from absurd_claim_task('alpha','worker',30,1)",
+                    [],
+                    |r| r.get(0),
+                )
+                .unwrap();
+            let suspended: (i64, Option<String>, Option<String>) = conn
+                .query_row(await_sql, (&row.0, &row.1, pattern), |r| {
+                    Ok((r.get(0)?, r.get(1)?, r.get(2)?))
+                })
+                .unwrap();
+            assert_eq!(suspended, (1, None, None));
+            tasks.push((row.0, row.1, pattern));
+        }
+
+        let pattern_waits: i64 = conn
+            .query_row("select count(*) from absurd_pattern_waits", [], |r| {
+                r.get(0)
+            })
+            .unwrap();
+        assert_eq!(pattern_waits, 2);
+
+        let _: Option<i64> = conn
+            .query_row(
+                "select absurd_emit_event('alpha', 'order:42:paid', ?1)",
+                [r#"{"amount":10}"#],
+                |r| r.get(0),
+            )
+            .unwrap();
+
+        let states: Vec<String> = tasks
+            .iter()
+            .map(|task| {
+                conn.query_row(
+                    "select state from absurd_tasks where queue_name = 'alpha' and task_id = ?1",
+                    [&task.0],
+                    |r| r.get(0),
+                )
+                .unwrap()
+            })
+            .collect();
+        assert_eq!(states, vec!["pending", "sleeping"]);
+
+        let _: String = conn
+            .query_row(
+                "select run_id from absurd_claim_task('alpha','worker',30,1)",
+                [],
+                |r| r.get(0),
+            )
+            .unwrap();
+        let resumed: (i64, Option<String>, Option<String>) = conn
+            .query_row(await_sql, (&tasks[0].0, &tasks[0].1, tasks[0].2), |r| {
+                Ok((r.get(0)?, r.get(1)?, r.get(2)?))
+            })
+            .unwrap();
+        assert_eq!(
+            resumed,
+            (
+                0,
+                Some(r#"{"amount":10}"#.to_string()),
+                Some("order:42:paid".to_string())
+            )
+        );
+
+        let row: (String, String) = conn
+            .query_row(
+                "select task_id, run_id from absurd_spawn_task('alpha','demo','{}','{}')",
+                [],
+                |r| Ok((r.get(0)?, r.get(1)?)),
+            )
+            .unwrap();
+        let _: String = conn
+            .query_row(
+                "select run_id from absurd_claim_task('alpha','worker',30,1)",
+                [],
+                |r| r.get(0),
+            )
+            .unwrap();
+        let immediate: (i64, Option<String>, Option<String>) = conn
+            .query_row(await_sql, (&row.0, &row.1, "order:*:paid"), |r| {
+                Ok((r.get(0)?, r.get(1)?, r.get(2)?))
+            })
+            .unwrap();
+        assert_eq!(immediate.0, 0);
+        assert_eq!(immediate.2.as_deref(), Some("order:42:paid"));
+
+        let placeholder_events: i64 = conn
+            .query_row(
+                "select count(*) from absurd_events where event_name like '%*%'",
+                [],
+                |r| r.get(0),
+            )
+            .unwrap();
+        assert_eq!(placeholder_events, 0);
+    }
+
+    #[test]
+    fn test_await_event_exact_name_with_glob_characters() {
+        unsafe {
+            sqlite3_auto_extension(Some(std::mem::transmute(sqlite3_absurd_init as *const ())));
+        }
+
+        let conn = Connection::open_in_memory().unwrap();
+        let _: i64 = conn
+            .query_row("select absurd_apply_migrations()", [], |row| row.get(0))
+            .unwrap();
+        let _: i64 = conn
+            .query_row("select absurd_create_queue('alpha')", [], |r| r.get(0))
+            .unwrap();
+
+        let row: (String, String) = conn
+            .query_row(
+                "select task_id, run_id from absurd_spawn_task('alpha','demo','{}','{}')",
+                [],
+                |r| Ok((r.get(0)?, r.get(1)?)),
+            )
+            .unwrap();
+        let claim = || {
+            let _: String = conn
+                .query_row(
+                    "select run_id from absurd_claim_task('alpha','worker',30,1)",
+                    [],
+                    |r| r.get(0),
+                )
+                .unwrap();
+        };
+        claim();
+
+        let await_sql = "select should_suspend, json(payload), matched_event
+                           from absurd_await_event('alpha', ?1, ?2, 'wait', 'order[1]')";
+        let suspended: (i64, Option<String>, Option<String>) = conn
+            .query_row(await_sql, (&row.0, &row.1), |r| {
+                Ok((r.get(0)?, r.get(1)?, r.get(2)?))
+            })
+            .unwrap();
+        assert_eq!(suspended, (1, None, None));
+        let pattern_waits: i64 = conn
+            .query_row("select count(*) from absurd_pattern_waits", [], |r| {
+                r.get(0)
+            })
+            .unwrap();
+        assert_eq!(pattern_waits, 0);
+
+        // As a glob, `order[1]` would match `order1`; as an exact name it does not.
+        let emit = |event_name: &str| {
+            let _: Option<i64> = conn
+                .query_row(
+                    "select absurd_emit_event('alpha', ?1, '{\"ok\":true}')",
+                    [event_name],
+                    |r| r.get(0),
+                )
+                .unwrap();
+        };
+        emit("order1");
+        let state: String = conn
+            .query_row(
+                "select state from absurd_tasks where task_id = ?1",
+                [&row.0],
+                |r| r.get(0),
+            )
+            .unwrap();
+        assert_eq!(state, "sleeping");
+
+        emit("order[1]");
+        claim();
+        let resumed: (i64, Option<String>, Option<String>) = conn
+            .query_row(await_sql, (&row.0, &row.1), |r| {
+                Ok((r.get(0)?, r.get(1)?, r.get(2)?))
+            })
+            .unwrap();
+        assert_eq!(
+            resumed,
+            (
+                0,
+                Some(r#"{"ok":true}"#.to_string()),
+                Some("order[1]".to_string())
+            )
+        );
+
+        let err = conn
+            .query_row(
+                "select should_suspend from absurd_await_event('alpha', ?1, ?2, 'other', 'x', null, null, 'regex')",
+                (&row.0, &row.1),
+                |r| r.get::<_, i64>(0),
+            )
+            .unwrap_err();
+        assert!(err.to_string().contains("match_mode"));
+    }
+
+    #[test]
+    fn test_await_events_any_and_all() {
+        unsafe {
+            sqlite3_auto_extension(Some(std::mem::transmute(sqlite3_absurd_init as *const ())));
+        }
+
+        let conn = Connection::open_in_memory().unwrap();
+        let _: i64 = conn
+            .query_row("select absurd_apply_migrations()", [], |row| row.get(0))
+            .unwrap();
+
+        let _: i64 = conn
+            .query_row("select absurd_create_queue('alpha')", [], |r| r.get(0))
+            .unwrap();
+
+        let await_sql = "select should_suspend, json(payload)
+                           from absurd_await_events('alpha', ?1, ?2, 'decision', ?3, ?4)";
+        let names = r#"["approved","rejected"]"#;
+        let mut tasks = Vec::new();
+        for mode in ["any", "all"] {
+            let row: (String, String) = conn
+                .query_row(
+                    "select task_id, run_id from absurd_spawn_task('alpha','demo','{}','{}')",
+                    [],
+                    |r| Ok((r.get(0)?, r.get(1)?)),
+                )
+                .unwrap();
+            let _: String = conn
+                .query_row(
+                    "select run_id from absurd_claim_task('alpha','worker',30,1)",
+                    [],
+                    |r| r.get(0),
+                )
+                .unwrap();
+            let suspended: (i64, Option<String>) = conn
+                .query_row(await_sql, (&row.0, &row.1, names, mode), |r| {
+                    Ok((r.get(0)?, r.get(1)?))
+                })
+                .unwrap();
+            assert_eq!(suspended, (1, None));
+            tasks.push((row.0, row.1, mode));
+        }
+
+        let waits: i64 = conn
+            .query_row("select count(*) from absurd_waits", [], |r| r.get(0))
+            .unwrap();
+        assert_eq!(waits, 4);
+
+        let task_state = |task_id: &str| -> String {
+            conn.query_row(
+                "select state from absurd_tasks where queue_name = 'alpha' and task_id = ?1",
+                [task_id],
+                |r| r.get(0),
+            )
+            .unwrap()
+        };
+
+        let _: Option<i64> = conn
+            .query_row(
+                "select absurd_emit_event('alpha', 'rejected', ?1)",
+                [r#"{"by":"ops"}"#],
+                |r| r.get(0),
+            )
+            .unwrap();
+        assert_eq!(task_state(&tasks[0].0), "pending");
+        assert_eq!(task_state(&tasks[1].0), "sleeping");
+
+        let any_checkpoint: String = conn
+            .query_row(
+                "select json(state) from absurd_checkpoints where task_id = ?1 and checkpoint_name = 'decision'",
+                [&tasks[0].0],
+                |r| r.get(0),
+            )
+            .unwrap();
+        assert_eq!(
+            any_checkpoint,
+            r#"{"fired":["rejected"],"payloads":{"rejected":{"by":"ops"}}}"#
+        );
+        let remaining: i64 = conn
+            .query_row(
+                "select count(*) from absurd_waits where task_id = ?1",
+                [&tasks[0].0],
+                |r| r.get(0),
+            )
+            .unwrap();
+        assert_eq!(remaining, 0);
+
+        let _: Option<i64> = conn
+            .query_row(
+                "select absurd_emit_event('alpha', 'approved', ?1)",
+                [r#"{"by":"lead"}"#],
+                |r| r.get(0),
+            )
+            .unwrap();
+        assert_eq!(task_state(&tasks[1].0), "pending");
+
+        let _: String = conn
+            .query_row(
+                "select run_id from absurd_claim_task('alpha','worker',30,2)",
+                [],
+                |r| r.get(0),
+            )
+            .unwrap();
+        let resumed: (i64, Option<String>) = conn
+            .query_row(await_sql, (&tasks[1].0, &tasks[1].1, names, "all"), |r| {
+                Ok((r.get(0)?, r.get(1)?))
+            })
+            .unwrap();
+        assert_eq!(resumed.0, 0);
+        assert_eq!(
+            resumed.1.as_deref(),
+            Some(
+                r#"{"fired":["approved","rejected"],"payloads":{"approved":{"by":"lead"},"rejected":{"by":"ops"}}}"#
+            )
+        );
+    }
+
+    #[test]
+    fn test_await_event_timeout_checkpoint() {
+        unsafe {
+            sqlite3_auto_extension(Some(std::mem::transmute(sqlite3_absurd_init as *const ())));
+        }
+
+        let conn = Connection::open_in_memory().unwrap();
+        let _: i64 = conn
+            .query_row("select absurd_apply_migrations()", [], |row| row.get(0))
+            .unwrap();
+        let _: i64 = conn
+            .query_row("select absurd_set_fake_now(1000)", [], |r| r.get(0))
+            .unwrap();
+
+        let _: i64 = conn
+            .query_row("select absurd_create_queue('alpha')", [], |r| r.get(0))
+            .unwrap();
+
+        let row: (String, String) = conn
+            .query_row(
+                "select task_id, run_id from absurd_spawn_task('alpha','demo','{}','{}')",
+                [],
+                |r| Ok((r.get(0)?, r.get(1)?)),
+            )
+            .unwrap();
+        let _: String = conn
+            .query_row(
+                "select run_id from absurd_claim_task('alpha','worker',30,1)",
+                [],
+                |r| r.get(0),
+            )
+            .unwrap();
+
+        let await_sql = "select should_suspend, json(payload), timed_out
+                           from absurd_await_event('alpha', ?1, ?2, 'stepA', 'eventA', 10)";
+        let suspended: (i64, Option<String>, i64) = conn
+            .query_row(await_sql, (&row.0, &row.1), |r| {
+                Ok((r.get(0)?, r.get(1)?, r.get(2)?))
+            })
+            .unwrap();
+        assert_eq!(suspended, (1, None, 0));
+
+        let _: i64 = conn
+            .query_row("select absurd_set_fake_now(12000)", [], |r| r.get(0))
+            .unwrap();
+        let claimed: String = conn
+            .query_row(
+                "select run_id from absurd_claim_task('alpha','worker',30,1)",
+                [],
+                |r| r.get(0),
+            )
+            .unwrap();
+        assert_eq!(claimed, row.1);
+
+        let checkpoint: (String, String) = conn
+            .query_row(
+                "select json(state), status from absurd_checkpoints
+                  where queue_name = 'alpha' and task_id = ?1 and checkpoint_name = 'stepA'",
+                [&row.0],
+                |r| Ok((r.get(0)?, r.get(1)?)),
+            )
+            .unwrap();
+        assert_eq!(
+            checkpoint,
+            (r#"{"timedOut":true}"#.to_string(), "timed_out".to_string())
+        );
+        let replayed: (i64, Option<String>, i64) = conn
+            .query_row(await_sql, (&row.0, &row.1), |r| {
+                Ok((r.get(0)?, r.get(1)?, r.get(2)?))
+            })
+            .unwrap();
+        assert_eq!(replayed, (0, Some(r#"{"timedOut":true}"#.to_string()), 1));
+
+        // The timeout belongs to stepA alone: a new await on the same event
+        // waits instead of reporting a timeout it never had.
+        let next: (i64, Option<String>, i64) = conn
+            .query_row(
+                "select should_suspend, json(payload), timed_out
+                   from absurd_await_event('alpha', ?1, ?2, 'stepB', 'eventA', 10)",
+                (&row.0, &row.1),
+                |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?)),
+            )
+            .unwrap();
+        assert_eq!(next, (1, None, 0));
+        let step_b: i64 = conn
+            .query_row(
+                "select count(*) from absurd_checkpoints
+                  where task_id = ?1 and checkpoint_name = 'stepB'",
+                [&row.0],
+                |r| r.get(0),
+            )
+            .unwrap();
+        assert_eq!(step_b, 0);
+    }
+
+    #[test]
+    fn test_await_event_timeout_without_wait_row() {
+        unsafe {
+            sqlite3_auto_extension(Some(std::mem::transmute(sqlite3_absurd_init as *const ())));
+        }
+
+        let conn = Connection::open_in_memory().unwrap();
+        let _: i64 = conn
+            .query_row("select absurd_apply_migrations()", [], |row| row.get(0))
+            .unwrap();
+        let _: i64 = conn
+            .query_row("select absurd_set_fake_now(1000)", [], |r| r.get(0))
+            .unwrap();
+        let _: i64 = conn
+            .query_row("select absurd_create_queue('alpha')", [], |r| r.get(0))
+            .unwrap();
+
+        let row: (String, String) = conn
+            .query_row(
+                "select task_id, run_id from absurd_spawn_task('alpha','demo','{}','{}')",
+                [],
+                |r| Ok((r.get(0)?, r.get(1)?)),
+            )
+            .unwrap();
+        let _: String = conn
+            .query_row(
+                "select run_id from absurd_claim_task('alpha','worker',30,1)",
+                [],
+                |r| r.get(0),
+            )
+            .unwrap();
+
+        let await_sql = "select should_suspend, json(payload), timed_out
+                           from absurd_await_event('alpha', ?1, ?2, 'stepA', 'eventA', 10)";
+        let suspended: (i64, Option<String>, i64) = conn
+            .query_row(await_sql, (&row.0, &row.1), |r| {
+                Ok((r.get(0)?, r.get(1)?, r.get(2)?))
+            })
+            .unwrap();
+        assert_eq!(suspended, (1, None, 0));
+
+        // The run wakes on its timeout with no wait row left for the claim
+        // to record, so the await itself has to report the timeout.
+        conn.execute("delete from absurd_waits where run_id = ?1", [&row.1])
+            .unwrap();
+        let _: i64 = conn
+            .query_row("select absurd_set_fake_now(12000)", [], |r| r.get(0))
+            .unwrap();
+        let claimed: String = conn
+            .query_row(
+                "select run_id from absurd_claim_task('alpha','worker',30,1)",
+                [],
+                |r| r.get(0),
+            )
+            .unwrap();
+        assert_eq!(claimed, row.1);
+        let checkpoints: i64 = conn
+            .query_row(
+                "select count(*) from absurd_checkpoints where task_id = ?1",
+                [&row.0],
+                |r| r.get(0),
+            )
+            .unwrap();
+        assert_eq!(checkpoints, 0);
+
+        let resumed: (i64, Option<String>, i64) = conn
+            .query_row(await_sql, (&row.0, &row.1), |r| {
+                Ok((r.get(0)?, r.get(1)?, r.get(2)?))
+            })
+            .unwrap();
+        assert_eq!(resumed, (0, Some(r#"{"timedOut":true}"#.to_string()), 1));
+        let status: String = conn
+            .query_row(
+                "select status from absurd_checkpoints
+                  where task_id = ?1 and checkpoint_name = 'stepA'",
+                [&row.0],
+                |r| r.get(0),
+            )
+            .unwrap();
+        assert_eq!(status, "timed_out");
+
+        let replayed: (i64, Option<String>, i64) = conn
+            .query_row(await_sql, (&row.0, &row.1), |r| {
+                Ok((r.get(0)?, r.get(1)?, r.get(2)?))
+            })
+            .unwrap();
+        assert_eq!(replayed, resumed);
+    }
+
+    #[test]
+    fn test_signal_task_buffered_and_delivered() {
+        unsafe {
+            sqlite3_auto_extension(Some(std::mem::transmute(sqlite3_absurd_init as *const ())));
+        }
+
+        let conn = Connection::open_in_memory().unwrap();
+        let _: i64 = conn
+            .query_row("select absurd_apply_migrations()", [], |row| row.get(0))
+            .unwrap();
+        let _: i64 = conn
+            .query_row("select absurd_create_queue('alpha')", [], |r| r.get(0))
+            .unwrap();
+
+        let row: (String, String) = conn
+            .query_row(
+                "select task_id, run_id from absurd_spawn_task('alpha','demo','{}','{}')",
+                [],
+                |r| Ok((r.get(0)?, r.get(1)?)),
+            )
+            .unwrap();
+        let _: String = conn
+            .query_row(
+                "select run_id from absurd_claim_task('alpha','worker',30,1)",
+                [],
+                |r| r.get(0),
+            )
+            .unwrap();
+
+        // Signals sent before the task waits are buffered and consumed once.
+        let delivered: i64 = conn
+            .query_row(
+                "select absurd_signal_task('alpha', ?1, 'approve', '{\"by\":\"ann\"}')",
+                [&row.0],
+                |r| r.get(0),
+            )
+            .unwrap();
+        assert_eq!(delivered, 0);
+
+        let await_sql = "select should_suspend, json(payload)
+                           from absurd_await_signal('alpha', ?1, ?2, ?3, 'approve')";
+        let first: (i64, Option<String>) = conn
+            .query_row(await_sql, (&row.0, &row.1, "step1"), |r| {
+                Ok((r.get(0)?, r.get(1)?))
+            })
+            .unwrap();
+        assert_eq!(first, (0, Some(r#"{"by":"ann"}"#.to_string())));
+
+        let buffered: i64 = conn
+            .query_row("select count(*) from absurd_signals", [], |r| r.get(0))
+            .unwrap();
+        assert_eq!(buffered, 0);
+
+        let second: (i64, Option<String>) = conn
+            .query_row(await_sql, (&row.0, &row.1, "step2"), |r| {
+                Ok((r.get(0)?, r.get(1)?))
+            })
+            .unwrap();
+        assert_eq!(second, (1, None));
+
+        // Events with the same name do not reach signal waits.
+        let _: Option<i64> = conn
+            .query_row(
+                "select absurd_emit_event('alpha', 'approve', '1')",
+                [],
+                |r| r.get(0),
+            )
+            .unwrap();
+        let state: String = conn
+            .query_row(
+                "select state from absurd_runs where run_id = ?1",
+                [&row.1],
+                |r| r.get(0),
+            )
+            .unwrap();
+        assert_eq!(state, "sleeping");
+
+        let delivered: i64 = conn
+            .query_row(
+                "select absurd_signal_task('alpha', ?1, 'approve', '2')",
+                [&row.0],
+                |r| r.get(0),
+            )
+            .unwrap();
+        assert_eq!(delivered, 1);
+
+        let claimed: String = conn
+            .query_row(
+                "select run_id from absurd_claim_task('alpha','worker',30,1)",
+                [],
+                |r| r.get(0),
+            )
+            .unwrap();
+        assert_eq!(claimed, row.1);
+        let replayed: (i64, Option<String>) = conn
+            .query_row(await_sql, (&row.0, &row.1, "step2"), |r| {
+                Ok((r.get(0)?, r.get(1)?))
+            })
+            .unwrap();
+        assert_eq!(replayed, (0, Some("2".to_string())));
+
+        let _: Option<i64> = conn
+            .query_row(
+                "select absurd_complete_run('alpha', ?1, null)",
+                [&row.1],
+                |r| r.get(0),
+            )
+            .unwrap();
+        let err = conn
+            .query_row(
+                "select absurd_signal_task('alpha', ?1, 'approve')",
+                [&row.0],
+                |r| r.get::<_, i64>(0),
+            )
+            .unwrap_err();
+        assert!(err.to_string().contains("no longer active"));
+    }
+
+    #[test]
+    fn test_await_signal_timeout_without_wait_row() {
+        unsafe {
+            sqlite3_auto_extension(Some(std::mem::transmute(sqlite3_absurd_init as *const ())));
+        }
+
+        let conn = Connection::open_in_memory().unwrap();
+        let _: i64 = conn
+            .query_row("select absurd_apply_migrations()", [], |row| row.get(0))
+            .unwrap();
+        let _: i64 = conn
+            .query_row("select absurd_set_fake_now(1000)", [], |r| r.get(0))
+            .unwrap();
+        let _: i64 = conn
+            .query_row("select absurd_create_queue('alpha')", [], |r| r.get(0))
+            .unwrap();
+
+        let row: (String, String) = conn
+            .query_row(
+                "select task_id, run_id from absurd_spawn_task('alpha','demo','{}','{}')",
+                [],
+                |r| Ok((r.get(0)?, r.get(1)?)),
+            )
+            .unwrap();
+        let claim = || -> String {
+            conn.query_row(
+                "select run_id from absurd_claim_task('alpha','worker',30,1)",
+                [],
+                |r| r.get(0),
+            )
+            .unwrap()
+        };
+        claim();
+
+        let await_sql = "select should_suspend, json(payload), timed_out
+                           from absurd_await_signal('alpha', ?1, ?2, 'approval', 'approve', 10)";
+        let suspended: (i64, Option<String>, i64) = conn
+            .query_row(await_sql, (&row.0, &row.1), |r| {
+                Ok((r.get(0)?, r.get(1)?, r.get(2)?))
+            })
+            .unwrap();
+        assert_eq!(suspended, (1, None, 0));
+
+        conn.execute(
+            "delete from absurd_signal_waits where run_id = ?1",
+            [&row.1],
+        )
+        .unwrap();
+        let _: i64 = conn
+            .query_row("select absurd_set_fake_now(12000)", [], |r| r.get(0))
+            .unwrap();
+        assert_eq!(claim(), row.1);
+
+        let resumed: (i64, Option<String>, i64) = conn
+            .query_row(await_sql, (&row.0, &row.1), |r| {
+                Ok((r.get(0)?, r.get(1)?, r.get(2)?))
+            })
+            .unwrap();
+        assert_eq!(resumed, (0, Some(r#"{"timedOut":true}"#.to_string()), 1));
+        let replayed: (i64, Option<String>, i64) = conn
+            .query_row(await_sql, (&row.0, &row.1), |r| {
+                Ok((r.get(0)?, r.get(1)?, r.get(2)?))
+            })
+            .unwrap();
+        assert_eq!(replayed, resumed);
+    }
+
+    #[test]
+    fn test_await_signal_after_timeout_waits_again() {
+        unsafe {
+            sqlite3_auto_extension(Some(std::mem::transmute(sqlite3_absurd_init as *const ())));
+        }
+
+        let conn = Connection::open_in_memory().unwrap();
+        let _: i64 = conn
+            .query_row("select absurd_apply_migrations()", [], |row| row.get(0))
+            .unwrap();
+        let _: i64 = conn
+            .query_row("select absurd_set_fake_now(1000)", [], |r| r.get(0))
+            .unwrap();
+        let _: i64 = conn
+            .query_row("select absurd_create_queue('alpha')", [], |r| r.get(0))
+            .unwrap();
+
+        let row: (String, String) = conn
+            .query_row(
+                "select task_id, run_id from absurd_spawn_task('alpha','demo','{}','{}')",
+                [],
+                |r| Ok((r.get(0)?, r.get(1)?)),
+            )
+            .unwrap();
+        let claim = || -> String {
+            conn.query_row(
+                "select run_id from absurd_claim_task('alpha','worker',30,1)",
+                [],
+                |r| r.get(0),
+            )
+            .unwrap()
+        };
+        claim();
+
+        let await_sql = "select should_suspend, json(payload), timed_out
+                           from absurd_await_signal('alpha', ?1, ?2, ?3, 'approve', 10)";
+        let await_step = |step: &str| -> (i64, Option<String>, i64) {
+            conn.query_row(await_sql, (&row.0, &row.1, step), |r| {
+                Ok((r.get(0)?, r.get(1)?, r.get(2)?))
+            })
+            .unwrap()
+        };
+        assert_eq!(await_step("s1"), (1, None, 0));
+
+        let _: i64 = conn
+            .query_row("select absurd_set_fake_now(20000)", [], |r| r.get(0))
+            .unwrap();
+        assert_eq!(claim(), row.1);
+        assert_eq!(
+            await_step("s1"),
+            (0, Some(r#"{"timedOut":true}"#.to_string()), 1)
+        );
+
+        // A later wait on the same signal suspends rather than inheriting
+        // the timeout of s1.
+        assert_eq!(await_step("s2"), (1, None, 0));
+        let s2: i64 = conn
+            .query_row(
+                "select count(*) from absurd_checkpoints
+                  where task_id = ?1 and checkpoint_name = 's2'",
+                [&row.0],
+                |r| r.get(0),
+            )
+            .unwrap();
+        assert_eq!(s2, 0);
+    }
+
+    #[test]
+    fn test_emit_event_across_queues() {
+        unsafe {
+            sqlite3_auto_extension(Some(std::mem::transmute(sqlite3_absurd_init as *const ())));
+        }
+
+        let conn = Connection::open_in_memory().unwrap();
+        let _: i64 = conn
+            .query_row("select absurd_apply_migrations()", [], |row| row.get(0))
+            .unwrap();
+        for queue in ["orders", "billing"] {
+            let _: i64 = conn
+                .query_row("select absurd_create_queue(?1)", [queue], |r| r.get(0))
+                .unwrap();
+        }
+
+        let row: (String, String) = conn
+            .query_row(
+                "select task_id, run_id from absurd_spawn_task('billing','invoice','{}','{}')",
+                [],
+                |r| Ok((r.get(0)?, r.get(1)?)),
+            )
+            .unwrap();
+        let claim_sql = "select run_id from absurd_claim_task('billing','worker',30,1)";
+        let _: String = conn.query_row(claim_sql, [], |r| r.get(0)).unwrap();
+
+        let await_sql = "select should_suspend, json(payload)
+                           from absurd_await_event('billing', ?1, ?2, ?3, ?4)";
+        let suspended: (i64, Option<String>) = conn
+            .query_row(await_sql, (&row.0, &row.1, "paid", "order:paid"), |r| {
+                Ok((r.get(0)?, r.get(1)?))
+            })
+            .unwrap();
+        assert_eq!(suspended.0, 1);
+
+        let err = conn
+            .query_row(
+                "select absurd_emit_event('orders', 'order:paid', '1', '[\"missing\"]')",
+                [],
+                |r| r.get::<_, Option<i64>>(0),
+            )
+            .unwrap_err();
+        assert!(err.to_string().contains("Queue not found: missing"));
+
+        let _: Option<i64> = conn
+            .query_row(
+                "select absurd_emit_event('orders', 'order:paid', '1', '[\"billing\"]')",
+                [],
+                |r| r.get(0),
+            )
+            .unwrap();
+        let _: String = conn.query_row(claim_sql, [], |r| r.get(0)).unwrap();
+        let woken: (i64, Option<String>) = conn
+            .query_row(await_sql, (&row.0, &row.1, "paid", "order:paid"), |r| {
+                Ok((r.get(0)?, r.get(1)?))
+            })
+            .unwrap();
+        assert_eq!(woken, (0, Some("1".to_string())));
+
+        let _: i64 = conn
+            .query_row(
+                "select absurd_forward_events('orders', 'billing', 'order:*')",
+                [],
+                |r| r.get(0),
+            )
+            .unwrap();
+        let suspended: (i64, Option<String>) = conn
+            .query_row(
+                await_sql,
+                (&row.0, &row.1, "shipped", "order:shipped"),
+                |r| Ok((r.get(0)?, r.get(1)?)),
+            )
+            .unwrap();
+        assert_eq!(suspended.0, 1);
+        let _: Option<i64> = conn
+            .query_row(
+                "select absurd_emit_event('orders', 'order:shipped', '2')",
+                [],
+                |r| r.get(0),
+            )
+            .unwrap();
+        let _: String = conn.query_row(claim_sql, [], |r| r.get(0)).unwrap();
+        let woken: (i64, Option<String>) = conn
+            .query_row(
+                await_sql,
+                (&row.0, &row.1, "shipped", "order:shipped"),
+                |r| Ok((r.get(0)?, r.get(1)?)),
+            )
+            .unwrap();
+        assert_eq!(woken, (0, Some("2".to_string())));
+
+        // Dropping the target removes its forwarding rules but not the source's events.
+        let _: i64 = conn
+            .query_row("select absurd_drop_queue('billing')", [], |r| r.get(0))
+            .unwrap();
+        let forwards: i64 = conn
+            .query_row("select count(*) from absurd_event_forwards", [], |r| {
+                r.get(0)
+            })
+            .unwrap();
+        assert_eq!(forwards, 0);
+        let source_events: i64 = conn
+            .query_row(
+                "select count(*) from absurd_events where queue_name = 'orders'",
+                [],
+                |r| r.get(0),
+            )
+            .unwrap();
+        assert_eq!(source_events, 2);
+        let _: Option<i64> = conn
+            .query_row(
+                "select absurd_emit_event('orders', 'order:refunded', '3')",
+                [],
+                |r| r.get(0),
+            )
+            .unwrap();
+    }
+
+    #[test]
+    fn test_payload_offload_and_blob_gc() {
+        unsafe {
+            sqlite3_auto_extension(Some(std::mem::transmute(sqlite3_absurd_init as *const ())));
+        }
+
+        let conn = Connection::open_in_memory().unwrap();
+        let _: i64 = conn
+            .query_row("select absurd_apply_migrations()", [], |row| row.get(0))
+            .unwrap();
+        let _: i64 = conn
+            .query_row("select absurd_create_queue('alpha')", [], |r| r.get(0))
+            .unwrap();
+        let _: i64 = conn
+            .query_row(
+                "select absurd_set_queue_setting('alpha', 'offload_threshold_bytes', '64')",
+                [],
+                |r| r.get(0),
+            )
+            .unwrap();
+
+        let big_params = format!(r#"{{"data":"{}"}}"#, "x".repeat(200));
+        let spawn_sql = "select task_id, run_id from absurd_spawn_task('alpha','demo',?1,'{}')";
+        let first: (String, String) = conn
+            .query_row(spawn_sql, [&big_params], |r| Ok((r.get(0)?, r.get(1)?)))
+            .unwrap();
+        let _: (String, String) = conn
+            .query_row(spawn_sql, [&big_params], |r| Ok((r.get(0)?, r.get(1)?)))
+            .unwrap();
+        let small: (String, String) = conn
+            .query_row(spawn_sql, [r#"{"small":true}"#], |r| {
+                Ok((r.get(0)?, r.get(1)?))
+            })
+            .unwrap();
+
+        let stored: String = conn
+            .query_row(
+                "select json(params) from absurd_tasks where task_id = ?1",
+                [&first.0],
+                |r| r.get(0),
+            )
+            .unwrap();
+        assert!(stored.starts_with(r#"{"$absurd_blob":""#));
+        let stored_small: String = conn
+            .query_row(
+                "select json(params) from absurd_tasks where task_id = ?1",
+                [&small.0],
+                |r| r.get(0),
+            )
+            .unwrap();
+        assert_eq!(stored_small, r#"{"small":true}"#);
+        let blobs: i64 = conn
+            .query_row("select count(*) from absurd_blobs", [], |r| r.get(0))
+            .unwrap();
+        assert_eq!(blobs, 1);
+
+        let claimed: (String, String) = conn
+            .query_row(
+                "select run_id, params from absurd_claim_task('alpha','worker',30,1)",
+                [],
+                |r| Ok((r.get(0)?, r.get(1)?)),
+            )
+            .unwrap();
+        assert_eq!(claimed, (first.1.clone(), big_params.clone()));
+
+        let big_state = format!(r#"{{"state":"{}"}}"#, "y".repeat(200));
+        let _: Option<i64> = conn
+            .query_row(
+                "select absurd_set_task_checkpoint_state('alpha', ?1, 'step', ?2, ?3)",
+                (&first.0, &big_state, &first.1),
+                |r| r.get(0),
+            )
+            .unwrap();
+        let state: String = conn
+            .query_row(
+                "select state from absurd_get_task_checkpoint_state('alpha', ?1, 'step')",
+                [&first.0],
+                |r| r.get(0),
+            )
+            .unwrap();
+        assert_eq!(state, big_state);
+
+        let _: Option<i64> = conn
+            .query_row(
+                "select absurd_complete_run('alpha', ?1, ?2)",
+                (&first.1, &big_state),
+                |r| r.get(0),
+            )
+            .unwrap();
+        let blobs: i64 = conn
+            .query_row("select count(*) from absurd_blobs", [], |r| r.get(0))
+            .unwrap();
+        assert_eq!(blobs, 2);
+
+        // The second task still references the params blob after the first is cleaned up.
+        conn.execute(
+            "update absurd_runs set completed_at = 1 where run_id = ?1",
+            [&first.1],
+        )
+        .unwrap();
+        let deleted: i64 = conn
+            .query_row("select absurd_cleanup_tasks('alpha', 1, 100)", [], |r| {
+                r.get(0)
+            })
+            .unwrap();
+        assert_eq!(deleted, 1);
+        let blobs: i64 = conn
+            .query_row("select count(*) from absurd_blobs", [], |r| r.get(0))
+            .unwrap();
+        assert_eq!(blobs, 1);
+    }
+
+    #[test]
+    fn test_blob_ref_counts_and_drop_queue() {
+        unsafe {
+            sqlite3_auto_extension(Some(std::mem::transmute(sqlite3_absurd_init as *const ())));
+        }
+
+        let conn = Connection::open_in_memory().unwrap();
+        let _: i64 = conn
+            .query_row("select absurd_apply_migrations()", [], |row| row.get(0))
+            .unwrap();
+        for queue in ["alpha", "beta"] {
+            let _: i64 = conn
+                .query_row("select absurd_create_queue(?1)", [queue], |r| r.get(0))
+                .unwrap();
+            let _: i64 = conn
+                .query_row(
+                    "select absurd_set_queue_setting(?1, 'offload_threshold_bytes', '64')",
+                    [queue],
+                    |r| r.get(0),
+                )
+                .unwrap();
+        }
+
+        let big_params = format!(r#"{{"data":"{}"}}"#, "x".repeat(200));
+        let only_alpha = format!(r#"{{"data":"{}"}}"#, "z".repeat(200));
+        for (queue, params) in [
+            ("alpha", &big_params),
+            ("alpha", &only_alpha),
+            ("beta", &big_params),
+        ] {
+            let _: String = conn
+                .query_row(
+                    "select task_id from absurd_spawn_task(?1,'demo',?2,'{}')",
+                    (queue, params),
+                    |r| r.get(0),
+                )
+                .unwrap();
+        }
+        let ref_counts: Vec<i64> = conn
+            .prepare("select ref_count from absurd_blobs order by ref_count")
+            .unwrap()
+            .query_map([], |r| r.get(0))
+            .unwrap()
+            .collect::<rusqlite::Result<_>>()
+            .unwrap();
+        assert_eq!(ref_counts, vec![1, 2]);
+
+        let _: i64 = conn
+            .query_row("select absurd_drop_queue('alpha')", [], |r| r.get(0))
+            .unwrap();
+        let remaining: (i64, i64) = conn
+            .query_row(
+                "select count(*), sum(ref_count) from absurd_blobs",
+                [],
+                |r| Ok((r.get(0)?, r.get(1)?)),
+            )
+            .unwrap();
+        assert_eq!(remaining, (1, 1));
+
+        let params: String = conn
+            .query_row(
+                "select params from absurd_claim_task('beta','worker',30,1)",
+                [],
+                |r| r.get(0),
+            )
+            .unwrap();
+        assert_eq!(params, big_params);
+    }
+
+    #[test]
+    fn test_payloads_shaped_like_envelopes_round_trip() {
+        unsafe {
+            sqlite3_auto_extension(Some(std::mem::transmute(sqlite3_absurd_init as *const ())));
+        }
+
+        let conn = Connection::open_in_memory().unwrap();
+        let _: i64 = conn
+            .query_row("select absurd_apply_migrations()", [], |row| row.get(0))
+            .unwrap();
+        let _: i64 = conn
+            .query_row("select absurd_create_queue('alpha')", [], |r| r.get(0))
+            .unwrap();
+        let _: i64 = conn
+            .query_row(
+                "select absurd_set_queue_setting('alpha', 'offload_threshold_bytes', '64')",
+                [],
+                |r| r.get(0),
+            )
+            .unwrap();
+
+        let lookalikes = [
+            r#"{"$absurd_blob":"deadbeef"}"#.to_string(),
+            r#"{"$absurd_compressed":"zstd","data":"AAAA"}"#.to_string(),
+            r#"{"$absurd_escaped":1}"#.to_string(),
+            format!(r#"{{"$absurd_blob":"{}"}}"#, "a".repeat(100)),
+        ];
+        for params in &lookalikes {
+            let _: String = conn
+                .query_row(
+                    "select task_id from absurd_spawn_task('alpha','demo',?1,'{}')",
+                    [params],
+                    |r| r.get(0),
+                )
+                .unwrap();
+            let claimed: (String, String, String) = conn
+                .query_row(
+                    "select task_id, run_id, params from absurd_claim_task('alpha','worker',30,1)",
+                    [],
+                    |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?)),
+                )
+                .unwrap();
+            assert_eq!(&claimed.2, params);
+
+            let _: Option<i64> = conn
+                .query_row(
+                    "select absurd_set_task_checkpoint_state('alpha', ?1, 'step', ?2, ?3)",
+                    (&claimed.0, params, &claimed.1),
+                    |r| r.get(0),
+                )
+                .unwrap();
+            let state: String = conn
+                .query_row(
+                    "select state from absurd_get_task_checkpoint_state('alpha', ?1, 'step')",
+                    [&claimed.0],
+                    |r| r.get(0),
+                )
+                .unwrap();
+            assert_eq!(&state, params);
+        }
+
+        let blobs: i64 = conn
+            .query_row("select count(*) from absurd_blobs", [], |r| r.get(0))
+            .unwrap();
+        assert_eq!(blobs, 1);
+    }
+
+    #[test]
+    fn test_payload_compression() {
+        unsafe {
+            sqlite3_auto_extension(Some(std::mem::transmute(sqlite3_absurd_init as *const ())));
+        }
+
+        let conn = Connection::open_in_memory().unwrap();
+        let _: i64 = conn
+            .query_row("select absurd_apply_migrations()", [], |row| row.get(0))
+            .unwrap();
+        let _: i64 = conn
+            .query_row("select absurd_create_queue('alpha')", [], |r| r.get(0))
+            .unwrap();
+
+        let big_params = format!(
+            r#"{{"items":[{}]}}"#,
+            vec![r#"{"sku":"a-1"}"#; 40].join(",")
+        );
+        let spawn_sql = "select task_id from absurd_spawn_task('alpha','demo',?1,'{}')";
+        let stored_sql = "select json(params) from absurd_tasks where task_id = ?1";
+
+        let err = conn
+            .query_row("select absurd_compress_payloads('alpha')", [], |r| {
+                r.get::<_, i64>(0)
+            })
+            .unwrap_err();
+        assert!(err.to_string().contains("compression is not enabled"));
+
+        // Rows written before compression is enabled are migrated in batches.
+        let mut existing = Vec::new();
+        for _ in 0..3 {
+            let task_id: String = conn
+                .query_row(spawn_sql, [&big_params], |r| r.get(0))
+                .unwrap();
+            existing.push(task_id);
+        }
+        let _: i64 = conn
+            .query_row(
+                "select absurd_set_queue_setting('alpha', 'compression', 'zstd')",
+                [],
+                |r| r.get(0),
+            )
+            .unwrap();
+        let batches: Vec<i64> = (0..3)
+            .map(|_| {
+                conn.query_row("select absurd_compress_payloads('alpha', 2)", [], |r| {
+                    r.get(0)
+                })
+                .unwrap()
+            })
+            .collect();
+        assert_eq!(batches, vec![2, 1, 0]);
+        let stored: String = conn
+            .query_row(stored_sql, [&existing[0]], |r| r.get(0))
+            .unwrap();
+        assert!(stored.starts_with(r#"{"$absurd_compressed":"zstd""#));
+        assert!(stored.len() < big_params.len());
+
+        let _: i64 = conn
+            .query_row(
+                "select absurd_set_queue_setting('alpha', 'compression', 'deflate')",
+                [],
+                |r| r.get(0),
+            )
+            .unwrap();
+        let small_task: String = conn
+            .query_row(spawn_sql, [r#"{"small":true}"#], |r| r.get(0))
+            .unwrap();
+        let stored_small: String = conn
+            .query_row(stored_sql, [&small_task], |r| r.get(0))
+            .unwrap();
+        assert_eq!(stored_small, r#"{"small":true}"#);
+
+        let (run_id, params): (String, String) = conn
+            .query_row(
+                "select run_id, params from absurd_claim_task('alpha','worker',30,1)",
+                [],
+                |r| Ok((r.get(0)?, r.get(1)?)),
+            )
+            .unwrap();
+        assert_eq!(params, big_params);
+
+        let _: Option<i64> = conn
+            .query_row(
+                "select absurd_set_task_checkpoint_state('alpha', ?1, 'step', ?2, ?3)",
+                (&existing[0], &big_params, &run_id),
+                |r| r.get(0),
+            )
+            .unwrap();
+        let raw_state: String = conn
+            .query_row(
+                "select json(state) from absurd_checkpoints where task_id = ?1",
+                [&existing[0]],
+                |r| r.get(0),
+            )
+            .unwrap();
+        assert!(raw_state.starts_with(r#"{"$absurd_compressed":"deflate""#));
+        let state: String = conn
+            .query_row(
+                "select state from absurd_get_task_checkpoint_state('alpha', ?1, 'step')",
+                [&existing[0]],
+                |r| r.get(0),
+            )
+            .unwrap();
+        assert_eq!(state, big_params);
+    }
+
+    #[test]
+    fn test_payload_encryption() {
+        unsafe {
+            sqlite3_auto_extension(Some(std::mem::transmute(sqlite3_absurd_init as *const ())));
+        }
+
+        let conn = Connection::open_in_memory().unwrap();
+        let _: i64 = conn
+            .query_row("select absurd_apply_migrations()", [], |row| row.get(0))
+            .unwrap();
+        let _: i64 = conn
+            .query_row("select absurd_create_queue('alpha')", [], |r| r.get(0))
+            .unwrap();
+
+        let spawn_sql = "select task_id from absurd_spawn_task('alpha','demo',?1,'{}')";
+        let stored_sql = "select json(params) from absurd_tasks where task_id = ?1";
+        let key_v1 = "00".repeat(32);
+        let key_v2 = "AQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQE=";
+
+        let plain_task: String = conn
+            .query_row(spawn_sql, [r#"{"secret":0}"#], |r| r.get(0))
+            .unwrap();
+        let _: i64 = conn
+            .query_row(
+                "select absurd_set_queue_setting('alpha', 'encryption', 'on')",
+                [],
+                |r| r.get(0),
+            )
+            .unwrap();
+        let err = conn
+            .query_row(spawn_sql, [r#"{"secret":1}"#], |r| r.get::<_, String>(0))
+            .unwrap_err();
+        assert!(err.to_string().contains("no encryption key set"));
+
+        let _: i64 = conn
+            .query_row(
+                "select absurd_set_encryption_key('v1', ?1)",
+                [&key_v1],
+                |r| r.get(0),
+            )
+            .unwrap();
+        let task_id: String = conn
+            .query_row(spawn_sql, [r#"{"secret":1}"#], |r| r.get(0))
+            .unwrap();
+        let stored: String = conn
+            .query_row(stored_sql, [&task_id], |r| r.get(0))
+            .unwrap();
+        assert!(stored.starts_with(r#"{"$absurd_encrypted":"v1""#));
+        assert!(!stored.contains("secret"));
+
+        let (run_id, params): (String, String) = conn
+            .query_row(
+                "select run_id, params from absurd_claim_task('alpha','worker',30,1)",
+                [],
+                |r| Ok((r.get(0)?, r.get(1)?)),
+            )
+            .unwrap();
+        assert_eq!(params, r#"{"secret":0}"#);
+        let _: Option<i64> = conn
+            .query_row(
+                "select absurd_set_task_checkpoint_state('alpha', ?1, 'step', '{\"n\":1}', ?2)",
+                (&plain_task, &run_id),
+                |r| r.get(0),
+            )
+            .unwrap();
+
+        // A new key becomes active; rotation rewrites plaintext and v1 rows.
+        let _: i64 = conn
+            .query_row(
+                "select absurd_set_encryption_key('v2', ?1)",
+                [key_v2],
+                |r| r.get(0),
+            )
+            .unwrap();
+        let batches: Vec<i64> = (0..3)
+            .map(|_| {
+                conn.query_row("select absurd_rotate_encryption_key('alpha', 2)", [], |r| {
+                    r.get(0)
+                })
+                .unwrap()
+            })
+            .collect();
+        assert_eq!(batches, vec![2, 1, 0]);
+        let _: i64 = conn
+            .query_row("select absurd_set_encryption_key('v1', null)", [], |r| {
+                r.get(0)
+            })
+            .unwrap();
+
+        let stored: String = conn
+            .query_row(stored_sql, [&plain_task], |r| r.get(0))
+            .unwrap();
+        assert!(stored.starts_with(r#"{"$absurd_encrypted":"v2""#));
+        let params: String = conn
+            .query_row(
+                "select params from absurd_claim_task('alpha','worker',30,1)",
+                [],
+                |r| r.get(0),
+            )
+            .unwrap();
+        assert_eq!(params, r#"{"secret":1}"#);
+        let state: String = conn
+            .query_row(
+                "select state from absurd_get_task_checkpoint_state('alpha', ?1, 'step')",
+                [&plain_task],
+                |r| r.get(0),
+            )
+            .unwrap();
+        assert_eq!(state, r#"{"n":1}"#);
+    }
+
+    #[test]
+    fn test_encrypted_headers() {
+        unsafe {
+            sqlite3_auto_extension(Some(std::mem::transmute(sqlite3_absurd_init as *const ())));
+        }
+
+        let conn = Connection::open_in_memory().unwrap();
+        let _: i64 = conn
+            .query_row("select absurd_apply_migrations()", [], |row| row.get(0))
+            .unwrap();
+        let _: i64 = conn
+            .query_row("select absurd_create_queue('alpha')", [], |r| r.get(0))
+            .unwrap();
+        let _: i64 = conn
+            .query_row(
+                "select absurd_set_queue_setting('alpha', 'encryption', 'on')",
+                [],
+                |r| r.get(0),
+            )
+            .unwrap();
+        let _: i64 = conn
+            .query_row(
+                "select absurd_set_encryption_key('v1', ?1)",
+                ["00".repeat(32)],
+                |r| r.get(0),
+            )
+            .unwrap();
+
+        let task_id: String = conn
+            .query_row(
+                r#"select task_id from absurd_spawn_task('alpha','demo','{}','{"headers":{"tenant":"acme"}}')"#,
+                [],
+                |r| r.get(0),
+            )
+            .unwrap();
+        let stored_sql = "select json(headers) from absurd_tasks where task_id = ?1";
+        let stored: String = conn
+            .query_row(stored_sql, [&task_id], |r| r.get(0))
+            .unwrap();
+        assert!(stored.starts_with(r#"{"$absurd_encrypted":"v1""#));
+        assert!(!stored.contains("acme"));
+
+        let err = conn
+            .query_row(
+                r#"select run_id from absurd_claim_task('alpha','worker',30,1,'{"headers":{"tenant":"acme"}}')"#,
+                [],
+                |r| r.get::<_, String>(0),
+            )
+            .unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("headers are not supported on encrypted queues"));
+
+        let _: i64 = conn
+            .query_row(
+                "select absurd_set_encryption_key('v2', ?1)",
+                ["11".repeat(32)],
+                |r| r.get(0),
+            )
+            .unwrap();
+        let rotated: i64 = conn
+            .query_row("select absurd_rotate_encryption_key('alpha')", [], |r| {
+                r.get(0)
+            })
+            .unwrap();
+        assert_eq!(rotated, 2);
+        let stored: String = conn
+            .query_row(stored_sql, [&task_id], |r| r.get(0))
+            .unwrap();
+        assert!(stored.starts_with(r#"{"$absurd_encrypted":"v2""#));
+
+        let headers: String = conn
+            .query_row(
+                "select headers from absurd_claim_task('alpha','worker',30,1)",
+                [],
+                |r| r.get(0),
+            )
+            .unwrap();
+        assert_eq!(headers, r#"{"tenant":"acme"}"#);
+    }
+
+    #[test]
+    fn test_decode_payload() {
+        unsafe {
+            sqlite3_auto_extension(Some(std::mem::transmute(sqlite3_absurd_init as *const ())));
+        }
+
+        let conn = Connection::open_in_memory().unwrap();
+        let _: i64 = conn
+            .query_row("select absurd_apply_migrations()", [], |row| row.get(0))
+            .unwrap();
+        for (queue, setting, value) in [
+            ("plain", "compression", None),
+            ("packed", "compression", Some("zstd")),
+            ("sealed", "encryption", Some("on")),
+        ] {
+            let _: i64 = conn
+                .query_row("select absurd_create_queue(?1)", [queue], |r| r.get(0))
+                .unwrap();
+            if value.is_some() {
+                let _: i64 = conn
+                    .query_row(
+                        "select absurd_set_queue_setting(?1, ?2, ?3)",
+                        (queue, setting, value),
+                        |r| r.get(0),
+                    )
+                    .unwrap();
+            }
+        }
+        let _: i64 = conn
+            .query_row(
+                "select absurd_set_encryption_key('v1', ?1)",
+                ["00".repeat(32)],
+                |r| r.get(0),
+            )
+            .unwrap();
+
+        let params = format!(r#"{{"note":"{}"}}"#, "x".repeat(256));
+        let spawn = |queue: &str, params: &str| -> String {
+            conn.query_row(
+                "select task_id from absurd_spawn_task(?1, 'demo', ?2, '{}')",
+                [queue, params],
+                |r| r.get(0),
+            )
+            .unwrap()
+        };
+        let stored = |task_id: &str| -> (String, Option<String>) {
+            conn.query_row(
+                "select json(params), absurd_decode_payload(json(params))
+                   from absurd_tasks where task_id = ?1",
+                [task_id],
+                |r| Ok((r.get(0)?, r.get(1)?)),
+            )
+            .unwrap()
+        };
+
+        let packed = spawn("packed", &params);
+        let (raw, decoded) = stored(&packed);
+        assert!(raw.starts_with(r#"{"$absurd_compressed":"zstd""#));
+        assert_eq!(decoded.as_deref(), Some(params.as_str()));
+
+        let escaped = spawn("plain", r#"{"$absurd_blob":"not ours"}"#);
+        let (raw, decoded) = stored(&escaped);
+        assert!(raw.starts_with(r#"{"$absurd_escaped":"#));
+        assert_eq!(decoded.as_deref(), Some(r#"{"$absurd_blob":"not ours"}"#));
+
+        let sealed = spawn("sealed", &params);
+        assert_eq!(stored(&sealed).1.as_deref(), Some(params.as_str()));
+        let _: i64 = conn
+            .query_row("select absurd_set_encryption_key('v1', null)", [], |r| {
+                r.get(0)
+            })
+            .unwrap();
+        assert_eq!(
+            stored(&sealed).1.as_deref(),
+            Some(r#""[encrypted with key v1]""#)
+        );
+
+        let null: Option<String> = conn
+            .query_row("select absurd_decode_payload(null)", [], |r| r.get(0))
+            .unwrap();
+        assert_eq!(null, None);
+        let err = conn
+            .query_row(
+                "select absurd_decode_payload(params) from absurd_tasks where task_id = ?1",
+                [&escaped],
+                |r| r.get::<_, String>(0),
+            )
+            .unwrap_err();
+        assert!(err.to_string().contains("pass json(column)"));
+    }
+
+    #[test]
+    fn test_encryption_keys_are_per_connection() {
+        unsafe {
+            sqlite3_auto_extension(Some(std::mem::transmute(sqlite3_absurd_init as *const ())));
+        }
+
+        let db_path = std::env::temp_dir().join(format!("absurd-keys-{}.db", Uuid::new_v4()));
+        let conn = Connection::open(&db_path).unwrap();
+        let _: i64 = conn
+            .query_row("select absurd_apply_migrations()", [], |row| row.get(0))
+            .unwrap();
+        let _: i64 = conn
+            .query_row("select absurd_create_queue('alpha')", [], |r| r.get(0))
+            .unwrap();
+        let _: i64 = conn
+            .query_row(
+                "select absurd_set_queue_setting('alpha', 'encryption', 'on')",
+                [],
+                |r| r.get(0),
+            )
+            .unwrap();
+        let _: i64 = conn
+            .query_row(
+                "select absurd_set_encryption_key('v1', ?1)",
+                ["00".repeat(32)],
+                |r| r.get(0),
+            )
+            .unwrap();
+        let _: String = conn
+            .query_row(
+                "select task_id from absurd_spawn_task('alpha','demo','{\"secret\":1}','{}')",
+                [],
+                |r| r.get(0),
+            )
+            .unwrap();
+        drop(conn);
+
+        // Keys go away with the connection that registered them.
+        let conn = Connection::open(&db_path).unwrap();
+        let err = conn
+            .query_row(
+                "select params from absurd_claim_task('alpha','worker',30,1)",
+                [],
+                |r| r.get::<_, String>(0),
+            )
+            .unwrap_err();
+        assert!(err.to_string().contains("encryption key not available: v1"));
+
+        drop(conn);
+        let _ = std::fs::remove_file(db_path);
+    }
+
+    #[test]
+    fn test_task_type_schema_and_defaults() {
+        unsafe {
+            sqlite3_auto_extension(Some(std::mem::transmute(sqlite3_absurd_init as *const ())));
+        }
+
+        let conn = Connection::open_in_memory().unwrap();
+        let _: i64 = conn
+            .query_row("select absurd_apply_migrations()", [], |row| row.get(0))
+            .unwrap();
+        let _: i64 = conn
+            .query_row("select absurd_create_queue('alpha')", [], |r| r.get(0))
+            .unwrap();
+
+        let err = conn
+            .query_row(
+                "select absurd_register_task_type('alpha', 'order', '{\"$ref\":\"#/x\"}')",
+                [],
+                |r| r.get::<_, i64>(0),
+            )
+            .unwrap_err();
+        assert!(err.to_string().contains("unsupported schema keyword: $ref"));
+
+        let schema = r#"{
+            "type": "object",
+            "required": ["order_id", "items"],
+            "properties": {
+                "order_id": {"type": "string", "pattern": "^ord-"},
+                "items": {
+                    "type": "array",
+                    "minItems": 1,
+                    "items": {
+                        "type": "object",
+                        "properties": {"sku": {"type": "string"}, "qty": {"type": "integer", "minimum": 1}},
+                        "additionalProperties": false
+                    }
+                }
+            }
+        }"#;
+        let _: i64 = conn
+            .query_row(
+                "select absurd_register_task_type('alpha', 'order', ?1, ?2)",
+                (
+                    schema,
+                    r#"{"max_attempts":7,"retry_strategy":{"kind":"fixed","base_seconds":2}}"#,
+                ),
+                |r| r.get(0),
+            )
+            .unwrap();
+
+        let spawn = |params: &str, options: &str| {
+            conn.query_row(
+                "select task_id from absurd_spawn_task('alpha', 'order', ?1, ?2)",
+                (params, options),
+                |r| r.get::<_, String>(0),
+            )
+        };
+        let cases = [
+            (
+                r#"{"items":[]}"#,
+                "at $.order_id: required property is missing",
+            ),
+            (
+                r#"{"order_id":"x-1","items":[{"sku":"a"}]}"#,
+                "at $.order_id: must match pattern ^ord-",
+            ),
+            (
+                r#"{"order_id":"ord-1","items":[{"sku":"a","qty":1},{"sku":5}]}"#,
+                "at $.items[1].sku: expected string, got integer",
+            ),
+            (
+                r#"{"order_id":"ord-1","items":[{"sku":"a","qty":0}]}"#,
+                "at $.items[0].qty: must be >= 1",
+            ),
+            (
+                r#"{"order_id":"ord-1","items":[{"sku":"a","color":"red"}]}"#,
+                "at $.items[0].color: additional property is not allowed",
+            ),
+        ];
+        for (params, expected) in cases {
+            let err = spawn(params, "{}").unwrap_err().to_string();
+            assert!(err.contains(expected), "{} not in {}", expected, err);
+        }
+        let count: i64 = conn
+            .query_row("select count(*) from absurd_tasks", [], |r| r.get(0))
+            .unwrap();
+        assert_eq!(count, 0);
+
+        let valid = r#"{"order_id":"ord-1","items":[{"sku":"a","qty":2}]}"#;
+        let defaulted = spawn(valid, "{}").unwrap();
+        let explicit = spawn(valid, r#"{"max_attempts":2}"#).unwrap();
+        let options_sql =
+            "select max_attempts, json(retry_strategy) from absurd_tasks where task_id = ?1";
+        let row: (i64, String) = conn
+            .query_row(options_sql, [&defaulted], |r| Ok((r.get(0)?, r.get(1)?)))
+            .unwrap();
+        assert_eq!(row, (7, r#"{"base_seconds":2,"kind":"fixed"}"#.to_string()));
+        let row: (i64, String) = conn
+            .query_row(options_sql, [&explicit], |r| Ok((r.get(0)?, r.get(1)?)))
+            .unwrap();
+        assert_eq!(row.0, 2);
+
+        // Other task names are unaffected, and unregistering lifts the schema.
+        let _: String = conn
+            .query_row(
+                "select task_id from absurd_spawn_task('alpha', 'other', '[1]')",
+                [],
+                |r| r.get(0),
+            )
+            .unwrap();
+        let removed: i64 = conn
+            .query_row(
+                "select absurd_unregister_task_type('alpha', 'order')",
+                [],
+                |r| r.get(0),
+            )
+            .unwrap();
+        assert_eq!(removed, 1);
+        spawn("[1]", "{}").unwrap();
+    }
+
+    #[test]
+    fn test_task_type_metadata_and_limits() {
+        unsafe {
+            sqlite3_auto_extension(Some(std::mem::transmute(sqlite3_absurd_init as *const ())));
+        }
+
+        let conn = Connection::open_in_memory().unwrap();
+        let _: i64 = conn
+            .query_row("select absurd_apply_migrations()", [], |row| row.get(0))
+            .unwrap();
+        let _: i64 = conn
+            .query_row("select absurd_create_queue('alpha')", [], |r| r.get(0))
+            .unwrap();
+
+        let err = conn
+            .query_row(
+                "select absurd_register_task_type('alpha', 'limited', null, null, '{\"concurrency_limit\":0}')",
+                [],
+                |r| r.get::<_, i64>(0),
+            )
+            .unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("concurrency_limit must be a positive integer"));
+        let _: i64 = conn
+            .query_row(
+                "select absurd_register_task_type('alpha', 'limited', null, ?1, ?2)",
+                (
+                    r#"{"headers":{"team":"billing","trace":"default"}}"#,
+                    r#"{"description":"Charge cards","owner_team":"billing","claim_timeout":120,"concurrency_limit":1}"#,
+                ),
+                |r| r.get(0),
+            )
+            .unwrap();
+
+        let first: String = conn
+            .query_row(
+                "select task_id from absurd_spawn_task('alpha', 'limited', '{}', '{\"headers\":{\"trace\":\"abc\"}}')",
+                [],
+                |r| r.get(0),
+            )
+            .unwrap();
+        let headers: String = conn
+            .query_row(
+                "select json(headers) from absurd_tasks where task_id = ?1",
+                [&first],
+                |r| r.get(0),
+            )
+            .unwrap();
+        assert_eq!(headers, r#"{"team":"billing","trace":"abc"}"#);
+        for task_name in ["limited", "limited", "free"] {
+            let _: String = conn
+                .query_row(
+                    "select task_id from absurd_spawn_task('alpha', ?1, '{}')",
+                    [task_name],
+                    |r| r.get(0),
+                )
+                .unwrap();
+        }
+
+        let claim_sql = "select run_id, task_name from absurd_claim_task('alpha','worker',30,10)";
+        let claimed: Vec<(String, String)> = conn
+            .prepare(claim_sql)
+            .unwrap()
+            .query_map([], |r| Ok((r.get(0)?, r.get(1)?)))
+            .unwrap()
+            .map(|row| row.unwrap())
+            .collect();
+        let mut names: Vec<&str> = claimed.iter().map(|(_, name)| name.as_str()).collect();
+        names.sort();
+        assert_eq!(names, vec!["free", "limited"]);
+
+        let timeouts: Vec<(String, i64)> = conn
+            .prepare(
+                "select t.task_name, r.claim_expires_at - r.started_at
+                   from absurd_runs r join absurd_tasks t using (queue_name, task_id)
+                  where r.state = 'running' order by t.task_name",
+            )
+            .unwrap()
+            .query_map([], |r| Ok((r.get(0)?, r.get(1)?)))
+            .unwrap()
+            .map(|row| row.unwrap())
+            .collect();
+        assert_eq!(
+            timeouts,
+            vec![
+                ("free".to_string(), 30_000),
+                ("limited".to_string(), 120_000)
+            ]
+        );
+
+        let listed: (String, String, i64, i64, i64, i64) = conn
+            .query_row(
+                "select description, owner_team, claim_timeout, concurrency_limit, pending, running
+                   from absurd_list_task_types('alpha')",
+                [],
+                |r| {
+                    Ok((
+                        r.get(0)?,
+                        r.get(1)?,
+                        r.get(2)?,
+                        r.get(3)?,
+                        r.get(4)?,
+                        r.get(5)?,
+                    ))
+                },
+            )
+            .unwrap();
+        assert_eq!(
+            listed,
+            (
+                "Charge cards".to_string(),
+                "billing".to_string(),
+                120,
+                1,
+                2,
+                1
+            )
+        );
+
+        let limited_run = &claimed
+            .iter()
+            .find(|(_, name)| name == "limited")
+            .unwrap()
+            .0;
+        let count: i64 = conn
+            .query_row(
+                "select count(*) from absurd_claim_task('alpha','worker',30,10)",
+                [],
+                |r| r.get(0),
+            )
+            .unwrap();
+        assert_eq!(count, 0);
+        let _: Option<i64> = conn
+            .query_row(
+                "select absurd_complete_run('alpha', ?1, '{}')",
+                [limited_run],
+                |r| r.get(0),
+            )
+            .unwrap();
+        let count: i64 = conn
+            .query_row(
+                "select count(*) from absurd_claim_task('alpha','worker',30,10)",
+                [],
+                |r| r.get(0),
+            )
+            .unwrap();
+        assert_eq!(count, 1);
+    }
+
+    #[test]
+    fn test_claim_task_filter() {
+        unsafe {
+            sqlite3_auto_extension(Some(std::mem::transmute(sqlite3_absurd_init as *const ())));
+        }
+
+        let conn = Connection::open_in_memory().unwrap();
+        let _: i64 = conn
+            .query_row("select absurd_apply_migrations()", [], |row| row.get(0))
+            .unwrap();
+        let _: i64 = conn
+            .query_row("select absurd_create_queue('alpha')", [], |r| r.get(0))
+            .unwrap();
+
+        for (task_name, region) in [
+            ("resize", "eu"),
+            ("email", "us"),
+            ("resize", "us"),
+            ("email", "eu"),
+        ] {
+            let options = format!(r#"{{"headers":{{"region":"{}"}}}}"#, region);
+            let _: String = conn
+                .query_row(
+                    "select task_id from absurd_spawn_task('alpha', ?1, '{}', ?2)",
+                    (task_name, &options),
+                    |r| r.get(0),
+                )
+                .unwrap();
+        }
+
+        let claim = |filter: &str| -> Vec<String> {
+            conn.prepare(
+                "select task_name || ':' || (headers ->> 'region')
+                   from absurd_claim_task('alpha', 'worker', 30, 10, ?1)",
+            )
+            .unwrap()
+            .query_map([filter], |r| r.get(0))
+            .unwrap()
+            .map(|row| row.unwrap())
+            .collect()
+        };
+
+        let err = conn
+            .query_row(
+                "select count(*) from absurd_claim_task('alpha', 'worker', 30, 10, '[]')",
+                [],
+                |r| r.get::<_, i64>(0),
+            )
+            .unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("task_names must be a non-empty array"));
+
+        assert_eq!(
+            claim(r#"{"task_names":["email"],"headers":{"region":"eu"}}"#),
+            vec!["email:eu"]
+        );
+        assert_eq!(claim(r#"["resize"]"#), vec!["resize:eu", "resize:us"]);
+        assert_eq!(
+            claim(r#"{"headers":{"region":"eu"}}"#),
+            Vec::<String>::new()
+        );
+        assert_eq!(claim(r#"["email","resize"]"#), vec!["email:us"]);
+
+        let plan: Vec<String> = conn
+            .prepare(
+                "explain query plan
+                 select 1 from absurd_tasks
+                  where queue_name = 'alpha' and task_name in ('email')",
+            )
+            .unwrap()
+            .query_map([], |r| r.get(3))
+            .unwrap()
+            .map(|row| row.unwrap())
+            .collect();
+        let plan = plan.join(" | ");
+        assert!(plan.contains("absurd_tasks_task_name_idx"), "{}", plan);
+    }
+
+    #[test]
+    fn test_claim_task_filter_uses_task_name_index() {
+        unsafe {
+            sqlite3_auto_extension(Some(std::mem::transmute(sqlite3_absurd_init as *const ())));
+        }
+
+        let conn = Connection::open_in_memory().unwrap();
+        let _: i64 = conn
+            .query_row("select absurd_apply_migrations()", [], |row| row.get(0))
+            .unwrap();
+        let plan = |filter: &claim::ClaimFilter, limited: bool| -> String {
+            let query = claim::candidate_query(filter, "fifo", limited);
+            let mut stmt = conn
+                .prepare(&format!("explain query plan {}", query))
+                .unwrap();
+            let values = ["alpha", "0", "1", r#"["alpha","beta"]"#];
+            let bound = rusqlite::params_from_iter(&values[..stmt.parameter_count()]);
+            let details: Vec<String> = stmt
+                .query_map(bound, |r| r.get(3))
+                .unwrap()
+                .collect::<rusqlite::Result<_>>()
+                .unwrap();
+            details.join("\n")
+        };
+
+        let by_name = claim::parse_claim_filter(r#"["alpha","beta"]"#).unwrap();
+        for limited in [false, true] {
+            let detail = plan(&by_name, limited);
+            assert!(
+                detail.contains(
+                    "SEARCH t USING INDEX absurd_tasks_task_name_idx (queue_name=? AND task_name=?"
+                ),
+                "{}",
+                detail
+            );
+        }
+
+        let unfiltered = plan(&claim::ClaimFilter::default(), false);
+        assert!(!unfiltered.contains("json_each"), "{}", unfiltered);
+        assert!(
+            unfiltered.contains("SEARCH r USING INDEX absurd_runs_state_available_idx"),
+            "{}",
+            unfiltered
+        );
+    }
+
+    #[test]
+    fn test_claim_tasks_multi() {
+        unsafe {
+            sqlite3_auto_extension(Some(std::mem::transmute(sqlite3_absurd_init as *const ())));
+        }
+
+        let conn = Connection::open_in_memory().unwrap();
+        let _: i64 = conn
+            .query_row("select absurd_apply_migrations()", [], |row| row.get(0))
+            .unwrap();
+        for queue in ["alpha", "beta", "gamma"] {
+            let _: i64 = conn
+                .query_row("select absurd_create_queue(?1)", [queue], |r| r.get(0))
+                .unwrap();
+            for i in 0..6 {
+                let task_name = if i % 2 == 0 { "even" } else { "odd" };
+                let _: String = conn
+                    .query_row(
+                        "select task_id from absurd_spawn_task(?1, ?2, '{}', '{}')",
+                        (queue, task_name),
+                        |r| r.get(0),
+                    )
+                    .unwrap();
+            }
+        }
+
+        let claim = |queues: &str, worker: &str, qty: i64| -> Vec<String> {
+            conn.prepare(
+                "select queue_name || ':' || task_name
+                   from absurd_claim_tasks_multi(?1, ?2, 30, ?3)",
+            )
+            .unwrap()
+            .query_map((queues, worker, qty), |r| r.get(0))
+            .unwrap()
+            .map(|row| row.unwrap())
+            .collect()
+        };
+
+        let weighted = r#"[{"queue_name":"alpha","weight":2},"beta"]"#;
+        assert_eq!(
+            claim(weighted, "w1", 3),
+            vec!["alpha:even", "beta:even", "alpha:odd"]
+        );
+        // Rotation carries over between polls for the same worker.
+        assert_eq!(claim(weighted, "w1", 1), vec!["alpha:even"]);
+        assert_eq!(claim(weighted, "w1", 1), vec!["beta:odd"]);
+        assert_eq!(claim(weighted, "w1", 1), vec!["alpha:odd"]);
+
+        let priority = r#"{"strategy":"priority","queues":[
+            {"queue_name":"gamma","task_filter":["odd"]},
+            "beta"
+        ]}"#;
+        assert_eq!(
+            claim(priority, "w2", 5),
+            vec![
+                "gamma:odd",
+                "gamma:odd",
+                "gamma:odd",
+                "beta:even",
+                "beta:odd"
+            ]
+        );
+
+        // Alpha runs out after two claims, so the last slot goes to beta.
+        assert_eq!(
+            claim(weighted, "w3", 4),
+            vec!["alpha:even", "beta:even", "alpha:odd", "beta:odd"]
+        );
+
+        let running: i64 = conn
+            .query_row(
+                "select count(*) from absurd_runs where state = 'running' and claimed_by = 'w2'",
+                [],
+                |r| r.get(0),
+            )
+            .unwrap();
+        assert_eq!(running, 5);
+        let queues: String = conn
+            .query_row(
+                "select queues from absurd_workers where worker_id = 'w2'",
+                [],
+                |r| r.get(0),
+            )
+            .unwrap();
+        assert_eq!(queues, r#"["gamma","beta"]"#);
+
+        for (queues, message) in [
+            ("[]", "queues must not be empty"),
+            (r#"["alpha","alpha"]"#, "queue listed more than once: alpha"),
+            (
+                r#"[{"queue_name":"alpha","weight":0}]"#,
+                "weight must be a positive integer",
+            ),
+            (
+                r#"{"strategy":"random","queues":["alpha"]}"#,
+                "strategy must be",
+            ),
+        ] {
+            let err = conn
+                .query_row(
+                    "select count(*) from absurd_claim_tasks_multi(?1, 'w4', 30, 1)",
+                    [queues],
+                    |r| r.get::<_, i64>(0),
+                )
+                .unwrap_err();
+            assert!(err.to_string().contains(message), "{}", err);
+        }
+    }
+
+    #[test]
+    fn test_claim_ordering_and_supersede() {
+        unsafe {
+            sqlite3_auto_extension(Some(std::mem::transmute(sqlite3_absurd_init as *const ())));
+        }
+
+        let conn = Connection::open_in_memory().unwrap();
+        let _: i64 = conn
+            .query_row("select absurd_apply_migrations()", [], |row| row.get(0))
+            .unwrap();
+        let _: i64 = conn
+            .query_row("select absurd_create_queue('alpha')", [], |r| r.get(0))
+            .unwrap();
+
+        let spawn = |now: i64, label: &str, options: &str| {
+            let _: i64 = conn
+                .query_row("select absurd_set_fake_now(?1)", [now], |r| r.get(0))
+                .unwrap();
+            let _: String = conn
+                .query_row(
+                    "select task_id from absurd_spawn_task('alpha', ?1, '{}', ?2)",
+                    (label, options),
+                    |r| r.get(0),
+                )
+                .unwrap();
+        };
+        let claim = |qty: i64| -> Vec<String> {
+            conn.prepare("select task_name from absurd_claim_task('alpha', 'worker', 30, ?1)")
+                .unwrap()
+                .query_map([qty], |r| r.get(0))
+                .unwrap()
+                .map(|row| row.unwrap())
+                .collect()
+        };
+        let set_ordering = |ordering: &str| {
+            let _: i64 = conn
+                .query_row(
+                    "select absurd_set_queue_setting('alpha', 'ordering', ?1)",
+                    [ordering],
+                    |r| r.get(0),
+                )
+                .unwrap();
+        };
+
+        let err = conn
+            .query_row(
+                "select absurd_set_queue_setting('alpha', 'ordering', 'random')",
+                [],
+                |r| r.get::<_, i64>(0),
+            )
+            .unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("ordering must be one of fifo, lifo or priority"));
+
+        spawn(1_000, "first", "{}");
+        spawn(2_000, "second", r#"{"priority":5}"#);
+        spawn(3_000, "third", r#"{"priority":1}"#);
+        spawn(4_000, "fourth", "{}");
+        let _: i64 = conn
+            .query_row("select absurd_set_fake_now(10000)", [], |r| r.get(0))
+            .unwrap();
+
+        set_ordering("lifo");
+        assert_eq!(claim(1), vec!["fourth"]);
+        set_ordering("priority");
+        assert_eq!(claim(2), vec!["second", "third"]);
+        set_ordering("fifo");
+        assert_eq!(claim(1), vec!["first"]);
+
+        // Claiming the newest task for a key cancels older ones that are not running.
+        spawn(20_000, "sync-a1", r#"{"supersede_key":"account-a"}"#);
+        spawn(21_000, "sync-b1", r#"{"supersede_key":"account-b"}"#);
+        spawn(22_000, "sync-a2", r#"{"supersede_key":"account-a"}"#);
+        spawn(23_000, "sync-a3", r#"{"supersede_key":"account-a"}"#);
+        set_ordering("lifo");
+        assert_eq!(claim(10), vec!["sync-a3", "sync-b1"]);
+
+        let states: Vec<String> = conn
+            .prepare(
+                "select t.task_name || ':' || t.state || ':' || r.state
+                   from absurd_tasks t
+                   join absurd_runs r on r.queue_name = t.queue_name and r.run_id = t.last_attempt_run
+                  where t.supersede_key is not null
+                  order by t.enqueue_at",
+            )
+            .unwrap()
+            .query_map([], |r| r.get(0))
+            .unwrap()
+            .map(|row| row.unwrap())
+            .collect();
+        assert_eq!(
+            states,
+            vec![
+                "sync-a1:cancelled:cancelled",
+                "sync-b1:running:running",
+                "sync-a2:cancelled:cancelled",
+                "sync-a3:running:running",
+            ]
+        );
+
+        // Siblings that are asleep waiting on an event are superseded too.
+        spawn(30_000, "sync-c1", r#"{"supersede_key":"account-c"}"#);
+        let (c1_task, c1_run): (String, String) = conn
+            .query_row(
+                "select task_id, run_id from absurd_claim_task('alpha', 'worker', 30, 1)",
+                [],
+                |r| Ok((r.get(0)?, r.get(1)?)),
+            )
+            .unwrap();
+        let suspended: i64 = conn
+            .query_row(
+                "select should_suspend from absurd_await_event('alpha', ?1, ?2, 'wait', 'go')",
+                (&c1_task, &c1_run),
+                |r| r.get(0),
+            )
+            .unwrap();
+        assert_eq!(suspended, 1);
+        spawn(31_000, "sync-c2", r#"{"supersede_key":"account-c"}"#);
+        assert_eq!(claim(1), vec!["sync-c2"]);
+        let c1: (String, String, i64) = conn
+            .query_row(
+                "select t.state, r.state,
+                        (select count(*) from absurd_waits w where w.task_id = t.task_id)
+                   from absurd_tasks t
+                   join absurd_runs r on r.queue_name = t.queue_name and r.run_id = t.last_attempt_run
+                  where t.task_id = ?1",
+                [&c1_task],
+                |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?)),
+            )
+            .unwrap();
+        assert_eq!(c1, ("cancelled".to_string(), "cancelled".to_string(), 0));
+
+        let err = conn
+            .query_row(
+                r#"select task_id from absurd_spawn_task('alpha', 'bad', '{}', '{"priority":"high"}')"#,
+                [],
+                |r| r.get::<_, String>(0),
+            )
+            .unwrap_err();
+        assert!(err.to_string().contains("priority must be an integer"));
+    }
+
+    #[test]
+    fn test_spawn_tasks_batch() {
+        unsafe {
+            sqlite3_auto_extension(Some(std::mem::transmute(sqlite3_absurd_init as *const ())));
+        }
+
+        let conn = Connection::open_in_memory().unwrap();
+        let _: i64 = conn
+            .query_row("select absurd_apply_migrations()", [], |row| row.get(0))
+            .unwrap();
+        let _: i64 = conn
+            .query_row("select absurd_create_queue('alpha')", [], |r| r.get(0))
+            .unwrap();
+        let (existing_id, _): (String, i64) = conn
+            .query_row(
+                r#"select task_id, created from absurd_spawn_task('alpha', 'old', '{}', '{"idempotency_key":"k1"}')"#,
+                [],
+                |r| Ok((r.get(0)?, r.get(1)?)),
+            )
+            .unwrap();
+
+        let tasks = r#"[
+            {"task_name":"first","params":{"n":1},"options":{"max_attempts":2}},
+            {"task_name":"second","options":{"idempotency_key":"k1"}},
+            {"task_name":"third","params":[1,2],"options":{"idempotency_key":"k2"}},
+            {"task_name":"fourth","options":{"idempotency_key":"k2"}}
+        ]"#;
+        let rows: Vec<(i64, String, i64)> = conn
+            .prepare("select item_index, task_id, created from absurd_spawn_tasks('alpha', ?1)")
+            .unwrap()
+            .query_map([tasks], |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?)))
+            .unwrap()
+            .map(|row| row.unwrap())
+            .collect();
+        assert_eq!(
+            rows.iter().map(|r| (r.0, r.2)).collect::<Vec<_>>(),
+            vec![(0, 1), (1, 0), (2, 1), (3, 0)]
+        );
+        assert_eq!(rows[1].1, existing_id);
+        assert_eq!(rows[3].1, rows[2].1);
+
+        let (params, max_attempts, run_state): (String, i64, String) = conn
+            .query_row(
+                "select json(t.params), t.max_attempts, r.state
+                   from absurd_tasks t
+                   join absurd_runs r on r.queue_name = t.queue_name and r.run_id = t.last_attempt_run
+                  where t.task_id = ?1",
+                [&rows[0].1],
+                |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?)),
+            )
+            .unwrap();
+        assert_eq!(params, r#"{"n":1}"#);
+        assert_eq!(max_attempts, 2);
+        assert_eq!(run_state, "pending");
+
+        // A bad item rolls back the whole batch.
+        let err = conn
+            .query_row(
+                r#"select count(*) from absurd_spawn_tasks('alpha', '[{"task_name":"ok"},{"task_name":"bad","options":{"max_attempts":0}}]')"#,
+                [],
+                |r| r.get::<_, i64>(0),
+            )
+            .unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("tasks[1]: max_attempts must be >= 1"),
+            "{}",
+            err
+        );
+        let count: i64 = conn
+            .query_row("select count(*) from absurd_tasks", [], |r| r.get(0))
+            .unwrap();
+        assert_eq!(count, 3);
+    }
+
+    #[test]
+    fn test_pause_queue_and_retry_task() {
+        unsafe {
+            sqlite3_auto_extension(Some(std::mem::transmute(sqlite3_absurd_init as *const ())));
+        }
+
+        let conn = Connection::open_in_memory().unwrap();
+        let _: i64 = conn
+            .query_row("select absurd_apply_migrations()", [], |row| row.get(0))
+            .unwrap();
+        let _: i64 = conn
+            .query_row("select absurd_create_queue('alpha')", [], |r| r.get(0))
+            .unwrap();
+        let task_id: String = conn
+            .query_row(
+                "select task_id from absurd_spawn_task('alpha', 'demo', '{}', '{\"max_attempts\":1}')",
+                [],
+                |r| r.get(0),
+            )
+            .unwrap();
+
+        let set_paused = |value: Option<&str>| {
+            let _: i64 = conn
+                .query_row(
+                    "select absurd_set_queue_setting('alpha', 'paused', ?1)",
+                    [value],
+                    |r| r.get(0),
+                )
+                .unwrap();
+        };
+        let claim = || -> Vec<String> {
+            conn.prepare("select run_id from absurd_claim_task('alpha', 'worker', 30, 1)")
+                .unwrap()
+                .query_map([], |r| r.get(0))
+                .unwrap()
+                .collect::<std::result::Result<_, _>>()
+                .unwrap()
+        };
+
+        set_paused(Some("true"));
+        assert!(claim().is_empty());
+        set_paused(None);
+        let runs = claim();
+        assert_eq!(runs.len(), 1);
+
+        let err = conn
+            .query_row("select absurd_retry_task('alpha', ?1)", [&task_id], |r| {
+                r.get::<_, String>(0)
+            })
+            .unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("only failed or cancelled tasks can be retried"));
+
+        let _: Option<i64> = conn
+            .query_row(
+                "select absurd_fail_run('alpha', ?1, '{\"name\":\"boom\"}', null)",
+                [&runs[0]],
+                |r| r.get(0),
+            )
+            .unwrap();
+        let state: String = conn
+            .query_row(
+                "select state from absurd_tasks where task_id = ?1",
+                [&task_id],
+                |r| r.get(0),
+            )
+            .unwrap();
+        assert_eq!(state, "failed");
+
+        let retry_run: String = conn
+            .query_row("select absurd_retry_task('alpha', ?1)", [&task_id], |r| {
+                r.get(0)
+            })
+            .unwrap();
+        let (state, attempts, last_run): (String, i64, String) = conn
+            .query_row(
+                "select state, attempts, last_attempt_run from absurd_tasks where task_id = ?1",
+                [&task_id],
+                |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?)),
+            )
+            .unwrap();
+        assert_eq!(state, "pending");
+        assert_eq!(attempts, 2);
+        assert_eq!(last_run, retry_run);
+        assert_eq!(claim(), vec![retry_run.clone()]);
+
+        // The retry went past max_attempts, so failing it ends the task.
+        let _: Option<i64> = conn
+            .query_row(
+                "select absurd_fail_run('alpha', ?1, '{\"name\":\"boom\"}', null)",
+                [&retry_run],
+                |r| r.get(0),
+            )
+            .unwrap();
+        let state: String = conn
+            .query_row(
+                "select state from absurd_tasks where task_id = ?1",
+                [&task_id],
+                |r| r.get(0),
+            )
+            .unwrap();
+        assert_eq!(state, "failed");
+
+        // A task stopped by its cancellation policy runs again after a retry
+        // instead of being cancelled by the same, already elapsed, clock.
+        let _: i64 = conn
+            .query_row("select absurd_set_fake_now(1000)", [], |r| r.get(0))
+            .unwrap();
+        let limited: String = conn
+            .query_row(
+                "select task_id from absurd_spawn_task('alpha', 'demo', '{}', '{\"cancellation\":{\"max_duration\":5}}')",
+                [],
+                |r| r.get(0),
+            )
+            .unwrap();
+        assert_eq!(claim().len(), 1);
+        let _: i64 = conn
+            .query_row("select absurd_set_fake_now(7000)", [], |r| r.get(0))
+            .unwrap();
+        assert!(claim().is_empty());
+        let task_state = || -> String {
+            conn.query_row(
+                "select state from absurd_tasks where task_id = ?1",
+                [&limited],
+                |r| r.get(0),
+            )
+            .unwrap()
+        };
+        assert_eq!(task_state(), "cancelled");
+
+        let retry_run: String = conn
+            .query_row("select absurd_retry_task('alpha', ?1)", [&limited], |r| {
+                r.get(0)
+            })
+            .unwrap();
+        assert_eq!(claim(), vec![retry_run]);
+        assert_eq!(task_state(), "running");
+    }
+
+    #[test]
+    fn test_export_import_queue() {
+        unsafe {
+            sqlite3_auto_extension(Some(std::mem::transmute(sqlite3_absurd_init as *const ())));
+        }
+
+        let conn = Connection::open_in_memory().unwrap();
+        let _: i64 = conn
+            .query_row("select absurd_apply_migrations()", [], |row| row.get(0))
+            .unwrap();
+        let _: i64 = conn
+            .query_row("select absurd_create_queue('alpha')", [], |r| r.get(0))
+            .unwrap();
+        let _: i64 = conn
+            .query_row(
+                "select absurd_set_queue_setting('alpha', 'compression', 'deflate')",
+                [],
+                |r| r.get(0),
+            )
+            .unwrap();
+
+        let big = "x".repeat(500);
+        let params = format!(r#"{{"big":"{}"}}"#, big);
+        let (task_id, run_id): (String, String) = conn
+            .query_row(
+                "select task_id, run_id from absurd_spawn_task('alpha', 'demo', ?1, '{}')",
+                [&params],
+                |r| Ok((r.get(0)?, r.get(1)?)),
+            )
+            .unwrap();
+        let _: String = conn
+            .query_row(
+                "select run_id from absurd_claim_task('alpha', 'worker', 30, 1)",
+                [],
+                |r| r.get(0),
+            )
+            .unwrap();
+        let _: Option<i64> = conn
+            .query_row(
+                "select absurd_set_task_checkpoint_state('alpha', ?1, 'step', '{\"v\":1}', ?2)",
+                [&task_id, &run_id],
+                |r| r.get(0),
+            )
+            .unwrap();
+        let _: Option<i64> = conn
+            .query_row(
+                "select absurd_complete_run('alpha', ?1, '{\"ok\":true}')",
+                [&run_id],
+                |r| r.get(0),
+            )
+            .unwrap();
+        let _: String = conn
+            .query_row(
+                "select task_id from absurd_spawn_task('alpha', 'demo', '{}', '{}')",
+                [],
+                |r| r.get(0),
+            )
+            .unwrap();
+        let _: Option<i64> = conn
+            .query_row("select absurd_emit_event('alpha', 'done', '{}')", [], |r| {
+                r.get(0)
+            })
+            .unwrap();
+
+        let rows: Vec<(String, String)> = conn
+            .prepare("select record_type, line from absurd_export_queue('alpha') order by line_no")
+            .unwrap()
+            .query_map([], |r| Ok((r.get(0)?, r.get(1)?)))
+            .unwrap()
+            .collect::<std::result::Result<_, _>>()
+            .unwrap();
+        let types: Vec<&str> = rows.iter().map(|(kind, _)| kind.as_str()).collect();
+        assert_eq!(
+            types,
+            vec![
+                "header",
+                "task",
+                "task",
+                "run",
+                "run",
+                "checkpoint",
+                "event"
+            ]
+        );
+        let first_task: serde_json::Value = serde_json::from_str(&rows[1].1).unwrap();
+        assert_eq!(first_task["task_id"], task_id.as_str());
+        assert_eq!(first_task["params"]["big"], big.as_str());
+        assert!(first_task.get("queue_name").is_none());
+        let jsonl = rows
+            .iter()
+            .map(|(_, line)| line.as_str())
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        let imported: i64 = conn
+            .query_row("select absurd_import_queue('beta', ?1)", [&jsonl], |r| {
+                r.get(0)
+            })
+            .unwrap();
+        assert_eq!(imported, 6);
+        let count = |sql: &str| -> i64 { conn.query_row(sql, [], |r| r.get(0)).unwrap() };
+        assert_eq!(
+            count("select count(*) from absurd_tasks where queue_name = 'beta'"),
+            2
+        );
+        let beta_params: String = conn
+            .query_row(
+                "select json(params) from absurd_tasks where queue_name = 'beta' and task_id = ?1",
+                [&task_id],
+                |r| r.get(0),
+            )
+            .unwrap();
+        assert_eq!(beta_params, params);
+        let checkpoint: String = conn
+            .query_row(
+                "select state from absurd_get_task_checkpoint_state('beta', ?1, 'step')",
+                [&task_id],
+                |r| r.get(0),
+            )
+            .unwrap();
+        assert_eq!(checkpoint, r#"{"v":1}"#);
+
+        // Keeping IDs twice collides; the whole import is rolled back.
+        let err = conn
+            .query_row(
+                "select absurd_import_queue('beta', ?1, 'keep')",
+                [&jsonl],
+                |r| r.get::<_, i64>(0),
+            )
+            .unwrap_err();
+        assert!(err.to_string().contains("line 2:"), "{}", err);
+        assert_eq!(
+            count("select count(*) from absurd_tasks where queue_name = 'beta'"),
+            2
+        );
+
+        let without_event = rows
+            .iter()
+            .filter(|(kind, _)| kind != "event")
+            .map(|(_, line)| line.as_str())
+            .collect::<Vec<_>>()
+            .join("\n");
+        let remapped: i64 = conn
+            .query_row(
+                "select absurd_import_queue('beta', ?1, 'remap')",
+                [&without_event],
+                |r| r.get(0),
+            )
+            .unwrap();
+        assert_eq!(remapped, 5);
+        assert_eq!(
+            count("select count(*) from absurd_tasks where queue_name = 'beta'"),
+            4
+        );
+        assert_eq!(
+            count(
+                "select count(*) from absurd_tasks t
+                   join absurd_runs r
+                     on r.queue_name = t.queue_name
+                    and r.run_id = t.last_attempt_run
+                    and r.task_id = t.task_id
+                  where t.queue_name = 'beta'"
+            ),
+            count(
+                "select count(*) from absurd_tasks
+                  where queue_name = 'beta' and last_attempt_run is not null"
+            )
+        );
+
+        let err = conn
+            .query_row(
+                "select absurd_import_queue('beta', '{\"type\":\"bogus\"}')",
+                [],
+                |r| r.get::<_, i64>(0),
+            )
+            .unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("line 1: unknown record type: bogus"));
+        let err = conn
+            .query_row(
+                "select absurd_import_queue('beta', '', 'rename')",
+                [],
+                |r| r.get::<_, i64>(0),
+            )
+            .unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("id_mode must be 'keep' or 'remap'"));
+
+        // A failed import leaves nothing behind, not even the queue row.
+        conn.query_row("select absurd_import_queue('gamma', 'not json')", [], |r| {
+            r.get::<_, i64>(0)
+        })
+        .unwrap_err();
+        assert_eq!(
+            0,
+            count("select count(*) from absurd_queues where queue_name = 'gamma'")
+        );
+        let foreign_keys: i64 = conn
+            .query_row("pragma foreign_keys", [], |r| r.get(0))
+            .unwrap();
+        assert_eq!(1, foreign_keys);
+    }
+
+    #[test]
+    fn test_archive_tasks() {
+        unsafe {
+            sqlite3_auto_extension(Some(std::mem::transmute(sqlite3_absurd_init as *const ())));
+        }
+
+        let conn = Connection::open_in_memory().unwrap();
+        let _: i64 = conn
+            .query_row("select absurd_apply_migrations()", [], |row| row.get(0))
+            .unwrap();
+        let _: i64 = conn
+            .query_row("select absurd_create_queue('alpha')", [], |r| r.get(0))
+            .unwrap();
+        let _: i64 = conn
+            .query_row(
+                "select absurd_set_queue_setting('alpha', 'offload_threshold_bytes', '64')",
+                [],
+                |r| r.get(0),
+            )
+            .unwrap();
+        let _: i64 = conn
+            .query_row(
+                "select absurd_set_queue_setting('alpha', 'checkpoint_history', 'on')",
+                [],
+                |r| r.get(0),
+            )
+            .unwrap();
+
+        let params = format!(r#"{{"blob":"{}"}}"#, "x".repeat(200));
+        let (task_id, run_id): (String, String) = conn
+            .query_row(
+                "select task_id, run_id from absurd_spawn_task('alpha', 'demo', ?1, '{}')",
+                [&params],
+                |r| Ok((r.get(0)?, r.get(1)?)),
+            )
+            .unwrap();
+        let _: String = conn
+            .query_row(
+                "select run_id from absurd_claim_task('alpha', 'worker', 30, 1)",
+                [],
+                |r| r.get(0),
+            )
+            .unwrap();
+        let _: Option<i64> = conn
+            .query_row(
+                "select absurd_set_task_checkpoint_state('alpha', ?1, 'step', '{\"v\":1}', ?2)",
+                [&task_id, &run_id],
+                |r| r.get(0),
+            )
+            .unwrap();
+        let _: Option<i64> = conn
+            .query_row(
+                "select absurd_complete_run('alpha', ?1, '{\"ok\":true}')",
+                [&run_id],
+                |r| r.get(0),
+            )
+            .unwrap();
+        conn.execute(
+            "update absurd_runs set completed_at = 1 where queue_name = 'alpha' and run_id = ?1",
+            [&run_id],
+        )
+        .unwrap();
+        let _: String = conn
+            .query_row(
+                "select task_id from absurd_spawn_task('alpha', 'demo', '{}', '{}')",
+                [],
+                |r| r.get(0),
+            )
+            .unwrap();
+
+        let err = conn
+            .query_row(
+                "select absurd_archive_tasks('alpha', 1, 'archive')",
+                [],
+                |r| r.get::<_, i64>(0),
+            )
+            .unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("archive schema is not attached: archive"));
+
+        conn.execute("attach database ':memory:' as archive", [])
+            .unwrap();
+        let archived: i64 = conn
+            .query_row(
+                "select absurd_archive_tasks('alpha', 1, 'archive')",
+                [],
+                |r| r.get(0),
+            )
+            .unwrap();
+        assert_eq!(archived, 1);
+
+        let count = |sql: &str| -> i64 { conn.query_row(sql, [], |r| r.get(0)).unwrap() };
+        assert_eq!(count("select count(*) from archive.absurd_tasks"), 1);
+        assert_eq!(count("select count(*) from archive.absurd_runs"), 1);
+        assert_eq!(count("select count(*) from archive.absurd_checkpoints"), 1);
+        assert_eq!(
+            count(
+                "select count(*) from archive.absurd_checkpoint_history
+                  where checkpoint_name = 'step' and state ->> '$.v' = 1"
+            ),
+            1
+        );
+        assert_eq!(count("select count(*) from archive.absurd_blobs"), 1);
+        assert_eq!(count("select count(*) from main.absurd_tasks"), 1);
+        assert_eq!(count("select count(*) from main.absurd_runs"), 1);
+        assert_eq!(count("select count(*) from main.absurd_checkpoints"), 0);
+        assert_eq!(
+            count("select count(*) from main.absurd_checkpoint_history"),
+            0
+        );
+        assert_eq!(count("select count(*) from main.absurd_blobs"), 0);
+        assert_eq!(
+            count(
+                "select count(*) from archive.absurd_tasks t
+                   join archive.absurd_blobs b on b.hash = t.params ->> '$.\"$absurd_blob\"'"
+            ),
+            1
+        );
+
+        let again: i64 = conn
+            .query_row(
+                "select absurd_archive_tasks('alpha', 1, 'archive', 10)",
+                [],
+                |r| r.get(0),
+            )
+            .unwrap();
+        assert_eq!(again, 0);
+    }
+
+    #[test]
+    fn test_queue_retention_maintenance() {
+        unsafe {
+            sqlite3_auto_extension(Some(std::mem::transmute(sqlite3_absurd_init as *const ())));
+        }
+
+        let conn = Connection::open_in_memory().unwrap();
+        let _: i64 = conn
+            .query_row("select absurd_apply_migrations()", [], |row| row.get(0))
+            .unwrap();
+        for queue in ["alpha", "beta"] {
+            let _: i64 = conn
+                .query_row("select absurd_create_queue(?1)", [queue], |r| r.get(0))
+                .unwrap();
+        }
+
+        for (key, value) in [
+            ("completed_retention", "7d"),
+            ("failed_retention", "30d"),
+            ("event_retention", "86400"),
+        ] {
+            let _: i64 = conn
+                .query_row(
+                    "select absurd_set_queue_setting('alpha', ?1, ?2)",
+                    [key, value],
+                    |r| r.get(0),
+                )
+                .unwrap();
+        }
+        let stored: String = conn
+            .query_row(
+                "select value from absurd_queue_settings
+                  where queue_name = 'alpha' and key = 'completed_retention'",
+                [],
+                |r| r.get(0),
+            )
+            .unwrap();
+        assert_eq!(stored, "604800");
+        let err = conn
+            .query_row(
+                "select absurd_set_queue_setting('alpha', 'event_retention', 'soon')",
+                [],
+                |r| r.get::<_, i64>(0),
+            )
+            .unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("event_retention must be a duration"));
+
+        const DAY_MS: i64 = 24 * 60 * 60 * 1000;
+        let start = 1_700_000_000_000_i64;
+        let _: i64 = conn
+            .query_row("select absurd_set_fake_now(?1)", [start], |r| r.get(0))
+            .unwrap();
+
+        for queue in ["alpha", "alpha", "beta"] {
+            let _: String = conn
+                .query_row(
+                    "select task_id from absurd_spawn_task(?1, 'demo', '{}', '{\"max_attempts\":1}')",
+                    [queue],
+                    |r| r.get(0),
+                )
+                .unwrap();
+        }
+        let alpha_runs: Vec<String> = conn
+            .prepare("select run_id from absurd_claim_task('alpha', 'worker', 30, 2)")
+            .unwrap()
+            .query_map([], |r| r.get(0))
+            .unwrap()
+            .collect::<std::result::Result<_, _>>()
+            .unwrap();
+        assert_eq!(alpha_runs.len(), 2);
+        let beta_run: String = conn
+            .query_row(
+                "select run_id from absurd_claim_task('beta', 'worker', 30, 1)",
+                [],
+                |r| r.get(0),
+            )
+            .unwrap();
+        for (queue, run_id) in [("alpha", &alpha_runs[0]), ("beta", &beta_run)] {
+            let _: Option<i64> = conn
+                .query_row(
+                    "select absurd_complete_run(?1, ?2, '{}')",
+                    [queue, run_id.as_str()],
+                    |r| r.get(0),
+                )
+                .unwrap();
+        }
+        let _: Option<i64> = conn
+            .query_row(
+                "select absurd_fail_run('alpha', ?1, '{\"name\":\"boom\"}', null)",
+                [&alpha_runs[1]],
+                |r| r.get(0),
+            )
+            .unwrap();
+        for queue in ["alpha", "beta"] {
+            let _: Option<i64> = conn
+                .query_row("select absurd_emit_event(?1, 'done')", [queue], |r| {
+                    r.get(0)
+                })
+                .unwrap();
+        }
+
+        let run_maintenance = |now: i64, batch: i64| -> i64 {
+            let _: i64 = conn
+                .query_row("select absurd_set_fake_now(?1)", [now], |r| r.get(0))
+                .unwrap();
+            conn.query_row("select absurd_run_maintenance(?1)", [batch], |r| r.get(0))
+                .unwrap()
+        };
+        let task_count = |queue: &str| -> i64 {
+            conn.query_row(
+                "select count(*) from absurd_tasks where queue_name = ?1",
+                [queue],
+                |r| r.get(0),
+            )
+            .unwrap()
+        };
+
+        // Nothing has outlived its retention yet.
+        assert_eq!(run_maintenance(start + 12 * 60 * 60 * 1000, 100), 0);
+
+        // Events expire after a day, completed tasks after a week.
+        assert_eq!(run_maintenance(start + 2 * DAY_MS, 100), 1);
+        assert_eq!(run_maintenance(start + 8 * DAY_MS, 100), 1);
+        assert_eq!(task_count("alpha"), 1);
+        assert_eq!(run_maintenance(start + 8 * DAY_MS, 100), 0);
+
+        // Failed tasks are kept for 30 days; beta has no policy at all.
+        assert_eq!(run_maintenance(start + 31 * DAY_MS, 1), 1);
+        assert_eq!(run_maintenance(start + 31 * DAY_MS, 1), 0);
+        assert_eq!(task_count("alpha"), 0);
+        assert_eq!(task_count("beta"), 1);
+        let beta_events: i64 = conn
+            .query_row(
+                "select count(*) from absurd_events where queue_name = 'beta'",
+                [],
+                |r| r.get(0),
+            )
+            .unwrap();
+        assert_eq!(beta_events, 1);
     }
 
     #[test]
@@ -0,0 +1,84 @@
+use crate::queue;
+use crate::run::{self, TerminalCutoffs};
+use crate::sql;
+use sqlite3ext_sys::sqlite3;
+use sqlite_loadable::prelude::*;
+use sqlite_loadable::{api, Error, Result};
+
+/// Names of queues with at least one retention setting.
+fn queues_with_retention(db: *mut sqlite3) -> Result<Vec<String>> {
+    let placeholders = queue::RETENTION_SETTING_KEYS
+        .iter()
+        .enumerate()
+        .map(|(idx, _)| format!("?{}", idx + 1))
+        .collect::<Vec<_>>()
+        .join(", ");
+    let query = format!(
+        "select distinct queue_name
+           from absurd_queue_settings
+          where key in ({})
+          order by queue_name",
+        placeholders
+    );
+    let mut stmt = sqlite_loadable::exec::Statement::prepare(db, &query).map_err(|err| {
+        Error::new_message(format!("failed to prepare retention lookup: {:?}", err))
+    })?;
+    for (idx, key) in queue::RETENTION_SETTING_KEYS.iter().enumerate() {
+        stmt.bind_text((idx + 1) as i32, key)
+            .map_err(|err| Error::new_message(format!("failed to bind key: {:?}", err)))?;
+    }
+    let mut queues = Vec::new();
+    for row in stmt.execute() {
+        let row =
+            row.map_err(|err| Error::new_message(format!("failed to read row: {:?}", err)))?;
+        queues.push(
+            row.get::<String>(0)
+                .map_err(|err| Error::new_message(format!("failed to read queue: {:?}", err)))?,
+        );
+    }
+    Ok(queues)
+}
+
+/// Apply one batch of a queue's retention policy. Returns rows deleted.
+fn apply_retention(db: *mut sqlite3, queue_name: &str, now: i64, limit: i64) -> Result<i64> {
+    let cutoff = |key: &str| -> Result<Option<i64>> {
+        Ok(queue::retention_secs(db, queue_name, key)?.map(|secs| now - secs.saturating_mul(1000)))
+    };
+    let cutoffs = TerminalCutoffs {
+        completed: cutoff("completed_retention")?,
+        failed: cutoff("failed_retention")?,
+        cancelled: cutoff("cancelled_retention")?,
+    };
+
+    let mut deleted = 0;
+    if cutoffs.completed.is_some() || cutoffs.failed.is_some() || cutoffs.cancelled.is_some() {
+        deleted += run::cleanup_tasks_impl(db, queue_name, &cutoffs, limit)?;
+    }
+    if let Some(event_cutoff) = cutoff("event_retention")? {
+        deleted += run::cleanup_events_impl(db, queue_name, event_cutoff, limit)?;
+    }
+    Ok(deleted)
+}
+
+/// SQL: absurd_run_maintenance([batch_size])
+/// Usage: apply every queue's retention settings in one bounded batch per queue; call until it returns 0.
+/// Section: Durable
+///
+/// Each queue is cleaned in its own transaction so that a large backlog never
+/// holds the write lock for longer than one batch.
+pub fn absurd_run_maintenance(
+    context: *mut sqlite3_context,
+    values: &[*mut sqlite3_value],
+) -> Result<()> {
+    let limit = run::parse_optional_limit(values.first().copied())?;
+    let db = api::context_db_handle(context);
+    let now = sql::now_ms_from_db(db);
+
+    let mut deleted = 0;
+    for queue_name in queues_with_retention(db)? {
+        deleted += apply_retention(db, &queue_name, now, limit)?;
+    }
+
+    api::result_int64(context, deleted);
+    Ok(())
+}
@@ -260,3 +260,91 @@ impl VTabCursor for MigrationRecordsCursor {
         Ok(self.rowid)
     }
 }
+
+const AVAILABLE_MIGRATIONS_SQL: &str = "CREATE TABLE x(id INTEGER, introduced_version TEXT)";
+
+/// SQL: absurd_available_migrations()
+/// Usage: list migrations bundled with this build of the extension (id, introduced_version).
+/// Section: Schema
+#[repr(C)]
+pub struct AvailableMigrationsTable {
+    base: sqlite3_vtab,
+}
+
+impl<'vtab> VTab<'vtab> for AvailableMigrationsTable {
+    type Aux = ();
+    type Cursor = AvailableMigrationsCursor;
+
+    fn connect(
+        _db: *mut sqlite3,
+        _aux: Option<&Self::Aux>,
+        _args: VTabArguments,
+    ) -> Result<(String, AvailableMigrationsTable)> {
+        let base: sqlite3_vtab = unsafe { std::mem::zeroed() };
+        let vtab = AvailableMigrationsTable { base };
+        Ok((AVAILABLE_MIGRATIONS_SQL.to_owned(), vtab))
+    }
+
+    fn destroy(&self) -> Result<()> {
+        Ok(())
+    }
+
+    fn best_index(&self, mut info: IndexInfo) -> core::result::Result<(), BestIndexError> {
+        info.set_estimated_cost(10.0);
+        info.set_estimated_rows(migrations::MIGRATIONS.len() as i64);
+        info.set_idxnum(1);
+        Ok(())
+    }
+
+    fn open(&mut self) -> Result<AvailableMigrationsCursor> {
+        let base: sqlite3_vtab_cursor = unsafe { std::mem::zeroed() };
+        Ok(AvailableMigrationsCursor { base, rowid: 0 })
+    }
+}
+
+#[repr(C)]
+pub struct AvailableMigrationsCursor {
+    base: sqlite3_vtab_cursor,
+    rowid: i64,
+}
+
+impl VTabCursor for AvailableMigrationsCursor {
+    fn filter(
+        &mut self,
+        _idx_num: c_int,
+        _idx_str: Option<&str>,
+        _values: &[*mut sqlite3_value],
+    ) -> Result<()> {
+        self.rowid = 0;
+        Ok(())
+    }
+
+    fn next(&mut self) -> Result<()> {
+        self.rowid += 1;
+        Ok(())
+    }
+
+    fn eof(&self) -> bool {
+        self.rowid as usize >= migrations::MIGRATIONS.len()
+    }
+
+    fn column(&self, context: *mut sqlite3_context, i: c_int) -> Result<()> {
+        let migration = match migrations::MIGRATIONS.get(self.rowid as usize) {
+            Some(migration) => migration,
+            None => {
+                api::result_null(context);
+                return Ok(());
+            }
+        };
+        match i {
+            0 => api::result_int64(context, migration.id),
+            1 => api::result_text(context, migration.introduced_version)?,
+            _ => api::result_null(context),
+        }
+        Ok(())
+    }
+
+    fn rowid(&self) -> Result<i64> {
+        Ok(self.rowid)
+    }
+}
@@ -0,0 +1,397 @@
+use crate::blob;
+use crate::crypto;
+use crate::queue;
+use crate::sql;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use flate2::read::{DeflateDecoder, DeflateEncoder};
+use flate2::Compression;
+use serde_json::Value as JsonValue;
+use sqlite3ext_sys::sqlite3;
+use sqlite_loadable::prelude::*;
+use sqlite_loadable::{api, Error, Result};
+use std::borrow::Cow;
+use std::io::Read;
+
+/// Compressed payloads are stored as `{"$absurd_compressed":"<codec>","data":"<base64>"}`
+/// so the column stays valid JSON.
+const COMPRESSED_PREFIX: &str = "{\"$absurd_compressed\":\"";
+const COMPRESSED_PATH: &str = "$.\"$absurd_compressed\"";
+
+/// User payloads whose top-level object has a key starting with
+/// `RESERVED_KEY_PREFIX` are stored as `{"$absurd_escaped":<payload>}`, so a
+/// stored value that looks like an envelope is always one of ours.
+const ESCAPED_PREFIX: &str = "{\"$absurd_escaped\":";
+const RESERVED_KEY_PREFIX: &str = "$absurd_";
+
+/// Payloads shorter than this are left as-is; the envelope would outweigh
+/// any savings.
+const COMPRESSION_MIN_BYTES: usize = 128;
+
+#[derive(Clone, Copy)]
+enum Codec {
+    Zstd,
+    Deflate,
+}
+
+impl Codec {
+    fn parse(value: &str) -> Option<Codec> {
+        match value {
+            "zstd" => Some(Codec::Zstd),
+            "deflate" => Some(Codec::Deflate),
+            _ => None,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Codec::Zstd => "zstd",
+            Codec::Deflate => "deflate",
+        }
+    }
+
+    fn compress(self, data: &[u8]) -> Result<Vec<u8>> {
+        let mut out = Vec::new();
+        let result = match self {
+            Codec::Zstd => zstd::stream::encode_all(data, 0).map(|bytes| out = bytes),
+            Codec::Deflate => DeflateEncoder::new(data, Compression::default())
+                .read_to_end(&mut out)
+                .map(|_| ()),
+        };
+        result
+            .map_err(|err| Error::new_message(format!("failed to compress payload: {:?}", err)))?;
+        Ok(out)
+    }
+
+    fn decompress(self, data: &[u8]) -> Result<String> {
+        let mut out = String::new();
+        let result = match self {
+            Codec::Zstd => zstd::stream::decode_all(data).and_then(|bytes| {
+                String::from_utf8(bytes)
+                    .map(|text| out = text)
+                    .map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidData, err))
+            }),
+            Codec::Deflate => DeflateDecoder::new(data)
+                .read_to_string(&mut out)
+                .map(|_| ()),
+        };
+        result.map_err(|err| {
+            Error::new_message(format!("failed to decompress payload: {:?}", err))
+        })?;
+        Ok(out)
+    }
+}
+
+/// Codec configured through the queue's `compression` setting, if any.
+fn queue_codec(db: *mut sqlite3, queue_name: &str) -> Result<Option<Codec>> {
+    Ok(queue::queue_setting(db, queue_name, "compression")?
+        .as_deref()
+        .and_then(Codec::parse))
+}
+
+fn compress_json(codec: Codec, json_text: &str) -> Result<String> {
+    let compressed = codec.compress(json_text.as_bytes())?;
+    Ok(format!(
+        "{}{}\",\"data\":\"{}\"}}",
+        COMPRESSED_PREFIX,
+        codec.name(),
+        BASE64.encode(compressed)
+    ))
+}
+
+fn needs_escape(json_text: &str) -> bool {
+    // Cheap pre-check; keys may spell `$` as `\u0024`.
+    if !json_text.contains("absurd_") {
+        return false;
+    }
+    match serde_json::from_str::<JsonValue>(json_text) {
+        Ok(JsonValue::Object(map)) => map.keys().any(|key| key.starts_with(RESERVED_KEY_PREFIX)),
+        _ => false,
+    }
+}
+
+fn strip_escape(raw: &str) -> Option<&str> {
+    raw.strip_prefix(ESCAPED_PREFIX)?.strip_suffix('}')
+}
+
+fn unescape(raw: Cow<'_, str>) -> Cow<'_, str> {
+    match raw {
+        Cow::Borrowed(text) => Cow::Borrowed(strip_escape(text).unwrap_or(text)),
+        Cow::Owned(text) => match strip_escape(&text) {
+            Some(inner) => Cow::Owned(inner.to_string()),
+            None => Cow::Owned(text),
+        },
+    }
+}
+
+/// Prepare a JSON payload for storage on `queue_name`: offload it to the
+/// blob table when it is over the threshold, otherwise compress it when the
+/// queue has a codec configured. Encrypted queues never offload, so no
+/// plaintext reaches the shared blob table; their payloads are compressed
+/// first and then encrypted.
+pub fn encode_json<'a>(
+    db: *mut sqlite3,
+    queue_name: &str,
+    json_text: &'a str,
+) -> Result<Cow<'a, str>> {
+    if needs_escape(json_text) {
+        let escaped = format!("{}{}}}", ESCAPED_PREFIX, json_text);
+        return Ok(Cow::Owned(
+            store_json(db, queue_name, &escaped)?.into_owned(),
+        ));
+    }
+    store_json(db, queue_name, json_text)
+}
+
+/// Prepare task headers for storage on `queue_name`. Encrypted queues store
+/// them like any payload; elsewhere they stay plain JSON so claim filters can
+/// match on them.
+pub fn encode_headers<'a>(
+    db: *mut sqlite3,
+    queue_name: &str,
+    json_text: &'a str,
+) -> Result<Cow<'a, str>> {
+    if queue::encryption_enabled(db, queue_name)? {
+        return encode_json(db, queue_name, json_text);
+    }
+    if needs_escape(json_text) {
+        return Ok(Cow::Owned(format!("{}{}}}", ESCAPED_PREFIX, json_text)));
+    }
+    Ok(Cow::Borrowed(json_text))
+}
+
+fn store_json<'a>(db: *mut sqlite3, queue_name: &str, json_text: &'a str) -> Result<Cow<'a, str>> {
+    let encrypted = queue::encryption_enabled(db, queue_name)?;
+    if !encrypted {
+        let stored = blob::offload_json(db, queue_name, json_text)?;
+        if let Cow::Owned(reference) = stored {
+            return Ok(Cow::Owned(reference));
+        }
+    }
+    let stored = match queue_codec(db, queue_name)? {
+        Some(codec) if json_text.len() >= COMPRESSION_MIN_BYTES => {
+            Cow::Owned(compress_json(codec, json_text)?)
+        }
+        _ => Cow::Borrowed(json_text),
+    };
+    if encrypted {
+        return Ok(Cow::Owned(crypto::encrypt_json(db, &stored)?));
+    }
+    Ok(stored)
+}
+
+/// Undo [`encode_json`] for a `json()` rendering of a stored value.
+pub fn decode_json<'a>(db: *mut sqlite3, raw: &'a str) -> Result<Cow<'a, str>> {
+    Ok(unescape(load_json(db, raw)?))
+}
+
+fn load_json<'a>(db: *mut sqlite3, raw: &'a str) -> Result<Cow<'a, str>> {
+    let raw = if raw.starts_with(crypto::ENCRYPTED_PREFIX) {
+        Cow::Owned(crypto::decrypt_json(db, raw)?)
+    } else {
+        Cow::Borrowed(raw)
+    };
+    if !raw.starts_with(COMPRESSED_PREFIX) {
+        return match raw {
+            Cow::Borrowed(raw) => blob::inline_json(db, raw),
+            Cow::Owned(raw) => Ok(Cow::Owned(blob::inline_json(db, &raw)?.into_owned())),
+        };
+    }
+    let envelope: JsonValue = serde_json::from_str(&raw)
+        .map_err(|err| Error::new_message(format!("invalid compressed payload: {:?}", err)))?;
+    let codec = envelope
+        .get("$absurd_compressed")
+        .and_then(|v| v.as_str())
+        .and_then(Codec::parse)
+        .ok_or_else(|| Error::new_message("unknown payload codec"))?;
+    let data = envelope
+        .get("data")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| Error::new_message("invalid compressed payload"))?;
+    let bytes = BASE64
+        .decode(data)
+        .map_err(|err| Error::new_message(format!("invalid compressed payload: {:?}", err)))?;
+    Ok(Cow::Owned(codec.decompress(&bytes)?))
+}
+
+/// SQL: absurd_decode_payload(json_text)
+/// Usage: return the payload a stored column holds, given its `json()` rendering: undoes escaping, compression, blob offload and encryption. Values encrypted with a key this connection has not loaded come back as the JSON string `"[encrypted with key <key_id>]"`.
+/// Section: Durable
+pub fn absurd_decode_payload(
+    context: *mut sqlite3_context,
+    values: &[*mut sqlite3_value],
+) -> Result<()> {
+    let value = values.first().expect("json_text");
+    if api::value_is_null(value) {
+        api::result_null(context);
+        return Ok(());
+    }
+    if api::value_type(value) == api::ValueType::Blob {
+        return Err(Error::new_message(
+            "absurd_decode_payload expects JSON text; pass json(column)",
+        ));
+    }
+    let raw = api::value_text(value)?;
+    let db = api::context_db_handle(context);
+    if raw.starts_with(crypto::ENCRYPTED_PREFIX) {
+        if let Some(key_id) = crypto::missing_key(db, raw)? {
+            let placeholder = JsonValue::String(format!("[encrypted with key {}]", key_id));
+            return sql::result_json_text(context, &placeholder.to_string());
+        }
+    }
+    sql::result_json_value(db, context, raw)
+}
+
+/// Stored payload columns rewritten by `absurd_compress_payloads`, as
+/// (table, column, key columns).
+const COMPRESSIBLE_COLUMNS: &[(&str, &str, &str)] = &[
+    ("absurd_tasks", "params", "task_id"),
+    ("absurd_tasks", "completed_payload", "task_id"),
+    ("absurd_runs", "result", "run_id"),
+    ("absurd_checkpoints", "state", "task_id, checkpoint_name"),
+];
+
+/// Whether `table.column` holds payloads written through [`encode_json`] or
+/// [`encode_headers`].
+pub fn is_payload_column(table: &str, column: &str) -> bool {
+    is_headers_column(table, column)
+        || COMPRESSIBLE_COLUMNS
+            .iter()
+            .any(|(t, c, _)| *t == table && *c == column)
+}
+
+fn is_headers_column(table: &str, column: &str) -> bool {
+    table == "absurd_tasks" && column == "headers"
+}
+
+/// Encode a value for `table.column` the way the write paths do.
+pub fn encode_column<'a>(
+    db: *mut sqlite3,
+    queue_name: &str,
+    table: &str,
+    column: &str,
+    json_text: &'a str,
+) -> Result<Cow<'a, str>> {
+    if is_headers_column(table, column) {
+        encode_headers(db, queue_name, json_text)
+    } else {
+        encode_json(db, queue_name, json_text)
+    }
+}
+
+/// SQL: absurd_compress_payloads(queue_name[, batch_size])
+/// Usage: compress up to batch_size (default 500) existing uncompressed payloads on a queue with the `compression` setting; returns rows rewritten, call until 0.
+/// Section: Durable
+pub fn absurd_compress_payloads(
+    context: *mut sqlite3_context,
+    values: &[*mut sqlite3_value],
+) -> Result<()> {
+    let queue_name = api::value_text_notnull(values.first().expect("queue_name"))?;
+    let batch_size = match values.get(1) {
+        Some(value) if !api::value_is_null(value) => api::value_int64(value),
+        _ => 500,
+    };
+    if batch_size <= 0 {
+        return Err(Error::new_message("batch_size must be positive"));
+    }
+
+    let db = api::context_db_handle(context);
+    let codec = queue_codec(db, queue_name)?
+        .ok_or_else(|| Error::new_message("compression is not enabled for this queue"))?;
+
+    sql::exec_with_bind_text(db, "begin immediate", &[])?;
+
+    let result = (|| -> Result<i64> {
+        let mut remaining = batch_size;
+        for (table, column, keys) in COMPRESSIBLE_COLUMNS {
+            if remaining == 0 {
+                break;
+            }
+            let rows = uncompressed_rows(db, queue_name, table, column, keys, remaining)?;
+            for (rowid, json_text) in rows {
+                let compressed = compress_json(codec, &json_text)?;
+                sql::exec_with_bind_text(
+                    db,
+                    &format!(
+                        "update {} set {} = jsonb(?1) where rowid = cast(?2 as integer)",
+                        table, column
+                    ),
+                    &[&compressed, &rowid.to_string()],
+                )?;
+                remaining -= 1;
+            }
+        }
+        Ok(batch_size - remaining)
+    })();
+
+    let rewritten = match result {
+        Ok(count) => {
+            sql::exec_with_bind_text(db, "commit", &[])?;
+            count
+        }
+        Err(err) => {
+            let _ = sql::exec_with_bind_text(db, "rollback", &[]);
+            return Err(err);
+        }
+    };
+    api::result_int64(context, rewritten);
+    Ok(())
+}
+
+/// Rows of `table.column` on the queue that are large enough to compress and
+/// are not already compressed, offloaded to a blob or encrypted.
+fn uncompressed_rows(
+    db: *mut sqlite3,
+    queue_name: &str,
+    table: &str,
+    column: &str,
+    keys: &str,
+    limit: i64,
+) -> Result<Vec<(i64, String)>> {
+    let query = format!(
+        "select rowid, json({column})
+           from {table}
+          where queue_name = ?1
+            and {column} is not null
+            and length(json({column})) >= cast(?2 as integer)
+            and {column} ->> ?3 is null
+            and {column} ->> ?4 is null
+            and {column} ->> ?5 is null
+          order by {keys}
+          limit cast(?6 as integer)",
+        column = column,
+        table = table,
+        keys = keys,
+    );
+    let mut stmt = sqlite_loadable::exec::Statement::prepare(db, &query)
+        .map_err(|err| Error::new_message(format!("failed to prepare payload scan: {:?}", err)))?;
+    let min_bytes = COMPRESSION_MIN_BYTES.to_string();
+    let limit_value = limit.to_string();
+    for (idx, value) in [
+        queue_name,
+        &min_bytes,
+        COMPRESSED_PATH,
+        blob::BLOB_REF_PATH,
+        crypto::ENCRYPTED_PATH,
+        &limit_value,
+    ]
+    .iter()
+    .enumerate()
+    {
+        stmt.bind_text((idx + 1) as i32, value)
+            .map_err(|err| Error::new_message(format!("failed to bind parameter: {:?}", err)))?;
+    }
+    let mut rows = Vec::new();
+    for row in stmt.execute() {
+        let row =
+            row.map_err(|err| Error::new_message(format!("failed to read row: {:?}", err)))?;
+        let rowid = row
+            .get::<i64>(0)
+            .map_err(|err| Error::new_message(format!("failed to read rowid: {:?}", err)))?;
+        let json_text = row
+            .get::<String>(1)
+            .map_err(|err| Error::new_message(format!("failed to read payload: {:?}", err)))?;
+        rows.push((rowid, json_text));
+    }
+    Ok(rows)
+}
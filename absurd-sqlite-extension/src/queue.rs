@@ -1,3 +1,5 @@
+use crate::sql;
+use crate::validate;
 use sqlite3ext_sys::sqlite3;
 use sqlite_loadable::prelude::*;
 use sqlite_loadable::{
@@ -7,6 +9,203 @@ use sqlite_loadable::{
 };
 use std::os::raw::c_int;
 
+const QUEUE_SETTING_KEYS: &[&str] = &[
+    "event_log",
+    "checkpoint_history",
+    "offload_threshold_bytes",
+    "compression",
+    "encryption",
+    "ordering",
+    "paused",
+    "completed_retention",
+    "failed_retention",
+    "cancelled_retention",
+    "event_retention",
+];
+
+/// Queue settings that hold a retention period in seconds.
+pub const RETENTION_SETTING_KEYS: &[&str] = &[
+    "completed_retention",
+    "failed_retention",
+    "cancelled_retention",
+    "event_retention",
+];
+
+/// Parse a retention period such as `7d`, `12h`, `30m`, `45s` or a plain
+/// number of seconds.
+fn parse_retention_secs(value: &str) -> Option<i64> {
+    let value = value.trim().to_ascii_lowercase();
+    let (digits, unit) = match value.char_indices().last()? {
+        (idx, 's') => (&value[..idx], 1),
+        (idx, 'm') => (&value[..idx], 60),
+        (idx, 'h') => (&value[..idx], 60 * 60),
+        (idx, 'd') => (&value[..idx], 24 * 60 * 60),
+        (idx, 'w') => (&value[..idx], 7 * 24 * 60 * 60),
+        _ => (value.as_str(), 1),
+    };
+    let amount = digits.trim().parse::<i64>().ok()?;
+    if amount < 0 {
+        return None;
+    }
+    amount.checked_mul(unit)
+}
+
+/// Normalize a queue setting value for storage.
+fn normalize_queue_setting(key: &str, value: &str) -> Result<String> {
+    match key {
+        "event_log" | "checkpoint_history" | "encryption" | "paused" => {
+            match value.trim().to_ascii_lowercase().as_str() {
+                "1" | "true" | "on" => Ok("1".to_string()),
+                "0" | "false" | "off" => Ok("0".to_string()),
+                _ => Err(Error::new_message(format!("{} must be a boolean", key))),
+            }
+        }
+        "compression" => match value.trim().to_ascii_lowercase().as_str() {
+            codec @ ("zstd" | "deflate" | "none") => Ok(codec.to_string()),
+            _ => Err(Error::new_message(
+                "compression must be one of zstd, deflate or none",
+            )),
+        },
+        "ordering" => match value.trim().to_ascii_lowercase().as_str() {
+            ordering @ ("fifo" | "lifo" | "priority") => Ok(ordering.to_string()),
+            _ => Err(Error::new_message(
+                "ordering must be one of fifo, lifo or priority",
+            )),
+        },
+        "completed_retention" | "failed_retention" | "cancelled_retention" | "event_retention" => {
+            match parse_retention_secs(value) {
+                Some(secs) => Ok(secs.to_string()),
+                None => Err(Error::new_message(format!(
+                    "{} must be a duration like 7d, 12h, 30m, 45s or seconds",
+                    key
+                ))),
+            }
+        }
+        "offload_threshold_bytes" => match value.trim().parse::<i64>() {
+            Ok(bytes) if bytes > 0 => Ok(bytes.to_string()),
+            _ => Err(Error::new_message(
+                "offload_threshold_bytes must be a positive integer",
+            )),
+        },
+        _ => Err(Error::new_message(format!(
+            "unknown queue setting: {}",
+            key
+        ))),
+    }
+}
+
+/// Read a per-queue setting, if it has been set.
+pub fn queue_setting(db: *mut sqlite3, queue_name: &str, key: &str) -> Result<Option<String>> {
+    let mut stmt = sqlite_loadable::exec::Statement::prepare(
+        db,
+        "select value from absurd_queue_settings where queue_name = ?1 and key = ?2",
+    )
+    .map_err(|err| Error::new_message(format!("failed to prepare queue setting: {:?}", err)))?;
+    stmt.bind_text(1, queue_name)
+        .map_err(|err| Error::new_message(format!("failed to bind queue_name: {:?}", err)))?;
+    stmt.bind_text(2, key)
+        .map_err(|err| Error::new_message(format!("failed to bind key: {:?}", err)))?;
+    let mut rows = stmt.execute();
+    match rows.next() {
+        Some(Ok(row)) => row
+            .get::<String>(0)
+            .map(Some)
+            .map_err(|err| Error::new_message(format!("failed to read queue setting: {:?}", err))),
+        Some(Err(err)) => Err(Error::new_message(format!(
+            "failed to read queue setting: {:?}",
+            err
+        ))),
+        None => Ok(None),
+    }
+}
+
+/// Whether the queue keeps an append-only log of event emissions.
+pub fn event_log_enabled(db: *mut sqlite3, queue_name: &str) -> Result<bool> {
+    Ok(queue_setting(db, queue_name, "event_log")?.as_deref() == Some("1"))
+}
+
+/// Whether every checkpoint write on the queue is also appended to history.
+pub fn checkpoint_history_enabled(db: *mut sqlite3, queue_name: &str) -> Result<bool> {
+    Ok(queue_setting(db, queue_name, "checkpoint_history")?.as_deref() == Some("1"))
+}
+
+/// Whether payloads on the queue are encrypted with the connection's key.
+pub fn encryption_enabled(db: *mut sqlite3, queue_name: &str) -> Result<bool> {
+    Ok(queue_setting(db, queue_name, "encryption")?.as_deref() == Some("1"))
+}
+
+/// Whether claims on the queue are suspended. Queued work is kept and
+/// claim expiry still runs; nothing new is handed out until resumed.
+pub fn queue_paused(db: *mut sqlite3, queue_name: &str) -> Result<bool> {
+    Ok(queue_setting(db, queue_name, "paused")?.as_deref() == Some("1"))
+}
+
+/// Order in which claims hand out runs: `fifo` (default), `lifo` or
+/// `priority` (highest task priority first, then fifo).
+pub fn claim_ordering(db: *mut sqlite3, queue_name: &str) -> Result<String> {
+    Ok(queue_setting(db, queue_name, "ordering")?.unwrap_or_else(|| "fifo".to_string()))
+}
+
+/// Retention period in seconds for one of [`RETENTION_SETTING_KEYS`], if set.
+pub fn retention_secs(db: *mut sqlite3, queue_name: &str, key: &str) -> Result<Option<i64>> {
+    Ok(queue_setting(db, queue_name, key)?.and_then(|value| value.parse::<i64>().ok()))
+}
+
+/// SQL: absurd_set_queue_setting(queue_name, key, value_or_null)
+/// Usage: set or clear a per-queue setting (`event_log`, `checkpoint_history`, `offload_threshold_bytes`, `compression`, `encryption`, `ordering`, `paused`, `completed_retention`, `failed_retention`, `cancelled_retention`, `event_retention`).
+/// Section: Durable
+pub fn absurd_set_queue_setting(
+    context: *mut sqlite3_context,
+    values: &[*mut sqlite3_value],
+) -> Result<()> {
+    let queue_name = api::value_text_notnull(values.first().expect("queue_name"))?;
+    let key = api::value_text_notnull(values.get(1).expect("key"))?.trim();
+    validate::queue_name(queue_name)?;
+    if !QUEUE_SETTING_KEYS.contains(&key) {
+        return Err(Error::new_message(format!(
+            "unknown queue setting: {}",
+            key
+        )));
+    }
+
+    let value = values.get(2).expect("value");
+    let normalized = if api::value_is_null(value) {
+        None
+    } else {
+        let raw = api::value_text(value)
+            .map_err(|err| Error::new_message(format!("invalid setting value: {:?}", err)))?;
+        Some(normalize_queue_setting(key, raw)?)
+    };
+
+    let db = api::context_db_handle(context);
+    let exists = sql::query_row_i64(
+        db,
+        "select count(*) from absurd_queues where queue_name = ?1",
+        &[queue_name],
+    )?;
+    if exists == 0 {
+        return Err(Error::new_message("Queue not found"));
+    }
+
+    match normalized {
+        Some(value) => sql::exec_with_bind_text(
+            db,
+            "insert into absurd_queue_settings (queue_name, key, value)
+             values (?1, ?2, ?3)
+             on conflict (queue_name, key) do update set value = excluded.value",
+            &[queue_name, key, &value],
+        )?,
+        None => sql::exec_with_bind_text(
+            db,
+            "delete from absurd_queue_settings where queue_name = ?1 and key = ?2",
+            &[queue_name, key],
+        )?,
+    }
+
+    api::result_int64(context, 1);
+    Ok(())
+}
+
 const LIST_QUEUES_SQL: &str = "CREATE TABLE x(queue_name TEXT, created_at INTEGER)";
 
 struct QueueRow {
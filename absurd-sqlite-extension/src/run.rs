@@ -1,6 +1,11 @@
+use crate::archive;
+use crate::blob;
+use crate::event;
+use crate::payload;
 use crate::retry;
 use crate::sql;
 use crate::validate;
+use crate::worker;
 use chrono::DateTime;
 use serde_json::Value as JsonValue;
 use sqlite3ext_sys::sqlite3;
@@ -55,7 +60,7 @@ fn parse_required_int(value: &*mut sqlite3_value, label: &str) -> Result<i64> {
     }
 }
 
-fn parse_required_ttl_secs(value: &*mut sqlite3_value, label: &str) -> Result<i64> {
+pub fn parse_required_ttl_secs(value: &*mut sqlite3_value, label: &str) -> Result<i64> {
     let ttl = parse_required_int(value, label)?;
     if ttl < 0 {
         return Err(Error::new_message(
@@ -65,7 +70,7 @@ fn parse_required_ttl_secs(value: &*mut sqlite3_value, label: &str) -> Result<i6
     Ok(ttl)
 }
 
-fn parse_optional_limit(value: Option<*mut sqlite3_value>) -> Result<i64> {
+pub fn parse_optional_limit(value: Option<*mut sqlite3_value>) -> Result<i64> {
     let value = match value {
         Some(value) => value,
         None => return Ok(1000),
@@ -163,6 +168,7 @@ fn fail_run_impl(
             "update absurd_runs
                 set state = 'failed',
                     wake_event = null,
+                    wake_step = null,
                     failed_at = cast(?1 as integer),
                     failure_reason = jsonb(?2),
                     claimed_by = null,
@@ -277,13 +283,15 @@ fn fail_run_impl(
             ],
         )?;
 
-        sql::exec_with_bind_text(
-            db,
-            "delete from absurd_waits
-              where queue_name = ?1
-                and run_id = ?2",
-            &[queue_name, run_id],
-        )?;
+        for table in event::WAIT_TABLES {
+            let delete_sql = format!(
+                "delete from {}
+                  where queue_name = ?1
+                    and run_id = ?2",
+                table
+            );
+            sql::exec_with_bind_text(db, &delete_sql, &[queue_name, run_id])?;
+        }
 
         Ok(())
     })();
@@ -365,6 +373,7 @@ pub fn absurd_complete_run(
             return Err(Error::new_message("run is not currently running"));
         }
 
+        let state_text = payload::encode_json(db, queue_name, &state_text)?;
         sql::exec_with_bind_text(
             db,
             "update absurd_runs
@@ -387,13 +396,15 @@ pub fn absurd_complete_run(
             &[&state_text, run_id, queue_name, &task_id],
         )?;
 
-        sql::exec_with_bind_text(
-            db,
-            "delete from absurd_waits
-              where queue_name = ?1
-                and run_id = ?2",
-            &[queue_name, run_id],
-        )?;
+        for table in event::WAIT_TABLES {
+            let delete_sql = format!(
+                "delete from {}
+                  where queue_name = ?1
+                    and run_id = ?2",
+                table
+            );
+            sql::exec_with_bind_text(db, &delete_sql, &[queue_name, run_id])?;
+        }
 
         Ok(())
     })();
@@ -465,7 +476,8 @@ pub fn absurd_extend_claim(
     let result = (|| -> Result<()> {
         let mut stmt = sqlite_loadable::exec::Statement::prepare(
             db,
-            "select t.state
+            "select t.state,
+                    coalesce(r.claimed_by, '') as claimed_by
                from absurd_runs r
                join absurd_tasks t
                  on t.queue_name = r.queue_name
@@ -486,6 +498,9 @@ pub fn absurd_extend_claim(
         let task_state = row
             .get::<String>(0)
             .map_err(|err| Error::new_message(format!("failed to read state: {:?}", err)))?;
+        let claimed_by = row
+            .get::<String>(1)
+            .map_err(|err| Error::new_message(format!("failed to read claimed_by: {:?}", err)))?;
 
         if task_state == "cancelled" {
             return Err(Error::new_message("Task has been cancelled"));
@@ -507,6 +522,10 @@ pub fn absurd_extend_claim(
             return Err(Error::new_message("run is not currently running"));
         }
 
+        if !claimed_by.is_empty() {
+            worker::touch_worker(db, &claimed_by, queue_name, now)?;
+        }
+
         Ok(())
     })();
 
@@ -522,6 +541,101 @@ pub fn absurd_extend_claim(
     }
 }
 
+/// Put the running runs selected by `filter_sql` back to pending without
+/// counting a new attempt. `filter_sql` is bound with `?2` as its only parameter.
+fn release_runs_impl(
+    db: *mut sqlite3,
+    queue_name: &str,
+    filter_sql: &str,
+    filter_value: &str,
+) -> Result<i64> {
+    let now_value = sql::now_ms_from_db(db).to_string();
+
+    sql::exec_with_bind_text(db, "begin immediate", &[])?;
+
+    let result = (|| -> Result<i64> {
+        let update_tasks_sql = format!(
+            "update absurd_tasks
+                set state = 'pending'
+              where queue_name = ?1
+                and state = 'running'
+                and task_id in (
+                  select task_id
+                    from absurd_runs
+                   where queue_name = ?1
+                     and state = 'running'
+                     and {}
+                )",
+            filter_sql
+        );
+        sql::exec_with_bind_text(db, &update_tasks_sql, &[queue_name, filter_value])?;
+
+        let update_runs_sql = format!(
+            "update absurd_runs
+                set state = 'pending',
+                    claimed_by = null,
+                    claim_expires_at = null,
+                    available_at = cast(?3 as integer)
+              where queue_name = ?1
+                and state = 'running'
+                and {}",
+            filter_sql
+        );
+        sql::exec_with_bind_text(
+            db,
+            &update_runs_sql,
+            &[queue_name, filter_value, &now_value],
+        )?;
+
+        sql::query_row_i64(db, "select changes()", &[])
+    })();
+
+    match result {
+        Ok(released) => {
+            sql::exec_with_bind_text(db, "commit", &[])?;
+            Ok(released)
+        }
+        Err(err) => {
+            let _ = sql::exec_with_bind_text(db, "rollback", &[]);
+            Err(err)
+        }
+    }
+}
+
+/// SQL: absurd_release_run(queue_name, run_id)
+/// Usage: return a running run to pending without counting an attempt; returns 1 if released.
+/// Section: Durable
+pub fn absurd_release_run(
+    context: *mut sqlite3_context,
+    values: &[*mut sqlite3_value],
+) -> Result<()> {
+    let queue_name = api::value_text_notnull(values.first().expect("queue_name"))?;
+    let run_id = api::value_text_notnull(values.get(1).expect("run_id"))?;
+
+    validate::queue_name(queue_name)?;
+    let db = api::context_db_handle(context);
+    let released = release_runs_impl(db, queue_name, "run_id = ?2", run_id)?;
+    api::result_int64(context, released);
+    Ok(())
+}
+
+/// SQL: absurd_release_worker_claims(queue_name, worker_id)
+/// Usage: return every run held by a worker to pending without counting attempts; returns the count.
+/// Section: Durable
+pub fn absurd_release_worker_claims(
+    context: *mut sqlite3_context,
+    values: &[*mut sqlite3_value],
+) -> Result<()> {
+    let queue_name = api::value_text_notnull(values.first().expect("queue_name"))?;
+    let worker_id = api::value_text_notnull(values.get(1).expect("worker_id"))?;
+
+    validate::queue_name(queue_name)?;
+    let db = api::context_db_handle(context);
+    let released = release_runs_impl(db, queue_name, "claimed_by = ?2", worker_id)?;
+    api::result_int64(context, released);
+    Ok(())
+}
+
 /// SQL: absurd_schedule_run(queue_name, run_id, wake_at_ms)
 /// Usage: put a running run to sleep until the given timestamp.
 /// Section: Durable
@@ -569,7 +683,8 @@ pub fn absurd_schedule_run(
                     claimed_by = null,
                     claim_expires_at = null,
                     available_at = cast(?1 as integer),
-                    wake_event = null
+                    wake_event = null,
+                    wake_step = null
               where queue_name = ?2
                 and run_id = ?3",
             &[&wake_at_value, queue_name, run_id],
@@ -600,7 +715,7 @@ pub fn absurd_schedule_run(
 }
 
 /// SQL: absurd_cleanup_tasks(queue_name, ttl_seconds[, limit])
-/// Usage: delete terminal tasks older than TTL, including runs/checkpoints/waits.
+/// Usage: delete terminal tasks older than TTL, including runs/checkpoints/waits, then drop unreferenced payload blobs.
 /// Section: Durable
 ///
 /// Remove terminal tasks for a queue that are older than the TTL cutoff.
@@ -624,7 +739,49 @@ pub fn absurd_cleanup_tasks(
     let db = api::context_db_handle(context);
     let now = sql::now_ms_from_db(db);
     let cutoff = now - ttl_seconds.saturating_mul(1000);
-    let cutoff_value = cutoff.to_string();
+    let cutoffs = TerminalCutoffs {
+        completed: Some(cutoff),
+        failed: Some(cutoff),
+        cancelled: Some(cutoff),
+    };
+
+    let deleted = cleanup_tasks_impl(db, queue_name, &cutoffs, limit)?;
+    api::result_int64(context, deleted);
+    Ok(())
+}
+
+/// Per-state cutoffs (ms) for task cleanup; a state without a cutoff is kept.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct TerminalCutoffs {
+    pub completed: Option<i64>,
+    pub failed: Option<i64>,
+    pub cancelled: Option<i64>,
+}
+
+/// Delete up to `limit` terminal tasks whose terminal time is older than the
+/// cutoff for their state. Returns the number of tasks deleted.
+pub fn cleanup_tasks_impl(
+    db: *mut sqlite3,
+    queue_name: &str,
+    cutoffs: &TerminalCutoffs,
+    limit: i64,
+) -> Result<i64> {
+    purge_terminal_tasks(db, queue_name, cutoffs, limit, None)
+}
+
+/// Shared body of cleanup and archiving: when `archive_schema` is set, the
+/// selected tasks are copied into that attached database before deletion.
+pub fn purge_terminal_tasks(
+    db: *mut sqlite3,
+    queue_name: &str,
+    cutoffs: &TerminalCutoffs,
+    limit: i64,
+    archive_schema: Option<&str>,
+) -> Result<i64> {
+    let cutoff_text = |cutoff: Option<i64>| cutoff.map(|c| c.to_string()).unwrap_or_default();
+    let completed_value = cutoff_text(cutoffs.completed);
+    let failed_value = cutoff_text(cutoffs.failed);
+    let cancelled_value = cutoff_text(cutoffs.cancelled);
     let limit_value = limit.to_string();
 
     sql::exec_with_bind_text(db, "pragma foreign_keys = off", &[])?;
@@ -650,6 +807,7 @@ pub fn absurd_cleanup_tasks(
              select task_id
                from (
                  select t.task_id as task_id,
+                        t.state as state,
                         case
                           when t.state = 'completed' then r.completed_at
                           when t.state = 'failed' then r.failed_at
@@ -664,21 +822,44 @@ pub fn absurd_cleanup_tasks(
                     and t.state in ('completed','failed','cancelled')
                )
               where terminal_at is not null
-                and terminal_at < cast(?2 as integer)
+                and terminal_at < case state
+                                    when 'completed' then cast(nullif(?2, '') as integer)
+                                    when 'failed' then cast(nullif(?3, '') as integer)
+                                    when 'cancelled' then cast(nullif(?4, '') as integer)
+                                  end
               order by terminal_at
-              limit cast(?3 as integer)",
+              limit cast(?5 as integer)",
             temp_table
         );
-        sql::exec_with_bind_text(db, &insert_sql, &[queue_name, &cutoff_value, &limit_value])?;
+        sql::exec_with_bind_text(
+            db,
+            &insert_sql,
+            &[
+                queue_name,
+                &completed_value,
+                &failed_value,
+                &cancelled_value,
+                &limit_value,
+            ],
+        )?;
+
+        if let Some(schema) = archive_schema {
+            archive::copy_selected_tasks(db, schema, queue_name, &temp_table)?;
+        }
 
         // Delete dependent rows first, then tasks, while foreign key checks are disabled.
-        let delete_waits_sql = format!(
-            "delete from absurd_waits
-              where queue_name = ?1
-                and task_id in (select task_id from {})",
-            temp_table
-        );
-        sql::exec_with_bind_text(db, &delete_waits_sql, &[queue_name])?;
+        for table in event::WAIT_TABLES
+            .iter()
+            .chain(["absurd_signals", "absurd_checkpoint_history"].iter())
+        {
+            let delete_sql = format!(
+                "delete from {}
+                  where queue_name = ?1
+                    and task_id in (select task_id from {})",
+                table, temp_table
+            );
+            sql::exec_with_bind_text(db, &delete_sql, &[queue_name])?;
+        }
 
         let delete_checkpoints_sql = format!(
             "delete from absurd_checkpoints
@@ -706,28 +887,26 @@ pub fn absurd_cleanup_tasks(
 
         let deleted = sql::query_row_i64(db, "select changes()", &[])?;
         sql::exec_batch(db, &drop_sql)?;
+        blob::collect_garbage(db)?;
         Ok(deleted)
     })();
 
-    let deleted = match deleted {
+    match deleted {
         Ok(count) => {
             sql::exec_with_bind_text(db, "commit", &[])?;
             sql::exec_with_bind_text(db, "pragma foreign_keys = on", &[])?;
-            count
+            Ok(count)
         }
         Err(err) => {
             let _ = sql::exec_with_bind_text(db, "rollback", &[]);
             let _ = sql::exec_with_bind_text(db, "pragma foreign_keys = on", &[]);
-            return Err(err);
+            Err(err)
         }
-    };
-
-    api::result_int64(context, deleted);
-    Ok(())
+    }
 }
 
 /// SQL: absurd_cleanup_events(queue_name, ttl_seconds[, limit])
-/// Usage: delete events older than TTL.
+/// Usage: delete events (and event log entries) older than TTL.
 /// Section: Durable
 pub fn absurd_cleanup_events(
     context: *mut sqlite3_context,
@@ -741,6 +920,20 @@ pub fn absurd_cleanup_events(
     let db = api::context_db_handle(context);
     let now = sql::now_ms_from_db(db);
     let cutoff = now - ttl_seconds.saturating_mul(1000);
+
+    let deleted = cleanup_events_impl(db, queue_name, cutoff, limit)?;
+    api::result_int64(context, deleted);
+    Ok(())
+}
+
+/// Delete up to `limit` events and up to `limit` event log entries emitted
+/// before `cutoff` (ms). Returns the number of rows deleted.
+pub fn cleanup_events_impl(
+    db: *mut sqlite3,
+    queue_name: &str,
+    cutoff: i64,
+    limit: i64,
+) -> Result<i64> {
     let cutoff_value = cutoff.to_string();
     let limit_value = limit.to_string();
 
@@ -760,23 +953,36 @@ pub fn absurd_cleanup_events(
               )",
             &[queue_name, &cutoff_value, &limit_value],
         )?;
+        let events_deleted = sql::query_row_i64(db, "select changes()", &[])?;
 
-        sql::query_row_i64(db, "select changes()", &[])
+        sql::exec_with_bind_text(
+            db,
+            "delete from absurd_event_log
+              where rowid in (
+                select rowid
+                  from absurd_event_log
+                 where queue_name = ?1
+                   and emitted_at < cast(?2 as integer)
+                 order by seq
+                 limit cast(?3 as integer)
+              )",
+            &[queue_name, &cutoff_value, &limit_value],
+        )?;
+        let log_deleted = sql::query_row_i64(db, "select changes()", &[])?;
+
+        Ok(events_deleted + log_deleted)
     })();
 
-    let deleted = match deleted {
+    match deleted {
         Ok(count) => {
             sql::exec_with_bind_text(db, "commit", &[])?;
-            count
+            Ok(count)
         }
         Err(err) => {
             let _ = sql::exec_with_bind_text(db, "rollback", &[]);
-            return Err(err);
+            Err(err)
         }
-    };
-
-    api::result_int64(context, deleted);
-    Ok(())
+    }
 }
 
 /// SQL: absurd_cancel_task(queue_name, task_id)
@@ -843,20 +1049,107 @@ pub fn absurd_cancel_task(
             &[queue_name, task_id],
         )?;
 
-        sql::exec_with_bind_text(
+        for table in event::WAIT_TABLES {
+            let delete_sql = format!(
+                "delete from {}
+                  where queue_name = ?1
+                    and task_id = ?2",
+                table
+            );
+            sql::exec_with_bind_text(db, &delete_sql, &[queue_name, task_id])?;
+        }
+
+        Ok(())
+    })();
+
+    match result {
+        Ok(()) => {
+            sql::exec_with_bind_text(db, "commit", &[])?;
+            Ok(())
+        }
+        Err(err) => {
+            let _ = sql::exec_with_bind_text(db, "rollback", &[]);
+            Err(err)
+        }
+    }
+}
+
+/// SQL: absurd_retry_task(queue_name, task_id)
+/// Usage: re-enqueue a failed or cancelled task as a new pending run with its max_delay/max_duration clocks restarted; returns the new run_id. The retry is one extra attempt: it is not refused by max_attempts, but if the budget is already spent a failure ends the task instead of retrying again.
+/// Section: Durable
+pub fn absurd_retry_task(
+    context: *mut sqlite3_context,
+    values: &[*mut sqlite3_value],
+) -> Result<()> {
+    let queue_name = api::value_text_notnull(values.first().expect("queue_name"))?;
+    let task_id = api::value_text_notnull(values.get(1).expect("task_id"))?;
+
+    validate::queue_name(queue_name)?;
+    let db = api::context_db_handle(context);
+    let now_value = sql::now_ms_from_db(db).to_string();
+
+    sql::exec_with_bind_text(db, "begin immediate", &[])?;
+
+    let result = (|| -> Result<String> {
+        let mut stmt = sqlite_loadable::exec::Statement::prepare(
             db,
-            "delete from absurd_waits
+            "select state, attempts
+               from absurd_tasks
               where queue_name = ?1
                 and task_id = ?2",
-            &[queue_name, task_id],
-        )?;
+        )
+        .map_err(|err| Error::new_message(format!("failed to prepare task lookup: {:?}", err)))?;
+        stmt.bind_text(1, queue_name)
+            .map_err(|err| Error::new_message(format!("failed to bind queue_name: {:?}", err)))?;
+        stmt.bind_text(2, task_id)
+            .map_err(|err| Error::new_message(format!("failed to bind task_id: {:?}", err)))?;
+        let row = stmt
+            .execute()
+            .next()
+            .ok_or_else(|| Error::new_message("Task not found"))?
+            .map_err(|err| Error::new_message(format!("failed to read task row: {:?}", err)))?;
+        let task_state = row
+            .get::<String>(0)
+            .map_err(|err| Error::new_message(format!("failed to read task state: {:?}", err)))?;
+        let attempts = row
+            .get::<i64>(1)
+            .map_err(|err| Error::new_message(format!("failed to read attempts: {:?}", err)))?;
 
-        Ok(())
+        if task_state != "failed" && task_state != "cancelled" {
+            return Err(Error::new_message(
+                "only failed or cancelled tasks can be retried",
+            ));
+        }
+
+        let run_id = Uuid::now_v7().to_string();
+        let attempt_value = (attempts + 1).to_string();
+        sql::exec_with_bind_text(
+            db,
+            "insert into absurd_runs (queue_name, run_id, task_id, attempt, state, available_at)
+             values (?1, ?2, ?3, cast(?4 as integer), 'pending', cast(?5 as integer))",
+            &[queue_name, &run_id, task_id, &attempt_value, &now_value],
+        )?;
+        sql::exec_with_bind_text(
+            db,
+            "update absurd_tasks
+                set state = 'pending',
+                    attempts = cast(?1 as integer),
+                    last_attempt_run = ?2,
+                    cancelled_at = null,
+                    completed_payload = null,
+                    enqueue_at = cast(?3 as integer),
+                    first_started_at = null
+              where queue_name = ?4
+                and task_id = ?5",
+            &[&attempt_value, &run_id, &now_value, queue_name, task_id],
+        )?;
+        Ok(run_id)
     })();
 
     match result {
-        Ok(()) => {
+        Ok(run_id) => {
             sql::exec_with_bind_text(db, "commit", &[])?;
+            api::result_text(context, &run_id)?;
             Ok(())
         }
         Err(err) => {
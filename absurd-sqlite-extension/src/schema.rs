@@ -0,0 +1,394 @@
+use regex::Regex;
+use serde_json::{Map, Value as JsonValue};
+use std::fmt;
+
+/// Keywords that change validation semantics but are not implemented here.
+/// Schemas using them are rejected at registration instead of being
+/// silently under-enforced.
+const UNSUPPORTED_KEYWORDS: &[&str] = &[
+    "$ref",
+    "$dynamicRef",
+    "patternProperties",
+    "propertyNames",
+    "prefixItems",
+    "contains",
+    "dependentRequired",
+    "dependentSchemas",
+    "if",
+    "unevaluatedProperties",
+    "unevaluatedItems",
+];
+
+const TYPE_NAMES: &[&str] = &[
+    "null", "boolean", "object", "array", "number", "integer", "string",
+];
+
+/// The first place a value fails its schema, with a `$.a.b[0]` style path.
+pub struct Violation {
+    pub path: String,
+    pub message: String,
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.path, self.message)
+    }
+}
+
+fn push_key(path: &str, key: &str) -> String {
+    let plain = !key.is_empty()
+        && !key.starts_with(|c: char| c.is_ascii_digit())
+        && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+    if plain {
+        format!("{}.{}", path, key)
+    } else {
+        format!("{}[{}]", path, JsonValue::String(key.to_string()))
+    }
+}
+
+fn violation(path: &str, message: impl Into<String>) -> Violation {
+    Violation {
+        path: path.to_string(),
+        message: message.into(),
+    }
+}
+
+/// Check that `schema` uses only the supported subset of JSON Schema and
+/// that its keywords have the expected shapes.
+pub fn check_schema(schema: &JsonValue) -> Result<(), Violation> {
+    check_schema_at(schema, "$")
+}
+
+fn check_schema_at(schema: &JsonValue, path: &str) -> Result<(), Violation> {
+    let obj = match schema {
+        JsonValue::Bool(_) => return Ok(()),
+        JsonValue::Object(obj) => obj,
+        _ => return Err(violation(path, "schema must be an object or boolean")),
+    };
+    for keyword in UNSUPPORTED_KEYWORDS {
+        if obj.contains_key(*keyword) {
+            return Err(violation(
+                path,
+                format!("unsupported schema keyword: {}", keyword),
+            ));
+        }
+    }
+    if let Some(types) = obj.get("type") {
+        let names: Vec<&JsonValue> = match types {
+            JsonValue::Array(items) => items.iter().collect(),
+            other => vec![other],
+        };
+        for name in names {
+            if !name.as_str().is_some_and(|name| TYPE_NAMES.contains(&name)) {
+                return Err(violation(&push_key(path, "type"), "unknown type"));
+            }
+        }
+    }
+    if let Some(properties) = obj.get("properties") {
+        let properties = properties
+            .as_object()
+            .ok_or_else(|| violation(&push_key(path, "properties"), "must be an object"))?;
+        let base = push_key(path, "properties");
+        for (key, sub) in properties {
+            check_schema_at(sub, &push_key(&base, key))?;
+        }
+    }
+    if let Some(required) = obj.get("required") {
+        let valid = required
+            .as_array()
+            .is_some_and(|items| items.iter().all(|v| v.is_string()));
+        if !valid {
+            return Err(violation(
+                &push_key(path, "required"),
+                "must be an array of strings",
+            ));
+        }
+    }
+    for keyword in ["additionalProperties", "items", "not"] {
+        if let Some(sub) = obj.get(keyword) {
+            check_schema_at(sub, &push_key(path, keyword))?;
+        }
+    }
+    for keyword in ["allOf", "anyOf", "oneOf"] {
+        if let Some(subs) = obj.get(keyword) {
+            let base = push_key(path, keyword);
+            let subs = subs
+                .as_array()
+                .filter(|subs| !subs.is_empty())
+                .ok_or_else(|| violation(&base, "must be a non-empty array"))?;
+            for (idx, sub) in subs.iter().enumerate() {
+                check_schema_at(sub, &format!("{}[{}]", base, idx))?;
+            }
+        }
+    }
+    if let Some(values) = obj.get("enum") {
+        if !values.is_array() {
+            return Err(violation(&push_key(path, "enum"), "must be an array"));
+        }
+    }
+    for keyword in [
+        "minimum",
+        "maximum",
+        "exclusiveMinimum",
+        "exclusiveMaximum",
+        "multipleOf",
+    ] {
+        if obj.get(keyword).is_some_and(|v| !v.is_number()) {
+            return Err(violation(&push_key(path, keyword), "must be a number"));
+        }
+    }
+    for keyword in [
+        "minLength",
+        "maxLength",
+        "minItems",
+        "maxItems",
+        "minProperties",
+        "maxProperties",
+    ] {
+        if obj.get(keyword).is_some_and(|v| !v.is_u64()) {
+            return Err(violation(
+                &push_key(path, keyword),
+                "must be a non-negative integer",
+            ));
+        }
+    }
+    if let Some(pattern) = obj.get("pattern") {
+        let pattern = pattern
+            .as_str()
+            .ok_or_else(|| violation(&push_key(path, "pattern"), "must be a string"))?;
+        Regex::new(pattern)
+            .map_err(|err| violation(&push_key(path, "pattern"), err.to_string()))?;
+    }
+    Ok(())
+}
+
+fn type_name(value: &JsonValue) -> &'static str {
+    match value {
+        JsonValue::Null => "null",
+        JsonValue::Bool(_) => "boolean",
+        JsonValue::Number(n) if n.is_i64() || n.is_u64() => "integer",
+        JsonValue::Number(_) => "number",
+        JsonValue::String(_) => "string",
+        JsonValue::Array(_) => "array",
+        JsonValue::Object(_) => "object",
+    }
+}
+
+fn matches_type(value: &JsonValue, name: &str) -> bool {
+    match name {
+        "integer" => value.as_f64().is_some_and(|n| n.fract() == 0.0),
+        "number" => value.is_number(),
+        other => type_name(value) == other,
+    }
+}
+
+/// Validate `instance` against a schema accepted by [`check_schema`],
+/// returning the first violation found.
+pub fn validate(schema: &JsonValue, instance: &JsonValue) -> Result<(), Violation> {
+    validate_at(schema, instance, "$")
+}
+
+fn validate_at(schema: &JsonValue, instance: &JsonValue, path: &str) -> Result<(), Violation> {
+    let obj = match schema {
+        JsonValue::Bool(true) => return Ok(()),
+        JsonValue::Bool(false) => return Err(violation(path, "no value is allowed here")),
+        JsonValue::Object(obj) => obj,
+        _ => return Ok(()),
+    };
+
+    if let Some(types) = obj.get("type") {
+        let names: Vec<&str> = match types {
+            JsonValue::Array(items) => items.iter().filter_map(|v| v.as_str()).collect(),
+            other => other.as_str().into_iter().collect(),
+        };
+        if !names.iter().any(|name| matches_type(instance, name)) {
+            return Err(violation(
+                path,
+                format!(
+                    "expected {}, got {}",
+                    names.join(" or "),
+                    type_name(instance)
+                ),
+            ));
+        }
+    }
+    if let Some(expected) = obj.get("const") {
+        if expected != instance {
+            return Err(violation(path, format!("must equal {}", expected)));
+        }
+    }
+    if let Some(JsonValue::Array(values)) = obj.get("enum") {
+        if !values.contains(instance) {
+            return Err(violation(
+                path,
+                format!("must be one of {}", JsonValue::Array(values.clone())),
+            ));
+        }
+    }
+
+    match instance {
+        JsonValue::Object(map) => validate_object(obj, map, path)?,
+        JsonValue::Array(items) => validate_array(obj, items, path)?,
+        JsonValue::String(text) => validate_string(obj, text, path)?,
+        JsonValue::Number(_) => validate_number(obj, instance, path)?,
+        _ => {}
+    }
+
+    if let Some(JsonValue::Array(subs)) = obj.get("allOf") {
+        for sub in subs {
+            validate_at(sub, instance, path)?;
+        }
+    }
+    if let Some(JsonValue::Array(subs)) = obj.get("anyOf") {
+        if !subs
+            .iter()
+            .any(|sub| validate_at(sub, instance, path).is_ok())
+        {
+            return Err(violation(path, "does not match any schema in anyOf"));
+        }
+    }
+    if let Some(JsonValue::Array(subs)) = obj.get("oneOf") {
+        let matched = subs
+            .iter()
+            .filter(|sub| validate_at(sub, instance, path).is_ok())
+            .count();
+        if matched != 1 {
+            return Err(violation(
+                path,
+                format!(
+                    "must match exactly one schema in oneOf, matched {}",
+                    matched
+                ),
+            ));
+        }
+    }
+    if let Some(sub) = obj.get("not") {
+        if validate_at(sub, instance, path).is_ok() {
+            return Err(violation(path, "must not match the schema in not"));
+        }
+    }
+    Ok(())
+}
+
+fn limit(obj: &Map<String, JsonValue>, keyword: &str) -> Option<u64> {
+    obj.get(keyword).and_then(|v| v.as_u64())
+}
+
+fn validate_object(
+    obj: &Map<String, JsonValue>,
+    map: &Map<String, JsonValue>,
+    path: &str,
+) -> Result<(), Violation> {
+    if let Some(JsonValue::Array(required)) = obj.get("required") {
+        for key in required.iter().filter_map(|v| v.as_str()) {
+            if !map.contains_key(key) {
+                return Err(violation(
+                    &push_key(path, key),
+                    "required property is missing",
+                ));
+            }
+        }
+    }
+    let properties = obj.get("properties").and_then(|v| v.as_object());
+    for (key, value) in map {
+        let child = push_key(path, key);
+        match properties.and_then(|props| props.get(key)) {
+            Some(sub) => validate_at(sub, value, &child)?,
+            None => match obj.get("additionalProperties") {
+                Some(JsonValue::Bool(false)) => {
+                    return Err(violation(&child, "additional property is not allowed"));
+                }
+                Some(sub) => validate_at(sub, value, &child)?,
+                None => {}
+            },
+        }
+    }
+    let count = map.len() as u64;
+    if limit(obj, "minProperties").is_some_and(|min| count < min) {
+        return Err(violation(path, "has too few properties"));
+    }
+    if limit(obj, "maxProperties").is_some_and(|max| count > max) {
+        return Err(violation(path, "has too many properties"));
+    }
+    Ok(())
+}
+
+fn validate_array(
+    obj: &Map<String, JsonValue>,
+    items: &[JsonValue],
+    path: &str,
+) -> Result<(), Violation> {
+    let count = items.len() as u64;
+    if let Some(min) = limit(obj, "minItems").filter(|min| count < *min) {
+        return Err(violation(path, format!("must have at least {} items", min)));
+    }
+    if let Some(max) = limit(obj, "maxItems").filter(|max| count > *max) {
+        return Err(violation(path, format!("must have at most {} items", max)));
+    }
+    if obj.get("uniqueItems") == Some(&JsonValue::Bool(true)) {
+        for (idx, item) in items.iter().enumerate() {
+            if items[..idx].contains(item) {
+                return Err(violation(&format!("{}[{}]", path, idx), "duplicate item"));
+            }
+        }
+    }
+    if let Some(sub) = obj.get("items") {
+        for (idx, item) in items.iter().enumerate() {
+            validate_at(sub, item, &format!("{}[{}]", path, idx))?;
+        }
+    }
+    Ok(())
+}
+
+fn validate_string(obj: &Map<String, JsonValue>, text: &str, path: &str) -> Result<(), Violation> {
+    let length = text.chars().count() as u64;
+    if let Some(min) = limit(obj, "minLength").filter(|min| length < *min) {
+        return Err(violation(
+            path,
+            format!("must be at least {} characters", min),
+        ));
+    }
+    if let Some(max) = limit(obj, "maxLength").filter(|max| length > *max) {
+        return Err(violation(
+            path,
+            format!("must be at most {} characters", max),
+        ));
+    }
+    if let Some(pattern) = obj.get("pattern").and_then(|v| v.as_str()) {
+        let matches = Regex::new(pattern)
+            .map(|re| re.is_match(text))
+            .unwrap_or(false);
+        if !matches {
+            return Err(violation(path, format!("must match pattern {}", pattern)));
+        }
+    }
+    Ok(())
+}
+
+fn validate_number(
+    obj: &Map<String, JsonValue>,
+    instance: &JsonValue,
+    path: &str,
+) -> Result<(), Violation> {
+    let value = instance.as_f64().unwrap_or_default();
+    let bound = |keyword: &str| obj.get(keyword).and_then(|v| v.as_f64());
+    if let Some(min) = bound("minimum").filter(|min| value < *min) {
+        return Err(violation(path, format!("must be >= {}", min)));
+    }
+    if let Some(max) = bound("maximum").filter(|max| value > *max) {
+        return Err(violation(path, format!("must be <= {}", max)));
+    }
+    if let Some(min) = bound("exclusiveMinimum").filter(|min| value <= *min) {
+        return Err(violation(path, format!("must be > {}", min)));
+    }
+    if let Some(max) = bound("exclusiveMaximum").filter(|max| value >= *max) {
+        return Err(violation(path, format!("must be < {}", max)));
+    }
+    if let Some(step) = bound("multipleOf").filter(|step| *step > 0.0) {
+        let ratio = value / step;
+        if (ratio - ratio.round()).abs() > 1e-9 {
+            return Err(violation(path, format!("must be a multiple of {}", step)));
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,534 @@
+use crate::event::{self, AwaitResult};
+use crate::sql;
+use crate::validate;
+use serde_json::Value as JsonValue;
+use sqlite3ext_sys::sqlite3;
+use sqlite_loadable::prelude::*;
+use sqlite_loadable::{
+    api,
+    table::{BestIndexError, ConstraintOperator, IndexInfo, VTab, VTabArguments, VTabCursor},
+    Error, Result,
+};
+use std::os::raw::c_int;
+
+/// Signals park runs under a wake_event that cannot collide with event names
+/// awaited by the same run.
+fn signal_wake_event(signal_name: &str) -> String {
+    format!("$signal:{}", signal_name)
+}
+
+fn parse_signal_payload(value: Option<&*mut sqlite3_value>) -> Result<String> {
+    let value = match value {
+        Some(value) if !api::value_is_null(value) => value,
+        _ => return Ok("null".to_string()),
+    };
+    let raw = api::value_text(value)
+        .map_err(|err| Error::new_message(format!("payload must be valid JSON: {:?}", err)))?
+        .trim()
+        .to_string();
+    if raw.is_empty() {
+        return Err(Error::new_message("payload must be valid JSON"));
+    }
+    let _: JsonValue = serde_json::from_str(&raw)
+        .map_err(|err| Error::new_message(format!("payload must be valid JSON: {:?}", err)))?;
+    Ok(raw)
+}
+
+/// Find a live wait for `signal_name` on the task, returning its run and step.
+fn signal_wait(
+    db: *mut sqlite3,
+    queue_name: &str,
+    task_id: &str,
+    signal_name: &str,
+    now_value: &str,
+) -> Result<Option<(String, String)>> {
+    let mut stmt = sqlite_loadable::exec::Statement::prepare(
+        db,
+        "select run_id, step_name
+           from absurd_signal_waits
+          where queue_name = ?1
+            and task_id = ?2
+            and signal_name = ?3
+            and (timeout_at is null or timeout_at > cast(?4 as integer))
+          order by created_at
+          limit 1",
+    )
+    .map_err(|err| {
+        Error::new_message(format!("failed to prepare signal wait lookup: {:?}", err))
+    })?;
+    stmt.bind_text(1, queue_name)
+        .map_err(|err| Error::new_message(format!("failed to bind queue_name: {:?}", err)))?;
+    stmt.bind_text(2, task_id)
+        .map_err(|err| Error::new_message(format!("failed to bind task_id: {:?}", err)))?;
+    stmt.bind_text(3, signal_name)
+        .map_err(|err| Error::new_message(format!("failed to bind signal_name: {:?}", err)))?;
+    stmt.bind_text(4, now_value)
+        .map_err(|err| Error::new_message(format!("failed to bind now: {:?}", err)))?;
+    let mut rows = stmt.execute();
+    match rows.next() {
+        Some(Ok(row)) => {
+            let run_id = row
+                .get::<String>(0)
+                .map_err(|err| Error::new_message(format!("failed to read run_id: {:?}", err)))?;
+            let step_name = row.get::<String>(1).map_err(|err| {
+                Error::new_message(format!("failed to read step_name: {:?}", err))
+            })?;
+            Ok(Some((run_id, step_name)))
+        }
+        Some(Err(err)) => Err(Error::new_message(format!(
+            "failed to read signal wait: {:?}",
+            err
+        ))),
+        None => Ok(None),
+    }
+}
+
+/// Take the oldest buffered signal for the task, removing it so no other
+/// await can observe it.
+fn take_buffered_signal(
+    db: *mut sqlite3,
+    queue_name: &str,
+    task_id: &str,
+    signal_name: &str,
+) -> Result<Option<String>> {
+    let mut stmt = sqlite_loadable::exec::Statement::prepare(
+        db,
+        "select signal_id, coalesce(json(payload), 'null')
+           from absurd_signals
+          where queue_name = ?1
+            and task_id = ?2
+            and signal_name = ?3
+          order by signal_id
+          limit 1",
+    )
+    .map_err(|err| Error::new_message(format!("failed to prepare signal lookup: {:?}", err)))?;
+    stmt.bind_text(1, queue_name)
+        .map_err(|err| Error::new_message(format!("failed to bind queue_name: {:?}", err)))?;
+    stmt.bind_text(2, task_id)
+        .map_err(|err| Error::new_message(format!("failed to bind task_id: {:?}", err)))?;
+    stmt.bind_text(3, signal_name)
+        .map_err(|err| Error::new_message(format!("failed to bind signal_name: {:?}", err)))?;
+    let mut rows = stmt.execute();
+    let (signal_id, payload) = match rows.next() {
+        Some(Ok(row)) => {
+            let signal_id = row.get::<i64>(0).map_err(|err| {
+                Error::new_message(format!("failed to read signal_id: {:?}", err))
+            })?;
+            let payload = row.get::<String>(1).map_err(|err| {
+                Error::new_message(format!("failed to read signal payload: {:?}", err))
+            })?;
+            (signal_id, payload)
+        }
+        Some(Err(err)) => {
+            return Err(Error::new_message(format!(
+                "failed to read signal: {:?}",
+                err
+            )))
+        }
+        None => return Ok(None),
+    };
+
+    sql::exec_with_bind_text(
+        db,
+        "delete from absurd_signals where signal_id = cast(?1 as integer)",
+        &[&signal_id.to_string()],
+    )?;
+    Ok(Some(payload))
+}
+
+/// SQL: absurd_signal_task(queue_name, task_id, signal_name[, payload_json])
+/// Usage: send a signal to one task; returns 1 when a waiting run was woken, 0 when the signal was buffered.
+/// Section: Durable
+pub fn absurd_signal_task(
+    context: *mut sqlite3_context,
+    values: &[*mut sqlite3_value],
+) -> Result<()> {
+    let queue_name = api::value_text_notnull(values.first().expect("queue_name"))?;
+    let task_id = api::value_text_notnull(values.get(1).expect("task_id"))?;
+    let signal_name = api::value_text_notnull(values.get(2).expect("signal_name"))?;
+    let payload_text = parse_signal_payload(values.get(3))?;
+
+    validate::queue_name(queue_name)?;
+    validate::signal_name(signal_name)?;
+
+    let db = api::context_db_handle(context);
+    let now_value = sql::now_ms_from_db(db).to_string();
+
+    sql::exec_with_bind_text(db, "begin immediate", &[])?;
+
+    let result = (|| -> Result<i64> {
+        let active = sql::query_row_i64(
+            db,
+            "select count(*)
+               from absurd_tasks
+              where queue_name = ?1
+                and task_id = ?2
+                and state not in ('completed', 'failed', 'cancelled')",
+            &[queue_name, task_id],
+        )?;
+        if active == 0 {
+            return Err(Error::new_message("Task not found or no longer active"));
+        }
+
+        if let Some((run_id, step_name)) =
+            signal_wait(db, queue_name, task_id, signal_name, &now_value)?
+        {
+            sql::exec_with_bind_text(
+                db,
+                "update absurd_runs
+                    set state = 'pending',
+                        available_at = cast(?1 as integer),
+                        wake_event = null,
+                        wake_step = null,
+                        event_payload = jsonb(?2),
+                        claimed_by = null,
+                        claim_expires_at = null
+                  where queue_name = ?3
+                    and run_id = ?4
+                    and state = 'sleeping'",
+                &[&now_value, &payload_text, queue_name, &run_id],
+            )?;
+
+            if sql::query_row_i64(db, "select changes()", &[])? > 0 {
+                event::write_await_checkpoint(
+                    db,
+                    queue_name,
+                    task_id,
+                    &step_name,
+                    &payload_text,
+                    &run_id,
+                    &now_value,
+                    "",
+                    "",
+                )?;
+
+                sql::exec_with_bind_text(
+                    db,
+                    "update absurd_tasks
+                        set state = 'pending'
+                      where queue_name = ?1
+                        and task_id = ?2",
+                    &[queue_name, task_id],
+                )?;
+
+                sql::exec_with_bind_text(
+                    db,
+                    "delete from absurd_signal_waits
+                      where queue_name = ?1
+                        and run_id = ?2
+                        and step_name = ?3",
+                    &[queue_name, &run_id, &step_name],
+                )?;
+                return Ok(1);
+            }
+        }
+
+        sql::exec_with_bind_text(
+            db,
+            "insert into absurd_signals (queue_name, task_id, signal_name, payload, created_at)
+             values (?1, ?2, ?3, jsonb(?4), cast(?5 as integer))",
+            &[queue_name, task_id, signal_name, &payload_text, &now_value],
+        )?;
+        Ok(0)
+    })();
+
+    let delivered = event::finish_await(db, result)?;
+    api::result_int64(context, delivered);
+    Ok(())
+}
+
+fn await_signal_impl(
+    db: *mut sqlite3,
+    queue_name: &str,
+    task_id: &str,
+    run_id: &str,
+    step_name: &str,
+    signal_name: &str,
+    timeout_secs: Option<i64>,
+) -> Result<AwaitResult> {
+    validate::queue_name(queue_name)?;
+    validate::step_name(step_name)?;
+    validate::signal_name(signal_name)?;
+
+    let now = sql::now_ms_from_db(db);
+    let now_value = now.to_string();
+    let timeout_at = timeout_secs.map(|secs| now + secs.saturating_mul(1000));
+    let timeout_at_value = timeout_at.map(|v| v.to_string()).unwrap_or_default();
+    let available_value = timeout_at.unwrap_or(event::FAR_FUTURE_MS).to_string();
+    let wake_event = signal_wake_event(signal_name);
+
+    sql::exec_with_bind_text(db, "begin immediate", &[])?;
+
+    let result = (|| -> Result<AwaitResult> {
+        if let Some(result) = event::load_await_checkpoint(db, queue_name, task_id, step_name)? {
+            return Ok(result);
+        }
+
+        let run = event::load_await_run(db, queue_name, run_id)?;
+        if run.state != "running" {
+            return Err(Error::new_message("Run must be running to await signals"));
+        }
+
+        if let Some(payload) = take_buffered_signal(db, queue_name, task_id, signal_name)? {
+            event::write_await_checkpoint(
+                db, queue_name, task_id, step_name, &payload, run_id, &now_value, "", "",
+            )?;
+            return Ok(AwaitResult {
+                should_suspend: 0,
+                payload: Some(payload),
+                seq: None,
+                matched_event: None,
+                timed_out: false,
+            });
+        }
+
+        if let Some(result) = event::resume_timed_out_wake(
+            db,
+            queue_name,
+            task_id,
+            step_name,
+            run_id,
+            &run,
+            &wake_event,
+            &now_value,
+        )? {
+            return Ok(result);
+        }
+
+        sql::exec_with_bind_text(
+            db,
+            "insert into absurd_signal_waits (queue_name, task_id, run_id, step_name, signal_name, timeout_at, created_at)
+             values (?1, ?2, ?3, ?4, ?5, nullif(?6, ''), cast(?7 as integer))
+             on conflict (queue_name, run_id, step_name)
+             do update set signal_name = excluded.signal_name,
+                           timeout_at = excluded.timeout_at,
+                           created_at = excluded.created_at",
+            &[
+                queue_name,
+                task_id,
+                run_id,
+                step_name,
+                signal_name,
+                &timeout_at_value,
+                &now_value,
+            ],
+        )?;
+
+        event::suspend_run(
+            db,
+            queue_name,
+            task_id,
+            run_id,
+            step_name,
+            &available_value,
+            &wake_event,
+        )?;
+
+        Ok(AwaitResult {
+            should_suspend: 1,
+            payload: None,
+            seq: None,
+            matched_event: None,
+            timed_out: false,
+        })
+    })();
+
+    event::finish_await(db, result)
+}
+
+static AWAIT_SIGNAL_CREATE_SQL: &str = "CREATE TABLE x(should_suspend INTEGER, payload BLOB, timed_out INTEGER, queue_name TEXT hidden, task_id TEXT hidden, run_id TEXT hidden, step_name TEXT hidden, signal_name TEXT hidden, timeout INTEGER hidden)";
+
+enum AwaitSignalColumns {
+    ShouldSuspend,
+    Payload,
+    TimedOut,
+    QueueName,
+    TaskId,
+    RunId,
+    StepName,
+    SignalName,
+    Timeout,
+}
+
+fn await_signal_column(index: i32) -> Option<AwaitSignalColumns> {
+    match index {
+        0 => Some(AwaitSignalColumns::ShouldSuspend),
+        1 => Some(AwaitSignalColumns::Payload),
+        2 => Some(AwaitSignalColumns::TimedOut),
+        3 => Some(AwaitSignalColumns::QueueName),
+        4 => Some(AwaitSignalColumns::TaskId),
+        5 => Some(AwaitSignalColumns::RunId),
+        6 => Some(AwaitSignalColumns::StepName),
+        7 => Some(AwaitSignalColumns::SignalName),
+        8 => Some(AwaitSignalColumns::Timeout),
+        _ => None,
+    }
+}
+
+/// SQL: absurd_await_signal(queue_name, task_id, run_id, step_name, signal_name[, timeout_secs])
+/// Usage: consume a signal sent to this task, suspending until one arrives or the timeout passes; returns should_suspend, payload and timed_out.
+/// Section: Durable
+#[repr(C)]
+pub struct AwaitSignalTable {
+    base: sqlite3_vtab,
+    db: *mut sqlite3,
+}
+
+impl<'vtab> VTab<'vtab> for AwaitSignalTable {
+    type Aux = ();
+    type Cursor = AwaitSignalCursor;
+
+    fn connect(
+        db: *mut sqlite3,
+        _aux: Option<&Self::Aux>,
+        _args: VTabArguments,
+    ) -> Result<(String, AwaitSignalTable)> {
+        let base: sqlite3_vtab = unsafe { std::mem::zeroed() };
+        let vtab = AwaitSignalTable { base, db };
+        Ok((AWAIT_SIGNAL_CREATE_SQL.to_owned(), vtab))
+    }
+
+    fn destroy(&self) -> Result<()> {
+        Ok(())
+    }
+
+    fn best_index(&self, mut info: IndexInfo) -> core::result::Result<(), BestIndexError> {
+        let mut has_queue = false;
+        let mut has_task = false;
+        let mut has_run = false;
+        let mut has_step = false;
+        let mut has_signal = false;
+
+        for mut constraint in info.constraints() {
+            let argv_index = match await_signal_column(constraint.column_idx()) {
+                Some(AwaitSignalColumns::QueueName) => {
+                    has_queue = true;
+                    1
+                }
+                Some(AwaitSignalColumns::TaskId) => {
+                    has_task = true;
+                    2
+                }
+                Some(AwaitSignalColumns::RunId) => {
+                    has_run = true;
+                    3
+                }
+                Some(AwaitSignalColumns::StepName) => {
+                    has_step = true;
+                    4
+                }
+                Some(AwaitSignalColumns::SignalName) => {
+                    has_signal = true;
+                    5
+                }
+                Some(AwaitSignalColumns::Timeout) => 6,
+                _ => continue,
+            };
+            if constraint.usable() && constraint.op() == Some(ConstraintOperator::EQ) {
+                constraint.set_omit(true);
+                constraint.set_argv_index(argv_index);
+            } else {
+                return Err(BestIndexError::Constraint);
+            }
+        }
+
+        if !has_queue || !has_task || !has_run || !has_step || !has_signal {
+            return Err(BestIndexError::Error);
+        }
+
+        info.set_estimated_cost(1.0);
+        info.set_estimated_rows(1);
+        info.set_idxnum(1);
+        Ok(())
+    }
+
+    fn open(&mut self) -> Result<AwaitSignalCursor> {
+        Ok(AwaitSignalCursor::new(self.db))
+    }
+}
+
+#[repr(C)]
+pub struct AwaitSignalCursor {
+    base: sqlite3_vtab_cursor,
+    db: *mut sqlite3,
+    rowid: i64,
+    result: Option<AwaitResult>,
+}
+
+impl AwaitSignalCursor {
+    fn new(db: *mut sqlite3) -> AwaitSignalCursor {
+        let base: sqlite3_vtab_cursor = unsafe { std::mem::zeroed() };
+        AwaitSignalCursor {
+            base,
+            db,
+            rowid: 0,
+            result: None,
+        }
+    }
+}
+
+impl VTabCursor for AwaitSignalCursor {
+    fn filter(
+        &mut self,
+        _idx_num: c_int,
+        _idx_str: Option<&str>,
+        values: &[*mut sqlite3_value],
+    ) -> Result<()> {
+        let queue_name = api::value_text_notnull(values.first().expect("queue_name"))?;
+        let task_id = api::value_text_notnull(values.get(1).expect("task_id"))?;
+        let run_id = api::value_text_notnull(values.get(2).expect("run_id"))?;
+        let step_name = api::value_text_notnull(values.get(3).expect("step_name"))?;
+        let signal_name = api::value_text_notnull(values.get(4).expect("signal_name"))?;
+        let timeout = event::parse_optional_timeout_secs(values.get(5).copied())?;
+
+        let result = await_signal_impl(
+            self.db,
+            queue_name,
+            task_id,
+            run_id,
+            step_name,
+            signal_name,
+            timeout,
+        )?;
+        self.result = Some(result);
+        self.rowid = 0;
+        Ok(())
+    }
+
+    fn next(&mut self) -> Result<()> {
+        self.rowid += 1;
+        Ok(())
+    }
+
+    fn eof(&self) -> bool {
+        self.result.is_none() || self.rowid > 0
+    }
+
+    fn column(&self, context: *mut sqlite3_context, i: c_int) -> Result<()> {
+        let result = match &self.result {
+            Some(result) => result,
+            None => {
+                api::result_null(context);
+                return Ok(());
+            }
+        };
+        match await_signal_column(i) {
+            Some(AwaitSignalColumns::ShouldSuspend) => {
+                api::result_int64(context, result.should_suspend)
+            }
+            Some(AwaitSignalColumns::Payload) => match &result.payload {
+                Some(value) => sql::result_json_text(context, value)?,
+                None => api::result_null(context),
+            },
+            Some(AwaitSignalColumns::TimedOut) => {
+                api::result_int64(context, result.timed_out as i64)
+            }
+            _ => api::result_null(context),
+        }
+        Ok(())
+    }
+
+    fn rowid(&self) -> Result<i64> {
+        Ok(self.rowid)
+    }
+}
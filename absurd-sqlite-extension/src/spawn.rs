@@ -1,6 +1,9 @@
+use crate::payload;
+use crate::schema;
 use crate::sql;
+use crate::task_type;
 use crate::validate;
-use serde_json::Value as JsonValue;
+use serde_json::{Map, Value as JsonValue};
 use sqlite3ext_sys::sqlite3;
 use sqlite_loadable::prelude::*;
 use sqlite_loadable::{
@@ -8,16 +11,20 @@ use sqlite_loadable::{
     table::{BestIndexError, ConstraintOperator, IndexInfo, VTab, VTabArguments, VTabCursor},
     Error, Result,
 };
+use std::borrow::Cow;
+use std::collections::HashMap;
 use std::os::raw::c_int;
 use uuid::Uuid;
 
 #[derive(Default)]
-struct SpawnOptions {
+pub struct SpawnOptions {
     headers: Option<String>,
     retry_strategy: Option<String>,
     max_attempts: Option<i64>,
     cancellation: Option<String>,
     idempotency_key: Option<String>,
+    priority: i64,
+    supersede_key: Option<String>,
 }
 
 struct SpawnResult {
@@ -27,18 +34,40 @@ struct SpawnResult {
     created: i64,
 }
 
-fn parse_spawn_options(raw: &str) -> Result<SpawnOptions> {
+fn options_object(raw: &str) -> Result<Map<String, JsonValue>> {
     if raw.trim().is_empty() {
-        return Ok(SpawnOptions::default());
+        return Ok(Map::new());
     }
     let parsed: JsonValue = serde_json::from_str(raw)
         .map_err(|err| Error::new_message(format!("options must be valid JSON: {:?}", err)))?;
-    if parsed.is_null() {
-        return Ok(SpawnOptions::default());
+    match parsed {
+        JsonValue::Null => Ok(Map::new()),
+        JsonValue::Object(obj) => Ok(obj),
+        _ => Err(Error::new_message("options must be a JSON object")),
+    }
+}
+
+/// Parse spawn options merged over a task type's `defaults`. Options given
+/// at spawn time win, except `headers`, which are merged key by key.
+pub fn parse_spawn_options(raw: &str, defaults: Option<&str>) -> Result<SpawnOptions> {
+    let mut obj = match defaults {
+        Some(defaults) => options_object(defaults)?,
+        None => Map::new(),
+    };
+    for (key, value) in options_object(raw)? {
+        if value.is_null() {
+            continue;
+        }
+        if key == "headers" {
+            if let (Some(JsonValue::Object(base)), JsonValue::Object(overrides)) =
+                (obj.get_mut("headers"), &value)
+            {
+                base.extend(overrides.clone());
+                continue;
+            }
+        }
+        obj.insert(key, value);
     }
-    let obj = parsed
-        .as_object()
-        .ok_or_else(|| Error::new_message("options must be a JSON object"))?;
 
     let headers = obj.get("headers").and_then(|v| {
         if v.is_null() {
@@ -66,6 +95,17 @@ fn parse_spawn_options(raw: &str) -> Result<SpawnOptions> {
         .and_then(|v| v.as_str())
         .map(|s| s.to_string())
         .filter(|s| !s.is_empty());
+    let supersede_key = obj
+        .get("supersede_key")
+        .and_then(|v| v.as_str())
+        .map(|s| s.to_string())
+        .filter(|s| !s.is_empty());
+    let priority = match obj.get("priority") {
+        None | Some(JsonValue::Null) => 0,
+        Some(value) => value
+            .as_i64()
+            .ok_or_else(|| Error::new_message("priority must be an integer"))?,
+    };
     let max_attempts = obj.get("max_attempts").and_then(|v| v.as_i64());
     if let Some(max) = max_attempts {
         if max < 1 {
@@ -79,6 +119,8 @@ fn parse_spawn_options(raw: &str) -> Result<SpawnOptions> {
         max_attempts,
         cancellation,
         idempotency_key,
+        priority,
+        supersede_key,
     })
 }
 
@@ -87,25 +129,63 @@ fn spawn_task_impl(
     queue_name: &str,
     task_name: &str,
     params: &str,
-    options: &SpawnOptions,
+    options_raw: &str,
 ) -> Result<SpawnResult> {
     validate::queue_name(queue_name)?;
     validate::task_name(task_name)?;
+    let task_type = task_type::load_task_type(db, queue_name, task_name)?;
+    spawn_typed_task(
+        db,
+        queue_name,
+        task_name,
+        params,
+        options_raw,
+        task_type.as_ref(),
+    )
+}
+
+/// Insert one task and its first run, given the task's already loaded type.
+fn spawn_typed_task(
+    db: *mut sqlite3,
+    queue_name: &str,
+    task_name: &str,
+    params: &str,
+    options_raw: &str,
+    task_type: Option<&task_type::TaskType>,
+) -> Result<SpawnResult> {
+    let params_value = if params.trim().is_empty() {
+        "null"
+    } else {
+        params
+    };
+    if let Some(json_schema) = task_type.and_then(|t| t.json_schema.as_ref()) {
+        let instance: JsonValue = serde_json::from_str(params_value)
+            .map_err(|err| Error::new_message(format!("params must be valid JSON: {:?}", err)))?;
+        schema::validate(json_schema, &instance).map_err(|violation| {
+            Error::new_message(format!(
+                "params failed schema validation for {} at {}",
+                task_name, violation
+            ))
+        })?;
+    }
+    let defaults = task_type.and_then(|t| t.default_options.as_deref());
+    let options = parse_spawn_options(options_raw, defaults)?;
 
     let task_id = Uuid::now_v7().to_string();
     let run_id = Uuid::now_v7().to_string();
     let attempt = 1_i64;
     let now = sql::now_ms_from_db(db);
 
-    let params_value = if params.trim().is_empty() {
-        "null"
-    } else {
-        params
+    let params_value = payload::encode_json(db, queue_name, params_value)?;
+    let headers_value = match options.headers.as_deref() {
+        Some(headers) => payload::encode_headers(db, queue_name, headers)?,
+        None => Cow::Borrowed(""),
     };
-    let headers_value = options.headers.as_deref().unwrap_or("");
     let retry_value = options.retry_strategy.as_deref().unwrap_or("");
     let cancellation_value = options.cancellation.as_deref().unwrap_or("");
     let idempotency_value = options.idempotency_key.as_deref().unwrap_or("");
+    let priority_value = options.priority.to_string();
+    let supersede_value = options.supersede_key.as_deref().unwrap_or("");
     let max_attempts_value = options
         .max_attempts
         .map(|v| v.to_string())
@@ -117,20 +197,22 @@ fn spawn_task_impl(
     if options.idempotency_key.is_some() {
         sql::exec_with_bind_text(
             db,
-            "insert or ignore into absurd_tasks (queue_name, task_id, task_name, params, headers, retry_strategy, max_attempts, cancellation, enqueue_at, first_started_at, state, attempts, last_attempt_run, completed_payload, cancelled_at, idempotency_key)
-             values (?1, ?2, ?3, jsonb(?4), case when ?5 = '' then null else jsonb(?5) end, case when ?6 = '' then null else jsonb(?6) end, nullif(cast(?7 as integer),0), case when ?8 = '' then null else jsonb(?8) end, cast(?9 as integer), null, 'pending', cast(?10 as integer), null, null, null, nullif(?11,''))",
+            "insert or ignore into absurd_tasks (queue_name, task_id, task_name, params, headers, retry_strategy, max_attempts, cancellation, enqueue_at, first_started_at, state, attempts, last_attempt_run, completed_payload, cancelled_at, idempotency_key, priority, supersede_key)
+             values (?1, ?2, ?3, jsonb(?4), case when ?5 = '' then null else jsonb(?5) end, case when ?6 = '' then null else jsonb(?6) end, nullif(cast(?7 as integer),0), case when ?8 = '' then null else jsonb(?8) end, cast(?9 as integer), null, 'pending', cast(?10 as integer), null, null, null, nullif(?11,''), cast(?12 as integer), nullif(?13,''))",
             &[
                 queue_name,
                 &task_id,
                 task_name,
-                params_value,
-                headers_value,
+                &params_value,
+                &headers_value,
                 retry_value,
                 &max_attempts_value,
                 cancellation_value,
                 &now_value,
                 &attempt_value,
                 idempotency_value,
+                &priority_value,
+                supersede_value,
             ],
         )
         .map_err(|e| {
@@ -154,19 +236,21 @@ fn spawn_task_impl(
     } else {
         sql::exec_with_bind_text(
             db,
-            "insert into absurd_tasks (queue_name, task_id, task_name, params, headers, retry_strategy, max_attempts, cancellation, enqueue_at, first_started_at, state, attempts, last_attempt_run, completed_payload, cancelled_at, idempotency_key)
-             values (?1, ?2, ?3, jsonb(?4), case when ?5 = '' then null else jsonb(?5) end, case when ?6 = '' then null else jsonb(?6) end, nullif(cast(?7 as integer),0), case when ?8 = '' then null else jsonb(?8) end, cast(?9 as integer), null, 'pending', cast(?10 as integer), null, null, null, null)",
+            "insert into absurd_tasks (queue_name, task_id, task_name, params, headers, retry_strategy, max_attempts, cancellation, enqueue_at, first_started_at, state, attempts, last_attempt_run, completed_payload, cancelled_at, idempotency_key, priority, supersede_key)
+             values (?1, ?2, ?3, jsonb(?4), case when ?5 = '' then null else jsonb(?5) end, case when ?6 = '' then null else jsonb(?6) end, nullif(cast(?7 as integer),0), case when ?8 = '' then null else jsonb(?8) end, cast(?9 as integer), null, 'pending', cast(?10 as integer), null, null, null, null, cast(?11 as integer), nullif(?12,''))",
             &[
                 queue_name,
                 &task_id,
                 task_name,
-                params_value,
-                headers_value,
+                &params_value,
+                &headers_value,
                 retry_value,
                 &max_attempts_value,
                 cancellation_value,
                 &now_value,
                 &attempt_value,
+                &priority_value,
+                supersede_value,
             ],
         )
         .map_err(|e| Error::new_message(format!("task insert failed: {:?}", e)))?;
@@ -244,7 +328,7 @@ fn spawn_column(index: i32) -> Option<SpawnColumns> {
 }
 
 /// SQL: absurd_spawn_task(queue_name, task_name, params_json[, options_json])
-/// Usage: create a task and its initial run; options may include headers, retry_strategy, max_attempts, cancellation, and idempotency_key.
+/// Usage: create a task and its initial run; options may include headers, retry_strategy, max_attempts, cancellation, idempotency_key, priority and supersede_key, and are merged over the task type's default_options.
 /// Section: Durable
 #[repr(C)]
 pub struct SpawnTaskTable {
@@ -367,8 +451,7 @@ impl VTabCursor for SpawnTaskCursor {
             ""
         };
 
-        let options = parse_spawn_options(options_raw)?;
-        let result = spawn_task_impl(self.db, queue_name, task_name, params, &options)?;
+        let result = spawn_task_impl(self.db, queue_name, task_name, params, options_raw)?;
         self.result = Some(result);
         self.rowid = 0;
         Ok(())
@@ -405,3 +488,250 @@ impl VTabCursor for SpawnTaskCursor {
         Ok(self.rowid)
     }
 }
+
+/// One element of the `tasks_json` array given to `absurd_spawn_tasks`.
+struct BatchItem {
+    task_name: String,
+    params: String,
+    options: String,
+}
+
+fn parse_batch_items(raw: &str) -> Result<Vec<BatchItem>> {
+    let parsed: JsonValue = serde_json::from_str(raw)
+        .map_err(|err| Error::new_message(format!("tasks must be valid JSON: {:?}", err)))?;
+    let entries = parsed
+        .as_array()
+        .ok_or_else(|| Error::new_message("tasks must be a JSON array"))?;
+    entries
+        .iter()
+        .enumerate()
+        .map(|(index, entry)| {
+            let invalid =
+                |message: &str| Error::new_message(format!("tasks[{}]: {}", index, message));
+            let obj = entry
+                .as_object()
+                .ok_or_else(|| invalid("must be an object with task_name, params and options"))?;
+            if let Some(key) = obj
+                .keys()
+                .find(|key| !matches!(key.as_str(), "task_name" | "params" | "options"))
+            {
+                return Err(invalid(&format!("unknown key {}", key)));
+            }
+            let task_name = obj
+                .get("task_name")
+                .and_then(|v| v.as_str())
+                .ok_or_else(|| invalid("task_name is required"))?;
+            let options = match obj.get("options") {
+                None | Some(JsonValue::Null) => String::new(),
+                Some(value @ JsonValue::Object(_)) => value.to_string(),
+                Some(_) => return Err(invalid("options must be a JSON object")),
+            };
+            Ok(BatchItem {
+                task_name: task_name.to_string(),
+                params: obj
+                    .get("params")
+                    .map(|v| v.to_string())
+                    .unwrap_or_else(|| "null".to_string()),
+                options,
+            })
+        })
+        .collect()
+}
+
+fn spawn_tasks_impl(db: *mut sqlite3, queue_name: &str, raw: &str) -> Result<Vec<SpawnResult>> {
+    validate::queue_name(queue_name)?;
+    let items = parse_batch_items(raw)?;
+
+    sql::exec_with_bind_text(db, "begin immediate", &[])?;
+
+    let result = (|| -> Result<Vec<SpawnResult>> {
+        let mut task_types: HashMap<String, Option<task_type::TaskType>> = HashMap::new();
+        let mut results = Vec::with_capacity(items.len());
+        for (index, item) in items.iter().enumerate() {
+            let in_item = |err: Error| {
+                Error::new_message(format!("tasks[{}]: {}", index, err.result_error_message()))
+            };
+            validate::task_name(&item.task_name).map_err(in_item)?;
+            if !task_types.contains_key(&item.task_name) {
+                let loaded = task_type::load_task_type(db, queue_name, &item.task_name)?;
+                task_types.insert(item.task_name.clone(), loaded);
+            }
+            let result = spawn_typed_task(
+                db,
+                queue_name,
+                &item.task_name,
+                &item.params,
+                &item.options,
+                task_types[&item.task_name].as_ref(),
+            )
+            .map_err(in_item)?;
+            results.push(result);
+        }
+        Ok(results)
+    })();
+
+    match result {
+        Ok(results) => {
+            sql::exec_with_bind_text(db, "commit", &[])?;
+            Ok(results)
+        }
+        Err(err) => {
+            let _ = sql::exec_with_bind_text(db, "rollback", &[]);
+            Err(err)
+        }
+    }
+}
+
+static SPAWN_BATCH_CREATE_SQL: &str = "CREATE TABLE x(item_index INTEGER, task_id TEXT, run_id TEXT, attempt INTEGER, created INTEGER, queue_name TEXT hidden, tasks TEXT hidden)";
+
+enum SpawnBatchColumns {
+    ItemIndex,
+    TaskId,
+    RunId,
+    Attempt,
+    Created,
+    QueueName,
+    Tasks,
+}
+
+fn spawn_batch_column(index: i32) -> Option<SpawnBatchColumns> {
+    match index {
+        0 => Some(SpawnBatchColumns::ItemIndex),
+        1 => Some(SpawnBatchColumns::TaskId),
+        2 => Some(SpawnBatchColumns::RunId),
+        3 => Some(SpawnBatchColumns::Attempt),
+        4 => Some(SpawnBatchColumns::Created),
+        5 => Some(SpawnBatchColumns::QueueName),
+        6 => Some(SpawnBatchColumns::Tasks),
+        _ => None,
+    }
+}
+
+/// SQL: absurd_spawn_tasks(queue_name, tasks_json)
+/// Usage: spawn a JSON array of `{"task_name", "params", "options"}` items in one transaction; returns one row per item in input order, with created=0 for idempotency-key hits.
+/// Section: Durable
+#[repr(C)]
+pub struct SpawnTasksTable {
+    base: sqlite3_vtab,
+    db: *mut sqlite3,
+}
+
+impl<'vtab> VTab<'vtab> for SpawnTasksTable {
+    type Aux = ();
+    type Cursor = SpawnTasksCursor;
+
+    fn connect(
+        db: *mut sqlite3,
+        _aux: Option<&Self::Aux>,
+        _args: VTabArguments,
+    ) -> Result<(String, SpawnTasksTable)> {
+        let base: sqlite3_vtab = unsafe { std::mem::zeroed() };
+        let vtab = SpawnTasksTable { base, db };
+        Ok((SPAWN_BATCH_CREATE_SQL.to_owned(), vtab))
+    }
+
+    fn destroy(&self) -> Result<()> {
+        Ok(())
+    }
+
+    fn best_index(&self, mut info: IndexInfo) -> core::result::Result<(), BestIndexError> {
+        let mut has_queue = false;
+        let mut has_tasks = false;
+
+        for mut constraint in info.constraints() {
+            let argv_index = match spawn_batch_column(constraint.column_idx()) {
+                Some(SpawnBatchColumns::QueueName) => 1,
+                Some(SpawnBatchColumns::Tasks) => 2,
+                _ => continue,
+            };
+            if constraint.usable() && constraint.op() == Some(ConstraintOperator::EQ) {
+                constraint.set_omit(true);
+                constraint.set_argv_index(argv_index);
+                has_queue |= argv_index == 1;
+                has_tasks |= argv_index == 2;
+            } else {
+                return Err(BestIndexError::Constraint);
+            }
+        }
+
+        if !has_queue || !has_tasks {
+            return Err(BestIndexError::Error);
+        }
+
+        info.set_estimated_cost(10.0);
+        info.set_estimated_rows(10);
+        info.set_idxnum(1);
+        Ok(())
+    }
+
+    fn open(&mut self) -> Result<SpawnTasksCursor> {
+        Ok(SpawnTasksCursor::new(self.db))
+    }
+}
+
+#[repr(C)]
+pub struct SpawnTasksCursor {
+    base: sqlite3_vtab_cursor,
+    db: *mut sqlite3,
+    rowid: i64,
+    results: Vec<SpawnResult>,
+}
+
+impl SpawnTasksCursor {
+    fn new(db: *mut sqlite3) -> SpawnTasksCursor {
+        let base: sqlite3_vtab_cursor = unsafe { std::mem::zeroed() };
+        SpawnTasksCursor {
+            base,
+            db,
+            rowid: 0,
+            results: Vec::new(),
+        }
+    }
+}
+
+impl VTabCursor for SpawnTasksCursor {
+    fn filter(
+        &mut self,
+        _idx_num: c_int,
+        _idx_str: Option<&str>,
+        values: &[*mut sqlite3_value],
+    ) -> Result<()> {
+        let queue_name = api::value_text_notnull(values.first().expect("queue_name"))?;
+        let tasks = api::value_text_notnull(values.get(1).expect("tasks"))?;
+        self.results = spawn_tasks_impl(self.db, queue_name, tasks)?;
+        self.rowid = 0;
+        Ok(())
+    }
+
+    fn next(&mut self) -> Result<()> {
+        self.rowid += 1;
+        Ok(())
+    }
+
+    fn eof(&self) -> bool {
+        self.rowid as usize >= self.results.len()
+    }
+
+    fn column(&self, context: *mut sqlite3_context, i: c_int) -> Result<()> {
+        let result = match self.results.get(self.rowid as usize) {
+            Some(result) => result,
+            None => {
+                api::result_null(context);
+                return Ok(());
+            }
+        };
+        match spawn_batch_column(i) {
+            Some(SpawnBatchColumns::ItemIndex) => api::result_int64(context, self.rowid),
+            Some(SpawnBatchColumns::TaskId) => api::result_text(context, &result.task_id)?,
+            Some(SpawnBatchColumns::RunId) => api::result_text(context, &result.run_id)?,
+            Some(SpawnBatchColumns::Attempt) => api::result_int64(context, result.attempt),
+            Some(SpawnBatchColumns::Created) => api::result_int64(context, result.created),
+            _ => api::result_null(context),
+        }
+        Ok(())
+    }
+
+    fn rowid(&self) -> Result<i64> {
+        Ok(self.rowid)
+    }
+}
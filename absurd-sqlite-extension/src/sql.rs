@@ -1,26 +1,36 @@
-use sqlite3ext_sys::{sqlite3, sqlite3_context};
+use crate::payload;
+use sqlite3ext_sys::{sqlite3, sqlite3_api_routines, sqlite3_context};
 use sqlite_loadable::ext::{
-    sqlite3ext_bind_text, sqlite3ext_finalize, sqlite3ext_prepare_v2, sqlite3ext_step,
+    sqlite3ext_bind_text, sqlite3ext_column_bytes, sqlite3ext_finalize, sqlite3ext_prepare_v2,
+    sqlite3ext_step,
 };
 use sqlite_loadable::{Error, Result, SQLITE_DONE, SQLITE_OKAY, SQLITE_ROW};
 use std::ffi::{CStr, CString};
 use std::os::raw::{c_char, c_void};
+use std::sync::atomic::{AtomicPtr, Ordering};
 use std::time::{SystemTime, UNIX_EPOCH};
 
-pub fn exec_with_bind_text(db: *mut sqlite3, sql: &str, params: &[&str]) -> Result<()> {
-    let mut stmt = std::ptr::null_mut();
-    let sql_c =
-        CString::new(sql).map_err(|err| Error::new_message(format!("invalid sql: {:?}", err)))?;
-    let rc =
-        unsafe { sqlite3ext_prepare_v2(db, sql_c.as_ptr(), -1, &mut stmt, std::ptr::null_mut()) };
-    if rc != SQLITE_OKAY {
-        return Err(Error::new_message("failed to prepare statement"));
+/// Routines table SQLite passed to the entrypoint. Calls that the
+/// `sqlite3ext_*` wrappers do not cover go through it so they reach the
+/// library that loaded the extension rather than a statically linked copy.
+static API: AtomicPtr<sqlite3_api_routines> = AtomicPtr::new(std::ptr::null_mut());
+
+pub fn set_api(api: *mut sqlite3_api_routines) {
+    if !api.is_null() {
+        API.store(api, Ordering::Release);
     }
+}
 
-    for (idx, value) in params.iter().enumerate() {
-        bind_text(stmt, (idx + 1) as i32, value)?;
+fn api() -> Result<&'static sqlite3_api_routines> {
+    let api = API.load(Ordering::Acquire);
+    if api.is_null() {
+        return Err(Error::new_message("extension was not initialized"));
     }
+    Ok(unsafe { &*api })
+}
 
+pub fn exec_with_bind_text(db: *mut sqlite3, sql: &str, params: &[&str]) -> Result<()> {
+    let stmt = prepare_bound(db, sql, params)?;
     let step_rc = unsafe { sqlite3ext_step(stmt) };
     unsafe { sqlite3ext_finalize(stmt) };
     if step_rc != SQLITE_DONE && step_rc != SQLITE_ROW {
@@ -32,6 +42,94 @@ pub fn exec_with_bind_text(db: *mut sqlite3, sql: &str, params: &[&str]) -> Resu
     Ok(())
 }
 
+/// Like [`exec_with_bind_text`], with `data` bound as a BLOB to the parameter
+/// after the text ones.
+pub fn exec_with_bind_blob(
+    db: *mut sqlite3,
+    sql: &str,
+    params: &[&str],
+    data: &[u8],
+) -> Result<()> {
+    let bind_blob = api()?
+        .bind_blob
+        .ok_or_else(|| Error::new_message("sqlite3_bind_blob is unavailable"))?;
+    let stmt = prepare_bound(db, sql, params)?;
+    // SQLITE_STATIC: `data` outlives the statement, which is finalized below.
+    let bind_rc = unsafe {
+        bind_blob(
+            stmt,
+            (params.len() + 1) as i32,
+            data.as_ptr().cast::<c_void>(),
+            data.len() as i32,
+            None,
+        )
+    };
+    if bind_rc != SQLITE_OKAY {
+        unsafe { sqlite3ext_finalize(stmt) };
+        return Err(Error::new_message("failed to bind blob"));
+    }
+    let step_rc = unsafe { sqlite3ext_step(stmt) };
+    unsafe { sqlite3ext_finalize(stmt) };
+    if step_rc != SQLITE_DONE && step_rc != SQLITE_ROW {
+        return Err(Error::new_message(format!(
+            "statement execution failed (code {})",
+            step_rc
+        )));
+    }
+    Ok(())
+}
+
+/// First column of the first row as raw bytes, or `None` when no row matches.
+pub fn query_row_blob(db: *mut sqlite3, sql: &str, params: &[&str]) -> Result<Option<Vec<u8>>> {
+    let column_blob = api()?
+        .column_blob
+        .ok_or_else(|| Error::new_message("sqlite3_column_blob is unavailable"))?;
+    let stmt = prepare_bound(db, sql, params)?;
+    let step_rc = unsafe { sqlite3ext_step(stmt) };
+    let data = if step_rc == SQLITE_ROW {
+        let ptr = unsafe { column_blob(stmt, 0) };
+        let len = unsafe { sqlite3ext_column_bytes(stmt, 0) } as usize;
+        Some(if ptr.is_null() || len == 0 {
+            Vec::new()
+        } else {
+            unsafe { std::slice::from_raw_parts(ptr.cast::<u8>(), len) }.to_vec()
+        })
+    } else {
+        None
+    };
+    unsafe { sqlite3ext_finalize(stmt) };
+    if step_rc != SQLITE_DONE && step_rc != SQLITE_ROW {
+        return Err(Error::new_message(format!(
+            "statement execution failed (code {})",
+            step_rc
+        )));
+    }
+    Ok(data)
+}
+
+fn prepare_bound(
+    db: *mut sqlite3,
+    sql: &str,
+    params: &[&str],
+) -> Result<*mut sqlite3ext_sys::sqlite3_stmt> {
+    let mut stmt = std::ptr::null_mut();
+    let sql_c =
+        CString::new(sql).map_err(|err| Error::new_message(format!("invalid sql: {:?}", err)))?;
+    let rc =
+        unsafe { sqlite3ext_prepare_v2(db, sql_c.as_ptr(), -1, &mut stmt, std::ptr::null_mut()) };
+    if rc != SQLITE_OKAY {
+        return Err(Error::new_message("failed to prepare statement"));
+    }
+
+    for (idx, value) in params.iter().enumerate() {
+        if let Err(err) = bind_text(stmt, (idx + 1) as i32, value) {
+            unsafe { sqlite3ext_finalize(stmt) };
+            return Err(err);
+        }
+    }
+    Ok(stmt)
+}
+
 pub fn exec_batch(db: *mut sqlite3, sql: &str) -> Result<()> {
     let sql_c =
         CString::new(sql).map_err(|err| Error::new_message(format!("invalid sql: {:?}", err)))?;
@@ -80,6 +178,73 @@ pub fn query_row_i64(db: *mut sqlite3, sql: &str, params: &[&str]) -> Result<i64
     }
 }
 
+/// Run `f` on state of type `T` attached to the connection under `name`. The
+/// state is created on first use and dropped by SQLite when the connection
+/// closes, so nothing outlives the handle it belongs to.
+pub fn with_connection_state<T: Default, R>(
+    db: *mut sqlite3,
+    name: &CStr,
+    f: impl FnOnce(&mut T) -> R,
+) -> Result<R> {
+    unsafe extern "C" fn drop_state<T>(state: *mut c_void) {
+        drop(Box::from_raw(state.cast::<T>()));
+    }
+
+    let api = api()?;
+    let (get_clientdata, set_clientdata) = api
+        .get_clientdata
+        .zip(api.set_clientdata)
+        .ok_or_else(|| Error::new_message("connection client data requires SQLite 3.44.0+"))?;
+    let mut state = unsafe { get_clientdata(db, name.as_ptr()) }.cast::<T>();
+    if state.is_null() {
+        state = Box::into_raw(Box::<T>::default());
+        // On failure SQLite runs the destructor itself.
+        let rc = unsafe {
+            set_clientdata(
+                db,
+                name.as_ptr(),
+                state.cast::<c_void>(),
+                Some(drop_state::<T>),
+            )
+        };
+        if rc != SQLITE_OKAY {
+            return Err(Error::new_message("failed to attach connection state"));
+        }
+    }
+    // Calls on a connection are serialized by SQLite and `f` does not call
+    // back into it, so this is the only live reference.
+    Ok(f(unsafe { &mut *state }))
+}
+
+/// Quote an identifier for interpolation into SQL text.
+pub fn quote_ident(name: &str) -> String {
+    format!("\"{}\"", name.replace('"', "\"\""))
+}
+
+/// Column names and declared types of `table` in `schema`, in table order.
+pub fn table_columns(db: *mut sqlite3, schema: &str, table: &str) -> Result<Vec<(String, String)>> {
+    let mut stmt = sqlite_loadable::exec::Statement::prepare(
+        db,
+        "select name, type from pragma_table_info(?1, ?2) order by cid",
+    )
+    .map_err(|err| Error::new_message(format!("failed to prepare table_info: {:?}", err)))?;
+    stmt.bind_text(1, table)
+        .map_err(|err| Error::new_message(format!("failed to bind table: {:?}", err)))?;
+    stmt.bind_text(2, schema)
+        .map_err(|err| Error::new_message(format!("failed to bind schema: {:?}", err)))?;
+    let mut columns = Vec::new();
+    for row in stmt.execute() {
+        let row =
+            row.map_err(|err| Error::new_message(format!("failed to read row: {:?}", err)))?;
+        let name = row
+            .get::<String>(0)
+            .map_err(|err| Error::new_message(format!("failed to read column: {:?}", err)))?;
+        let decl_type = row.get::<String>(1).unwrap_or_default();
+        columns.push((name, decl_type));
+    }
+    Ok(columns)
+}
+
 pub fn query_row_strings(
     db: *mut sqlite3,
     sql: &str,
@@ -128,11 +293,8 @@ unsafe extern "C" fn cstring_destructor(raw: *mut c_void) {
     drop(CString::from_raw(raw.cast::<c_char>()));
 }
 
-pub fn result_json_value(
-    _db: *mut sqlite3,
-    context: *mut sqlite3_context,
-    raw: &str,
-) -> Result<()> {
+/// Return stored JSON that was not written through `payload::encode_json`.
+pub fn result_json_text(context: *mut sqlite3_context, raw: &str) -> Result<()> {
     if raw.trim().is_empty() {
         sqlite_loadable::api::result_null(context);
         return Ok(());
@@ -142,6 +304,17 @@ pub fn result_json_value(
     Ok(())
 }
 
+pub fn result_json_value(db: *mut sqlite3, context: *mut sqlite3_context, raw: &str) -> Result<()> {
+    if raw.trim().is_empty() {
+        sqlite_loadable::api::result_null(context);
+        return Ok(());
+    }
+    let raw = payload::decode_json(db, raw)?;
+    sqlite_loadable::api::result_text(context, &raw)?;
+    sqlite_loadable::api::result_subtype(context, b'J');
+    Ok(())
+}
+
 pub fn now_ms() -> i64 {
     SystemTime::now()
         .duration_since(UNIX_EPOCH)
@@ -0,0 +1,466 @@
+use crate::schema;
+use crate::spawn;
+use crate::sql;
+use crate::validate;
+use serde_json::Value as JsonValue;
+use sqlite3ext_sys::sqlite3;
+use sqlite_loadable::prelude::*;
+use sqlite_loadable::{
+    api,
+    table::{BestIndexError, ConstraintOperator, IndexInfo, VTab, VTabArguments, VTabCursor},
+    Error, Result,
+};
+use std::os::raw::c_int;
+
+/// Spawn options a task type may supply defaults for.
+const DEFAULT_OPTION_KEYS: &[&str] = &[
+    "headers",
+    "retry_strategy",
+    "max_attempts",
+    "cancellation",
+    "priority",
+];
+
+/// Optional descriptive and scheduling fields accepted in `metadata_json`.
+#[derive(Default)]
+struct TaskTypeMetadata {
+    description: Option<String>,
+    owner_team: Option<String>,
+    claim_timeout: Option<i64>,
+    concurrency_limit: Option<i64>,
+}
+
+pub struct TaskType {
+    pub json_schema: Option<JsonValue>,
+    pub default_options: Option<String>,
+}
+
+/// Load the registered type for `task_name` on the queue, if any.
+pub fn load_task_type(
+    db: *mut sqlite3,
+    queue_name: &str,
+    task_name: &str,
+) -> Result<Option<TaskType>> {
+    let mut stmt = sqlite_loadable::exec::Statement::prepare(
+        db,
+        "select coalesce(json(json_schema), ''), coalesce(json(default_options), '')
+           from absurd_task_types
+          where queue_name = ?1 and task_name = ?2",
+    )
+    .map_err(|err| Error::new_message(format!("failed to prepare task type: {:?}", err)))?;
+    stmt.bind_text(1, queue_name)
+        .map_err(|err| Error::new_message(format!("failed to bind queue_name: {:?}", err)))?;
+    stmt.bind_text(2, task_name)
+        .map_err(|err| Error::new_message(format!("failed to bind task_name: {:?}", err)))?;
+    let mut rows = stmt.execute();
+    let row = match rows.next() {
+        Some(Ok(row)) => row,
+        Some(Err(err)) => {
+            return Err(Error::new_message(format!(
+                "failed to read task type: {:?}",
+                err
+            )))
+        }
+        None => return Ok(None),
+    };
+    let schema_raw = row
+        .get::<String>(0)
+        .map_err(|err| Error::new_message(format!("failed to read json_schema: {:?}", err)))?;
+    let options_raw = row
+        .get::<String>(1)
+        .map_err(|err| Error::new_message(format!("failed to read default_options: {:?}", err)))?;
+    let json_schema = if schema_raw.is_empty() {
+        None
+    } else {
+        Some(
+            serde_json::from_str(&schema_raw)
+                .map_err(|err| Error::new_message(format!("invalid json_schema: {:?}", err)))?,
+        )
+    };
+    Ok(Some(TaskType {
+        json_schema,
+        default_options: Some(options_raw).filter(|raw| !raw.is_empty()),
+    }))
+}
+
+fn optional_text(value: Option<&*mut sqlite3_value>) -> Result<Option<&str>> {
+    match value {
+        Some(value) if !api::value_is_null(value) => Ok(Some(api::value_text(value)?)),
+        _ => Ok(None),
+    }
+}
+
+fn check_default_options(raw: &str) -> Result<()> {
+    let parsed: JsonValue = serde_json::from_str(raw).map_err(|err| {
+        Error::new_message(format!("default_options must be valid JSON: {:?}", err))
+    })?;
+    let obj = parsed
+        .as_object()
+        .ok_or_else(|| Error::new_message("default_options must be a JSON object"))?;
+    if let Some(key) = obj
+        .keys()
+        .find(|key| !DEFAULT_OPTION_KEYS.contains(&key.as_str()))
+    {
+        return Err(Error::new_message(format!(
+            "unsupported default option: {}",
+            key
+        )));
+    }
+    spawn::parse_spawn_options(raw, None)?;
+    Ok(())
+}
+
+fn parse_metadata(raw: &str) -> Result<TaskTypeMetadata> {
+    let parsed: JsonValue = serde_json::from_str(raw)
+        .map_err(|err| Error::new_message(format!("metadata must be valid JSON: {:?}", err)))?;
+    let obj = parsed
+        .as_object()
+        .ok_or_else(|| Error::new_message("metadata must be a JSON object"))?;
+    let mut metadata = TaskTypeMetadata::default();
+    for (key, value) in obj {
+        if value.is_null() {
+            continue;
+        }
+        match key.as_str() {
+            "description" | "owner_team" => {
+                let text = value
+                    .as_str()
+                    .ok_or_else(|| Error::new_message(format!("{} must be a string", key)))?
+                    .to_string();
+                if key == "description" {
+                    metadata.description = Some(text);
+                } else {
+                    metadata.owner_team = Some(text);
+                }
+            }
+            "claim_timeout" | "concurrency_limit" => {
+                let number = value.as_i64().filter(|n| *n > 0).ok_or_else(|| {
+                    Error::new_message(format!("{} must be a positive integer", key))
+                })?;
+                if key == "claim_timeout" {
+                    metadata.claim_timeout = Some(number);
+                } else {
+                    metadata.concurrency_limit = Some(number);
+                }
+            }
+            other => {
+                return Err(Error::new_message(format!(
+                    "unsupported metadata field: {}",
+                    other
+                )))
+            }
+        }
+    }
+    Ok(metadata)
+}
+
+/// SQL: absurd_register_task_type(queue_name, task_name, json_schema_or_null[, default_options_json[, metadata_json]])
+/// Usage: register or replace a task type; spawns validate params against json_schema and merge their options over default_options; metadata may set description, owner_team, claim_timeout (overrides the worker's) and concurrency_limit.
+/// Section: Durable
+pub fn absurd_register_task_type(
+    context: *mut sqlite3_context,
+    values: &[*mut sqlite3_value],
+) -> Result<()> {
+    let queue_name = api::value_text_notnull(values.first().expect("queue_name"))?;
+    let task_name = api::value_text_notnull(values.get(1).expect("task_name"))?;
+    validate::queue_name(queue_name)?;
+    validate::task_name(task_name)?;
+    let json_schema = optional_text(values.get(2))?;
+    let default_options = optional_text(values.get(3))?;
+
+    if let Some(raw) = json_schema {
+        let parsed: JsonValue = serde_json::from_str(raw).map_err(|err| {
+            Error::new_message(format!("json_schema must be valid JSON: {:?}", err))
+        })?;
+        schema::check_schema(&parsed).map_err(|violation| {
+            Error::new_message(format!("invalid json_schema at {}", violation))
+        })?;
+    }
+    if let Some(raw) = default_options {
+        check_default_options(raw)?;
+    }
+    let metadata = match optional_text(values.get(4))? {
+        Some(raw) => parse_metadata(raw)?,
+        None => TaskTypeMetadata::default(),
+    };
+
+    let db = api::context_db_handle(context);
+    let exists = sql::query_row_i64(
+        db,
+        "select count(*) from absurd_queues where queue_name = ?1",
+        &[queue_name],
+    )?;
+    if exists == 0 {
+        return Err(Error::new_message("Queue not found"));
+    }
+
+    let now_value = sql::now_ms_from_db(db).to_string();
+    sql::exec_with_bind_text(
+        db,
+        "insert into absurd_task_types (queue_name, task_name, json_schema, default_options, description, owner_team, claim_timeout, concurrency_limit, created_at, updated_at)
+         values (?1, ?2, case when ?3 = '' then null else jsonb(?3) end, case when ?4 = '' then null else jsonb(?4) end, nullif(?5, ''), nullif(?6, ''), cast(nullif(?7, '') as integer), cast(nullif(?8, '') as integer), cast(?9 as integer), cast(?9 as integer))
+         on conflict (queue_name, task_name) do update set
+           json_schema = excluded.json_schema,
+           default_options = excluded.default_options,
+           description = excluded.description,
+           owner_team = excluded.owner_team,
+           claim_timeout = excluded.claim_timeout,
+           concurrency_limit = excluded.concurrency_limit,
+           updated_at = excluded.updated_at",
+        &[
+            queue_name,
+            task_name,
+            json_schema.unwrap_or(""),
+            default_options.unwrap_or(""),
+            metadata.description.as_deref().unwrap_or(""),
+            metadata.owner_team.as_deref().unwrap_or(""),
+            &metadata
+                .claim_timeout
+                .map(|v| v.to_string())
+                .unwrap_or_default(),
+            &metadata
+                .concurrency_limit
+                .map(|v| v.to_string())
+                .unwrap_or_default(),
+            &now_value,
+        ],
+    )?;
+
+    api::result_int64(context, 1);
+    Ok(())
+}
+
+/// SQL: absurd_unregister_task_type(queue_name, task_name)
+/// Usage: remove a registered task type; returns 1 if it existed.
+/// Section: Durable
+pub fn absurd_unregister_task_type(
+    context: *mut sqlite3_context,
+    values: &[*mut sqlite3_value],
+) -> Result<()> {
+    let queue_name = api::value_text_notnull(values.first().expect("queue_name"))?;
+    let task_name = api::value_text_notnull(values.get(1).expect("task_name"))?;
+
+    let db = api::context_db_handle(context);
+    sql::exec_with_bind_text(
+        db,
+        "delete from absurd_task_types where queue_name = ?1 and task_name = ?2",
+        &[queue_name, task_name],
+    )?;
+    let changes = sql::query_row_i64(db, "select changes()", &[])?;
+    api::result_int64(context, changes);
+    Ok(())
+}
+
+static LIST_TASK_TYPES_SQL: &str = "CREATE TABLE x(task_name TEXT, description TEXT, owner_team TEXT, json_schema BLOB, default_options BLOB, claim_timeout INTEGER, concurrency_limit INTEGER, pending INTEGER, running INTEGER, sleeping INTEGER, completed INTEGER, failed INTEGER, cancelled INTEGER, updated_at INTEGER, queue_name TEXT hidden)";
+
+struct TaskTypeRow {
+    task_name: String,
+    description: String,
+    owner_team: String,
+    json_schema: String,
+    default_options: String,
+    claim_timeout: i64,
+    concurrency_limit: i64,
+    counts: [i64; 6],
+    updated_at: i64,
+}
+
+/// SQL: absurd_list_task_types(queue_name)
+/// Usage: list registered task types with their metadata and live task counts by state.
+/// Section: Durable
+#[repr(C)]
+pub struct ListTaskTypesTable {
+    base: sqlite3_vtab,
+    db: *mut sqlite3,
+}
+
+impl<'vtab> VTab<'vtab> for ListTaskTypesTable {
+    type Aux = ();
+    type Cursor = ListTaskTypesCursor;
+
+    fn connect(
+        db: *mut sqlite3,
+        _aux: Option<&Self::Aux>,
+        _args: VTabArguments,
+    ) -> Result<(String, ListTaskTypesTable)> {
+        let base: sqlite3_vtab = unsafe { std::mem::zeroed() };
+        let vtab = ListTaskTypesTable { base, db };
+        Ok((LIST_TASK_TYPES_SQL.to_owned(), vtab))
+    }
+
+    fn destroy(&self) -> Result<()> {
+        Ok(())
+    }
+
+    fn best_index(&self, mut info: IndexInfo) -> core::result::Result<(), BestIndexError> {
+        let mut has_queue = false;
+
+        for mut constraint in info.constraints() {
+            if constraint.column_idx() == 14 {
+                if constraint.usable() && constraint.op() == Some(ConstraintOperator::EQ) {
+                    constraint.set_omit(true);
+                    constraint.set_argv_index(1);
+                    has_queue = true;
+                } else {
+                    return Err(BestIndexError::Constraint);
+                }
+            }
+        }
+
+        if !has_queue {
+            return Err(BestIndexError::Error);
+        }
+
+        info.set_estimated_cost(10.0);
+        info.set_estimated_rows(10);
+        info.set_idxnum(1);
+        Ok(())
+    }
+
+    fn open(&mut self) -> Result<ListTaskTypesCursor> {
+        Ok(ListTaskTypesCursor::new(self.db))
+    }
+}
+
+#[repr(C)]
+pub struct ListTaskTypesCursor {
+    base: sqlite3_vtab_cursor,
+    db: *mut sqlite3,
+    rowid: i64,
+    rows: Vec<TaskTypeRow>,
+}
+
+impl ListTaskTypesCursor {
+    fn new(db: *mut sqlite3) -> ListTaskTypesCursor {
+        let base: sqlite3_vtab_cursor = unsafe { std::mem::zeroed() };
+        ListTaskTypesCursor {
+            base,
+            db,
+            rowid: 0,
+            rows: Vec::new(),
+        }
+    }
+}
+
+impl VTabCursor for ListTaskTypesCursor {
+    fn filter(
+        &mut self,
+        _idx_num: c_int,
+        _idx_str: Option<&str>,
+        values: &[*mut sqlite3_value],
+    ) -> Result<()> {
+        let queue_name = api::value_text_notnull(values.first().expect("queue_name"))?;
+        validate::queue_name(queue_name)?;
+
+        let mut stmt = sqlite_loadable::exec::Statement::prepare(
+            self.db,
+            "select tt.task_name,
+                    coalesce(tt.description, ''),
+                    coalesce(tt.owner_team, ''),
+                    coalesce(json(tt.json_schema), ''),
+                    coalesce(json(tt.default_options), ''),
+                    coalesce(tt.claim_timeout, 0),
+                    coalesce(tt.concurrency_limit, 0),
+                    count(t.task_id) filter (where t.state = 'pending'),
+                    count(t.task_id) filter (where t.state = 'running'),
+                    count(t.task_id) filter (where t.state = 'sleeping'),
+                    count(t.task_id) filter (where t.state = 'completed'),
+                    count(t.task_id) filter (where t.state = 'failed'),
+                    count(t.task_id) filter (where t.state = 'cancelled'),
+                    tt.updated_at
+               from absurd_task_types tt
+               left join absurd_tasks t
+                 on t.queue_name = tt.queue_name
+                and t.task_name = tt.task_name
+              where tt.queue_name = ?1
+              group by tt.task_name
+              order by tt.task_name",
+        )
+        .map_err(|err| {
+            Error::new_message(format!("failed to prepare task type list: {:?}", err))
+        })?;
+        stmt.bind_text(1, queue_name)
+            .map_err(|err| Error::new_message(format!("failed to bind queue_name: {:?}", err)))?;
+
+        let mut rows = Vec::new();
+        for row in stmt.execute() {
+            let row =
+                row.map_err(|err| Error::new_message(format!("failed to read row: {:?}", err)))?;
+            let text = |idx: i32| {
+                row.get::<String>(idx).map_err(|err| {
+                    Error::new_message(format!("failed to read task type: {:?}", err))
+                })
+            };
+            let int = |idx: i32| {
+                row.get::<i64>(idx).map_err(|err| {
+                    Error::new_message(format!("failed to read task type: {:?}", err))
+                })
+            };
+            rows.push(TaskTypeRow {
+                task_name: text(0)?,
+                description: text(1)?,
+                owner_team: text(2)?,
+                json_schema: text(3)?,
+                default_options: text(4)?,
+                claim_timeout: int(5)?,
+                concurrency_limit: int(6)?,
+                counts: [int(7)?, int(8)?, int(9)?, int(10)?, int(11)?, int(12)?],
+                updated_at: int(13)?,
+            });
+        }
+        self.rows = rows;
+        self.rowid = 0;
+        Ok(())
+    }
+
+    fn next(&mut self) -> Result<()> {
+        self.rowid += 1;
+        Ok(())
+    }
+
+    fn eof(&self) -> bool {
+        self.rowid as usize >= self.rows.len()
+    }
+
+    fn column(&self, context: *mut sqlite3_context, i: c_int) -> Result<()> {
+        let record = match self.rows.get(self.rowid as usize) {
+            Some(record) => record,
+            None => {
+                api::result_null(context);
+                return Ok(());
+            }
+        };
+        let optional_text = |value: &str| -> Result<()> {
+            if value.is_empty() {
+                api::result_null(context);
+                Ok(())
+            } else {
+                api::result_text(context, value)
+            }
+        };
+        let optional_int = |value: i64| {
+            if value == 0 {
+                api::result_null(context);
+            } else {
+                api::result_int64(context, value);
+            }
+        };
+        match i {
+            0 => api::result_text(context, &record.task_name)?,
+            1 => optional_text(&record.description)?,
+            2 => optional_text(&record.owner_team)?,
+            3 => sql::result_json_text(context, &record.json_schema)?,
+            4 => sql::result_json_text(context, &record.default_options)?,
+            5 => optional_int(record.claim_timeout),
+            6 => optional_int(record.concurrency_limit),
+            7..=12 => api::result_int64(context, record.counts[(i - 7) as usize]),
+            13 => api::result_int64(context, record.updated_at),
+            _ => api::result_null(context),
+        }
+        Ok(())
+    }
+
+    fn rowid(&self) -> Result<i64> {
+        Ok(self.rowid)
+    }
+}
@@ -40,3 +40,12 @@ pub fn event_name(event_name: &str) -> Result<()> {
     }
     Ok(())
 }
+
+pub fn signal_name(signal_name: &str) -> Result<()> {
+    if signal_name.trim().is_empty() {
+        return Err(sqlite_loadable::Error::new_message(
+            "signal_name must be provided",
+        ));
+    }
+    Ok(())
+}
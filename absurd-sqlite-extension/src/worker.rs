@@ -0,0 +1,312 @@
+use crate::sql;
+use serde_json::Value as JsonValue;
+use sqlite3ext_sys::sqlite3;
+use sqlite_loadable::prelude::*;
+use sqlite_loadable::{
+    api,
+    table::{BestIndexError, IndexInfo, VTab, VTabArguments, VTabCursor},
+    Error, Result,
+};
+use std::os::raw::c_int;
+
+const LIST_WORKERS_SQL: &str = "CREATE TABLE x(worker_id TEXT, hostname TEXT, pid INTEGER, queues BLOB, metadata BLOB, first_seen INTEGER, last_seen INTEGER, running_count INTEGER)";
+
+struct WorkerRow {
+    worker_id: String,
+    hostname: String,
+    pid: i64,
+    queues: String,
+    metadata: String,
+    first_seen: i64,
+    last_seen: i64,
+    running_count: i64,
+}
+
+/// Split a `hostname:pid` worker id (the SDK default) into its parts.
+fn parse_worker_identity(worker_id: &str) -> (Option<&str>, Option<i64>) {
+    match worker_id.rsplit_once(':') {
+        Some((host, pid)) if !host.is_empty() => match pid.parse::<i64>() {
+            Ok(pid) if pid > 0 => (Some(host), Some(pid)),
+            _ => (None, None),
+        },
+        _ => (None, None),
+    }
+}
+
+/// Record that `worker_id` is alive and serving `queue_name`.
+///
+/// Called from claim and heartbeat paths inside their transactions.
+pub fn touch_worker(db: *mut sqlite3, worker_id: &str, queue_name: &str, now: i64) -> Result<()> {
+    let (hostname, pid) = parse_worker_identity(worker_id);
+    let pid_value = pid.map(|value| value.to_string()).unwrap_or_default();
+    let now_value = now.to_string();
+    sql::exec_with_bind_text(
+        db,
+        "insert into absurd_workers (worker_id, hostname, pid, queues, first_seen, last_seen)
+         values (?1, nullif(?2, ''), cast(nullif(?3, '') as integer), json_array(?4), cast(?5 as integer), cast(?5 as integer))
+         on conflict(worker_id) do update
+            set last_seen = excluded.last_seen,
+                hostname = coalesce(absurd_workers.hostname, excluded.hostname),
+                pid = coalesce(absurd_workers.pid, excluded.pid),
+                queues = case
+                  when exists (select 1 from json_each(absurd_workers.queues) where value = ?4)
+                    then absurd_workers.queues
+                  else json_insert(absurd_workers.queues, '$[#]', ?4)
+                end",
+        &[
+            worker_id,
+            hostname.unwrap_or(""),
+            &pid_value,
+            queue_name,
+            &now_value,
+        ],
+    )
+}
+
+/// SQL: absurd_register_worker(worker_id[, metadata_json])
+/// Usage: register a worker with optional metadata (hostname/pid keys fill their columns).
+/// Section: Durable
+pub fn absurd_register_worker(
+    context: *mut sqlite3_context,
+    values: &[*mut sqlite3_value],
+) -> Result<()> {
+    let worker_id = api::value_text_notnull(values.first().expect("worker_id"))?.trim();
+    if worker_id.is_empty() {
+        return Err(Error::new_message("worker_id must be provided"));
+    }
+
+    let metadata_raw = values
+        .get(1)
+        .and_then(|value| {
+            if api::value_is_null(value) {
+                None
+            } else {
+                let raw = api::value_text(value).ok()?.trim().to_string();
+                if raw.is_empty() {
+                    None
+                } else {
+                    Some(raw)
+                }
+            }
+        })
+        .unwrap_or_default();
+
+    let (parsed_host, parsed_pid) = parse_worker_identity(worker_id);
+    let mut hostname = parsed_host.map(str::to_string);
+    let mut pid = parsed_pid;
+    if !metadata_raw.is_empty() {
+        let metadata: JsonValue = serde_json::from_str(&metadata_raw)
+            .map_err(|err| Error::new_message(format!("metadata must be valid JSON: {:?}", err)))?;
+        let object = metadata
+            .as_object()
+            .ok_or_else(|| Error::new_message("metadata must be a JSON object"))?;
+        if let Some(value) = object.get("hostname").and_then(JsonValue::as_str) {
+            hostname = Some(value.to_string());
+        }
+        if let Some(value) = object.get("pid").and_then(JsonValue::as_i64) {
+            pid = Some(value);
+        }
+    }
+
+    let db = api::context_db_handle(context);
+    let now_value = sql::now_ms_from_db(db).to_string();
+    let pid_value = pid.map(|value| value.to_string()).unwrap_or_default();
+    sql::exec_with_bind_text(
+        db,
+        "insert into absurd_workers (worker_id, hostname, pid, metadata, first_seen, last_seen)
+         values (?1, nullif(?2, ''), cast(nullif(?3, '') as integer),
+                 case when ?4 = '' then null else jsonb(?4) end,
+                 cast(?5 as integer), cast(?5 as integer))
+         on conflict(worker_id) do update
+            set last_seen = excluded.last_seen,
+                hostname = coalesce(excluded.hostname, absurd_workers.hostname),
+                pid = coalesce(excluded.pid, absurd_workers.pid),
+                metadata = coalesce(excluded.metadata, absurd_workers.metadata)",
+        &[
+            worker_id,
+            hostname.as_deref().unwrap_or(""),
+            &pid_value,
+            &metadata_raw,
+            &now_value,
+        ],
+    )?;
+
+    api::result_int64(context, 1);
+    Ok(())
+}
+
+/// SQL: absurd_list_workers()
+/// Usage: list known workers with queues served, liveness timestamps and running claims.
+/// Section: Durable
+#[repr(C)]
+pub struct ListWorkersTable {
+    base: sqlite3_vtab,
+    db: *mut sqlite3,
+}
+
+impl<'vtab> VTab<'vtab> for ListWorkersTable {
+    type Aux = ();
+    type Cursor = ListWorkersCursor;
+
+    fn connect(
+        db: *mut sqlite3,
+        _aux: Option<&Self::Aux>,
+        _args: VTabArguments,
+    ) -> Result<(String, ListWorkersTable)> {
+        let base: sqlite3_vtab = unsafe { std::mem::zeroed() };
+        let vtab = ListWorkersTable { base, db };
+        Ok((LIST_WORKERS_SQL.to_owned(), vtab))
+    }
+
+    fn destroy(&self) -> Result<()> {
+        Ok(())
+    }
+
+    fn best_index(&self, mut info: IndexInfo) -> core::result::Result<(), BestIndexError> {
+        info.set_estimated_cost(10.0);
+        info.set_estimated_rows(10);
+        info.set_idxnum(1);
+        Ok(())
+    }
+
+    fn open(&mut self) -> Result<ListWorkersCursor> {
+        Ok(ListWorkersCursor::new(self.db))
+    }
+}
+
+#[repr(C)]
+pub struct ListWorkersCursor {
+    base: sqlite3_vtab_cursor,
+    db: *mut sqlite3,
+    rowid: i64,
+    rows: Vec<WorkerRow>,
+}
+
+impl ListWorkersCursor {
+    fn new(db: *mut sqlite3) -> ListWorkersCursor {
+        let base: sqlite3_vtab_cursor = unsafe { std::mem::zeroed() };
+        ListWorkersCursor {
+            base,
+            db,
+            rowid: 0,
+            rows: Vec::new(),
+        }
+    }
+}
+
+impl VTabCursor for ListWorkersCursor {
+    fn filter(
+        &mut self,
+        _idx_num: c_int,
+        _idx_str: Option<&str>,
+        _values: &[*mut sqlite3_value],
+    ) -> Result<()> {
+        let mut stmt = sqlite_loadable::exec::Statement::prepare(
+            self.db,
+            "select w.worker_id,
+                    coalesce(w.hostname, '') as hostname,
+                    coalesce(w.pid, 0) as pid,
+                    w.queues,
+                    coalesce(json(w.metadata), '') as metadata,
+                    w.first_seen,
+                    w.last_seen,
+                    (select count(*)
+                       from absurd_runs r
+                      where r.claimed_by = w.worker_id
+                        and r.state = 'running') as running_count
+               from absurd_workers w
+              order by w.last_seen desc, w.worker_id",
+        )
+        .map_err(|err| Error::new_message(format!("failed to prepare worker list: {:?}", err)))?;
+        let mut rows = Vec::new();
+        for row in stmt.execute() {
+            let row = row.map_err(|err| {
+                Error::new_message(format!("failed to read worker row: {:?}", err))
+            })?;
+            let worker_id = row.get::<String>(0).map_err(|err| {
+                Error::new_message(format!("failed to read worker_id: {:?}", err))
+            })?;
+            let hostname = row
+                .get::<String>(1)
+                .map_err(|err| Error::new_message(format!("failed to read hostname: {:?}", err)))?;
+            let pid = row
+                .get::<i64>(2)
+                .map_err(|err| Error::new_message(format!("failed to read pid: {:?}", err)))?;
+            let queues = row
+                .get::<String>(3)
+                .map_err(|err| Error::new_message(format!("failed to read queues: {:?}", err)))?;
+            let metadata = row
+                .get::<String>(4)
+                .map_err(|err| Error::new_message(format!("failed to read metadata: {:?}", err)))?;
+            let first_seen = row.get::<i64>(5).map_err(|err| {
+                Error::new_message(format!("failed to read first_seen: {:?}", err))
+            })?;
+            let last_seen = row.get::<i64>(6).map_err(|err| {
+                Error::new_message(format!("failed to read last_seen: {:?}", err))
+            })?;
+            let running_count = row.get::<i64>(7).map_err(|err| {
+                Error::new_message(format!("failed to read running_count: {:?}", err))
+            })?;
+            rows.push(WorkerRow {
+                worker_id,
+                hostname,
+                pid,
+                queues,
+                metadata,
+                first_seen,
+                last_seen,
+                running_count,
+            });
+        }
+        self.rows = rows;
+        self.rowid = 0;
+        Ok(())
+    }
+
+    fn next(&mut self) -> Result<()> {
+        self.rowid += 1;
+        Ok(())
+    }
+
+    fn eof(&self) -> bool {
+        self.rowid as usize >= self.rows.len()
+    }
+
+    fn column(&self, context: *mut sqlite3_context, i: c_int) -> Result<()> {
+        let record = match self.rows.get(self.rowid as usize) {
+            Some(record) => record,
+            None => {
+                api::result_null(context);
+                return Ok(());
+            }
+        };
+        match i {
+            0 => api::result_text(context, &record.worker_id)?,
+            1 => {
+                if record.hostname.is_empty() {
+                    api::result_null(context)
+                } else {
+                    api::result_text(context, &record.hostname)?
+                }
+            }
+            2 => {
+                if record.pid == 0 {
+                    api::result_null(context)
+                } else {
+                    api::result_int64(context, record.pid)
+                }
+            }
+            3 => sql::result_json_text(context, &record.queues)?,
+            4 => sql::result_json_text(context, &record.metadata)?,
+            5 => api::result_int64(context, record.first_seen),
+            6 => api::result_int64(context, record.last_seen),
+            7 => api::result_int64(context, record.running_count),
+            _ => api::result_null(context),
+        }
+        Ok(())
+    }
+
+    fn rowid(&self) -> Result<i64> {
+        Ok(self.rowid)
+    }
+}
@@ -51,6 +51,8 @@ export interface SpawnOptions {
   queue?: string;
   cancellation?: CancellationPolicy;
   idempotencyKey?: string;
+  priority?: number;
+  supersedeKey?: string;
 }
 
 export interface ClaimedTask {
@@ -282,6 +284,8 @@ export class TaskContext {
     );
     const cache = new Map<string, JsonValue>();
     for (const row of result.rows) {
+      // Timed-out waits are resolved through awaitEvent, not the cache.
+      if (row.status === "timed_out") continue;
       cache.set(row.checkpoint_name, row.state);
     }
     return new TaskContext(
@@ -368,7 +372,7 @@ export class TaskContext {
        FROM absurd.get_task_checkpoint_state($1, $2, $3)`,
       [this.queueName, this.task.task_id, checkpointName]
     );
-    if (result.rows.length > 0) {
+    if (result.rows.length > 0 && result.rows[0].status !== "timed_out") {
       const state = result.rows[0].state;
       this.checkpointCache.set(checkpointName, state);
       return state;
@@ -430,8 +434,12 @@ export class TaskContext {
       this.task.event_payload = null;
       throw new TimeoutError(`Timed out waiting for event "${eventName}"`);
     }
-    const result = await this.con.query<{ should_suspend: number; payload: JsonValue }>(
-      `SELECT should_suspend, payload
+    const result = await this.con.query<{
+      should_suspend: number;
+      payload: JsonValue;
+      timed_out: number;
+    }>(
+      `SELECT should_suspend, payload, timed_out
         FROM absurd.await_event($1, $2, $3, $4, $5, $6)`,
       [
         this.queueName,
@@ -445,7 +453,12 @@ export class TaskContext {
     if (result.rows.length === 0) {
       throw new Error("Failed to await event");
     }
-    const { should_suspend, payload } = result.rows[0];
+    const { should_suspend, payload, timed_out } = result.rows[0];
+    if (timed_out) {
+      this.task.wake_event = null;
+      this.task.event_payload = null;
+      throw new TimeoutError(`Timed out waiting for event "${eventName}"`);
+    }
     if (!should_suspend) {
       this.checkpointCache.set(checkpointName, payload);
       this.task.event_payload = null;
@@ -994,6 +1007,12 @@ function normalizeSpawnOptions(options: SpawnOptions): JsonObject {
   if (options.idempotencyKey !== undefined) {
     normalized.idempotency_key = options.idempotencyKey;
   }
+  if (options.priority !== undefined) {
+    normalized.priority = options.priority;
+  }
+  if (options.supersedeKey !== undefined) {
+    normalized.supersede_key = options.supersedeKey;
+  }
   return normalized;
 }
 
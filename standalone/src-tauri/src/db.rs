@@ -1,4 +1,4 @@
-use anyhow::{Context, Result};
+use anyhow::Result;
 use rusqlite::Connection;
 use serde_json::Value;
 use std::path::{Path, PathBuf};
@@ -63,50 +63,12 @@ impl DatabaseHandle {
 
     pub fn connect(&self, app_handle: &AppHandle) -> Result<Connection> {
         let db_path = self.db_path(app_handle)?;
-        let conn = Connection::open(db_path)?;
-
-        log::info!("using SQLite version: {}", rusqlite::version());
-
-        let extension_path = resolve_extension_path(app_handle);
-        if extension_path.is_none() {
+        let extension_path = resolve_extension_path(app_handle).ok_or_else(|| {
             // fail early if no extension found
             log::error!("SQLite extension path could not be resolved");
-            return Err(anyhow::anyhow!("SQLite extension not found"));
-        }
-
-        log::debug!("Loading SQLite extension from {:?}", extension_path);
-        // Safety: extension from own build
-        unsafe {
-            if let Err(err) = conn
-                .load_extension_enable()
-                .context("enable extension loading")
-            {
-                log::error!("Failed to enable SQLite extension loading: {:#}", err);
-                return Err(err);
-            }
-            // remove the extension part from the path
-            let extension_path_no_ext = extension_path.unwrap().with_extension("");
-            if let Err(err) = conn
-                .load_extension(
-                    extension_path_no_ext.to_string_lossy().as_ref(),
-                    Some("sqlite3_absurd_init"),
-                )
-                .context("load SQLite extension")
-            {
-                log::error!("Failed to load SQLite extension: {:#}", err);
-                return Err(err);
-            }
-            if let Err(err) = conn
-                .load_extension_disable()
-                .context("disable extension loading")
-            {
-                log::error!("Failed to disable SQLite extension loading: {:#}", err);
-                return Err(err);
-            }
-        }
-        log::debug!("SQLite extension loaded successfully");
-
-        Ok(conn)
+            anyhow::anyhow!("SQLite extension not found")
+        })?;
+        absurd_server::db::open(&db_path, &extension_path)
     }
 }
 
@@ -117,7 +79,7 @@ pub fn extension_path(app_handle: &AppHandle) -> Result<String> {
 }
 
 fn resolve_extension_path(app_handle: &AppHandle) -> Option<PathBuf> {
-    let lib_name = extension_lib_name();
+    let lib_name = absurd_server::db::extension_lib_name();
     #[cfg(debug_assertions)]
     {
         let manifest_dir = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
@@ -185,13 +147,3 @@ fn resolve_extension_path(app_handle: &AppHandle) -> Option<PathBuf> {
     }
     None
 }
-
-fn extension_lib_name() -> String {
-    if cfg!(target_os = "windows") {
-        "absurd.dll".to_string()
-    } else if cfg!(target_os = "macos") {
-        "libabsurd.dylib".to_string()
-    } else {
-        "libabsurd.so".to_string()
-    }
-}
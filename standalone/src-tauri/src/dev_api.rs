@@ -1,45 +1,68 @@
-use axum::{
-    extract::{Path, Query, State},
-    routing::post,
-    Json, Router,
-};
+use absurd_server::auth::{self, ApiToken, AuthConfig, Scope};
+use absurd_server::procedures::ProcedureHost;
+use absurd_server::tls::{self, TlsOptions};
 use serde::{Deserialize, Serialize};
-use serde_json::{json, Value};
-use std::sync::Mutex;
+use serde_json::Value;
+use std::net::IpAddr;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
 use tauri::{AppHandle, Manager};
 use tauri_plugin_store::StoreExt;
 use tokio::sync::oneshot;
 use tokio::task::JoinHandle;
-use tower_http::cors::CorsLayer;
 
 use crate::db::DatabaseHandle;
-use crate::db_commands::{
-    CleanupQueueOptions, CleanupTarget, EventFilters, TaskRunFilters, TauriDataProvider,
-};
 use crate::worker;
 
 const DEV_API_PORT_DEFAULT: u16 = 11223;
 const DEV_API_PORT_ATTEMPTS: u16 = 10;
 const DEV_API_STORE_PATH: &str = "dev-api.json";
 const DEV_API_ENABLED_KEY: &str = "dev_api_enabled";
-
-#[derive(Clone)]
-struct DevApiContext {
+const DEV_API_BIND_ADDRESS_KEY: &str = "dev_api_bind_address";
+const DEV_API_TOKENS_KEY: &str = "dev_api_tokens";
+const DEV_API_TLS_KEY: &str = "dev_api_tls";
+const DEV_API_BIND_ADDRESS_DEFAULT: &str = "127.0.0.1";
+
+/// Serves the shared data procedures from the app's database, plus the
+/// worker controls that only exist in the desktop app.
+struct TauriHost {
     app_handle: AppHandle,
 }
 
-#[derive(Deserialize)]
-#[serde(rename_all = "camelCase")]
-struct TrpcRequest {
-    id: Option<Value>,
-    json: Option<Value>,
-    input: Option<Value>,
-}
+impl ProcedureHost for TauriHost {
+    fn connect(&self) -> anyhow::Result<rusqlite::Connection> {
+        let db_handle = self.app_handle.state::<DatabaseHandle>();
+        db_handle.connect(&self.app_handle)
+    }
 
-#[derive(Deserialize)]
-struct TrpcQuery {
-    input: Option<String>,
-    json: Option<String>,
+    fn db_path(&self) -> anyhow::Result<String> {
+        let db_handle = self.app_handle.state::<DatabaseHandle>();
+        db_handle.db_path(&self.app_handle)
+    }
+
+    fn handle_host_procedure(
+        &self,
+        procedure: &str,
+        input: Value,
+    ) -> Option<Result<Value, String>> {
+        let app_handle = &self.app_handle;
+        let result = match procedure {
+            "getWorkerStatus" => worker::get_worker_status_inner(app_handle)
+                .and_then(|status| serde_json::to_value(status).map_err(|err| err.to_string())),
+            "getWorkerLogs" => worker::get_worker_logs(app_handle.clone())
+                .and_then(|logs| serde_json::to_value(logs).map_err(|err| err.to_string())),
+            "setWorkerBinaryPath" => serde_json::from_value::<WorkerPathInput>(input)
+                .map_err(|err| err.to_string())
+                .and_then(|payload| worker::set_worker_binary_path_inner(app_handle, &payload.path))
+                .and_then(|status| serde_json::to_value(status).map_err(|err| err.to_string())),
+            "startWorker" => worker::start_worker_inner(app_handle)
+                .and_then(|status| serde_json::to_value(status).map_err(|err| err.to_string())),
+            "stopWorker" => worker::stop_worker_inner(app_handle)
+                .and_then(|status| serde_json::to_value(status).map_err(|err| err.to_string())),
+            _ => return None,
+        };
+        Some(result)
+    }
 }
 
 #[derive(Deserialize)]
@@ -55,10 +78,59 @@ pub struct DevApiStatus {
     pub running: bool,
     pub port: Option<u16>,
     pub desired_port: u16,
+    pub bind_address: Option<String>,
+    pub https: bool,
+    pub auth_required: bool,
+}
+
+/// HTTPS settings for the dev API. With `client_ca_path` set, clients must
+/// present a certificate signed by that CA and are granted `client_scope`.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DevApiTls {
+    pub cert_path: String,
+    pub key_path: String,
+    #[serde(default)]
+    pub client_ca_path: Option<String>,
+    #[serde(default = "default_client_scope")]
+    pub client_scope: Scope,
+}
+
+fn default_client_scope() -> Scope {
+    Scope::Read
+}
+
+/// A stored token as shown in settings; the hash never leaves the backend.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DevApiTokenInfo {
+    pub name: String,
+    pub scope: Scope,
+    pub created_at: Option<i64>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DevApiConfig {
+    pub bind_address: String,
+    pub tls: Option<DevApiTls>,
+    pub tokens: Vec<DevApiTokenInfo>,
+}
+
+/// Returned once when a token is created; only its hash is stored.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreatedDevApiToken {
+    pub name: String,
+    pub scope: Scope,
+    pub secret: String,
 }
 
 struct RunningDevApi {
     port: u16,
+    bind_address: IpAddr,
+    https: bool,
+    auth_required: bool,
     shutdown: oneshot::Sender<()>,
     handle: JoinHandle<()>,
 }
@@ -89,6 +161,13 @@ impl DevApiState {
             running,
             port,
             desired_port,
+            bind_address: running_guard
+                .as_ref()
+                .map(|server| server.bind_address.to_string()),
+            https: running_guard.as_ref().is_some_and(|server| server.https),
+            auth_required: running_guard
+                .as_ref()
+                .is_some_and(|server| server.auth_required),
         }
     }
 }
@@ -107,6 +186,89 @@ pub async fn set_dev_api_enabled(
     set_enabled(&app_handle, enabled).await
 }
 
+#[tauri::command]
+pub async fn get_dev_api_config(app_handle: AppHandle) -> Result<DevApiConfig, String> {
+    Ok(DevApiConfig {
+        bind_address: load_bind_address(&app_handle)?.to_string(),
+        tls: load_store_value(&app_handle, DEV_API_TLS_KEY)?,
+        tokens: load_tokens(&app_handle)?
+            .into_iter()
+            .map(|token| DevApiTokenInfo {
+                name: token.name,
+                scope: token.scope,
+                created_at: token.created_at,
+            })
+            .collect(),
+    })
+}
+
+#[tauri::command]
+pub async fn create_dev_api_token(
+    app_handle: AppHandle,
+    name: String,
+    scope: Scope,
+) -> Result<CreatedDevApiToken, String> {
+    let name = name.trim().to_string();
+    if name.is_empty() {
+        return Err("Token name is required".to_string());
+    }
+    let mut tokens = load_tokens(&app_handle)?;
+    if tokens.iter().any(|token| token.name == name) {
+        return Err(format!("A token named {:?} already exists", name));
+    }
+
+    let secret = auth::generate_secret().map_err(|err| err.to_string())?;
+    let mut token = ApiToken::from_secret(&name, scope, &secret);
+    token.created_at = Some(chrono::Utc::now().timestamp_millis());
+    tokens.push(token);
+    persist_store_value(&app_handle, DEV_API_TOKENS_KEY, &tokens)?;
+    restart_if_running(&app_handle).await?;
+
+    Ok(CreatedDevApiToken {
+        name,
+        scope,
+        secret,
+    })
+}
+
+#[tauri::command]
+pub async fn revoke_dev_api_token(app_handle: AppHandle, name: String) -> Result<(), String> {
+    let mut tokens = load_tokens(&app_handle)?;
+    let before = tokens.len();
+    tokens.retain(|token| token.name != name);
+    if tokens.len() == before {
+        return Err(format!("No token named {:?}", name));
+    }
+    persist_store_value(&app_handle, DEV_API_TOKENS_KEY, &tokens)?;
+    restart_if_running(&app_handle).await
+}
+
+#[tauri::command]
+pub async fn set_dev_api_network(
+    app_handle: AppHandle,
+    bind_address: String,
+    tls: Option<DevApiTls>,
+) -> Result<DevApiStatus, String> {
+    let bind_address = parse_bind_address(&bind_address)?;
+    if let Some(tls) = &tls {
+        // Validate the files up front so a bad path is reported here rather
+        // than when the server next starts.
+        tls::server_config(&tls_options(tls)).map_err(|err| format!("{:#}", err))?;
+    }
+    build_auth_config(&load_tokens(&app_handle)?, tls.as_ref()).check_bind(bind_address)?;
+
+    persist_store_value(
+        &app_handle,
+        DEV_API_BIND_ADDRESS_KEY,
+        &bind_address.to_string(),
+    )?;
+    persist_store_value(&app_handle, DEV_API_TLS_KEY, &tls)?;
+    restart_if_running(&app_handle).await?;
+
+    let state = app_handle.state::<DevApiState>();
+    Ok(state.status())
+}
+
 pub fn load_dev_api_enabled(app_handle: &AppHandle) -> Option<bool> {
     let store = app_handle.store(DEV_API_STORE_PATH).ok()?;
     if store.reload().is_err() {
@@ -126,42 +288,87 @@ fn persist_dev_api_enabled(app_handle: &AppHandle, enabled: bool) -> Result<(),
     store.save().map_err(|err| err.to_string())
 }
 
-pub fn parse_dev_api_enabled(value: Option<&serde_json::Value>) -> bool {
-    match value {
-        Some(Value::Bool(flag)) => *flag,
-        Some(Value::String(text)) => text.parse::<bool>().unwrap_or(false),
-        Some(Value::Null) => true,
-        Some(_) => true,
-        None => false,
+fn load_store_value<T: serde::de::DeserializeOwned>(
+    app_handle: &AppHandle,
+    key: &str,
+) -> Result<Option<T>, String> {
+    let store = app_handle
+        .store(DEV_API_STORE_PATH)
+        .map_err(|err| err.to_string())?;
+    let _ = store.reload();
+    match store.get(key) {
+        None | Some(Value::Null) => Ok(None),
+        Some(value) => serde_json::from_value(value)
+            .map(Some)
+            .map_err(|err| format!("Invalid {} setting: {}", key, err)),
     }
 }
 
-#[cfg(test)]
-mod tests {
-    use super::parse_dev_api_enabled;
-    use serde_json::json;
+fn persist_store_value<T: Serialize>(
+    app_handle: &AppHandle,
+    key: &str,
+    value: &T,
+) -> Result<(), String> {
+    let store = app_handle
+        .store(DEV_API_STORE_PATH)
+        .map_err(|err| err.to_string())?;
+    let value = serde_json::to_value(value).map_err(|err| err.to_string())?;
+    store.set(key, value);
+    store.save().map_err(|err| err.to_string())
+}
 
-    #[test]
-    fn parses_dev_api_enabled_true() {
-        let value = json!(true);
-        assert!(parse_dev_api_enabled(Some(&value)));
+fn load_tokens(app_handle: &AppHandle) -> Result<Vec<ApiToken>, String> {
+    Ok(load_store_value(app_handle, DEV_API_TOKENS_KEY)?.unwrap_or_default())
+}
+
+fn load_bind_address(app_handle: &AppHandle) -> Result<IpAddr, String> {
+    let stored: Option<String> = load_store_value(app_handle, DEV_API_BIND_ADDRESS_KEY)?;
+    parse_bind_address(stored.as_deref().unwrap_or(DEV_API_BIND_ADDRESS_DEFAULT))
+}
+
+fn parse_bind_address(value: &str) -> Result<IpAddr, String> {
+    let value = value.trim();
+    if value.is_empty() {
+        return DEV_API_BIND_ADDRESS_DEFAULT
+            .parse()
+            .map_err(|err: std::net::AddrParseError| err.to_string());
     }
+    value.parse().map_err(|_| {
+        format!(
+            "Invalid bind address {:?}; use an IP such as 0.0.0.0",
+            value
+        )
+    })
+}
 
-    #[test]
-    fn parses_dev_api_enabled_string() {
-        let value = json!("true");
-        assert!(parse_dev_api_enabled(Some(&value)));
+fn tls_options(tls: &DevApiTls) -> TlsOptions {
+    TlsOptions {
+        cert_path: PathBuf::from(&tls.cert_path),
+        key_path: PathBuf::from(&tls.key_path),
+        client_ca_path: tls
+            .client_ca_path
+            .as_deref()
+            .filter(|path| !path.trim().is_empty())
+            .map(PathBuf::from),
     }
+}
 
-    #[test]
-    fn parses_dev_api_enabled_null() {
-        let value = json!(null);
-        assert!(parse_dev_api_enabled(Some(&value)));
+fn build_auth_config(tokens: &[ApiToken], tls: Option<&DevApiTls>) -> AuthConfig {
+    AuthConfig {
+        tokens: tokens.to_vec(),
+        client_cert_scope: tls
+            .filter(|tls| tls_options(tls).client_ca_path.is_some())
+            .map(|tls| tls.client_scope),
     }
+}
 
-    #[test]
-    fn parses_dev_api_enabled_missing() {
-        assert!(!parse_dev_api_enabled(None));
+pub fn parse_dev_api_enabled(value: Option<&serde_json::Value>) -> bool {
+    match value {
+        Some(Value::Bool(flag)) => *flag,
+        Some(Value::String(text)) => text.parse::<bool>().unwrap_or(false),
+        Some(Value::Null) => true,
+        Some(_) => true,
+        None => false,
     }
 }
 
@@ -203,6 +410,20 @@ pub async fn ensure_running(app_handle: &AppHandle) -> Result<(), String> {
     Ok(())
 }
 
+/// Pick up changed settings by restarting the server if it is up.
+async fn restart_if_running(app_handle: &AppHandle) -> Result<(), String> {
+    let running = {
+        let state = app_handle.state::<DevApiState>();
+        let running = state.running.lock().unwrap().is_some();
+        running
+    };
+    if running {
+        stop_running(app_handle).await?;
+        ensure_running(app_handle).await?;
+    }
+    Ok(())
+}
+
 pub async fn stop_running(app_handle: &AppHandle) -> Result<(), String> {
     let state = app_handle.state::<DevApiState>();
     let running = {
@@ -219,20 +440,25 @@ pub async fn stop_running(app_handle: &AppHandle) -> Result<(), String> {
 }
 
 async fn start_server(app_handle: AppHandle, desired_port: u16) -> Result<RunningDevApi, String> {
-    let (listener, port) = bind_with_fallback(desired_port).await?;
+    let bind_address = load_bind_address(&app_handle)?;
+    let tls_settings: Option<DevApiTls> = load_store_value(&app_handle, DEV_API_TLS_KEY)?;
+    let auth = build_auth_config(&load_tokens(&app_handle)?, tls_settings.as_ref());
+    auth.check_bind(bind_address)?;
+    let tls = tls_settings
+        .as_ref()
+        .map(|tls| tls::server_config(&tls_options(tls)))
+        .transpose()
+        .map_err(|err| format!("{:#}", err))?;
+
+    let (listener, port) = bind_with_fallback(bind_address, desired_port).await?;
     let (shutdown_tx, shutdown_rx) = oneshot::channel();
+    let https = tls.is_some();
+    let auth_required = auth.is_enabled();
 
-    let context = DevApiContext { app_handle };
-    let router = Router::new()
-        .route(
-            "/absurd-data/:procedure",
-            post(trpc_post_handler).get(trpc_get_handler),
-        )
-        .with_state(context)
-        .layer(CorsLayer::permissive());
+    let router = absurd_server::http::router(Arc::new(TauriHost { app_handle }), auth);
 
     let handle = tokio::spawn(async move {
-        let server = axum::serve(listener, router).with_graceful_shutdown(async move {
+        let server = absurd_server::http::serve(listener, router, tls, async move {
             let _ = shutdown_rx.await;
         });
         if let Err(err) = server.await {
@@ -242,15 +468,21 @@ async fn start_server(app_handle: AppHandle, desired_port: u16) -> Result<Runnin
 
     Ok(RunningDevApi {
         port,
+        bind_address,
+        https,
+        auth_required,
         shutdown: shutdown_tx,
         handle,
     })
 }
 
-async fn bind_with_fallback(start_port: u16) -> Result<(tokio::net::TcpListener, u16), String> {
+async fn bind_with_fallback(
+    bind_address: IpAddr,
+    start_port: u16,
+) -> Result<(tokio::net::TcpListener, u16), String> {
     for offset in 0..DEV_API_PORT_ATTEMPTS {
         let port = start_port.saturating_add(offset);
-        match tokio::net::TcpListener::bind(("127.0.0.1", port)).await {
+        match tokio::net::TcpListener::bind((bind_address, port)).await {
             Ok(listener) => return Ok((listener, port)),
             Err(_) => continue,
         }
@@ -259,278 +491,64 @@ async fn bind_with_fallback(start_port: u16) -> Result<(tokio::net::TcpListener,
     Err("Failed to bind dev API server port after 10 attempts".to_string())
 }
 
-async fn trpc_post_handler(
-    Path(procedure): Path<String>,
-    State(context): State<DevApiContext>,
-    Json(payload): Json<TrpcRequest>,
-) -> Json<Value> {
-    let id = payload.id.unwrap_or(Value::Null);
-    let input = payload.json.or(payload.input).unwrap_or(Value::Null);
-    execute_trpc(&context, &procedure, id, input).await
-}
-
-async fn trpc_get_handler(
-    Path(procedure): Path<String>,
-    State(context): State<DevApiContext>,
-    Query(query): Query<TrpcQuery>,
-) -> Json<Value> {
-    let input = query
-        .input
-        .or(query.json)
-        .and_then(|value| serde_json::from_str::<Value>(&value).ok())
-        .unwrap_or(Value::Null);
-    execute_trpc(&context, &procedure, Value::Null, input).await
-}
-
-async fn execute_trpc(
-    context: &DevApiContext,
-    procedure: &str,
-    id: Value,
-    input: Value,
-) -> Json<Value> {
-    let app_handle = context.app_handle.clone();
-    let procedure_name = procedure.to_string();
-
-    let result =
-        tokio::task::spawn_blocking(move || handle_procedure(&app_handle, &procedure_name, input))
-            .await
-            .map_err(|err| err.to_string())
-            .and_then(|result| result);
-
-    let body = match result {
-        Ok(data) => json!({ "id": id, "result": { "data": data } }),
-        Err(message) => json!({
-            "id": id,
-            "error": {
-                "message": message,
-                "code": -32603,
-                "data": { "code": "INTERNAL_SERVER_ERROR", "httpStatus": 500 }
-            }
-        }),
-    };
-
-    Json(body)
-}
+#[cfg(test)]
+mod tests {
+    use super::{build_auth_config, parse_bind_address, parse_dev_api_enabled, DevApiTls};
+    use absurd_server::auth::Scope;
+    use serde_json::json;
 
-fn handle_procedure(
-    app_handle: &AppHandle,
-    procedure: &str,
-    input: Value,
-) -> Result<Value, String> {
-    match procedure {
-        "health" => Ok(json!({ "ok": true })),
-        "getOverviewMetrics" => with_provider(app_handle, |provider| {
-            let metrics = provider.get_overview_metrics()?;
-            Ok(serde_json::to_value(metrics)?)
-        }),
-        "getQueueMetrics" => with_provider(app_handle, |provider| {
-            let metrics = provider.get_queue_metrics()?;
-            Ok(serde_json::to_value(metrics)?)
-        }),
-        "getTaskRuns" => with_provider(app_handle, |provider| {
-            let runs = provider.get_task_runs()?;
-            Ok(serde_json::to_value(runs)?)
-        }),
-        "getTaskRunsForQueue" => {
-            let payload: QueueNameInput = parse_input(input)?;
-            with_provider(app_handle, |provider| {
-                let runs = provider.get_task_runs_for_queue(&payload.queue_name)?;
-                Ok(serde_json::to_value(runs)?)
-            })
-        }
-        "getTaskRunsPage" => {
-            let payload: TaskRunFilters = parse_input(input)?;
-            with_provider(app_handle, |provider| {
-                let page = provider.get_task_runs_page(payload)?;
-                Ok(serde_json::to_value(page)?)
-            })
-        }
-        "getTaskHistory" => {
-            let payload: TaskIdInput = parse_input(input)?;
-            with_provider(app_handle, |provider| {
-                let runs = provider.get_task_history(&payload.task_id)?;
-                Ok(serde_json::to_value(runs)?)
-            })
-        }
-        "getTaskInfo" => {
-            let payload: TaskIdInput = parse_input(input)?;
-            with_provider(app_handle, |provider| {
-                let info = provider.get_task_info(&payload.task_id)?;
-                Ok(serde_json::to_value(info)?)
-            })
-        }
-        "getTaskCheckpointStatuses" => {
-            let payload: TaskIdInput = parse_input(input)?;
-            with_provider(app_handle, |provider| {
-                let statuses = provider.get_task_checkpoint_statuses(&payload.task_id)?;
-                Ok(serde_json::to_value(statuses)?)
-            })
-        }
-        "getTaskCheckpoints" => {
-            let payload: TaskIdInput = parse_input(input)?;
-            with_provider(app_handle, |provider| {
-                let checkpoints = provider.get_task_checkpoints(&payload.task_id)?;
-                Ok(serde_json::to_value(checkpoints)?)
-            })
-        }
-        "getQueueNames" => with_provider(app_handle, |provider| {
-            let names = provider.get_queue_names()?;
-            Ok(serde_json::to_value(names)?)
-        }),
-        "getQueueSummaries" => with_provider(app_handle, |provider| {
-            let summaries = provider.get_queue_summaries()?;
-            Ok(serde_json::to_value(summaries)?)
-        }),
-        "getTaskNameOptions" => {
-            let payload: OptionalQueueNameInput = parse_optional_input(input)?;
-            with_provider(app_handle, |provider| {
-                let names = provider.get_task_name_options(payload.queue_name.as_deref())?;
-                Ok(serde_json::to_value(names)?)
-            })
-        }
-        "createQueue" => {
-            let payload: QueueNameInput = parse_input(input)?;
-            with_provider(app_handle, |provider| {
-                provider.create_queue(&payload.queue_name)?;
-                Ok(Value::Null)
-            })
-        }
-        "cleanupQueue" => {
-            let payload: CleanupQueueInput = parse_input(input)?;
-            with_provider(app_handle, |provider| {
-                let result = provider.cleanup_queue(payload.into())?;
-                Ok(serde_json::to_value(result)?)
-            })
-        }
-        "getEventFilterDefaults" => {
-            let payload: OptionalQueueNameInput = parse_optional_input(input)?;
-            with_provider(app_handle, |provider| {
-                let defaults = provider.get_event_filter_defaults(payload.queue_name.as_deref())?;
-                Ok(serde_json::to_value(defaults)?)
-            })
-        }
-        "getEvents" => with_provider(app_handle, |provider| {
-            let events = provider.get_events()?;
-            Ok(serde_json::to_value(events)?)
-        }),
-        "getFilteredEvents" => {
-            let payload: EventFilters = parse_input(input)?;
-            with_provider(app_handle, |provider| {
-                let events = provider.get_filtered_events(Some(payload))?;
-                Ok(serde_json::to_value(events)?)
-            })
-        }
-        "getSettingsInfo" => {
-            let db_handle = app_handle.state::<DatabaseHandle>();
-            let db_path = db_handle
-                .db_path(app_handle)
-                .map_err(|err| err.to_string())?;
-            with_provider(app_handle, |provider| {
-                let info = provider.get_settings_info(db_path)?;
-                Ok(serde_json::to_value(info)?)
-            })
-        }
-        "getWorkerStatus" => {
-            let status = worker::get_worker_status_inner(app_handle)?;
-            Ok(serde_json::to_value(status).map_err(|err| err.to_string())?)
-        }
-        "getWorkerLogs" => {
-            let logs = worker::get_worker_logs(app_handle.clone())?;
-            Ok(serde_json::to_value(logs).map_err(|err| err.to_string())?)
-        }
-        "setWorkerBinaryPath" => {
-            let payload: WorkerPathInput = parse_input(input)?;
-            let status = worker::set_worker_binary_path_inner(app_handle, &payload.path)?;
-            Ok(serde_json::to_value(status).map_err(|err| err.to_string())?)
-        }
-        "startWorker" => {
-            let status = worker::start_worker_inner(app_handle)?;
-            Ok(serde_json::to_value(status).map_err(|err| err.to_string())?)
-        }
-        "stopWorker" => {
-            let status = worker::stop_worker_inner(app_handle)?;
-            Ok(serde_json::to_value(status).map_err(|err| err.to_string())?)
-        }
-        "getMigrations" => with_provider(app_handle, |provider| {
-            let migrations = provider.get_migrations()?;
-            Ok(serde_json::to_value(migrations)?)
-        }),
-        "applyMigrationsAll" => with_provider(app_handle, |provider| {
-            let applied = provider.apply_migrations_all()?;
-            Ok(serde_json::to_value(applied)?)
-        }),
-        "applyMigration" => {
-            let payload: MigrationInput = parse_input(input)?;
-            with_provider(app_handle, |provider| {
-                let applied = provider.apply_migration(payload.migration_id)?;
-                Ok(serde_json::to_value(applied)?)
-            })
-        }
-        _ => Err(format!("Unknown procedure: {}", procedure)),
+    #[test]
+    fn parses_bind_address() {
+        assert_eq!(parse_bind_address("").unwrap().to_string(), "127.0.0.1");
+        assert_eq!(
+            parse_bind_address(" 0.0.0.0 ").unwrap().to_string(),
+            "0.0.0.0"
+        );
+        assert!(parse_bind_address("localhost").is_err());
     }
-}
 
-fn with_provider<T>(
-    app_handle: &AppHandle,
-    f: impl FnOnce(&TauriDataProvider) -> anyhow::Result<T>,
-) -> Result<T, String> {
-    let db_handle = app_handle.state::<DatabaseHandle>();
-    let conn = db_handle
-        .connect(app_handle)
-        .map_err(|err| err.to_string())?;
-    let provider = TauriDataProvider::new(&conn);
-    f(&provider).map_err(|err| err.to_string())
-}
-
-fn parse_input<T: for<'de> Deserialize<'de>>(value: Value) -> Result<T, String> {
-    serde_json::from_value(value).map_err(|err| err.to_string())
-}
-
-fn parse_optional_input<T: for<'de> Deserialize<'de> + Default>(value: Value) -> Result<T, String> {
-    if value.is_null() {
-        return Ok(T::default());
+    #[test]
+    fn client_scope_needs_a_client_ca() {
+        let tls: DevApiTls = serde_json::from_value(json!({
+            "certPath": "server.pem",
+            "keyPath": "server.key",
+            "clientCaPath": ""
+        }))
+        .unwrap();
+        assert_eq!(tls.client_scope, Scope::Read);
+        assert!(!build_auth_config(&[], Some(&tls)).is_enabled());
+
+        let tls = DevApiTls {
+            client_ca_path: Some("ca.pem".to_string()),
+            client_scope: Scope::Admin,
+            ..tls
+        };
+        assert_eq!(
+            build_auth_config(&[], Some(&tls)).client_cert_scope,
+            Some(Scope::Admin)
+        );
     }
-    serde_json::from_value(value).map_err(|err| err.to_string())
-}
 
-#[derive(Deserialize)]
-#[serde(rename_all = "camelCase")]
-struct QueueNameInput {
-    queue_name: String,
-}
-
-#[derive(Default, Deserialize)]
-#[serde(rename_all = "camelCase")]
-struct OptionalQueueNameInput {
-    queue_name: Option<String>,
-}
-
-#[derive(Deserialize)]
-#[serde(rename_all = "camelCase")]
-struct TaskIdInput {
-    task_id: String,
-}
+    #[test]
+    fn parses_dev_api_enabled_true() {
+        let value = json!(true);
+        assert!(parse_dev_api_enabled(Some(&value)));
+    }
 
-#[derive(Deserialize)]
-#[serde(rename_all = "camelCase")]
-struct MigrationInput {
-    migration_id: i64,
-}
+    #[test]
+    fn parses_dev_api_enabled_string() {
+        let value = json!("true");
+        assert!(parse_dev_api_enabled(Some(&value)));
+    }
 
-#[derive(Deserialize)]
-#[serde(rename_all = "camelCase")]
-struct CleanupQueueInput {
-    queue_name: String,
-    target: CleanupTarget,
-    ttl_seconds: i64,
-}
+    #[test]
+    fn parses_dev_api_enabled_null() {
+        let value = json!(null);
+        assert!(parse_dev_api_enabled(Some(&value)));
+    }
 
-impl From<CleanupQueueInput> for CleanupQueueOptions {
-    fn from(value: CleanupQueueInput) -> Self {
-        Self {
-            queue_name: value.queue_name,
-            target: value.target,
-            ttl_seconds: value.ttl_seconds,
-        }
+    #[test]
+    fn parses_dev_api_enabled_missing() {
+        assert!(!parse_dev_api_enabled(None));
     }
 }
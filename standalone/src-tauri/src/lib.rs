@@ -9,6 +9,7 @@ use crate::{db::DatabaseHandle, worker::load_worker_binary_path};
 mod db;
 mod db_commands;
 mod dev_api;
+mod maintenance;
 mod ui;
 mod worker;
 
@@ -40,10 +41,13 @@ pub fn run() {
             db_commands::get_task_info,
             db_commands::get_task_checkpoint_statuses,
             db_commands::get_task_checkpoints,
+            db_commands::get_task_checkpoint_history,
             db_commands::get_queue_names,
             db_commands::get_queue_summaries,
             db_commands::create_queue,
             db_commands::cleanup_queue,
+            db_commands::export_queue,
+            db_commands::import_queue,
             db_commands::get_task_name_options,
             db_commands::get_event_filter_defaults,
             db_commands::get_events,
@@ -52,8 +56,15 @@ pub fn run() {
             db_commands::get_migrations,
             db_commands::apply_migrations_all,
             db_commands::apply_migration,
+            db_commands::get_workers,
+            db_commands::get_task_types,
+            db_commands::release_worker_claims,
             dev_api::get_dev_api_status,
             dev_api::set_dev_api_enabled,
+            dev_api::get_dev_api_config,
+            dev_api::create_dev_api_token,
+            dev_api::revoke_dev_api_token,
+            dev_api::set_dev_api_network,
             worker::get_worker_status,
             worker::get_worker_logs,
             worker::set_worker_binary_path,
@@ -152,6 +163,7 @@ pub fn run() {
 
             ui::tray::setup(app)?;
             ui::tray::start_updates(app_handle.clone());
+            maintenance::start(app_handle.clone());
 
             #[cfg(not(any(target_os = "android", target_os = "ios")))]
             {
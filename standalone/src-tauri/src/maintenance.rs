@@ -0,0 +1,48 @@
+use std::time::Duration;
+use tauri::{async_runtime, AppHandle, Manager};
+use tokio::time::interval;
+
+use crate::db::DatabaseHandle;
+
+const MAINTENANCE_INTERVAL: Duration = Duration::from_secs(5 * 60);
+const MAINTENANCE_BATCH_SIZE: i64 = 500;
+const MAX_BATCHES_PER_TICK: usize = 20;
+
+/// Periodically apply each queue's retention settings in bounded batches.
+pub fn start(app_handle: AppHandle) {
+    async_runtime::spawn(async move {
+        let mut ticker = interval(MAINTENANCE_INTERVAL);
+        loop {
+            ticker.tick().await;
+            // The batches are blocking SQLite calls; keep them off the
+            // runtime's worker threads.
+            let handle = app_handle.clone();
+            match async_runtime::spawn_blocking(move || run_once(&handle)).await {
+                Ok(Ok(())) => {}
+                Ok(Err(err)) => log::warn!("Failed to run queue maintenance: {}", err),
+                Err(err) => log::warn!("Queue maintenance task failed: {}", err),
+            }
+        }
+    });
+}
+
+fn run_once(app_handle: &AppHandle) -> anyhow::Result<()> {
+    let db_handle = app_handle.state::<DatabaseHandle>();
+    let conn = db_handle.connect(app_handle)?;
+    let mut total = 0;
+    for _ in 0..MAX_BATCHES_PER_TICK {
+        let deleted: i64 = conn.query_row(
+            "select absurd_run_maintenance(?1)",
+            [MAINTENANCE_BATCH_SIZE],
+            |row| row.get(0),
+        )?;
+        if deleted == 0 {
+            break;
+        }
+        total += deleted;
+    }
+    if total > 0 {
+        log::info!("Queue maintenance removed {} rows", total);
+    }
+    Ok(())
+}
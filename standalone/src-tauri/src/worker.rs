@@ -11,6 +11,7 @@ use tauri_plugin_store::StoreExt;
 use tokio::time::sleep;
 
 use crate::db::{extension_path, DatabaseHandle};
+use absurd_server::provider::DataProvider;
 
 const WORKER_STORE_PATH: &str = "worker.json";
 const WORKER_PATH_KEY: &str = "worker_binary_path";
@@ -284,7 +285,7 @@ pub fn start_worker_inner(app_handle: &AppHandle) -> Result<WorkerStatus, String
                         payload.code,
                         payload.signal
                     );
-                    handle_worker_exit(&app_handle);
+                    handle_worker_exit(&app_handle, pid);
                     break;
                 }
                 CommandEvent::Error(err) => {
@@ -356,7 +357,7 @@ fn stop_worker_process(worker: RunningWorker, pid: u32) -> Result<(), String> {
     }
 }
 
-fn handle_worker_exit(app_handle: &AppHandle) {
+fn handle_worker_exit(app_handle: &AppHandle, pid: u32) {
     let state = app_handle.state::<WorkerState>();
     let stopped = {
         let mut flag = state.stop_requested.lock().unwrap();
@@ -368,6 +369,12 @@ fn handle_worker_exit(app_handle: &AppHandle) {
     *state.running.lock().unwrap() = None;
 
     if stopped {
+        // Hand runs the worker still held back to the queue right away instead
+        // of waiting for their claims to expire. Crashes keep the timeout path so
+        // a task that kills its worker still burns attempts.
+        if let Err(err) = release_process_claims(app_handle, pid) {
+            log::warn!("Failed to release claims for worker pid {}: {}", pid, err);
+        }
         return;
     }
 
@@ -428,6 +435,24 @@ fn handle_worker_exit(app_handle: &AppHandle) {
     });
 }
 
+fn release_process_claims(app_handle: &AppHandle, pid: u32) -> anyhow::Result<()> {
+    let db_handle = app_handle.state::<DatabaseHandle>();
+    let conn = db_handle.connect(app_handle)?;
+    let provider = DataProvider::new(&conn);
+    let result = provider.release_process_claims(pid)?;
+    if result.released_count > 0 {
+        push_worker_log(
+            app_handle,
+            "stdout",
+            format!(
+                "Released {} claimed run(s) held by the stopped worker.",
+                result.released_count
+            ),
+        );
+    }
+    Ok(())
+}
+
 fn parse_command(command: &str) -> Result<(String, Vec<String>), String> {
     let mut args = Vec::new();
     let mut current = String::new();
@@ -57,6 +57,22 @@ export type TaskCheckpoint = {
   updatedAt: string;
 };
 
+export type CheckpointChange = {
+  path: string;
+  kind: "added" | "removed" | "changed";
+  before: string | null;
+  after: string | null;
+};
+
+export type CheckpointVersion = {
+  attempt: number;
+  runId: string | null;
+  status: string;
+  stateJson: string | null;
+  updatedAt: string;
+  changes: CheckpointChange[];
+};
+
 export type TaskRunFilters = {
   queueName?: string;
   status?: TaskRun["status"];
@@ -106,6 +122,22 @@ export type CleanupResult = {
   deletedCount: number;
 };
 
+export type QueueExport = {
+  queueName: string;
+  recordCount: number;
+  jsonl: string;
+};
+
+export type ImportQueueOptions = {
+  queueName: string;
+  jsonl: string;
+  remapIds?: boolean;
+};
+
+export type ImportResult = {
+  importedCount: number;
+};
+
 export type MigrationStatus = {
   status: "applied" | "missing";
   appliedCount: number;
@@ -138,6 +170,40 @@ export type WorkerLogs = {
   lines: WorkerLogLine[];
 };
 
+export type WorkerSummary = {
+  workerId: string;
+  hostname: string | null;
+  pid: number | null;
+  queues: string[];
+  firstSeen: string;
+  lastSeen: string;
+  lastSeenMs: number;
+  lastSeenAgo: string;
+  heldRuns: TaskRun[];
+};
+
+export type TaskTypeSummary = {
+  queueName: string;
+  taskName: string;
+  description: string | null;
+  ownerTeam: string | null;
+  claimTimeout: number | null;
+  concurrencyLimit: number | null;
+  schemaJson: string | null;
+  defaultOptionsJson: string | null;
+  pending: number;
+  running: number;
+  sleeping: number;
+  completed: number;
+  failed: number;
+  cancelled: number;
+  updatedAt: string;
+};
+
+export type ReleaseClaimsResult = {
+  releasedCount: number;
+};
+
 export type MigrationEntry = {
   id: number;
   introducedVersion: string;
@@ -155,11 +221,17 @@ export type AbsurdDataProvider = {
   getTaskInfo: (taskId: string) => Promise<TaskInfo | null>;
   getTaskCheckpointStatuses: (taskId: string) => Promise<CheckpointStatusCount[]>;
   getTaskCheckpoints: (taskId: string) => Promise<TaskCheckpoint[]>;
+  getTaskCheckpointHistory: (
+    taskId: string,
+    checkpointName: string
+  ) => Promise<CheckpointVersion[]>;
   getQueueNames: () => Promise<string[]>;
   getTaskNameOptions: (queueName?: string) => Promise<string[]>;
   getQueueSummaries: () => Promise<QueueSummary[]>;
   createQueue: (queueName: string) => Promise<void>;
   cleanupQueue: (options: CleanupQueueOptions) => Promise<CleanupResult>;
+  exportQueue: (queueName: string) => Promise<QueueExport>;
+  importQueue: (options: ImportQueueOptions) => Promise<ImportResult>;
   getEventFilterDefaults: (queueName?: string) => Promise<EventFilterDefaults>;
   getEvents: () => Promise<EventEntry[]>;
   getFilteredEvents: (filters: { queueName?: string; eventName?: string }) => Promise<EventEntry[]>;
@@ -169,6 +241,9 @@ export type AbsurdDataProvider = {
   setWorkerBinaryPath: (path: string) => Promise<WorkerStatus>;
   startWorker: () => Promise<WorkerStatus>;
   stopWorker: () => Promise<WorkerStatus>;
+  getWorkers: () => Promise<WorkerSummary[]>;
+  releaseWorkerClaims: (workerId: string) => Promise<ReleaseClaimsResult>;
+  getTaskTypes: () => Promise<TaskTypeSummary[]>;
   getMigrations: () => Promise<MigrationEntry[]>;
   applyMigrationsAll: () => Promise<number>;
   applyMigration: (migrationId: number) => Promise<number>;
@@ -194,12 +269,16 @@ export const tauriAbsurdProvider: AbsurdDataProvider = {
   getTaskCheckpointStatuses: (taskId) =>
     tauriInvoke("get_task_checkpoint_statuses", { taskId }),
   getTaskCheckpoints: (taskId) => tauriInvoke("get_task_checkpoints", { taskId }),
+  getTaskCheckpointHistory: (taskId, checkpointName) =>
+    tauriInvoke("get_task_checkpoint_history", { taskId, checkpointName }),
   getQueueNames: () => tauriInvoke("get_queue_names"),
   getTaskNameOptions: (queueName) =>
     tauriInvoke("get_task_name_options", { queue_name: queueName ?? null }),
   getQueueSummaries: () => tauriInvoke("get_queue_summaries"),
   createQueue: (queueName) => tauriInvoke("create_queue", { queueName }),
   cleanupQueue: (options) => tauriInvoke("cleanup_queue", { options }),
+  exportQueue: (queueName) => tauriInvoke("export_queue", { queueName }),
+  importQueue: (options) => tauriInvoke("import_queue", { options }),
   getEventFilterDefaults: (queueName) =>
     tauriInvoke("get_event_filter_defaults", queueName ? { queue_name: queueName } : undefined),
   getEvents: () => tauriInvoke("get_events"),
@@ -210,6 +289,9 @@ export const tauriAbsurdProvider: AbsurdDataProvider = {
   setWorkerBinaryPath: (path) => tauriInvoke("set_worker_binary_path", { path }),
   startWorker: () => tauriInvoke("start_worker"),
   stopWorker: () => tauriInvoke("stop_worker"),
+  getWorkers: () => tauriInvoke("get_workers"),
+  releaseWorkerClaims: (workerId) => tauriInvoke("release_worker_claims", { workerId }),
+  getTaskTypes: () => tauriInvoke("get_task_types"),
   getMigrations: () => tauriInvoke("get_migrations"),
   applyMigrationsAll: () => tauriInvoke("apply_migrations_all"),
   applyMigration: (migrationId) =>
@@ -248,6 +330,12 @@ const DEV_API_PORT_BASE = 11223;
 const DEV_API_PORT_ATTEMPTS = 10;
 const DEV_API_REQUEST_TIMEOUT_MS = 400;
 const DEV_API_RETRY_DELAY_MS = 2000;
+// Point the web UI at an absurd-server on another host, e.g.
+// VITE_ABSURD_API_URL=http://queue-box:11223. Unset probes localhost ports.
+const DEV_API_BASE_URL_OVERRIDE: string | undefined = import.meta.env.VITE_ABSURD_API_URL;
+// Bearer token for a server that requires authentication, created in the
+// desktop app's settings or passed to absurd-server with --token.
+const DEV_API_TOKEN: string | undefined = import.meta.env.VITE_ABSURD_API_TOKEN;
 
 let devApiBaseUrl: string | null | undefined = undefined;
 let devApiLastFailureMs: number | null = null;
@@ -272,9 +360,13 @@ const resolveDevApiBaseUrl = async (): Promise<string | null> => {
   }
 
   devApiResolveInFlight = (async () => {
-    for (let attempt = 0; attempt < DEV_API_PORT_ATTEMPTS; attempt += 1) {
-      const port = DEV_API_PORT_BASE + attempt;
-      const baseUrl = `http://localhost:${port}`;
+    const candidates = DEV_API_BASE_URL_OVERRIDE
+      ? [DEV_API_BASE_URL_OVERRIDE.replace(/\/+$/, "")]
+      : Array.from(
+          { length: DEV_API_PORT_ATTEMPTS },
+          (_, attempt) => `http://localhost:${DEV_API_PORT_BASE + attempt}`,
+        );
+    for (const baseUrl of candidates) {
       const ok = await probeDevApi(baseUrl);
       if (ok) {
         devApiBaseUrl = baseUrl;
@@ -328,6 +420,8 @@ const getTrpcClient = async () => {
       links: [
         httpLink({
           url: `${baseUrl}/absurd-data`,
+          headers: () =>
+            DEV_API_TOKEN ? { authorization: `Bearer ${DEV_API_TOKEN}` } : {},
         }),
       ],
     });
@@ -376,12 +470,16 @@ const trpcAbsurdProvider: AbsurdDataProvider = {
   getTaskCheckpointStatuses: (taskId) =>
     trpcQuery("getTaskCheckpointStatuses", { taskId }),
   getTaskCheckpoints: (taskId) => trpcQuery("getTaskCheckpoints", { taskId }),
+  getTaskCheckpointHistory: (taskId, checkpointName) =>
+    trpcQuery("getTaskCheckpointHistory", { taskId, checkpointName }),
   getQueueNames: () => trpcQuery("getQueueNames"),
   getTaskNameOptions: (queueName) =>
     trpcQuery("getTaskNameOptions", queueName ? { queueName } : null),
   getQueueSummaries: () => trpcQuery("getQueueSummaries"),
   createQueue: (queueName) => trpcMutation("createQueue", { queueName }),
   cleanupQueue: (options) => trpcMutation("cleanupQueue", options),
+  exportQueue: (queueName) => trpcQuery("exportQueue", { queueName }),
+  importQueue: (options) => trpcMutation("importQueue", options),
   getEventFilterDefaults: (queueName) =>
     trpcQuery("getEventFilterDefaults", queueName ? { queueName } : null),
   getEvents: () => trpcQuery("getEvents"),
@@ -392,6 +490,9 @@ const trpcAbsurdProvider: AbsurdDataProvider = {
   setWorkerBinaryPath: (path) => trpcMutation("setWorkerBinaryPath", { path }),
   startWorker: () => trpcMutation("startWorker"),
   stopWorker: () => trpcMutation("stopWorker"),
+  getWorkers: () => trpcQuery("getWorkers"),
+  releaseWorkerClaims: (workerId) => trpcMutation("releaseWorkerClaims", { workerId }),
+  getTaskTypes: () => trpcQuery("getTaskTypes"),
   getMigrations: () => trpcQuery("getMigrations"),
   applyMigrationsAll: () => trpcMutation("applyMigrationsAll"),
   applyMigration: (migrationId) =>
@@ -413,6 +514,8 @@ export const mockAbsurdProvider: AbsurdDataProvider = {
   },
   createQueue: async () => {},
   cleanupQueue: async () => ({ deletedCount: 0 }),
+  exportQueue: async (queueName) => ({ queueName, recordCount: 0, jsonl: "" }),
+  importQueue: async () => ({ importedCount: 0 }),
   getOverviewMetrics: async () => ({
     activeQueues: 1,
     messagesProcessed: 0,
@@ -628,6 +731,7 @@ export const mockAbsurdProvider: AbsurdDataProvider = {
   },
   getTaskCheckpointStatuses: async () => [],
   getTaskCheckpoints: async () => [],
+  getTaskCheckpointHistory: async () => [],
   getQueueSummaries: async () => [
     {
       name: "default",
@@ -736,6 +840,44 @@ export const mockAbsurdProvider: AbsurdDataProvider = {
     };
     return { ...mockWorkerStatus };
   },
+  getWorkers: async () => {
+    const runs = await mockAbsurdProvider.getTaskRuns();
+    return [
+      {
+        workerId: "mordor.local:89695",
+        hostname: "mordor.local",
+        pid: 89695,
+        queues: ["default"],
+        firstSeen: "Dec 22, 2025, 8:57 AM",
+        lastSeen: "Dec 27, 2025, 3:03:12 PM",
+        lastSeenMs: Date.parse("Dec 27, 2025, 3:03 PM"),
+        lastSeenAgo: "5d ago",
+        heldRuns: runs.filter(
+          (run) => run.status === "running" && run.worker === "mordor.local:89695",
+        ),
+      },
+    ];
+  },
+  releaseWorkerClaims: async () => ({ releasedCount: 0 }),
+  getTaskTypes: async () => [
+    {
+      queueName: "default",
+      taskName: "send-email",
+      description: "Deliver transactional email through the provider API.",
+      ownerTeam: "growth",
+      claimTimeout: 120,
+      concurrencyLimit: 4,
+      schemaJson: "{\n  \"type\": \"object\",\n  \"required\": [\n    \"to\"\n  ]\n}",
+      defaultOptionsJson: "{\n  \"max_attempts\": 5\n}",
+      pending: 3,
+      running: 1,
+      sleeping: 0,
+      completed: 42,
+      failed: 2,
+      cancelled: 0,
+      updatedAt: "Dec 22, 2025, 8:57 AM",
+    },
+  ],
   getMigrations: async () => mockMigrations.map((entry) => ({ ...entry })),
   applyMigrationsAll: async () => {
     let applied = 0;